@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Collects `migrations/sqlite/NNN_name.sql` (and optional
+/// `NNN_name.down.sql`) into a generated table consumed by
+/// `data::migrations`.
+fn main() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations/sqlite");
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    let mut ups: BTreeMap<u32, (String, PathBuf)> = BTreeMap::new();
+    let mut downs: BTreeMap<u32, PathBuf> = BTreeMap::new();
+    for entry in fs::read_dir(&dir).expect("reading migrations dir") {
+        let path = entry.expect("migration entry").path();
+        let Some(file) = path.file_name().and_then(|f| f.to_str()) else {
+            continue;
+        };
+        let Some(stem) = file.strip_suffix(".sql") else {
+            continue;
+        };
+        println!("cargo:rerun-if-changed={}", path.display());
+        let (stem, is_down) = match stem.strip_suffix(".down") {
+            Some(stem) => (stem, true),
+            None => (stem, false),
+        };
+        let digits = stem.split('_').next().unwrap_or_default();
+        let version: u32 = digits
+            .parse()
+            .unwrap_or_else(|_| panic!("migration {} must start with a number", file));
+        if is_down {
+            downs.insert(version, path.clone());
+        } else if let Some((existing, _)) = ups.insert(version, (stem.to_string(), path.clone())) {
+            panic!(
+                "duplicate migration version {} ({} and {})",
+                version, existing, stem
+            );
+        }
+    }
+    for version in downs.keys() {
+        assert!(
+            ups.contains_key(version),
+            "down script for unknown migration {}",
+            version
+        );
+    }
+
+    let mut out = String::from("pub static MIGRATIONS: &[Migration] = &[\n");
+    for (version, (name, path)) in &ups {
+        let down = match downs.get(version) {
+            Some(path) => format!("Some(include_str!({:?}))", path.display().to_string()),
+            None => "None".to_string(),
+        };
+        out.push_str(&format!(
+            "    Migration {{ version: {}, name: {:?}, up: include_str!({:?}), down: {} }},\n",
+            version,
+            name,
+            path.display().to_string(),
+            down
+        ));
+    }
+    out.push_str("];\n");
+
+    let dest = PathBuf::from(env::var("OUT_DIR").unwrap()).join("migrations.rs");
+    fs::write(dest, out).expect("writing migrations table");
+}
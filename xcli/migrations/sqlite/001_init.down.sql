@@ -0,0 +1,8 @@
+DROP TABLE IF EXISTS outbox;
+DROP TABLE IF EXISTS cache_likes;
+DROP TABLE IF EXISTS cache_follows;
+DROP TABLE IF EXISTS cache_posts;
+DROP TABLE IF EXISTS cache_profiles;
+DROP TABLE IF EXISTS reports;
+DROP TABLE IF EXISTS tasks;
+DROP TABLE IF EXISTS settings;
@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_outbox_due;
+ALTER TABLE outbox DROP COLUMN updated_at;
+ALTER TABLE outbox DROP COLUMN next_attempt_at;
+ALTER TABLE outbox DROP COLUMN last_error;
+ALTER TABLE outbox DROP COLUMN attempts;
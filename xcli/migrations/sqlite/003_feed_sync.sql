@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS feed_sync (
+    filter TEXT PRIMARY KEY,
+    synced_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_cache_posts_author ON cache_posts(author, created_at);
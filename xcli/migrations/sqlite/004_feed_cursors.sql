@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS feed_cursors (
+    filter TEXT PRIMARY KEY,
+    before_created_at TEXT,
+    before_id TEXT,
+    updated_at TEXT NOT NULL
+);
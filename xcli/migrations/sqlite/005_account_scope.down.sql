@@ -0,0 +1,32 @@
+ALTER TABLE outbox DROP COLUMN user_id;
+
+DROP TABLE IF EXISTS feed_cursors;
+CREATE TABLE feed_cursors (
+    filter TEXT PRIMARY KEY,
+    before_created_at TEXT,
+    before_id TEXT,
+    updated_at TEXT NOT NULL
+);
+
+DROP TABLE IF EXISTS feed_sync;
+CREATE TABLE feed_sync (
+    filter TEXT PRIMARY KEY,
+    synced_at TEXT NOT NULL
+);
+
+DROP INDEX IF EXISTS idx_cache_posts_author;
+CREATE TABLE cache_posts_unscoped (
+    id TEXT PRIMARY KEY,
+    author TEXT NOT NULL,
+    body TEXT NOT NULL,
+    audience TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    author_handle TEXT,
+    liked INTEGER NOT NULL DEFAULT 0,
+    like_count INTEGER NOT NULL DEFAULT 0
+);
+INSERT OR IGNORE INTO cache_posts_unscoped(id, author, body, audience, created_at, author_handle, liked, like_count)
+    SELECT id, author, body, audience, created_at, author_handle, liked, like_count FROM cache_posts;
+DROP TABLE cache_posts;
+ALTER TABLE cache_posts_unscoped RENAME TO cache_posts;
+CREATE INDEX IF NOT EXISTS idx_cache_posts_author ON cache_posts(author, created_at);
@@ -0,0 +1,39 @@
+-- Cached posts, feed cursors and sync times belong to the account that
+-- fetched them, and outbox items to the account that queued them. Existing
+-- cached posts can't be attributed to an account and are dropped; the next
+-- feed refresh fetches them again.
+DROP INDEX IF EXISTS idx_cache_posts_author;
+DROP TABLE IF EXISTS cache_posts;
+CREATE TABLE cache_posts (
+    viewer TEXT NOT NULL,
+    id TEXT NOT NULL,
+    author TEXT NOT NULL,
+    body TEXT NOT NULL,
+    audience TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    author_handle TEXT,
+    liked INTEGER NOT NULL DEFAULT 0,
+    like_count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (viewer, id)
+);
+CREATE INDEX idx_cache_posts_author ON cache_posts(viewer, author, created_at);
+
+DROP TABLE IF EXISTS feed_sync;
+CREATE TABLE feed_sync (
+    viewer TEXT NOT NULL,
+    filter TEXT NOT NULL,
+    synced_at TEXT NOT NULL,
+    PRIMARY KEY (viewer, filter)
+);
+
+DROP TABLE IF EXISTS feed_cursors;
+CREATE TABLE feed_cursors (
+    viewer TEXT NOT NULL,
+    filter TEXT NOT NULL,
+    before_created_at TEXT,
+    before_id TEXT,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (viewer, filter)
+);
+
+ALTER TABLE outbox ADD COLUMN user_id TEXT NOT NULL DEFAULT '';
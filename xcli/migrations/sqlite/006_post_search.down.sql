@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS cache_posts_fts_update;
+DROP TRIGGER IF EXISTS cache_posts_fts_delete;
+DROP TRIGGER IF EXISTS cache_posts_fts_insert;
+DROP TABLE IF EXISTS cache_posts_fts;
@@ -0,0 +1,28 @@
+-- Full-text index over cached posts for /search. The index keeps its own
+-- copy of the searchable text and is kept in step with cache_posts by
+-- triggers; viewer and post_id are stored unindexed to join back.
+CREATE VIRTUAL TABLE cache_posts_fts USING fts5(
+    body,
+    author_handle,
+    viewer UNINDEXED,
+    post_id UNINDEXED,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO cache_posts_fts(body, author_handle, viewer, post_id)
+    SELECT body, author_handle, viewer, id FROM cache_posts;
+
+CREATE TRIGGER cache_posts_fts_insert AFTER INSERT ON cache_posts BEGIN
+    INSERT INTO cache_posts_fts(body, author_handle, viewer, post_id)
+        VALUES (new.body, new.author_handle, new.viewer, new.id);
+END;
+
+CREATE TRIGGER cache_posts_fts_delete AFTER DELETE ON cache_posts BEGIN
+    DELETE FROM cache_posts_fts WHERE viewer = old.viewer AND post_id = old.id;
+END;
+
+CREATE TRIGGER cache_posts_fts_update AFTER UPDATE OF body, author_handle ON cache_posts BEGIN
+    DELETE FROM cache_posts_fts WHERE viewer = old.viewer AND post_id = old.id;
+    INSERT INTO cache_posts_fts(body, author_handle, viewer, post_id)
+        VALUES (new.body, new.author_handle, new.viewer, new.id);
+END;
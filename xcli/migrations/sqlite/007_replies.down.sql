@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_cache_posts_root;
+ALTER TABLE cache_posts DROP COLUMN reply_to_handle;
+ALTER TABLE cache_posts DROP COLUMN root_id;
+ALTER TABLE cache_posts DROP COLUMN reply_to;
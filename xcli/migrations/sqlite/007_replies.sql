@@ -0,0 +1,6 @@
+-- Replies: the post answered, the post that started the conversation and
+-- the parent's author handle for the "replying to" line.
+ALTER TABLE cache_posts ADD COLUMN reply_to TEXT;
+ALTER TABLE cache_posts ADD COLUMN root_id TEXT;
+ALTER TABLE cache_posts ADD COLUMN reply_to_handle TEXT;
+CREATE INDEX idx_cache_posts_root ON cache_posts(viewer, root_id);
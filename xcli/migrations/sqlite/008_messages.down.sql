@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_cache_messages_peer;
+DROP TABLE IF EXISTS cache_messages;
@@ -0,0 +1,16 @@
+-- Direct messages seen by each signed-in account. `peer` is the other
+-- participant, so a conversation is every row with the same viewer and peer.
+CREATE TABLE cache_messages (
+    viewer TEXT NOT NULL,
+    id TEXT NOT NULL,
+    peer TEXT NOT NULL,
+    sender TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    read_at TEXT,
+    sender_handle TEXT,
+    recipient_handle TEXT,
+    PRIMARY KEY (viewer, id)
+);
+CREATE INDEX idx_cache_messages_peer ON cache_messages(viewer, peer, created_at);
@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS cache_posts_spans_delete;
+ALTER TABLE cache_posts DROP COLUMN spans_indexed;
+DROP INDEX IF EXISTS idx_cache_post_spans_value;
+DROP TABLE IF EXISTS cache_post_spans;
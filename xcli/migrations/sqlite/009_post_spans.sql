@@ -0,0 +1,19 @@
+-- Mentions, hashtags and URLs parsed out of cached post bodies, for the
+-- `#tag` and `mentions` feeds. Byte offsets into the body; `value` is the
+-- lowercased handle or tag, or the URL. Posts cached before this migration
+-- have `spans_indexed = 0` and are parsed on the next start.
+CREATE TABLE cache_post_spans (
+    viewer TEXT NOT NULL,
+    post_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    span_start INTEGER NOT NULL,
+    span_end INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (viewer, post_id, span_start)
+);
+CREATE INDEX idx_cache_post_spans_value ON cache_post_spans(viewer, kind, value);
+ALTER TABLE cache_posts ADD COLUMN spans_indexed INTEGER NOT NULL DEFAULT 0;
+
+CREATE TRIGGER cache_posts_spans_delete AFTER DELETE ON cache_posts BEGIN
+    DELETE FROM cache_post_spans WHERE viewer = old.viewer AND post_id = old.id;
+END;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_notifications_viewer;
+DROP TABLE IF EXISTS notifications;
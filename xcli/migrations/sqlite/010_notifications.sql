@@ -0,0 +1,16 @@
+-- Notifications fetched for each account, with local read state. `id` is
+-- the short number `/notifications open` takes; `key` is the server's id.
+CREATE TABLE notifications (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    viewer TEXT NOT NULL,
+    key TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    actor_handle TEXT,
+    post_id TEXT,
+    excerpt TEXT,
+    created_at TEXT NOT NULL,
+    read_at TEXT,
+    UNIQUE (viewer, key)
+);
+CREATE INDEX idx_notifications_viewer ON notifications(viewer, created_at);
@@ -0,0 +1,7 @@
+-- Lines entered on the TUI command line, for Up/Down recall and Ctrl-R
+-- across sessions.
+CREATE TABLE command_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    line TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Posts written in the composer but not sent yet.
+CREATE TABLE drafts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    body TEXT NOT NULL,
+    audience TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
@@ -0,0 +1,18 @@
+-- In-progress tasks go back to open and cancelled ones to done.
+DROP TABLE IF EXISTS task_tags;
+DROP INDEX IF EXISTS idx_tasks_due;
+CREATE TABLE tasks_basic (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    status TEXT NOT NULL CHECK(status IN ('open','done')) DEFAULT 'open',
+    created_at TEXT NOT NULL,
+    done_at TEXT
+);
+INSERT INTO tasks_basic(id, title, description, status, created_at, done_at)
+    SELECT id, title, description,
+        CASE status WHEN 'in_progress' THEN 'open' WHEN 'cancelled' THEN 'done' ELSE status END,
+        created_at, done_at
+    FROM tasks;
+DROP TABLE tasks;
+ALTER TABLE tasks_basic RENAME TO tasks;
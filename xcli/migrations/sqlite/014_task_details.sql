@@ -0,0 +1,26 @@
+-- Tasks gain a priority (0 low .. 3 urgent), an optional due time (unix
+-- seconds), in_progress and cancelled states, an updated_at stamp and tags.
+-- SQLite can't change a CHECK constraint in place, so the table is rebuilt.
+CREATE TABLE tasks_details (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    status TEXT NOT NULL CHECK(status IN ('open','in_progress','done','cancelled')) DEFAULT 'open',
+    priority INTEGER NOT NULL CHECK(priority BETWEEN 0 AND 3) DEFAULT 1,
+    due_at INTEGER,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    done_at TEXT
+);
+INSERT INTO tasks_details(id, title, description, status, created_at, updated_at, done_at)
+    SELECT id, title, description, status, created_at, COALESCE(done_at, created_at), done_at FROM tasks;
+DROP TABLE tasks;
+ALTER TABLE tasks_details RENAME TO tasks;
+CREATE INDEX idx_tasks_due ON tasks(status, due_at);
+
+CREATE TABLE task_tags (
+    task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (task_id, tag)
+);
+CREATE INDEX idx_task_tags_tag ON task_tags(tag);
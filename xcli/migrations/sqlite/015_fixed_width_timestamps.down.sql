@@ -0,0 +1 @@
+-- The fixed-width timestamps are still valid RFC 3339; nothing to undo.
@@ -0,0 +1,29 @@
+-- Rewrites stored timestamps as fixed-width UTC text with microseconds
+-- (2026-10-18T09:00:00.500000Z) so that comparing them as strings orders
+-- them by time. SQLite keeps milliseconds, so older rows lose anything
+-- finer; values it can't read are left as they are.
+UPDATE tasks SET
+    created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', created_at) || '000Z', created_at),
+    updated_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', updated_at) || '000Z', updated_at),
+    done_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', done_at) || '000Z', done_at);
+UPDATE reports SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', created_at) || '000Z', created_at);
+UPDATE cache_profiles SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', created_at) || '000Z', created_at);
+UPDATE cache_posts SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', created_at) || '000Z', created_at);
+UPDATE cache_follows SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', created_at) || '000Z', created_at);
+UPDATE cache_likes SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', created_at) || '000Z', created_at);
+UPDATE outbox SET
+    created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', created_at) || '000Z', created_at),
+    updated_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', updated_at) || '000Z', updated_at);
+UPDATE feed_sync SET synced_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', synced_at) || '000Z', synced_at);
+UPDATE feed_cursors SET
+    before_created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', before_created_at) || '000Z', before_created_at),
+    updated_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', updated_at) || '000Z', updated_at);
+UPDATE cache_messages SET
+    created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', created_at) || '000Z', created_at),
+    read_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', read_at) || '000Z', read_at);
+UPDATE notifications SET
+    created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', created_at) || '000Z', created_at),
+    read_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', read_at) || '000Z', read_at);
+UPDATE command_history SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', created_at) || '000Z', created_at);
+UPDATE drafts SET updated_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', updated_at) || '000Z', updated_at);
+UPDATE scheduled_posts SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%f', created_at) || '000Z', created_at);
@@ -0,0 +1,2 @@
+-- The ids left in outbox payloads are ignored by older builds.
+ALTER TABLE scheduled_posts DROP COLUMN post_id;
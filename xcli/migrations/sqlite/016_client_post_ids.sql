@@ -0,0 +1,16 @@
+-- Posts and messages are inserted with an id chosen here, so a retry after
+-- a lost response hits the row it already created instead of adding
+-- another. Scheduled posts keep theirs in `post_id`; queued outbox items
+-- carry it in the payload. Existing rows get a random v4 uuid.
+ALTER TABLE scheduled_posts ADD COLUMN post_id TEXT NOT NULL DEFAULT '';
+UPDATE scheduled_posts SET post_id =
+    lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4'
+    || substr(lower(hex(randomblob(2))), 2) || '-'
+    || substr('89ab', 1 + abs(random()) % 4, 1) || substr(lower(hex(randomblob(2))), 2) || '-'
+    || lower(hex(randomblob(6)));
+UPDATE outbox SET payload = json_set(payload, '$.id',
+    lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4'
+    || substr(lower(hex(randomblob(2))), 2) || '-'
+    || substr('89ab', 1 + abs(random()) % 4, 1) || substr(lower(hex(randomblob(2))), 2) || '-'
+    || lower(hex(randomblob(6))))
+WHERE kind IN ('post', 'message') AND json_extract(payload, '$.id') IS NULL;
@@ -0,0 +1,4 @@
+-- When an item was claimed for delivery (unix seconds). A claim only counts
+-- as abandoned once it is older than the lease, so a starting TUI leaves
+-- items alone that another process is still sending.
+ALTER TABLE outbox ADD COLUMN claimed_at INTEGER;
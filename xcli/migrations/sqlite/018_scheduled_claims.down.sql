@@ -0,0 +1 @@
+ALTER TABLE scheduled_posts DROP COLUMN claimed_at;
@@ -0,0 +1,3 @@
+-- When a post was claimed for publishing (unix seconds), so that only
+-- claims older than the lease are taken back from `xcli flush-scheduled`.
+ALTER TABLE scheduled_posts ADD COLUMN claimed_at INTEGER;
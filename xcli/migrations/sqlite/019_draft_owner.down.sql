@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_drafts_user;
+ALTER TABLE drafts DROP COLUMN user_id;
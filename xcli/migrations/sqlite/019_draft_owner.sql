@@ -0,0 +1,4 @@
+-- Drafts belong to the account that wrote them. Drafts saved before this,
+-- or while signed out, have no owner and are listed for every account.
+ALTER TABLE drafts ADD COLUMN user_id TEXT NOT NULL DEFAULT '';
+CREATE INDEX idx_drafts_user ON drafts(user_id, updated_at);
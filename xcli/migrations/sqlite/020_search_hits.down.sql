@@ -0,0 +1 @@
+ALTER TABLE cache_posts DROP COLUMN in_feed;
@@ -0,0 +1,3 @@
+-- Posts cached only because a server search returned them are kept out of
+-- the offline feeds. A later feed fetch of the same post sets `in_feed`.
+ALTER TABLE cache_posts ADD COLUMN in_feed INTEGER NOT NULL DEFAULT 1;
@@ -1,16 +1,32 @@
 mod state;
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
-use std::io::{self, Stdout};
+use std::io::{self, Stdout, Write};
 use std::time::{Duration, Instant};
+use std::{env, fs, process};
+use time::OffsetDateTime;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
 
-use crate::commands::{self, CommandContext};
-use crate::data::AppDatabase;
-use crate::supabase::SupabaseClient;
+use crate::commands::{self, complete, Command, CommandContext};
+use crate::data::{
+    cache::CacheDao,
+    drafts::DraftDao,
+    history::{HistoryDao, HISTORY_LIMIT},
+    messages::MessageDao,
+    notifications::NotificationDao,
+    AppDatabase,
+};
+use crate::domain::AudienceScope;
+use crate::realtime::{RealtimeConfig, RealtimeWorker};
+use crate::supabase::{error, SupabaseClient};
+use crate::sync::{self, OutboxWorker, ScheduledWorker, SyncEvent};
 
 use state::AppState;
 
@@ -18,18 +34,81 @@ pub struct App<'a> {
     terminal: Terminal<CrosstermBackend<Stdout>>,
     state: AppState,
     ctx: CommandContext<'a>,
+    sync_tx: UnboundedSender<SyncEvent>,
+    sync_events: UnboundedReceiver<SyncEvent>,
+    _outbox: OutboxWorker,
+    _scheduled: ScheduledWorker,
+    /// Running while an account is signed in.
+    _realtime: Option<RealtimeWorker>,
+    /// User id of the active account, to notice `/account switch` and logins.
+    viewer: Option<String>,
+    feed_probe: Option<JoinHandle<()>>,
+    last_probe: Instant,
+    message_poll: Option<JoinHandle<()>>,
+    last_message_poll: Instant,
+    notification_poll: Option<JoinHandle<()>>,
+    last_notification_poll: Instant,
+    last_draft_save: Instant,
 }
 
+/// How often to look for scheduled posts that are due.
+const SCHEDULED_POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// How often to retry Supabase while the feed panel shows cached posts.
+const OFFLINE_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+/// How often to check for new direct messages while signed in.
+const MESSAGE_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// How often to check for new notifications while signed in.
+const NOTIFICATION_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// How often the composer's text is saved as a draft while it changes.
+const DRAFT_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(3);
+
 impl<'a> App<'a> {
     pub fn new(db: &'a AppDatabase, supabase: &'a SupabaseClient) -> Result<Self> {
         let stdout = io::stdout();
         let backend = CrosstermBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
-        let state = AppState::new();
+        let history = HistoryDao::new(db)
+            .recent(HISTORY_LIMIT)
+            .unwrap_or_else(|err| {
+                tracing::warn!(?err, "failed to load command history");
+                Vec::new()
+            });
+        let state = AppState::new(history);
+        match CacheDao::new(db).index_pending_spans() {
+            Ok(0) => {}
+            Ok(count) => tracing::info!(count, "indexed mentions and hashtags of cached posts"),
+            Err(err) => tracing::warn!(?err, "failed to index cached posts"),
+        }
+        let (tx, sync_events) = mpsc::unbounded_channel();
+        let outbox = OutboxWorker::spawn(
+            db.clone(),
+            supabase.clone(),
+            Duration::from_secs(15),
+            tx.clone(),
+        );
+        let scheduled = ScheduledWorker::spawn(
+            db.clone(),
+            supabase.clone(),
+            SCHEDULED_POLL_INTERVAL,
+            tx.clone(),
+        );
         Ok(Self {
             terminal,
             state,
             ctx: CommandContext::new(db, supabase),
+            sync_tx: tx,
+            sync_events,
+            _outbox: outbox,
+            _scheduled: scheduled,
+            _realtime: None,
+            viewer: None,
+            feed_probe: None,
+            last_probe: Instant::now(),
+            message_poll: None,
+            last_message_poll: Instant::now(),
+            notification_poll: None,
+            last_notification_poll: Instant::now(),
+            last_draft_save: Instant::now(),
         })
     }
 
@@ -38,6 +117,7 @@ impl<'a> App<'a> {
         let mut last_tick = Instant::now();
         let tick_rate = Duration::from_millis(200);
         self.terminal.clear()?;
+        self.sync_account().await;
         loop {
             self.terminal.draw(|f| self.state.draw(f))?;
             let timeout = tick_rate
@@ -45,11 +125,7 @@ impl<'a> App<'a> {
                 .unwrap_or_else(|| Duration::from_secs(0));
             if event::poll(timeout)? {
                 match event::read()? {
-                    Event::Key(key) => {
-                        if self.handle_key(key).await? {
-                            break;
-                        }
-                    }
+                    Event::Key(key) if self.handle_key(key).await? => break,
                     Event::Resize(width, height) => {
                         self.state.set_size(width, height);
                     }
@@ -57,53 +133,505 @@ impl<'a> App<'a> {
                 }
             }
             if last_tick.elapsed() >= tick_rate {
+                self.drain_sync_events();
+                self.probe_offline_feed();
+                self.poll_messages();
+                self.poll_notifications();
+                self.autosave_draft();
                 self.state.tick();
                 last_tick = Instant::now();
             }
         }
+        self.save_draft();
         disable_raw_mode()?;
         self.terminal.show_cursor()?;
         Ok(())
     }
 
+    fn drain_sync_events(&mut self) {
+        while let Ok(event) = self.sync_events.try_recv() {
+            if let SyncEvent::NewPost(mut post) = event {
+                let cache = CacheDao::new(self.ctx.db);
+                if let Err(err) = cache.fill_handles(std::slice::from_mut(&mut post)) {
+                    tracing::warn!(?err, "failed to look up realtime post author");
+                }
+                if let Some(viewer) = &self.viewer {
+                    if let Err(err) = cache.upsert_post(viewer, &post) {
+                        tracing::warn!(?err, "failed to cache realtime post");
+                    }
+                }
+                self.state.push_incoming(post);
+                continue;
+            }
+            if let SyncEvent::MessagesReceived { user_id, unread } = &event {
+                // A poll that finished after an account switch is stale.
+                if self.viewer.as_ref() != Some(user_id) {
+                    continue;
+                }
+                self.state.set_unread(*unread);
+            }
+            if let SyncEvent::NotificationsReceived {
+                user_id, unread, ..
+            } = &event
+            {
+                if self.viewer.as_ref() != Some(user_id) {
+                    continue;
+                }
+                self.state.set_unread_notifications(*unread);
+            }
+            self.state.push_status(event.to_string());
+            if let SyncEvent::FeedRefreshed { filter, posts } = event {
+                // Only replace the view if it is still the cached feed we probed for.
+                if self.state.cached_feed().map(|c| &c.filter) == Some(&filter) {
+                    self.state.update_feed(posts);
+                }
+            }
+        }
+    }
+
+    fn probe_offline_feed(&mut self) {
+        let Some(filter) = self.state.cached_feed().map(|c| c.filter.clone()) else {
+            return;
+        };
+        let busy = self
+            .feed_probe
+            .as_ref()
+            .map(|probe| !probe.is_finished())
+            .unwrap_or(false);
+        if busy || self.last_probe.elapsed() < OFFLINE_PROBE_INTERVAL {
+            return;
+        }
+        self.last_probe = Instant::now();
+        self.feed_probe = Some(sync::feed::spawn_probe(
+            self.ctx.db.clone(),
+            self.ctx.supabase.clone(),
+            filter,
+            self.sync_tx.clone(),
+        ));
+    }
+
+    fn poll_messages(&mut self) {
+        let busy = self
+            .message_poll
+            .as_ref()
+            .map(|poll| !poll.is_finished())
+            .unwrap_or(false);
+        if self.viewer.is_none() || busy || self.last_message_poll.elapsed() < MESSAGE_POLL_INTERVAL
+        {
+            return;
+        }
+        self.last_message_poll = Instant::now();
+        self.message_poll = Some(sync::messages::spawn_poll(
+            self.ctx.db.clone(),
+            self.ctx.supabase.clone(),
+            self.sync_tx.clone(),
+        ));
+    }
+
+    fn poll_notifications(&mut self) {
+        let busy = self
+            .notification_poll
+            .as_ref()
+            .map(|poll| !poll.is_finished())
+            .unwrap_or(false);
+        if self.viewer.is_none()
+            || busy
+            || self.last_notification_poll.elapsed() < NOTIFICATION_POLL_INTERVAL
+        {
+            return;
+        }
+        self.last_notification_poll = Instant::now();
+        self.notification_poll = Some(sync::notifications::spawn_poll(
+            self.ctx.db.clone(),
+            self.ctx.supabase.clone(),
+            self.sync_tx.clone(),
+        ));
+    }
+
+    /// Picks up the active account after a login, logout or switch. On a
+    /// change the feed is cleared and Realtime resubscribes with the new
+    /// session.
+    async fn sync_account(&mut self) {
+        let session = match self.ctx.supabase.restore_session().await {
+            Ok(session) => session,
+            Err(err) => {
+                tracing::warn!(?err, "failed to read active account");
+                return;
+            }
+        };
+        let viewer = session.as_ref().map(|s| s.user_id.clone());
+        let switched = viewer != self.viewer;
+        if switched {
+            if let Some(probe) = self.feed_probe.take() {
+                probe.abort();
+            }
+            if let Some(poll) = self.message_poll.take() {
+                poll.abort();
+            }
+            if let Some(poll) = self.notification_poll.take() {
+                poll.abort();
+            }
+            self._realtime = viewer.as_ref().map(|_| {
+                RealtimeWorker::spawn(
+                    self.ctx.supabase.clone(),
+                    RealtimeConfig::from_supabase(&self.ctx.supabase.cfg),
+                    self.sync_tx.clone(),
+                )
+            });
+        }
+        let unread = viewer
+            .as_ref()
+            .and_then(|viewer| MessageDao::new(self.ctx.db).unread_count(viewer).ok())
+            .unwrap_or_default();
+        let notifications = viewer
+            .as_ref()
+            .and_then(|viewer| NotificationDao::new(self.ctx.db).unread_count(viewer).ok())
+            .unwrap_or_default();
+        self.viewer = viewer;
+        self.state.set_account(session.map(|s| s.email), switched);
+        self.state.set_unread(unread);
+        self.state.set_unread_notifications(notifications);
+    }
+
+    /// Runs `cmd` and shows its output. Returns whether it succeeded.
+    async fn run_command(&mut self, cmd: Command) -> bool {
+        // Adding or finishing a task also returns it, but only a listing
+        // takes over the pane.
+        let listing = matches!(cmd, Command::TasksList { .. });
+        match commands::execute(self.ctx, cmd).await {
+            Ok(output) => {
+                match (output.feed, output.cached) {
+                    (Some(feed), Some(cached)) => {
+                        self.state.show_cached_feed(feed, cached);
+                        self.last_probe = Instant::now();
+                    }
+                    (Some(feed), None) => match output.search {
+                        Some(query) => self.state.show_search(feed, query),
+                        None => self.state.update_feed(feed),
+                    },
+                    _ => {}
+                }
+                if let Some(thread) = output.thread {
+                    self.state.show_thread(thread);
+                }
+                if let Some(card) = output.profile {
+                    self.state.show_profile(card);
+                }
+                if let Some(mailbox) = output.mailbox {
+                    self.state.show_mailbox(mailbox);
+                }
+                if let Some(notifications) = output.notifications {
+                    self.state.show_notifications(notifications);
+                }
+                if let Some(drafts) = output.drafts {
+                    self.state.show_drafts(drafts);
+                }
+                if let Some(tasks) = output.tasks.filter(|_| listing) {
+                    self.state.show_tasks(tasks);
+                }
+                if let Some(draft) = output.draft {
+                    self.save_draft();
+                    self.state.open_composer(draft);
+                }
+                if let Some(more) = output.more {
+                    self.state.append_feed(more);
+                }
+                if let Some(post) = output.post {
+                    self.state.replace_post(post);
+                }
+                self.state.push_status(output.message);
+                self.sync_account().await;
+                true
+            }
+            Err(err) => {
+                self.state
+                    .push_status(format!("Error: {}", error::user_message(&err)));
+                self.sync_account().await;
+                false
+            }
+        }
+    }
+
+    /// Moving down past the last loaded post pulls in the next older page.
+    async fn move_selection(&mut self, delta: isize) {
+        if self.state.move_selection(delta) {
+            self.run_command(Command::FeedMore { filter: None }).await;
+            self.state.move_selection(1);
+        }
+    }
+
+    /// Runs a per-post key action on the selected post.
+    async fn post_action(&mut self, key: char) {
+        let Some(post) = self.state.selected_post() else {
+            return;
+        };
+        let post_id = post.id.to_string();
+        match key {
+            'l' if post.liked => {
+                self.run_command(Command::Unlike { post_id }).await;
+            }
+            'l' => {
+                self.run_command(Command::Like { post_id }).await;
+            }
+            'r' => self
+                .state
+                .start_editing_with(&format!("/reply {} \"", post_id)),
+            'o' => {
+                self.run_command(Command::Thread { post_id }).await;
+            }
+            'y' => match copy_to_clipboard(&post_id) {
+                Ok(()) => self.state.push_status(format!("Copied {}", post_id)),
+                Err(err) => self
+                    .state
+                    .push_status(format!("Error: copy failed: {}", err)),
+            },
+            _ => {}
+        }
+    }
+
     async fn handle_key(&mut self, key: KeyEvent) -> Result<bool> {
         if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
             return Ok(true);
         }
+        if self.state.composer().is_some() {
+            self.compose_key(key).await;
+            return Ok(false);
+        }
+        if self.state.is_editing() {
+            self.edit_key(key).await;
+            return Ok(false);
+        }
         match key.code {
             KeyCode::Esc => {
                 self.state.toggle_input_mode();
             }
-            KeyCode::Char('/') if !self.state.is_editing() => {
-                self.state.start_editing();
-                self.state.push_char('/');
+            KeyCode::Char('/') => self.state.start_editing_with("/"),
+            KeyCode::Char('n') => self.state.show_incoming(),
+            KeyCode::Char('m') => self.state.toggle_pane(),
+            KeyCode::Char('c') => {
+                self.run_command(Command::Compose {
+                    text: None,
+                    audience: AudienceScope::default(),
+                })
+                .await;
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1).await,
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1).await,
+            KeyCode::PageUp => {
+                self.move_selection(-(self.state.feed_page() as isize))
+                    .await
             }
+            KeyCode::PageDown => self.move_selection(self.state.feed_page() as isize).await,
+            KeyCode::Home | KeyCode::Char('g') => self.state.select_first(),
+            KeyCode::End | KeyCode::Char('G') => self.state.select_last(),
+            KeyCode::Char(c @ ('l' | 'r' | 'o' | 'y')) => self.post_action(c).await,
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Keys in compose mode. Enter starts a new line; Ctrl-S sends.
+    async fn compose_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let Some(composer) = self.state.composer() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => {
+                let saved = self.save_draft();
+                self.state.close_composer();
+                self.state.push_status(match saved {
+                    Some(id) => format!("Kept as draft #{}, /drafts open {} to continue", id, id),
+                    None => "Discarded the empty post".to_string(),
+                });
+            }
+            KeyCode::Char('s') if ctrl => self.send_composed().await,
+            KeyCode::Char('e') if ctrl => {
+                if let Err(err) = self.edit_externally() {
+                    self.state.push_status(format!("Error: {:#}", err));
+                }
+            }
+            KeyCode::Tab => composer.cycle_audience(),
+            KeyCode::Enter => composer.newline(),
+            KeyCode::Backspace => composer.backspace(),
+            KeyCode::Delete => composer.delete(),
+            KeyCode::Left => composer.left(),
+            KeyCode::Right => composer.right(),
+            KeyCode::Up => composer.up(),
+            KeyCode::Down => composer.down(),
+            KeyCode::Home => composer.home(),
+            KeyCode::End => composer.end(),
+            KeyCode::Char(c) if !ctrl => composer.insert(c),
+            _ => {}
+        }
+    }
+
+    /// Saves the composer's text as a draft and posts it; the composer
+    /// stays open if that fails.
+    async fn send_composed(&mut self) {
+        let Some(id) = self.save_draft() else {
+            self.state.push_status("Nothing to send".to_string());
+            return;
+        };
+        if self.run_command(Command::DraftSend { id }).await {
+            self.state.close_composer();
+        }
+    }
+
+    /// Saves the composer's text as a draft; emptying the composer removes
+    /// its draft instead. Returns the id of the draft kept, if any.
+    fn save_draft(&mut self) -> Option<i64> {
+        let composer = self.state.composer()?;
+        let dao = DraftDao::new(self.ctx.db);
+        let user_id = self.viewer.as_deref().unwrap_or_default();
+        if composer.is_empty() {
+            if let Some(id) = composer.draft_id.take() {
+                if let Err(err) = dao.delete(user_id, id) {
+                    tracing::warn!(?err, "failed to remove empty draft");
+                }
+            }
+            return None;
+        }
+        match dao.save(user_id, &composer.draft()) {
+            Ok(id) => {
+                composer.draft_id = Some(id);
+                Some(id)
+            }
+            Err(err) => {
+                tracing::warn!(?err, "failed to save draft");
+                None
+            }
+        }
+    }
+
+    fn autosave_draft(&mut self) {
+        if self.last_draft_save.elapsed() < DRAFT_AUTOSAVE_INTERVAL {
+            return;
+        }
+        if self.state.composer().is_some_and(|c| c.take_dirty()) {
+            self.save_draft();
+            self.last_draft_save = Instant::now();
+        }
+    }
+
+    /// Hands the composer's text to `$VISUAL` or `$EDITOR` (else `vi`) in a
+    /// temp file and takes back what was saved there.
+    fn edit_externally(&mut self) -> Result<()> {
+        let Some(text) = self.state.composer().map(|c| c.text()) else {
+            return Ok(());
+        };
+        let path = env::temp_dir().join(format!("xcli-draft-{}.txt", Uuid::new_v4()));
+        fs::write(&path, text)?;
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_string());
+        let mut words = editor.split_whitespace();
+        let program = words.next().unwrap_or("vi");
+        disable_raw_mode()?;
+        let status = process::Command::new(program)
+            .args(words)
+            .arg(&path)
+            .status();
+        enable_raw_mode()?;
+        self.terminal.clear()?;
+        let edited = match status {
+            Ok(status) if status.success() => {
+                fs::read_to_string(&path).context("reading the edited draft")
+            }
+            Ok(status) => Err(anyhow!("{} exited with {}", program, status)),
+            Err(err) => Err(anyhow!(err).context(format!("failed to start {}", program))),
+        };
+        fs::remove_file(&path).ok();
+        let edited = edited?;
+        if let Some(composer) = self.state.composer() {
+            composer.set_text(edited.trim_end_matches('\n'));
+        }
+        Ok(())
+    }
+
+    /// Keys in command mode: line editing, history recall and search, and
+    /// tab completion.
+    async fn edit_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let editor = self.state.editor();
+        if editor.search_query().is_some() {
+            match key.code {
+                KeyCode::Char('r') if ctrl => return editor.search_older(),
+                KeyCode::Char('g') if ctrl => return editor.cancel_search(),
+                KeyCode::Char(c) if !ctrl && !alt => return editor.search_push(c),
+                KeyCode::Backspace => return editor.search_backspace(),
+                KeyCode::Esc | KeyCode::Enter => {}
+                // Any other key keeps the match and edits it.
+                _ => editor.accept_search(),
+            }
+        }
+        match key.code {
+            KeyCode::Esc => self.state.toggle_input_mode(),
             KeyCode::Enter => {
                 if let Some(command) = self.state.take_command() {
+                    match HistoryDao::new(self.ctx.db).push(&command, OffsetDateTime::now_utc()) {
+                        Ok(true) => self.state.editor().remember(&command),
+                        Ok(false) => {}
+                        Err(err) => tracing::warn!(?err, "failed to save command history"),
+                    }
                     match commands::parse_command(&command) {
-                        Ok(cmd) => match commands::execute(self.ctx, cmd).await {
-                            Ok(output) => {
-                                if let Some(feed) = output.feed {
-                                    self.state.update_feed(feed);
-                                }
-                                self.state.push_status(output.message);
-                            }
-                            Err(err) => self.state.push_status(format!("Error: {}", err)),
-                        },
-                        Err(err) => self.state.push_status(format!("Parse error: {}", err)),
+                        Ok(cmd) => {
+                            self.run_command(cmd).await;
+                        }
+                        Err(err) => self.state.reject_command(&command, &err),
                     }
                 }
             }
-            KeyCode::Backspace => {
-                self.state.backspace();
-            }
-            KeyCode::Char(c) => {
-                if self.state.is_editing() {
-                    self.state.push_char(c);
+            KeyCode::Tab => self.complete(),
+            KeyCode::Char('r') if ctrl => editor.search_older(),
+            KeyCode::Char('a') if ctrl => editor.home(),
+            KeyCode::Char('e') if ctrl => editor.end(),
+            KeyCode::Char('w') if ctrl => editor.delete_word(),
+            KeyCode::Char('u') if ctrl => editor.delete_to_start(),
+            KeyCode::Char('k') if ctrl => editor.delete_to_end(),
+            KeyCode::Char('b') if alt => editor.word_left(),
+            KeyCode::Char('f') if alt => editor.word_right(),
+            KeyCode::Backspace if alt || ctrl => editor.delete_word(),
+            KeyCode::Left if ctrl || alt => editor.word_left(),
+            KeyCode::Right if ctrl || alt => editor.word_right(),
+            KeyCode::Left => editor.left(),
+            KeyCode::Right => editor.right(),
+            KeyCode::Home => editor.home(),
+            KeyCode::End => editor.end(),
+            KeyCode::Backspace => editor.backspace(),
+            KeyCode::Delete => editor.delete(),
+            KeyCode::Up => editor.history_prev(),
+            KeyCode::Down => editor.history_next(),
+            KeyCode::Char(c) if !ctrl => editor.insert(c),
+            _ => {}
+        }
+    }
+
+    /// Completes the word before the cursor; several candidates are listed
+    /// in the status panel.
+    fn complete(&mut self) {
+        let editor = self.state.editor();
+        let line = editor.text()[..editor.cursor()].to_string();
+        let viewer = self.viewer.as_deref().unwrap_or_default();
+        match complete::complete(self.ctx.db, viewer, &line) {
+            Ok(completion) => {
+                self.state
+                    .editor()
+                    .complete(completion.start, &completion.candidates);
+                if completion.candidates.len() > 1 {
+                    self.state.push_status(completion.candidates.join("  "));
                 }
             }
-            _ => {}
+            Err(err) => tracing::warn!(?err, "completion failed"),
         }
-        Ok(false)
     }
 }
+
+/// Puts `text` on the clipboard with an OSC 52 escape, which terminals
+/// forward to the system clipboard, over SSH too.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
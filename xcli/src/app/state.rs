@@ -1,24 +1,60 @@
+use std::ops::Range;
+
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::Frame;
 
-use crate::domain::Post;
-use crate::ui;
+use time::OffsetDateTime;
+
+use crate::commands::{CachedFeed, ParseError};
+use crate::domain::{Draft, Mailbox, Notification, Post, ProfileCard, SearchQuery, Task, Thread};
+use crate::ui::{self, Composer, FeedView, LineEditor, Pane};
 
 pub struct AppState {
-    input: String,
+    editor: LineEditor,
     editing: bool,
+    /// The last line that failed to parse and the bytes to underline, while
+    /// it is still in the editor unchanged.
+    rejected: Option<(String, Range<usize>)>,
     status: Vec<String>,
     feed: Vec<Post>,
+    cached_feed: Option<CachedFeed>,
+    feed_list: ui::FeedList,
+    feed_exhausted: bool,
+    incoming: Vec<Post>,
+    /// Whether the feed panel shows a feed, search results or a thread.
+    view: FeedView,
+    /// Email of the active account, shown atop the status panel.
+    account: Option<String>,
+    /// Last inbox, conversation or notification list loaded.
+    pane: Option<Pane>,
+    show_pane: bool,
+    unread: i64,
+    unread_notifications: i64,
+    /// Open while compose mode is on.
+    composer: Option<Composer>,
     size: Rect,
 }
 
 impl AppState {
-    pub fn new() -> Self {
+    /// `history` seeds Up/Down recall and Ctrl-R, oldest first.
+    pub fn new(history: Vec<String>) -> Self {
         Self {
-            input: String::new(),
+            editor: LineEditor::new(history),
             editing: false,
+            rejected: None,
             status: vec!["Press / to enter command mode".to_string()],
             feed: Vec::new(),
+            cached_feed: None,
+            feed_list: ui::FeedList::default(),
+            feed_exhausted: false,
+            incoming: Vec::new(),
+            view: FeedView::Feed,
+            account: None,
+            pane: None,
+            show_pane: false,
+            unread: 0,
+            unread_notifications: 0,
+            composer: None,
             size: Rect::default(),
         }
     }
@@ -41,11 +77,47 @@ impl AppState {
             )
             .split(chunks[0]);
 
-        let nav = ui::nav_panel();
+        let nav = ui::nav_panel(self.unread_notifications);
         f.render_widget(nav, main_chunks[0]);
 
-        let feed = ui::feed_panel(&self.feed);
-        f.render_widget(feed, main_chunks[1]);
+        // The composer, or else the lower pane, takes the bottom of the
+        // feed column.
+        let feed_area = match (&self.composer, &self.pane) {
+            (Some(composer), _) => {
+                let split = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                    .split(main_chunks[1]);
+                let area = split[1];
+                let (panel, (row, column)) = ui::compose_panel(composer, area.width, area.height);
+                f.render_widget(panel, area);
+                f.set_cursor(area.x + 1 + column, area.y + 1 + row);
+                split[0]
+            }
+            (None, Some(pane)) if self.show_pane => {
+                let split = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+                    .split(main_chunks[1]);
+                f.render_widget(ui::pane_panel(pane, split[1].height), split[1]);
+                split[0]
+            }
+            _ => main_chunks[1],
+        };
+
+        let offline = self
+            .cached_feed
+            .as_ref()
+            .map(|cached| offline_label(cached.synced_at));
+        let items = ui::feed_items(
+            &self.feed,
+            &self.view,
+            feed_area.width.saturating_sub(2 + ui::SELECTION_WIDTH),
+        );
+        self.feed_list
+            .measure(&items, feed_area.height.saturating_sub(2) as usize);
+        let feed = ui::feed_panel(items, offline.as_deref(), self.incoming.len(), &self.view);
+        f.render_stateful_widget(feed, feed_area, &mut self.feed_list.state);
 
         let status_lines = self
             .status
@@ -54,42 +126,64 @@ impl AppState {
             .take(10)
             .cloned()
             .collect::<Vec<_>>();
-        let status = ui::status_panel(status_lines);
+        let status = ui::status_panel(self.account.as_deref(), self.unread, status_lines);
         f.render_widget(status, main_chunks[2]);
 
-        let input = ui::command_panel(&self.input, self.editing);
+        let error = self
+            .rejected
+            .as_ref()
+            .filter(|(line, _)| line == self.editor.text())
+            .map(|(_, span)| span.clone());
+        let (input, column) = ui::command_panel(&self.editor, self.editing, error, chunks[1].width);
         f.render_widget(input, chunks[1]);
+        if self.editing {
+            f.set_cursor(chunks[1].x + 1 + column, chunks[1].y + 1);
+        }
     }
 
+    /// Esc leaves a history search first, then command mode.
     pub fn toggle_input_mode(&mut self) {
+        if self.editor.search_query().is_some() {
+            self.editor.cancel_search();
+            return;
+        }
         self.editing = !self.editing;
         if !self.editing {
-            self.input.clear();
+            self.editor.clear();
         }
     }
 
     pub fn start_editing(&mut self) {
         self.editing = true;
-        self.input.clear();
+        self.editor.clear();
     }
 
-    pub fn push_char(&mut self, c: char) {
-        self.input.push(c);
+    /// Opens the command line with `text` already typed.
+    pub fn start_editing_with(&mut self, text: &str) {
+        self.start_editing();
+        self.editor.set_text(text);
     }
 
-    pub fn backspace(&mut self) {
-        self.input.pop();
+    pub fn editor(&mut self) -> &mut LineEditor {
+        &mut self.editor
     }
 
     pub fn take_command(&mut self) -> Option<String> {
-        if self.input.trim().is_empty() {
-            self.input.clear();
+        self.editor.accept_search();
+        if self.editor.text().trim().is_empty() {
+            self.editor.clear();
             return None;
         }
-        let cmd = self.input.clone();
-        self.input.clear();
         self.editing = false;
-        Some(cmd)
+        Some(self.editor.take())
+    }
+
+    /// Reports a parse error and puts the line back in the editor with the
+    /// offending part underlined.
+    pub fn reject_command(&mut self, line: &str, err: &ParseError) {
+        self.push_status(format!("Parse error: {}", err));
+        self.start_editing_with(line);
+        self.rejected = Some((line.to_string(), err.span()));
     }
 
     pub fn push_status(&mut self, msg: String) {
@@ -106,7 +200,188 @@ impl AppState {
         self.editing
     }
 
+    /// Switching accounts drops everything shown for the previous one.
+    pub fn set_account(&mut self, email: Option<String>, switched: bool) {
+        self.account = email;
+        if switched {
+            self.update_feed(Vec::new());
+            self.incoming.clear();
+            self.pane = None;
+            self.unread = 0;
+            self.unread_notifications = 0;
+        }
+    }
+
+    /// Shows the inbox or a conversation in the messages pane.
+    pub fn show_mailbox(&mut self, mailbox: Mailbox) {
+        self.pane = Some(Pane::Mailbox(mailbox));
+        self.show_pane = true;
+    }
+
+    /// Lists notifications in the lower pane; the badge follows their
+    /// read state.
+    pub fn show_notifications(&mut self, notifications: Vec<Notification>) {
+        self.unread_notifications = notifications.iter().filter(|n| n.is_unread()).count() as i64;
+        self.pane = Some(Pane::Notifications(notifications));
+        self.show_pane = true;
+    }
+
+    pub fn show_drafts(&mut self, drafts: Vec<Draft>) {
+        self.pane = Some(Pane::Drafts(drafts));
+        self.show_pane = true;
+    }
+
+    pub fn show_tasks(&mut self, tasks: Vec<Task>) {
+        self.pane = Some(Pane::Tasks(tasks));
+        self.show_pane = true;
+    }
+
+    /// Enters compose mode on `draft`, replacing any open composer.
+    pub fn open_composer(&mut self, draft: Draft) {
+        self.editing = false;
+        self.composer = Some(Composer::new(draft));
+    }
+
+    pub fn composer(&mut self) -> Option<&mut Composer> {
+        self.composer.as_mut()
+    }
+
+    pub fn close_composer(&mut self) -> Option<Composer> {
+        self.composer.take()
+    }
+
+    pub fn toggle_pane(&mut self) {
+        self.show_pane = !self.show_pane;
+    }
+
+    pub fn set_unread(&mut self, unread: i64) {
+        self.unread = unread;
+    }
+
+    pub fn set_unread_notifications(&mut self, unread: i64) {
+        self.unread_notifications = unread;
+    }
+
     pub fn update_feed(&mut self, posts: Vec<Post>) {
         self.feed = posts;
+        self.cached_feed = None;
+        self.view = FeedView::Feed;
+        self.feed_list.reset(self.feed.len());
+        self.feed_exhausted = false;
+    }
+
+    /// Shows `/search` results; there are no older pages to scroll into.
+    pub fn show_search(&mut self, posts: Vec<Post>, query: SearchQuery) {
+        self.update_feed(posts);
+        self.view = FeedView::Search(query);
+        self.feed_exhausted = true;
+    }
+
+    /// Shows a conversation, each reply indented under its parent.
+    pub fn show_thread(&mut self, thread: Thread) {
+        self.update_feed(thread.posts());
+        self.view = FeedView::Thread {
+            root: thread.root,
+            depths: thread.depths(),
+        };
+        self.feed_exhausted = true;
+    }
+
+    /// Shows a user's recent posts with their profile in the title.
+    pub fn show_profile(&mut self, card: ProfileCard) {
+        self.update_feed(card.posts);
+        self.view = FeedView::Profile {
+            profile: card.profile,
+            stats: card.stats,
+        };
+        self.feed_exhausted = true;
+    }
+
+    /// Shows posts served from the local cache while Supabase is unreachable.
+    pub fn show_cached_feed(&mut self, posts: Vec<Post>, cached: CachedFeed) {
+        self.update_feed(posts);
+        self.cached_feed = Some(cached);
+    }
+
+    /// Appends an older page; an empty page means the feed is exhausted.
+    pub fn append_feed(&mut self, posts: Vec<Post>) {
+        if posts.is_empty() {
+            self.feed_exhausted = true;
+        }
+        self.feed.extend(posts);
+        self.feed_list.clamp(self.feed.len());
+    }
+
+    /// Moves the selection by `delta` posts. Returns true when the user
+    /// moves down past the last loaded post and an older page should be
+    /// fetched.
+    pub fn move_selection(&mut self, delta: isize) -> bool {
+        self.feed_list.move_by(delta, self.feed.len()) && !self.feed_exhausted
+    }
+
+    pub fn select_first(&mut self) {
+        self.feed_list.first(self.feed.len());
+    }
+
+    pub fn select_last(&mut self) {
+        self.feed_list.last(self.feed.len());
+    }
+
+    pub fn selected_post(&self) -> Option<&Post> {
+        self.feed_list
+            .selected()
+            .and_then(|index| self.feed.get(index))
+    }
+
+    /// Holds a post pushed over Realtime until the user asks to see it, so
+    /// the feed doesn't shift under them.
+    pub fn push_incoming(&mut self, post: Post) {
+        let known = self
+            .feed
+            .iter()
+            .chain(&self.incoming)
+            .any(|p| p.id == post.id);
+        if !known {
+            self.incoming.push(post);
+        }
+    }
+
+    /// Moves held Realtime posts to the top of the feed, newest first.
+    pub fn show_incoming(&mut self) {
+        if self.incoming.is_empty() || !matches!(self.view, FeedView::Feed) {
+            return;
+        }
+        let mut posts = std::mem::take(&mut self.incoming);
+        posts.sort_by_key(|post| std::cmp::Reverse(post.created_at));
+        posts.append(&mut self.feed);
+        self.feed = posts;
+        self.feed_list.reset(self.feed.len());
+    }
+
+    /// Posts on one screen of the feed, for PgUp/PgDn.
+    pub fn feed_page(&self) -> usize {
+        self.feed_list.page()
+    }
+
+    pub fn cached_feed(&self) -> Option<&CachedFeed> {
+        self.cached_feed.as_ref()
+    }
+
+    pub fn replace_post(&mut self, post: Post) {
+        if let Some(existing) = self.feed.iter_mut().find(|p| p.id == post.id) {
+            *existing = post;
+        }
+    }
+}
+
+fn offline_label(synced_at: Option<OffsetDateTime>) -> String {
+    let Some(synced_at) = synced_at else {
+        return "offline — never synced".to_string();
+    };
+    let minutes = (OffsetDateTime::now_utc() - synced_at).whole_minutes();
+    match minutes {
+        m if m < 1 => "offline — cached just now".to_string(),
+        m if m < 120 => format!("offline — cached {} minutes ago", m),
+        m => format!("offline — cached {} hours ago", m / 60),
     }
 }
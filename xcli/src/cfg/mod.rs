@@ -5,10 +5,15 @@ use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
 
+use crate::supabase::secrets::BackendKind;
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct AppConfig {
     pub default_feed: Option<String>,
     pub supabase_project: Option<String>,
+    /// `auto` (default), `keyring` or `file`.
+    #[serde(default)]
+    pub session_backend: BackendKind,
 }
 
 impl Default for AppConfig {
@@ -16,6 +21,7 @@ impl Default for AppConfig {
         Self {
             default_feed: Some("global".into()),
             supabase_project: None,
+            session_backend: BackendKind::default(),
         }
     }
 }
@@ -0,0 +1,449 @@
+pub mod output;
+pub mod script;
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use time::OffsetDateTime;
+
+use crate::commands::{self, Command, CommandContext, CommandOutput};
+use crate::domain::schedule::{parse_at, parse_in};
+use crate::domain::{
+    parse_due, AudienceScope, FeedFilter, NewTask, SearchQuery, TaskChanges, TaskPriority,
+    TaskQuery, TaskSort, TaskStatus, FEED_PAGE_SIZE,
+};
+
+pub use crate::commands::Invalid;
+pub use output::{render, render_error, Failure, OutputFormat};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Terminal Twitter client powered by Supabase")]
+pub struct Cli {
+    /// Run a slash command in non-interactive mode
+    #[arg(long)]
+    pub command: Option<String>,
+
+    /// How one-shot commands print their results
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    #[command(subcommand)]
+    pub action: Option<Action>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Action {
+    /// Inspect or move the local SQLite schema
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+    /// Publish scheduled posts that are due, then exit (e.g. from cron)
+    FlushScheduled,
+    /// Run a file of slash commands, one per line (`-` reads stdin)
+    Run {
+        path: String,
+        /// Only parse and validate the script
+        #[arg(long)]
+        dry_run: bool,
+        /// Stop at the first failing command, like `set -e`
+        #[arg(short = 'e', long)]
+        stop_on_error: bool,
+    },
+    #[command(flatten)]
+    Client(ClientAction),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DbAction {
+    /// List migrations and whether they have been applied
+    Status,
+    /// Apply or revert migrations up to a version (latest by default)
+    Migrate {
+        #[arg(long)]
+        to: Option<u32>,
+    },
+}
+
+/// One-shot commands; each maps onto a `commands::Command`.
+#[derive(Subcommand, Debug)]
+pub enum ClientAction {
+    /// Sign in with email and password (prompted for when left out)
+    Login {
+        #[arg(long)]
+        email: String,
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Sign in through the browser with a passkey
+    Passkey,
+    /// Sign out of the active account
+    Logout,
+    /// Print the signed-in account
+    Whoami,
+    /// Publish a post now, or later with --at or --in
+    Post {
+        text: String,
+        #[arg(long, default_value_t = AudienceScope::Public)]
+        audience: AudienceScope,
+        /// Local time such as 2026-10-19T09:00, or RFC 3339
+        #[arg(long, conflicts_with = "delay")]
+        at: Option<String>,
+        /// Delay such as 45m, 2h or 1d12h
+        #[arg(long = "in", value_name = "DELAY")]
+        delay: Option<String>,
+    },
+    /// Reply to a post
+    Reply {
+        post_id: String,
+        text: String,
+        /// Defaults to the audience of the post being answered
+        #[arg(long)]
+        audience: Option<AudienceScope>,
+    },
+    /// Print a feed, fetching further pages until --limit posts
+    Feed {
+        /// global, following, me, mentions or #tag
+        #[arg(long, default_value = "global")]
+        filter: FeedFilter,
+        #[arg(long, default_value_t = FEED_PAGE_SIZE)]
+        limit: usize,
+    },
+    /// Print the conversation around a post
+    Thread {
+        post_id: String,
+    },
+    Like {
+        post_id: String,
+    },
+    Unlike {
+        post_id: String,
+    },
+    Follow {
+        handle: String,
+    },
+    Unfollow {
+        handle: String,
+    },
+    /// Print a profile and its recent posts
+    Profile {
+        handle: String,
+    },
+    /// Search the user directory
+    Who {
+        query: String,
+    },
+    /// Search posts
+    Search {
+        text: String,
+        #[arg(long)]
+        author: Option<String>,
+        /// Only posts on or after this date, e.g. 2026-01-01
+        #[arg(long)]
+        since: Option<String>,
+    },
+    /// Send a direct message, or print the conversation without text
+    Dm {
+        handle: String,
+        text: Option<String>,
+    },
+    /// List conversations
+    Inbox,
+    /// Fetch and list notifications
+    Notifications,
+    /// Manage local tasks
+    Tasks {
+        #[command(subcommand)]
+        action: TasksAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TasksAction {
+    /// Add a task; prints its id
+    Add {
+        title: String,
+        #[arg(default_value = "")]
+        description: String,
+        /// low, normal, high or urgent
+        #[arg(long, default_value = "normal")]
+        priority: TaskPriority,
+        /// Delay such as 3d, a date such as 2026-11-01, or a local time
+        #[arg(long)]
+        due: Option<String>,
+        /// May be repeated
+        #[arg(long)]
+        tag: Vec<String>,
+    },
+    /// List tasks
+    Ls {
+        /// open, in_progress, done or cancelled
+        #[arg(long)]
+        status: Option<TaskStatus>,
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only tasks of this priority or higher
+        #[arg(long)]
+        priority: Option<TaskPriority>,
+        /// Only unfinished tasks due before this, e.g. 7d
+        #[arg(long)]
+        due: Option<String>,
+        /// created, due, priority or updated
+        #[arg(long, default_value = "created")]
+        sort: TaskSort,
+    },
+    /// Change a task's title, description, priority or due time
+    Edit {
+        id: i64,
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+        #[arg(long)]
+        priority: Option<TaskPriority>,
+        /// As for add, or "none" to clear it
+        #[arg(long)]
+        due: Option<String>,
+    },
+    /// Mark a task in progress
+    Start { id: i64 },
+    /// Complete a task and write its report
+    Done { id: i64 },
+    /// Cancel a task
+    Cancel { id: i64 },
+    /// Put a done or cancelled task back to open
+    Reopen { id: i64 },
+    /// Delete a task
+    Rm { id: i64 },
+    Tag {
+        id: i64,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    Untag {
+        id: i64,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+}
+
+impl ClientAction {
+    /// How many posts `feed` should print.
+    pub fn limit(&self) -> Option<usize> {
+        match self {
+            ClientAction::Feed { limit, .. } => Some(*limit),
+            _ => None,
+        }
+    }
+
+    /// The slash command this subcommand stands for. Arguments that don't
+    /// parse come back as `Invalid`.
+    pub fn to_command(self) -> Result<Command> {
+        let invalid = |err: anyhow::Error| anyhow::Error::from(Invalid(err.to_string()));
+        let post_id = |id: String| {
+            uuid::Uuid::parse_str(&id)
+                .map(|id| id.to_string())
+                .map_err(|_| invalid(anyhow!("invalid post id {}", id)))
+        };
+        let handle = |handle: String| handle.trim_start_matches('@').to_string();
+        let due = |at: &str| parse_due(at, OffsetDateTime::now_utc()).map_err(invalid);
+        Ok(match self {
+            ClientAction::Login { email, password } => {
+                let password = match password {
+                    Some(password) => password,
+                    None => rpassword::prompt_password("Password: ")?,
+                };
+                Command::Login { email, password }
+            }
+            ClientAction::Passkey => Command::Passkey,
+            ClientAction::Logout => Command::Logout,
+            ClientAction::Whoami => Command::WhoAmI,
+            ClientAction::Post {
+                text,
+                audience,
+                at,
+                delay,
+            } => {
+                let send_at = match (at, delay) {
+                    (Some(at), _) => Some(parse_at(&at).map_err(invalid)?),
+                    (None, Some(delay)) => {
+                        Some(parse_in(&delay, OffsetDateTime::now_utc()).map_err(invalid)?)
+                    }
+                    (None, None) => None,
+                };
+                match send_at {
+                    Some(send_at) => Command::SchedulePost {
+                        text,
+                        audience,
+                        send_at,
+                    },
+                    None => Command::Post { text, audience },
+                }
+            }
+            ClientAction::Reply {
+                post_id: id,
+                text,
+                audience,
+            } => Command::Reply {
+                post_id: post_id(id)?,
+                text,
+                audience,
+            },
+            ClientAction::Feed { filter, .. } => Command::Feed { filter },
+            ClientAction::Thread { post_id: id } => Command::Thread {
+                post_id: post_id(id)?,
+            },
+            ClientAction::Like { post_id: id } => Command::Like {
+                post_id: post_id(id)?,
+            },
+            ClientAction::Unlike { post_id: id } => Command::Unlike {
+                post_id: post_id(id)?,
+            },
+            ClientAction::Follow { handle: h } => Command::Follow { handle: handle(h) },
+            ClientAction::Unfollow { handle: h } => Command::Unfollow { handle: handle(h) },
+            ClientAction::Profile { handle: h } => Command::Profile { handle: handle(h) },
+            ClientAction::Who { query } => Command::Who { query },
+            ClientAction::Search {
+                text,
+                author,
+                since,
+            } => Command::Search {
+                query: SearchQuery {
+                    text,
+                    author: author.map(handle),
+                    since: since
+                        .map(|date| {
+                            SearchQuery::parse_since(&date).map_err(|_| {
+                                invalid(anyhow!("--since expects a date like 2026-01-01"))
+                            })
+                        })
+                        .transpose()?,
+                },
+            },
+            ClientAction::Dm { handle: h, text } => Command::Dm {
+                handle: handle(h),
+                text,
+            },
+            ClientAction::Inbox => Command::Inbox,
+            ClientAction::Notifications => Command::Notifications,
+            ClientAction::Tasks { action } => match action {
+                TasksAction::Add {
+                    title,
+                    description,
+                    priority,
+                    due: due_at,
+                    tag,
+                } => Command::TasksAdd {
+                    task: NewTask {
+                        title,
+                        description,
+                        priority,
+                        due_at: due_at.map(|at| due(&at)).transpose()?,
+                        tags: tag,
+                    },
+                },
+                TasksAction::Ls {
+                    status,
+                    tag,
+                    priority,
+                    due: due_before,
+                    sort,
+                } => Command::TasksList {
+                    query: TaskQuery {
+                        status,
+                        tag,
+                        priority,
+                        due_before: due_before.map(|at| due(&at)).transpose()?,
+                        sort,
+                    },
+                },
+                TasksAction::Edit {
+                    id,
+                    title,
+                    description,
+                    priority,
+                    due: due_at,
+                } => {
+                    let changes = TaskChanges {
+                        title,
+                        description,
+                        priority,
+                        due_at: due_at
+                            .map(|at| match at.as_str() {
+                                "none" => Ok(None),
+                                _ => due(&at).map(Some),
+                            })
+                            .transpose()?,
+                    };
+                    if changes.is_empty() {
+                        return Err(invalid(anyhow!(
+                            "give --title, --description, --priority or --due"
+                        )));
+                    }
+                    Command::TasksEdit { id, changes }
+                }
+                TasksAction::Start { id } => Command::TasksSetStatus {
+                    id,
+                    status: TaskStatus::InProgress,
+                },
+                TasksAction::Done { id } => Command::TasksDone { id },
+                TasksAction::Cancel { id } => Command::TasksSetStatus {
+                    id,
+                    status: TaskStatus::Cancelled,
+                },
+                TasksAction::Reopen { id } => Command::TasksSetStatus {
+                    id,
+                    status: TaskStatus::Open,
+                },
+                TasksAction::Rm { id } => Command::TasksDelete { id },
+                TasksAction::Tag { id, tags } => Command::TasksTag { id, tags },
+                TasksAction::Untag { id, tags } => Command::TasksUntag { id, tags },
+            },
+        })
+    }
+}
+
+/// Runs a one-shot command. With a `limit`, `Command::Feed` keeps loading
+/// older pages until it has that many posts or runs out.
+pub async fn execute(
+    ctx: CommandContext<'_>,
+    command: Command,
+    limit: Option<usize>,
+) -> Result<CommandOutput> {
+    match (command, limit) {
+        (Command::Feed { filter }, Some(limit)) => feed(ctx, filter, limit).await,
+        (command, _) => commands::execute(ctx, command).await,
+    }
+}
+
+async fn feed(ctx: CommandContext<'_>, filter: FeedFilter, limit: usize) -> Result<CommandOutput> {
+    let mut output = commands::execute(
+        ctx,
+        Command::Feed {
+            filter: filter.clone(),
+        },
+    )
+    .await?;
+    let mut posts = output.feed.take().unwrap_or_default();
+    while posts.len() < limit {
+        let more = commands::execute(
+            ctx,
+            Command::FeedMore {
+                filter: Some(filter.clone()),
+            },
+        )
+        .await?
+        .more
+        .unwrap_or_default();
+        if more.is_empty() {
+            break;
+        }
+        posts.extend(more);
+    }
+    posts.truncate(limit);
+    output.message = match output.cached {
+        Some(_) => format!("Offline: showing {} cached {} posts", posts.len(), filter),
+        None => format!("Fetched {} {} posts", posts.len(), filter),
+    };
+    output.feed = Some(posts);
+    Ok(output)
+}
@@ -0,0 +1,176 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::commands::{CommandOutput, Invalid, ParseError};
+use crate::domain::{Post, Report, Task};
+use crate::supabase::{LoginRequired, SupabaseError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// The status message, then one line per post
+    #[default]
+    Text,
+    /// One JSON object with the message and any posts, tasks and reports
+    Json,
+    /// One JSON object per post, task or report, tagged with its `type`
+    Ndjson,
+}
+
+/// Why a one-shot command failed, as told to scripts by the exit status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    Other,
+    /// Bad syntax or arguments, or a request Supabase rejected. Matches
+    /// the status clap uses for usage errors.
+    Invalid,
+    /// Not signed in, the session was refused or a row-level security
+    /// policy said no.
+    Auth,
+    /// Supabase could not be reached or was unavailable.
+    Network,
+}
+
+impl Failure {
+    pub fn of(err: &anyhow::Error) -> Self {
+        if err
+            .chain()
+            .any(|cause| cause.is::<Invalid>() || cause.is::<ParseError>())
+        {
+            return Failure::Invalid;
+        }
+        if err.chain().any(|cause| cause.is::<LoginRequired>()) {
+            return Failure::Auth;
+        }
+        match SupabaseError::find(err) {
+            Some(SupabaseError::Unauthorized(_) | SupabaseError::Forbidden(_)) => Failure::Auth,
+            // Supabase Auth answers a wrong password with a 400.
+            Some(SupabaseError::Rejected(api))
+                if matches!(
+                    api.code.as_deref(),
+                    Some("invalid_grant" | "invalid_credentials")
+                ) =>
+            {
+                Failure::Auth
+            }
+            Some(SupabaseError::Rejected(_) | SupabaseError::Conflict(_)) => Failure::Invalid,
+            Some(error) if error.is_transient() => Failure::Network,
+            _ => Failure::Other,
+        }
+    }
+
+    pub fn code(self) -> u8 {
+        match self {
+            Failure::Other => 1,
+            Failure::Invalid => 2,
+            Failure::Auth => 3,
+            Failure::Network => 4,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Failure::Other => "error",
+            Failure::Invalid => "invalid",
+            Failure::Auth => "auth",
+            Failure::Network => "network",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    message: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    posts: Vec<&'a Post>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    tasks: &'a [Task],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    reports: &'a [Report],
+}
+
+#[derive(Serialize)]
+struct Record<'a, T> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(flatten)]
+    value: &'a T,
+}
+
+/// Formats `output` for stdout, without a trailing newline.
+pub fn render(output: &CommandOutput, format: OutputFormat) -> Result<String> {
+    let posts = posts(output);
+    let tasks = output.tasks.as_deref().unwrap_or_default();
+    let reports = output.reports.as_deref().unwrap_or_default();
+    Ok(match format {
+        OutputFormat::Text => {
+            let mut lines = vec![output.message.clone()];
+            lines.extend(posts.iter().map(|post| {
+                format!(
+                    "{}  @{}  {}",
+                    post.id,
+                    post.author_handle.as_deref().unwrap_or("?"),
+                    post.body.replace('\n', " ")
+                )
+            }));
+            lines.join("\n")
+        }
+        OutputFormat::Json => serde_json::to_string(&Document {
+            message: &output.message,
+            posts,
+            tasks,
+            reports,
+        })?,
+        OutputFormat::Ndjson => {
+            let mut lines = Vec::new();
+            for post in posts {
+                lines.push(record("post", post)?);
+            }
+            for task in tasks {
+                lines.push(record("task", task)?);
+            }
+            for report in reports {
+                lines.push(record("report", report)?);
+            }
+            if lines.is_empty() {
+                lines.push(
+                    serde_json::json!({"type": "message", "message": output.message}).to_string(),
+                );
+            }
+            lines.join("\n")
+        }
+    })
+}
+
+/// Formats a failure for stderr.
+pub fn render_error(err: &anyhow::Error, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Text => format!("error: {:#}", err),
+        OutputFormat::Json | OutputFormat::Ndjson => serde_json::json!({
+            "type": "error",
+            "kind": Failure::of(err).as_str(),
+            "message": format!("{:#}", err),
+        })
+        .to_string(),
+    }
+}
+
+fn record<T: Serialize>(kind: &'static str, value: &T) -> Result<String> {
+    Ok(serde_json::to_string(&Record { kind, value })?)
+}
+
+/// Every post the command produced: the feed or a page of it, a thread, a
+/// profile's posts or a single new or changed post.
+fn posts(output: &CommandOutput) -> Vec<&Post> {
+    let mut posts = Vec::new();
+    posts.extend(output.feed.iter().flatten());
+    posts.extend(output.more.iter().flatten());
+    if let Some(thread) = &output.thread {
+        posts.extend(thread.entries.iter().map(|entry| &entry.post));
+    }
+    if let Some(card) = &output.profile {
+        posts.extend(card.posts.iter());
+    }
+    posts.extend(output.post.iter());
+    posts
+}
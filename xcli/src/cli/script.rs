@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::commands::{self, CommandContext, CommandOutput};
+
+use super::Invalid;
+
+/// Variables scripts can use, each set from the output of the command that
+/// last produced it, with the stand-in a dry run substitutes.
+pub const VARIABLES: &[(&str, &str)] = &[
+    ("task_id", "0"),
+    ("post_id", "00000000-0000-0000-0000-000000000000"),
+    ("report_path", "reports/report.md"),
+    ("count", "0"),
+    ("message", "message"),
+];
+
+/// One meaningful line of a script: a slash command or a `set -e` / `set +e`
+/// directive. Blank lines and `#` comments are dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Command { line: usize, text: String },
+    StopOnError(bool),
+}
+
+pub fn parse_script(source: &str) -> Result<Vec<Step>> {
+    let mut steps = Vec::new();
+    for (index, raw) in source.lines().enumerate() {
+        let line = index + 1;
+        let text = raw.trim();
+        match text {
+            "" => {}
+            _ if text.starts_with('#') => {}
+            "set -e" => steps.push(Step::StopOnError(true)),
+            "set +e" => steps.push(Step::StopOnError(false)),
+            _ if text.starts_with('/') => steps.push(Step::Command {
+                line,
+                text: text.to_string(),
+            }),
+            _ => {
+                return Err(Invalid(format!(
+                    "line {}: expected a slash command, `set -e` or `set +e`",
+                    line
+                ))
+                .into())
+            }
+        }
+    }
+    Ok(steps)
+}
+
+/// Values captured from command output so far.
+#[derive(Debug, Default)]
+pub struct Variables(HashMap<&'static str, String>);
+
+impl Variables {
+    /// Remembers what `output` produced; variables it has no value for keep
+    /// their previous one.
+    pub fn record(&mut self, output: &CommandOutput) {
+        let posts = output.feed.as_ref().or(output.more.as_ref());
+        let tasks = output.tasks.as_deref().unwrap_or_default();
+        let mut set = |name, value: Option<String>| {
+            if let Some(value) = value {
+                self.0.insert(name, value);
+            }
+        };
+        set(
+            "message",
+            Some(output.message.lines().collect::<Vec<_>>().join(" ")),
+        );
+        set(
+            "task_id",
+            match tasks {
+                [task] => Some(task.id.to_string()),
+                _ => None,
+            },
+        );
+        set(
+            "post_id",
+            output
+                .post
+                .as_ref()
+                .or(posts.and_then(|posts| posts.first()))
+                .map(|post| post.id.to_string()),
+        );
+        set(
+            "report_path",
+            output
+                .reports
+                .as_ref()
+                .and_then(|reports| reports.first())
+                .map(|report| report.path.clone()),
+        );
+        set(
+            "count",
+            posts
+                .map(Vec::len)
+                .or(output.tasks.as_ref().map(Vec::len))
+                .map(|count| count.to_string()),
+        );
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+/// Replaces `$name` and `${name}` in `text`; `$$` is a literal `$`, as is a
+/// `$` not followed by a letter, `_` or `{`, e.g. in `costs $5`. `lookup`
+/// returns `None` for names that have no value. Quotes, backslashes and
+/// whitespace in a value are escaped, so it stays one argument, quoted or
+/// not.
+pub fn substitute(text: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('$') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+        let (name, after) = match rest.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced
+                    .find('}')
+                    .ok_or_else(|| anyhow!("unclosed ${{ in {}", text))?;
+                (&braced[..end], &braced[end + 1..])
+            }
+            None if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') => {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+            None => {
+                out.push('$');
+                continue;
+            }
+        };
+        if name.is_empty() {
+            out.push('$');
+            continue;
+        }
+        if !VARIABLES.iter().any(|(known, _)| *known == name) {
+            return Err(anyhow!(
+                "unknown variable ${}; scripts can use {}",
+                name,
+                VARIABLES
+                    .iter()
+                    .map(|(known, _)| format!("${}", known))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        let value =
+            lookup(name).ok_or_else(|| anyhow!("${} is not set by an earlier command", name))?;
+        for c in value.chars() {
+            if c == '"' || c == '\\' || c.is_whitespace() {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        rest = after;
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Parses every command with the dry-run stand-ins for variables, without
+/// running anything. Returns how many commands there are, or every problem
+/// found, one per line.
+pub fn check(steps: &[Step]) -> Result<usize> {
+    let stand_in = |name: &str| {
+        VARIABLES
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, value)| value.to_string())
+    };
+    let mut commands = 0;
+    let mut problems = Vec::new();
+    for step in steps {
+        let Step::Command { line, text } = step else {
+            continue;
+        };
+        commands += 1;
+        if let Err(err) =
+            substitute(text, stand_in).and_then(|text| Ok(commands::parse_command(&text)?))
+        {
+            problems.push(format!("line {}: {}", line, err));
+        }
+    }
+    if !problems.is_empty() {
+        return Err(Invalid(problems.join("\n")).into());
+    }
+    Ok(commands)
+}
+
+/// Runs the commands in order with one shared context, after checking them
+/// all. `report` sees each output, and each failure the script carries on
+/// past; errors name the script line. A failure stops the script while
+/// `set -e` (or `stop_on_error`) is in effect and is returned; otherwise the
+/// rest still runs and the first failure is returned at the end.
+pub async fn run(
+    ctx: CommandContext<'_>,
+    steps: &[Step],
+    mut stop_on_error: bool,
+    mut report: impl FnMut(&Result<CommandOutput>),
+) -> Result<()> {
+    check(steps)?;
+    let mut variables = Variables::default();
+    let mut failures = Vec::new();
+    for step in steps {
+        let (line, text) = match step {
+            Step::StopOnError(on) => {
+                stop_on_error = *on;
+                continue;
+            }
+            Step::Command { line, text } => (*line, text),
+        };
+        let result = match substitute(text, |name| variables.get(name).map(str::to_string)) {
+            Ok(text) => match commands::parse_command(&text) {
+                Ok(command) => commands::execute(ctx, command).await,
+                Err(err) => Err(err.into()),
+            },
+            Err(err) => Err(Invalid(err.to_string()).into()),
+        }
+        .with_context(|| format!("line {}: {}", line, text));
+        if result.is_ok() || !stop_on_error {
+            report(&result);
+        }
+        match result {
+            Ok(output) => variables.record(&output),
+            Err(err) => {
+                if stop_on_error {
+                    return Err(err);
+                }
+                failures.push(err);
+            }
+        }
+    }
+    let count = failures.len();
+    match failures.into_iter().next() {
+        None => Ok(()),
+        Some(first) => Err(first.context(format!(
+            "{} command{} failed",
+            count,
+            if count == 1 { "" } else { "s" }
+        ))),
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::Result;
+
+use crate::data::{cache::CacheDao, drafts::DraftDao, tasks::TaskDao, AppDatabase};
+use crate::domain::{AudienceScope, FeedFilter, TaskPriority, TaskStatus};
+
+use super::table::{self, COMMANDS};
+
+/// Most handles offered at once.
+const HANDLE_LIMIT: usize = 20;
+
+/// The word being completed, from byte `start` up to the cursor, and what
+/// could replace it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Completion {
+    pub start: usize,
+    pub candidates: Vec<String>,
+}
+
+/// Completes the last word of `line`, the command line up to the cursor:
+/// command names, subcommands, feed filters, audiences, cached handles, task
+/// ids, priorities, sort keys and tags. Draft ids are those of `viewer`.
+pub fn complete(db: &AppDatabase, viewer: &str, line: &str) -> Result<Completion> {
+    let start = line.rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+    let word = &line[start..];
+    let args = line[..start].split_whitespace().collect::<Vec<_>>();
+    let options = if args.is_empty() {
+        commands("/")
+    } else if word.starts_with("audience:") {
+        audiences()
+    } else if word.starts_with("priority:") {
+        prefixed(
+            "priority:",
+            TaskPriority::ALL.iter().map(TaskPriority::as_str),
+        )
+    } else if word.starts_with("sort:") {
+        prefixed(
+            "sort:",
+            ["created", "due", "priority", "updated"].into_iter(),
+        )
+    } else if word.starts_with("tag:") && args.first() == Some(&"/tasks") {
+        prefixed("tag:", TaskDao::new(db).tags()?.iter().map(String::as_str))
+    } else if let Some(prefix) = word.strip_prefix('@') {
+        handles(db, prefix)?
+    } else {
+        arguments(db, viewer, &args)?
+    };
+    Ok(Completion {
+        start,
+        candidates: options
+            .into_iter()
+            .filter(|option| option.starts_with(word))
+            .collect(),
+    })
+}
+
+fn arguments(db: &AppDatabase, viewer: &str, args: &[&str]) -> Result<Vec<String>> {
+    let words = |list: &[&str]| list.iter().map(|word| word.to_string()).collect();
+    let filters = || {
+        [
+            FeedFilter::Global,
+            FeedFilter::Following,
+            FeedFilter::Me,
+            FeedFilter::Mentions,
+        ]
+        .iter()
+        .map(FeedFilter::to_string)
+        .collect::<Vec<_>>()
+    };
+    Ok(match args {
+        ["/help"] => commands(""),
+        ["/tasks", "ls", ..] => TaskStatus::ALL
+            .iter()
+            .map(|status| status.as_str().to_string())
+            .collect(),
+        ["/tasks", sub @ ("start" | "done" | "cancel" | "reopen" | "edit" | "tag" | "untag" | "rm")] =>
+        {
+            // Reopening only makes sense for finished tasks, the rest for
+            // unfinished ones; rm takes any.
+            TaskDao::new(db)
+                .list(None)?
+                .iter()
+                .filter(|task| match *sub {
+                    "reopen" => !task.status.is_active(),
+                    "rm" => true,
+                    _ => task.status.is_active(),
+                })
+                .map(|task| task.id.to_string())
+                .collect()
+        }
+        ["/tasks", "tag", _] => TaskDao::new(db).tags()?,
+        ["/tasks", "untag", id] => match id.trim_start_matches('#').parse() {
+            Ok(id) => TaskDao::new(db)
+                .get(id)
+                .map(|task| task.tags)
+                .unwrap_or_default(),
+            Err(_) => Vec::new(),
+        },
+        ["/drafts", "open" | "send" | "rm"] => DraftDao::new(db)
+            .list(viewer)?
+            .iter()
+            .filter_map(|draft| draft.id)
+            .map(|id| id.to_string())
+            .collect(),
+        ["/feed"] => {
+            let mut options = filters();
+            options.push("more".to_string());
+            options
+        }
+        ["/feed", "more"] => filters(),
+        ["/profile"] => {
+            let mut options = vec!["edit".to_string()];
+            options.extend(handles(db, "")?);
+            options
+        }
+        ["/follow" | "/unfollow" | "/dm"] => handles(db, "")?,
+        ["/post", ..] | ["/reply", ..] | ["/compose", ..] => audiences(),
+        [name] => name
+            .strip_prefix('/')
+            .and_then(table::find)
+            .map(|spec| words(spec.subcommands))
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    })
+}
+
+/// Command names in alphabetical order, each after `prefix`.
+fn commands(prefix: &str) -> Vec<String> {
+    let mut names = COMMANDS
+        .iter()
+        .map(|spec| format!("{}{}", prefix, spec.name))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+/// `key:value` for each value.
+fn prefixed<'a>(key: &str, values: impl Iterator<Item = &'a str>) -> Vec<String> {
+    values.map(|value| format!("{}{}", key, value)).collect()
+}
+
+fn audiences() -> Vec<String> {
+    AudienceScope::all()
+        .iter()
+        .map(|scope| format!("audience:{}", scope))
+        .collect()
+}
+
+fn handles(db: &AppDatabase, prefix: &str) -> Result<Vec<String>> {
+    Ok(CacheDao::new(db)
+        .handles(prefix, HANDLE_LIMIT)?
+        .into_iter()
+        .map(|handle| format!("@{}", handle))
+        .collect())
+}
@@ -1,14 +1,47 @@
+pub mod complete;
+pub mod parse;
+pub mod table;
+
 use anyhow::{anyhow, Result};
-use regex::Regex;
 use std::collections::HashMap;
-use std::str::FromStr;
+use thiserror::Error;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::data::{
+    cache::CacheDao, drafts::DraftDao, messages::MessageDao, notifications::NotificationDao,
+    outbox::OutboxDao, reports::ReportDao, scheduled::ScheduledDao, tasks::TaskDao, AppDatabase,
+};
+use crate::domain::schedule::local_label;
+use crate::domain::{
+    AudienceScope, Draft, FeedCursor, FeedFilter, Mailbox, Message, NewTask, Notification,
+    OutboxPayload, Post, ProfileCard, Report, SearchQuery, Session, Task, TaskChanges, TaskQuery,
+    TaskStatus, Thread, DIRECTORY_LIMIT, FEED_PAGE_SIZE, NOTIFICATION_LIMIT, POST_MAX_CHARS,
+    PROFILE_POSTS, SEARCH_LIMIT,
+};
+use crate::supabase::{is_offline, BrowserLogin, LoginRequired, SupabaseClient};
+use crate::sync::{
+    feed as sync_feed, messages as sync_messages, notifications as sync_notifications,
+    outbox as sync_outbox, SyncEvent,
+};
+
+/// Most messages shown when opening a conversation.
+const CONVERSATION_LIMIT: usize = 200;
 
-use crate::data::{cache::CacheDao, reports::ReportDao, tasks::TaskDao, AppDatabase};
-use crate::domain::{AudienceScope, FeedFilter, TaskStatus};
-use crate::supabase::SupabaseClient;
+pub use parse::ParseError;
+
+/// A command that could not be parsed or has invalid arguments, such as a
+/// post over the length limit or scheduled in the past.
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct Invalid(pub String);
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Command {
+    /// `/help`, or `/help <command>` for one command's forms.
+    Help {
+        command: Option<String>,
+    },
     Login {
         email: String,
         password: String,
@@ -18,179 +51,150 @@ pub enum Command {
         text: String,
         audience: AudienceScope,
     },
+    /// Holds a post back until `send_at` (`/post "..." at:...` or `in:...`).
+    SchedulePost {
+        text: String,
+        audience: AudienceScope,
+        send_at: OffsetDateTime,
+    },
+    Scheduled,
+    /// Changes a scheduled post; `None` fields keep their value. Also arms a
+    /// failed post again.
+    ScheduledEdit {
+        id: i64,
+        text: Option<String>,
+        audience: Option<AudienceScope>,
+        send_at: Option<OffsetDateTime>,
+    },
+    ScheduledCancel {
+        id: i64,
+    },
+    /// Opens the composer, optionally with some text already in it.
+    Compose {
+        text: Option<String>,
+        audience: AudienceScope,
+    },
+    Drafts,
+    DraftOpen {
+        id: i64,
+    },
+    /// Posts draft `id` through the outbox and removes it.
+    DraftSend {
+        id: i64,
+    },
+    DraftDelete {
+        id: i64,
+    },
+    Reply {
+        post_id: String,
+        text: String,
+        /// Defaults to the audience of the post being answered.
+        audience: Option<AudienceScope>,
+    },
+    Thread {
+        post_id: String,
+    },
     Feed {
         filter: FeedFilter,
     },
+    FeedMore {
+        filter: Option<FeedFilter>,
+    },
     Follow {
         handle: String,
     },
+    Unfollow {
+        handle: String,
+    },
     Like {
         post_id: String,
     },
+    Unlike {
+        post_id: String,
+    },
+    Profile {
+        handle: String,
+    },
+    /// Sets the signed-in user's display name; `None` clears it.
+    ProfileEdit {
+        display_name: Option<String>,
+    },
+    /// Searches the user directory by handle or display name.
+    Who {
+        query: String,
+    },
     WhoAmI,
     Logout,
     TasksAdd {
-        title: String,
-        description: String,
+        task: NewTask,
     },
     TasksList {
-        filter: Option<TaskStatus>,
+        query: TaskQuery,
+    },
+    TasksEdit {
+        id: i64,
+        changes: TaskChanges,
+    },
+    /// Starts, cancels or reopens a task; finishing one goes through
+    /// `TasksDone` so it gets a report.
+    TasksSetStatus {
+        id: i64,
+        status: TaskStatus,
     },
     TasksDone {
         id: i64,
     },
+    TasksTag {
+        id: i64,
+        tags: Vec<String>,
+    },
+    TasksUntag {
+        id: i64,
+        tags: Vec<String>,
+    },
+    TasksDelete {
+        id: i64,
+    },
     ReportSync,
     SettingsShow,
     SettingsSet {
         key: String,
         value: String,
     },
+    AccountList,
+    AccountSwitch {
+        name: String,
+    },
+    AccountRemove {
+        name: String,
+    },
+    Search {
+        query: SearchQuery,
+    },
+    /// Sends `text` to `handle`, or just opens the conversation without it.
+    Dm {
+        handle: String,
+        text: Option<String>,
+    },
+    Inbox,
+    Notifications,
+    /// Marks notification `id` read and shows its post or profile.
+    NotificationOpen {
+        id: i64,
+    },
+    NotificationsRead,
+    OutboxList,
+    OutboxRetry {
+        id: i64,
+    },
+    OutboxDrop {
+        id: i64,
+    },
 }
 
-pub fn parse_command(input: &str) -> Result<Command> {
-    let input = input.trim();
-    if !input.starts_with('/') {
-        return Err(anyhow!("commands must start with '/'"));
-    }
-    let mut parts = input[1..].split_whitespace();
-    let cmd = parts.next().ok_or_else(|| anyhow!("missing command"))?;
-    match cmd {
-        "login" => {
-            let mut email = None;
-            let mut password = None;
-            for chunk in parts {
-                if let Some(rest) = chunk.strip_prefix("email:") {
-                    email = Some(rest.to_string());
-                } else if let Some(rest) = chunk.strip_prefix("pw:") {
-                    password = Some(rest.to_string());
-                }
-            }
-            Ok(Command::Login {
-                email: email.ok_or_else(|| anyhow!("email missing"))?,
-                password: password.ok_or_else(|| anyhow!("pw missing"))?,
-            })
-        }
-        "passkey" => Ok(Command::Passkey),
-        "post" => {
-            let quoted =
-                extract_quoted(input).ok_or_else(|| anyhow!("post requires quoted text"))?;
-            let audience = extract_named(input, "audience")
-                .map(|s| AudienceScope::from_str(&s))
-                .transpose()?
-                .unwrap_or_default();
-            Ok(Command::Post {
-                text: quoted,
-                audience,
-            })
-        }
-        "feed" => {
-            let filter = parts
-                .next()
-                .map(|s| FeedFilter::from_str(s))
-                .transpose()?
-                .unwrap_or_default();
-            Ok(Command::Feed { filter })
-        }
-        "follow" => Ok(Command::Follow {
-            handle: parts
-                .next()
-                .ok_or_else(|| anyhow!("missing handle"))?
-                .trim_start_matches('@')
-                .to_string(),
-        }),
-        "like" => Ok(Command::Like {
-            post_id: parts
-                .next()
-                .ok_or_else(|| anyhow!("missing post id"))?
-                .to_string(),
-        }),
-        "whoami" => Ok(Command::WhoAmI),
-        "logout" => Ok(Command::Logout),
-        "tasks" => parse_tasks(parts.collect::<Vec<_>>().as_slice()),
-        "report" => Ok(Command::ReportSync),
-        "settings" => parse_settings(parts.collect::<Vec<_>>().as_slice()),
-        _ => Err(anyhow!("unknown command")),
-    }
-}
-
-fn parse_tasks(args: &[&str]) -> Result<Command> {
-    match args.first().copied() {
-        Some("add") => {
-            let text = args[1..].join(" ");
-            let captures = extract_two_quotes(&text)
-                .ok_or_else(|| anyhow!("usage: /tasks add \"title\" \"desc\""))?;
-            Ok(Command::TasksAdd {
-                title: captures.0,
-                description: captures.1,
-            })
-        }
-        Some("ls") => {
-            let filter = args.get(1).and_then(|s| TaskStatus::from_str(s).ok());
-            Ok(Command::TasksList { filter })
-        }
-        Some("done") => {
-            let id = args
-                .get(1)
-                .ok_or_else(|| anyhow!("missing id"))?
-                .parse::<i64>()?;
-            Ok(Command::TasksDone { id })
-        }
-        _ => Err(anyhow!("unknown tasks command")),
-    }
-}
-
-fn parse_settings(args: &[&str]) -> Result<Command> {
-    match args.first().copied() {
-        Some("show") => Ok(Command::SettingsShow),
-        Some("set") => {
-            let mut iter = args.iter().skip(1);
-            let pair = iter
-                .next()
-                .ok_or_else(|| anyhow!("usage: /settings set key=value"))?;
-            let mut split = pair.splitn(2, '=');
-            let key = split.next().unwrap().to_string();
-            let value = split
-                .next()
-                .ok_or_else(|| anyhow!("missing value"))?
-                .to_string();
-            Ok(Command::SettingsSet { key, value })
-        }
-        _ => Err(anyhow!("unknown settings command")),
-    }
-}
-
-fn extract_quoted(input: &str) -> Option<String> {
-    let re = Regex::new(r#""([^"]+)""#).ok()?;
-    re.captures(input)
-        .and_then(|cap| cap.get(1).map(|m| m.as_str().to_string()))
-}
-
-fn extract_named(input: &str, key: &str) -> Option<String> {
-    let pattern = format!("{}:([\\w-]+)", key);
-    let re = Regex::new(&pattern).ok()?;
-    re.captures(input)
-        .and_then(|cap| cap.get(1).map(|m| m.as_str().to_string()))
-}
-
-fn extract_two_quotes(input: &str) -> Option<(String, String)> {
-    let re = Regex::new(r#""([^"]+)"\s+"([^"]+)""#).ok()?;
-    re.captures(input).and_then(|cap| {
-        let first = cap.get(1)?.as_str().to_string();
-        let second = cap.get(2)?.as_str().to_string();
-        Some((first, second))
-    })
-}
-
-impl FromStr for TaskStatus {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self> {
-        match s.to_lowercase().as_str() {
-            "open" => Ok(TaskStatus::Open),
-            "done" => Ok(TaskStatus::Done),
-            _ => Err(anyhow!("invalid status")),
-        }
-    }
+/// Parses a command line such as `/post "hi" audience:private`.
+pub fn parse_command(input: &str) -> Result<Command, ParseError> {
+    table::parse_command(input)
 }
 
 #[derive(Clone, Copy)]
@@ -208,11 +212,49 @@ impl<'a> CommandContext<'a> {
 #[derive(Debug, Default)]
 pub struct CommandOutput {
     pub message: String,
-    pub feed: Option<Vec<crate::domain::Post>>,
+    pub feed: Option<Vec<Post>>,
+    /// Older posts to append below the current feed (`/feed more`).
+    pub more: Option<Vec<Post>>,
+    /// A post that was just created, or whose cached state changed, e.g.
+    /// after a like.
+    pub post: Option<Post>,
+    /// Set when `feed` came from the local cache because Supabase was
+    /// unreachable.
+    pub cached: Option<CachedFeed>,
+    /// Set when `feed` holds `/search` results for this query.
+    pub search: Option<SearchQuery>,
+    /// A conversation to show in place of the feed (`/thread`).
+    pub thread: Option<Thread>,
+    /// The inbox or a conversation for the messages pane.
+    pub mailbox: Option<Mailbox>,
+    /// A profile to show in place of the feed, with its recent posts.
+    pub profile: Option<ProfileCard>,
+    /// Notifications for the side pane.
+    pub notifications: Option<Vec<Notification>>,
+    /// A draft to open in the composer (`/compose`, `/drafts open`).
+    pub draft: Option<Draft>,
+    /// Saved drafts for the side pane.
+    pub drafts: Option<Vec<Draft>>,
+    /// Tasks that were listed, added or completed.
+    pub tasks: Option<Vec<Task>>,
+    /// Reports that were listed, or the one written when a task was
+    /// completed.
+    pub reports: Option<Vec<Report>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedFeed {
+    pub filter: FeedFilter,
+    /// When this filter was last fetched successfully, if ever.
+    pub synced_at: Option<OffsetDateTime>,
 }
 
 pub async fn execute(ctx: CommandContext<'_>, command: Command) -> Result<CommandOutput> {
     match command {
+        Command::Help { command } => Ok(CommandOutput {
+            message: table::help_text(command.as_deref()),
+            ..Default::default()
+        }),
         Command::Login { email, password } => {
             let session = ctx.supabase.login_email(&email, &password).await?;
             Ok(CommandOutput {
@@ -221,50 +263,303 @@ pub async fn execute(ctx: CommandContext<'_>, command: Command) -> Result<Comman
             })
         }
         Command::Passkey => {
-            passkey_flow().await?;
+            let session = passkey_flow(ctx.supabase).await?;
             Ok(CommandOutput {
-                message: "Passkey flow completed".to_string(),
+                message: format!("Logged in as {}", session.email),
                 ..Default::default()
             })
         }
         Command::Post { text, audience } => {
-            let session = ctx
-                .supabase
-                .ensure_session()
-                .await?
-                .ok_or_else(|| anyhow!("login required"))?;
-            let post = ctx.supabase.create_post(&session, &text, audience).await?;
-            let cache = CacheDao::new(ctx.db);
-            cache.upsert_post(&post)?;
+            queue_and_flush(
+                ctx,
+                OutboxPayload::Post {
+                    id: Uuid::new_v4(),
+                    text,
+                    audience,
+                    reply_to: None,
+                },
+            )
+            .await
+        }
+        Command::SchedulePost {
+            text,
+            audience,
+            send_at,
+        } => {
+            check_schedule(&text, Some(send_at))?;
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            let scheduled =
+                ScheduledDao::new(ctx.db).add(&session.user_id, &text, audience, send_at)?;
             Ok(CommandOutput {
-                message: format!("Posted {}", post.id),
+                message: format!(
+                    "Scheduled post #{} for {}, /scheduled to review",
+                    scheduled.id,
+                    local_label(send_at)
+                ),
                 ..Default::default()
             })
         }
-        Command::Feed { filter } => {
-            let session = ctx
-                .supabase
-                .ensure_session()
-                .await?
-                .ok_or_else(|| anyhow!("login required"))?;
-            let posts = ctx.supabase.fetch_feed(&session, filter).await?;
-            let cache = CacheDao::new(ctx.db);
-            for post in &posts {
-                cache.upsert_post(post)?;
+        Command::Scheduled => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            let posts = ScheduledDao::new(ctx.db).list(&session.user_id)?;
+            if posts.is_empty() {
+                return Ok(CommandOutput {
+                    message: "No scheduled posts".to_string(),
+                    ..Default::default()
+                });
             }
+            let now = OffsetDateTime::now_utc();
             Ok(CommandOutput {
-                message: format!("Fetched {} posts", posts.len()),
-                feed: Some(posts),
+                message: posts
+                    .iter()
+                    .map(|post| post.describe(now))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                ..Default::default()
             })
         }
-        Command::Follow { handle } => Ok(CommandOutput {
-            message: format!("Follow request queued for @{}", handle),
-            ..Default::default()
-        }),
-        Command::Like { post_id } => Ok(CommandOutput {
-            message: format!("Like queued for {}", post_id),
+        Command::ScheduledEdit {
+            id,
+            text,
+            audience,
+            send_at,
+        } => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            let dao = ScheduledDao::new(ctx.db);
+            let current = dao
+                .get(&session.user_id, id)?
+                .ok_or_else(|| anyhow!("no scheduled post #{}", id))?;
+            check_schedule(text.as_deref().unwrap_or(&current.body), send_at)?;
+            let updated = dao
+                .update(&session.user_id, id, text.as_deref(), audience, send_at)?
+                .ok_or_else(|| anyhow!("scheduled post #{} is being sent", id))?;
+            Ok(CommandOutput {
+                message: format!(
+                    "Scheduled post #{} updated, sending {}",
+                    id,
+                    local_label(updated.send_at)
+                ),
+                ..Default::default()
+            })
+        }
+        Command::ScheduledCancel { id } => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            if !ScheduledDao::new(ctx.db).cancel(&session.user_id, id)? {
+                return Err(anyhow!("no scheduled post #{}, or it is being sent", id));
+            }
+            Ok(CommandOutput {
+                message: format!("Cancelled scheduled post #{}", id),
+                ..Default::default()
+            })
+        }
+        Command::Compose { text, audience } => Ok(CommandOutput {
+            message: "Composing a post".to_string(),
+            draft: Some(Draft::new(text.unwrap_or_default(), audience)),
             ..Default::default()
         }),
+        Command::Drafts => {
+            let drafts = DraftDao::new(ctx.db).list(&active_user_id(ctx).await?)?;
+            Ok(CommandOutput {
+                message: format!(
+                    "{} draft{}",
+                    drafts.len(),
+                    if drafts.len() == 1 { "" } else { "s" }
+                ),
+                drafts: Some(drafts),
+                ..Default::default()
+            })
+        }
+        Command::DraftOpen { id } => {
+            let draft = DraftDao::new(ctx.db)
+                .get(&active_user_id(ctx).await?, id)?
+                .ok_or_else(|| anyhow!("no draft #{}", id))?;
+            Ok(CommandOutput {
+                message: format!("Editing draft #{}", id),
+                draft: Some(draft),
+                ..Default::default()
+            })
+        }
+        Command::DraftSend { id } => {
+            let user_id = active_user_id(ctx).await?;
+            let dao = DraftDao::new(ctx.db);
+            let draft = dao
+                .get(&user_id, id)?
+                .ok_or_else(|| anyhow!("no draft #{}", id))?;
+            let length = draft.body.chars().count();
+            if draft.body.trim().is_empty() {
+                return Err(Invalid(format!("draft #{} is empty", id)).into());
+            }
+            if length > POST_MAX_CHARS {
+                return Err(Invalid(format!(
+                    "draft #{} is {} characters, the limit is {}",
+                    id, length, POST_MAX_CHARS
+                ))
+                .into());
+            }
+            let output = queue_and_flush(
+                ctx,
+                OutboxPayload::Post {
+                    id: Uuid::new_v4(),
+                    text: draft.body,
+                    audience: draft.audience,
+                    reply_to: None,
+                },
+            )
+            .await?;
+            // Queued in the outbox, so the draft is no longer needed.
+            dao.delete(&user_id, id)?;
+            Ok(output)
+        }
+        Command::DraftDelete { id } => {
+            let user_id = active_user_id(ctx).await?;
+            let dao = DraftDao::new(ctx.db);
+            if !dao.delete(&user_id, id)? {
+                return Err(anyhow!("no draft #{}", id));
+            }
+            Ok(CommandOutput {
+                message: format!("Deleted draft #{}", id),
+                drafts: Some(dao.list(&user_id)?),
+                ..Default::default()
+            })
+        }
+        Command::Reply {
+            post_id,
+            text,
+            audience,
+        } => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            let parent = CacheDao::new(ctx.db).get_post(&session.user_id, &post_id)?;
+            let audience = audience.or(parent.map(|p| p.audience)).unwrap_or_default();
+            queue_and_flush(
+                ctx,
+                OutboxPayload::Post {
+                    id: Uuid::new_v4(),
+                    text,
+                    audience,
+                    reply_to: Some(post_id),
+                },
+            )
+            .await
+        }
+        Command::Thread { post_id } => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            open_thread(ctx, &session, &post_id).await
+        }
+        Command::Feed { filter } => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            match sync_feed::refresh(ctx.db, ctx.supabase, &filter).await {
+                Ok(posts) => Ok(CommandOutput {
+                    message: format!("Fetched {} posts", posts.len()),
+                    feed: Some(posts),
+                    ..Default::default()
+                }),
+                Err(err) if is_offline(&err) => {
+                    let (posts, cached) = cached_page(ctx, &session, &filter, None)?;
+                    Ok(CommandOutput {
+                        message: format!("Offline: showing {} cached posts", posts.len()),
+                        feed: Some(posts),
+                        cached: Some(cached),
+                        ..Default::default()
+                    })
+                }
+                Err(err) => Err(err),
+            }
+        }
+        Command::FeedMore { filter } => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            let cache = CacheDao::new(ctx.db);
+            let filter = match filter {
+                Some(filter) => filter,
+                None => cache
+                    .last_feed_filter(&session.user_id)?
+                    .unwrap_or_default(),
+            };
+            let before = cache.feed_cursor(&session.user_id, &filter)?;
+            let (posts, cached) =
+                match sync_feed::fetch_page(ctx.db, ctx.supabase, &filter, before).await {
+                    Ok((_, posts)) => (posts, None),
+                    Err(err) if is_offline(&err) => {
+                        let (posts, cached) = cached_page(ctx, &session, &filter, before)?;
+                        (posts, Some(cached))
+                    }
+                    Err(err) => return Err(err),
+                };
+            let message = if posts.is_empty() {
+                "No older posts".to_string()
+            } else {
+                format!("Loaded {} older {} posts", posts.len(), filter)
+            };
+            Ok(CommandOutput {
+                message,
+                more: Some(posts),
+                cached,
+                ..Default::default()
+            })
+        }
+        Command::Follow { handle } => queue_and_flush(ctx, OutboxPayload::Follow { handle }).await,
+        Command::Unfollow { handle } => {
+            queue_and_flush(ctx, OutboxPayload::Unfollow { handle }).await
+        }
+        Command::Like { post_id } => queue_and_flush(ctx, OutboxPayload::Like { post_id }).await,
+        Command::Unlike { post_id } => {
+            queue_and_flush(ctx, OutboxPayload::Unlike { post_id }).await
+        }
+        Command::Profile { handle } => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            open_profile(ctx, &session, &handle).await
+        }
+        Command::ProfileEdit { display_name } => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            let profile = ctx
+                .supabase
+                .update_profile(&session, display_name.as_deref())
+                .await?;
+            CacheDao::new(ctx.db).upsert_profile(&profile)?;
+            let message = match &profile.display_name {
+                Some(name) => format!("Display name set to {}", name),
+                None => "Display name cleared".to_string(),
+            };
+            Ok(CommandOutput {
+                message,
+                ..Default::default()
+            })
+        }
+        Command::Who { query } => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            let cache = CacheDao::new(ctx.db);
+            let (profiles, offline) = match ctx
+                .supabase
+                .search_profiles(&session, &query, DIRECTORY_LIMIT)
+                .await
+            {
+                Ok(profiles) => {
+                    for profile in &profiles {
+                        cache.upsert_profile(profile)?;
+                    }
+                    (profiles, false)
+                }
+                Err(err) if is_offline(&err) => {
+                    (cache.search_profiles(&query, DIRECTORY_LIMIT)?, true)
+                }
+                Err(err) => return Err(err),
+            };
+            let mut lines = vec![format!(
+                "{} profile{} matching \"{}\"{}",
+                profiles.len(),
+                if profiles.len() == 1 { "" } else { "s" },
+                query,
+                if offline {
+                    " (cached profiles only)"
+                } else {
+                    ""
+                }
+            )];
+            lines.extend(profiles.iter().map(|profile| profile.label()));
+            Ok(CommandOutput {
+                message: lines.join("\n"),
+                ..Default::default()
+            })
+        }
         Command::WhoAmI => {
             if let Some(session) = ctx.supabase.restore_session().await? {
                 Ok(CommandOutput {
@@ -285,35 +580,58 @@ pub async fn execute(ctx: CommandContext<'_>, command: Command) -> Result<Comman
                 ..Default::default()
             })
         }
-        Command::TasksAdd { title, description } => {
+        Command::TasksAdd { task } => {
             let dao = TaskDao::new(ctx.db);
-            let task = dao.add(&title, &description)?;
+            let task = dao.insert(&task)?;
             Ok(CommandOutput {
                 message: format!("Task {} created", task.id),
+                tasks: Some(vec![task]),
                 ..Default::default()
             })
         }
-        Command::TasksList { filter } => {
+        Command::TasksList { query } => {
             let dao = TaskDao::new(ctx.db);
-            let tasks = dao.list(filter)?;
+            let tasks = dao.query(&query)?;
+            let now = OffsetDateTime::now_utc();
             let text = tasks
                 .iter()
-                .map(|task| format!("#{} [{}] {}", task.id, task.status.as_str(), task.title))
+                .map(|task| task.describe(now))
                 .collect::<Vec<_>>()
                 .join("\n");
             Ok(CommandOutput {
                 message: text,
+                tasks: Some(tasks),
                 ..Default::default()
             })
         }
         Command::TasksDone { id } => {
             let dao = TaskDao::new(ctx.db);
+            // Finishing it again would move `done_at` and write a second
+            // report.
+            if dao
+                .get(id)
+                .is_ok_and(|task| task.status == TaskStatus::Done)
+            {
+                return Err(Invalid(format!("task {} is already done", id)).into());
+            }
             if let Some(task) = dao.mark_done(id)? {
-                let report = crate::reports::generate_report(ctx.db, &task)?;
+                // The report only uses the session to pick the viewer's
+                // cached posts, so it is written even if no session can be
+                // read, e.g. without a keyring or passphrase.
+                let viewer = ctx
+                    .supabase
+                    .restore_session()
+                    .await
+                    .ok()
+                    .flatten()
+                    .map(|s| s.user_id);
+                let report = crate::reports::generate_report(ctx.db, viewer.as_deref(), &task)?;
                 let dao = ReportDao::new(ctx.db);
                 dao.insert(&report)?;
                 Ok(CommandOutput {
                     message: format!("Task {} done; report: {}", task.id, report.path),
+                    tasks: Some(vec![task]),
+                    reports: Some(vec![report]),
                     ..Default::default()
                 })
             } else {
@@ -323,11 +641,44 @@ pub async fn execute(ctx: CommandContext<'_>, command: Command) -> Result<Comman
                 })
             }
         }
+        Command::TasksEdit { id, changes } => {
+            let task = TaskDao::new(ctx.db).edit(id, &changes)?;
+            Ok(task_output(task, "updated"))
+        }
+        Command::TasksSetStatus { id, status } => {
+            let dao = TaskDao::new(ctx.db);
+            let (task, verb) = match status {
+                TaskStatus::Open => (dao.reopen(id)?, "reopened"),
+                TaskStatus::InProgress => (dao.set_status(id, status)?, "started"),
+                _ => (dao.set_status(id, status)?, "cancelled"),
+            };
+            Ok(task_output(task, verb))
+        }
+        Command::TasksTag { id, tags } => {
+            let task = TaskDao::new(ctx.db).add_tags(id, &tags)?;
+            Ok(task_output(task, "tagged"))
+        }
+        Command::TasksUntag { id, tags } => {
+            let task = TaskDao::new(ctx.db).remove_tags(id, &tags)?;
+            Ok(task_output(task, "untagged"))
+        }
+        Command::TasksDelete { id } => {
+            let message = if TaskDao::new(ctx.db).delete(id)? {
+                format!("Task {} deleted", id)
+            } else {
+                "Task not found".to_string()
+            };
+            Ok(CommandOutput {
+                message,
+                ..Default::default()
+            })
+        }
         Command::ReportSync => {
             let dao = ReportDao::new(ctx.db);
             let reports = dao.latest(10)?;
             Ok(CommandOutput {
                 message: format!("Reports: {}", reports.len()),
+                reports: Some(reports),
                 ..Default::default()
             })
         }
@@ -360,32 +711,469 @@ pub async fn execute(ctx: CommandContext<'_>, command: Command) -> Result<Comman
                 ..Default::default()
             })
         }
+        Command::AccountList => {
+            let accounts = ctx.supabase.sessions.accounts().await?;
+            if accounts.is_empty() {
+                return Ok(CommandOutput {
+                    message: "No accounts signed in".to_string(),
+                    ..Default::default()
+                });
+            }
+            let text = accounts
+                .iter()
+                .map(|account| {
+                    let marker = if account.active { "*" } else { " " };
+                    format!("{} {}", marker, account.email)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            Ok(CommandOutput {
+                message: text,
+                ..Default::default()
+            })
+        }
+        Command::AccountSwitch { name } => {
+            let session = ctx.supabase.sessions.switch(&name).await?;
+            Ok(CommandOutput {
+                message: format!("Switched to {}", session.email),
+                ..Default::default()
+            })
+        }
+        Command::AccountRemove { name } => {
+            let account = ctx.supabase.sessions.remove(&name).await?;
+            let message = if account.active {
+                format!("Removed {}; no account is active", account.email)
+            } else {
+                format!("Removed {}", account.email)
+            };
+            Ok(CommandOutput {
+                message,
+                ..Default::default()
+            })
+        }
+        Command::Search { query } => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            let cache = CacheDao::new(ctx.db);
+            // Server hits are cached so one bm25 ranking covers both sources,
+            // but not as feed posts.
+            let online = match ctx
+                .supabase
+                .search_posts(&session, &query, SEARCH_LIMIT)
+                .await
+            {
+                Ok(posts) => {
+                    for post in &posts {
+                        cache.upsert_search_hit(&session.user_id, post)?;
+                    }
+                    true
+                }
+                Err(err) => {
+                    tracing::debug!(?err, "server search unavailable; using cache only");
+                    false
+                }
+            };
+            let posts = cache.search_posts(&session.user_id, &query, SEARCH_LIMIT)?;
+            Ok(CommandOutput {
+                message: format!(
+                    "{} result{} for {}{}",
+                    posts.len(),
+                    if posts.len() == 1 { "" } else { "s" },
+                    query,
+                    if online { "" } else { " (cached posts only)" }
+                ),
+                feed: Some(posts),
+                search: Some(query),
+                ..Default::default()
+            })
+        }
+        Command::Dm { handle, text } => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            let sent = match text {
+                Some(text) => Some(
+                    queue_and_flush(
+                        ctx,
+                        OutboxPayload::Message {
+                            id: Uuid::new_v4(),
+                            handle: handle.clone(),
+                            text,
+                        },
+                    )
+                    .await?
+                    .message,
+                ),
+                None => None,
+            };
+            let (peer, messages, offline) = open_conversation(ctx, &session, &handle).await?;
+            let message = match sent {
+                Some(sent) => sent,
+                None => format!(
+                    "{} message{} with @{}{}",
+                    messages.len(),
+                    if messages.len() == 1 { "" } else { "s" },
+                    handle,
+                    if offline { " (offline)" } else { "" }
+                ),
+            };
+            Ok(CommandOutput {
+                message,
+                mailbox: Some(Mailbox::Conversation {
+                    peer,
+                    peer_handle: handle,
+                    messages,
+                }),
+                ..Default::default()
+            })
+        }
+        Command::Inbox => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            let offline = match sync_messages::refresh(ctx.db, ctx.supabase, &session).await {
+                Ok(_) => false,
+                Err(err) if is_offline(&err) => true,
+                Err(err) => return Err(err),
+            };
+            let conversations = MessageDao::new(ctx.db).conversations(&session.user_id)?;
+            let unread: i64 = conversations.iter().map(|c| c.unread).sum();
+            Ok(CommandOutput {
+                message: format!(
+                    "{} conversation{}, {} unread{}",
+                    conversations.len(),
+                    if conversations.len() == 1 { "" } else { "s" },
+                    unread,
+                    if offline { " (offline)" } else { "" }
+                ),
+                mailbox: Some(Mailbox::Inbox(conversations)),
+                ..Default::default()
+            })
+        }
+        Command::Notifications => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            let offline = match sync_notifications::refresh(ctx.db, ctx.supabase, &session).await {
+                Ok(_) => false,
+                Err(err) if is_offline(&err) => true,
+                Err(err) => return Err(err),
+            };
+            let dao = NotificationDao::new(ctx.db);
+            let notifications = dao.list(&session.user_id, NOTIFICATION_LIMIT)?;
+            let unread = dao.unread_count(&session.user_id)?;
+            Ok(CommandOutput {
+                message: format!(
+                    "{} notification{}, {} unread{}",
+                    notifications.len(),
+                    if notifications.len() == 1 { "" } else { "s" },
+                    unread,
+                    if offline { " (offline)" } else { "" }
+                ),
+                notifications: Some(notifications),
+                ..Default::default()
+            })
+        }
+        Command::NotificationOpen { id } => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            let dao = NotificationDao::new(ctx.db);
+            let notification = dao
+                .get(&session.user_id, id)?
+                .ok_or_else(|| anyhow!("no notification #{}", id))?;
+            dao.mark_read(&session.user_id, Some(id), OffsetDateTime::now_utc())?;
+            let mut output = match (notification.post_id, &notification.actor_handle) {
+                (Some(post_id), _) => open_thread(ctx, &session, &post_id.to_string()).await?,
+                (None, Some(handle)) => open_profile(ctx, &session, handle).await?,
+                (None, None) => return Err(anyhow!("notification #{} has nothing to open", id)),
+            };
+            output.message = format!("{} · {}", notification.describe(), output.message);
+            output.notifications = Some(dao.list(&session.user_id, NOTIFICATION_LIMIT)?);
+            Ok(output)
+        }
+        Command::NotificationsRead => {
+            let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+            let dao = NotificationDao::new(ctx.db);
+            let count = dao.mark_read(&session.user_id, None, OffsetDateTime::now_utc())?;
+            Ok(CommandOutput {
+                message: format!(
+                    "Marked {} notification{} read",
+                    count,
+                    if count == 1 { "" } else { "s" }
+                ),
+                notifications: Some(dao.list(&session.user_id, NOTIFICATION_LIMIT)?),
+                ..Default::default()
+            })
+        }
+        Command::OutboxList => {
+            let user_id = ctx
+                .supabase
+                .restore_session()
+                .await?
+                .map(|s| s.user_id)
+                .unwrap_or_default();
+            let items = OutboxDao::new(ctx.db).list(&user_id, 20)?;
+            if items.is_empty() {
+                return Ok(CommandOutput {
+                    message: "Outbox empty".to_string(),
+                    ..Default::default()
+                });
+            }
+            let text = items
+                .iter()
+                .map(|item| {
+                    let mut line = format!(
+                        "#{} [{}] {} attempts:{}",
+                        item.id,
+                        item.status,
+                        item.payload.describe(),
+                        item.attempts
+                    );
+                    if let Some(err) = &item.last_error {
+                        line.push_str(&format!(" error: {}", err));
+                    }
+                    line
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            Ok(CommandOutput {
+                message: text,
+                ..Default::default()
+            })
+        }
+        Command::OutboxRetry { id } => {
+            let user_id = active_user_id(ctx).await?;
+            if !OutboxDao::new(ctx.db).retry(&user_id, id)? {
+                return Ok(CommandOutput {
+                    message: format!("Outbox #{} is not pending or failed", id),
+                    ..Default::default()
+                });
+            }
+            Ok(CommandOutput {
+                message: flush_message(ctx, id).await,
+                ..Default::default()
+            })
+        }
+        Command::OutboxDrop { id } => {
+            let user_id = active_user_id(ctx).await?;
+            let message = if sync_outbox::drop_item(ctx.db, &user_id, id)? {
+                format!("Outbox #{} dropped", id)
+            } else {
+                format!("Outbox #{} not found or in flight", id)
+            };
+            Ok(CommandOutput {
+                message,
+                ..Default::default()
+            })
+        }
     }
 }
 
-async fn passkey_flow() -> Result<()> {
-    use tokio::net::TcpListener;
+/// Writes a mutation to the outbox before anything touches the network, then
+/// tries to deliver it immediately. Anything that fails stays queued for the
+/// background worker.
+async fn queue_and_flush(ctx: CommandContext<'_>, payload: OutboxPayload) -> Result<CommandOutput> {
+    let session = ctx.supabase.restore_session().await?.ok_or(LoginRequired)?;
+    let item = OutboxDao::new(ctx.db).enqueue(&session.user_id, &payload)?;
+    sync_outbox::mirror(ctx.db, &session.user_id, &payload, true)?;
+    let (message, created) = flush(ctx, item.id).await;
+    let post = match &payload {
+        OutboxPayload::Like { post_id } | OutboxPayload::Unlike { post_id } => {
+            CacheDao::new(ctx.db).get_post(&session.user_id, post_id)?
+        }
+        _ => created,
+    };
+    Ok(CommandOutput {
+        message,
+        post,
+        ..Default::default()
+    })
+}
 
-    let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
-    let port = listener.local_addr()?.port();
-    let url = format!("http://localhost:{}/callback", port);
-    tracing::info!(%url, "passkey callback listening");
-    if let Err(err) = open::that_detached(&url) {
-        tracing::warn!(?err, "failed to open browser");
+/// The signed-in account's id, or an empty one while signed out, which
+/// only matches local rows that have no owner.
+async fn active_user_id(ctx: CommandContext<'_>) -> Result<String> {
+    Ok(ctx
+        .supabase
+        .restore_session()
+        .await?
+        .map(|s| s.user_id)
+        .unwrap_or_default())
+}
+
+/// Rejects a scheduled post that is empty, too long or set for a time in
+/// the past.
+fn check_schedule(text: &str, send_at: Option<OffsetDateTime>) -> Result<(), Invalid> {
+    let length = text.chars().count();
+    if text.trim().is_empty() {
+        return Err(Invalid("post requires quoted text".to_string()));
+    }
+    if length > POST_MAX_CHARS {
+        return Err(Invalid(format!(
+            "post is {} characters, the limit is {}",
+            length, POST_MAX_CHARS
+        )));
+    }
+    if let Some(send_at) = send_at.filter(|at| *at < OffsetDateTime::now_utc()) {
+        return Err(Invalid(format!("{} is in the past", local_label(send_at))));
     }
-    let (mut socket, _) = listener.accept().await?;
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
-    let mut buf = vec![0u8; 4096];
-    let n = socket.read(&mut buf).await?;
-    let request = String::from_utf8_lossy(&buf[..n]);
-    let token = request
-        .split("session=")
-        .nth(1)
-        .and_then(|part| part.split_whitespace().next())
-        .ok_or_else(|| anyhow!("no session token"))?;
-    tracing::info!("received session {}", token.len());
-    socket
-        .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nPasskey captured. You may close this window.")
-        .await?;
     Ok(())
 }
+
+/// Reports a task change, or that there was no such task.
+fn task_output(task: Option<Task>, verb: &str) -> CommandOutput {
+    match task {
+        Some(task) => CommandOutput {
+            message: format!("Task {} {}", task.id, verb),
+            tasks: Some(vec![task]),
+            ..Default::default()
+        },
+        None => CommandOutput {
+            message: "Task not found".to_string(),
+            ..Default::default()
+        },
+    }
+}
+
+/// The conversation around `post_id`, fetched when reachable and built
+/// from the cache.
+async fn open_thread(
+    ctx: CommandContext<'_>,
+    session: &Session,
+    post_id: &str,
+) -> Result<CommandOutput> {
+    let cache = CacheDao::new(ctx.db);
+    let offline = match ctx.supabase.fetch_thread(session, post_id).await {
+        Ok(posts) => {
+            for post in &posts {
+                cache.upsert_post(&session.user_id, post)?;
+            }
+            false
+        }
+        Err(err) if is_offline(&err) => true,
+        Err(err) => return Err(err),
+    };
+    let post = cache
+        .get_post(&session.user_id, post_id)?
+        .ok_or_else(|| anyhow!("post {} not found", post_id))?;
+    let root = post.thread_root();
+    let thread = Thread::build(root, cache.thread(&session.user_id, &root.to_string())?);
+    let replies = thread.entries.len().saturating_sub(1);
+    Ok(CommandOutput {
+        message: format!(
+            "Thread with {} repl{}{}",
+            replies,
+            if replies == 1 { "y" } else { "ies" },
+            if offline {
+                " (offline, cached posts only)"
+            } else {
+                ""
+            }
+        ),
+        thread: Some(thread),
+        ..Default::default()
+    })
+}
+
+/// `handle`'s profile card, from the cache while offline.
+async fn open_profile(
+    ctx: CommandContext<'_>,
+    session: &Session,
+    handle: &str,
+) -> Result<CommandOutput> {
+    let cache = CacheDao::new(ctx.db);
+    let (card, offline) = match ctx.supabase.profile(session, handle).await {
+        Ok(card) => {
+            cache.upsert_profile(&card.profile)?;
+            for post in &card.posts {
+                cache.upsert_post(&session.user_id, post)?;
+            }
+            (card, false)
+        }
+        Err(err) if is_offline(&err) => {
+            let profile = cache
+                .profile_by_handle(handle)?
+                .ok_or_else(|| anyhow!("@{} isn't cached yet; try again online", handle))?;
+            let posts =
+                cache.posts_by_author(&session.user_id, &profile.id.to_string(), PROFILE_POSTS)?;
+            let card = ProfileCard {
+                profile,
+                stats: None,
+                posts,
+            };
+            (card, true)
+        }
+        Err(err) => return Err(err),
+    };
+    Ok(CommandOutput {
+        message: format!(
+            "{}{}",
+            card.profile.summary(card.stats),
+            if offline { " (offline)" } else { "" }
+        ),
+        profile: Some(card),
+        ..Default::default()
+    })
+}
+
+/// Messages exchanged with `handle`, after syncing and marking theirs read.
+/// Falls back to cached messages while offline; the flag says so.
+async fn open_conversation(
+    ctx: CommandContext<'_>,
+    session: &Session,
+    handle: &str,
+) -> Result<(Option<Uuid>, Vec<Message>, bool)> {
+    let dao = MessageDao::new(ctx.db);
+    let offline = match sync_messages::refresh(ctx.db, ctx.supabase, session).await {
+        Ok(_) => false,
+        Err(err) if is_offline(&err) => true,
+        Err(err) => return Err(err),
+    };
+    let peer = match dao.peer_by_handle(&session.user_id, handle)? {
+        Some(peer) => peer,
+        None if offline => return Ok((None, Vec::new(), true)),
+        None => ctx.supabase.find_profile(session, handle).await?.id,
+    };
+    let id = peer.to_string();
+    sync_messages::mark_read(ctx.db, ctx.supabase, session, &id).await?;
+    Ok((
+        Some(peer),
+        dao.conversation(&session.user_id, &id, CONVERSATION_LIMIT)?,
+        offline,
+    ))
+}
+
+/// Serves one page of `filter` from the local cache while offline.
+fn cached_page(
+    ctx: CommandContext<'_>,
+    session: &Session,
+    filter: &FeedFilter,
+    before: Option<FeedCursor>,
+) -> Result<(Vec<Post>, CachedFeed)> {
+    let cache = CacheDao::new(ctx.db);
+    let posts = cache.list_feed(filter, &session.user_id, before.as_ref(), FEED_PAGE_SIZE)?;
+    sync_feed::remember_cursor(ctx.db, &session.user_id, filter, before, &posts)?;
+    let synced_at = cache.feed_synced_at(&session.user_id, filter)?;
+    let filter = filter.clone();
+    Ok((posts, CachedFeed { filter, synced_at }))
+}
+
+async fn flush_message(ctx: CommandContext<'_>, id: i64) -> String {
+    flush(ctx, id).await.0
+}
+
+/// Tries to deliver outbox item `id` now. Returns the status line and the
+/// post it created, if any.
+async fn flush(ctx: CommandContext<'_>, id: i64) -> (String, Option<Post>) {
+    match sync_outbox::flush_one(ctx.db, ctx.supabase, id).await {
+        Ok(Some(SyncEvent::Delivered { summary, post, .. })) => (summary, post),
+        Ok(Some(event)) => (event.to_string(), None),
+        Ok(None) => (format!("Outbox #{} queued", id), None),
+        Err(err) => (format!("Outbox #{} queued: {}", id, err), None),
+    }
+}
+
+/// How long to wait for the browser to come back from the login page.
+const PASSKEY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(180);
+
+async fn passkey_flow(supabase: &SupabaseClient) -> Result<Session> {
+    let login = BrowserLogin::bind(PASSKEY_TIMEOUT).await?;
+    let url = login.authorize_url(supabase.cfg.login_url()?)?;
+    tracing::info!(%url, "waiting for browser login");
+    if let Err(err) = open::that_detached(&url) {
+        tracing::warn!(?err, %url, "failed to open browser; open the url manually");
+    }
+    login.complete(supabase).await
+}
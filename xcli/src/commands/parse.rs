@@ -0,0 +1,292 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::ops::Range;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use super::table::CommandSpec;
+
+/// Why a command line didn't parse. Every variant carries the byte range of
+/// the offending text so the command line can underline it; something left
+/// out is reported at the end of the line.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseError {
+    #[error("commands start with '/'")]
+    NotACommand { span: Range<usize> },
+    #[error("unclosed quote")]
+    UnclosedQuote { span: Range<usize> },
+    #[error("nothing to escape after '\\'")]
+    DanglingEscape { span: Range<usize> },
+    #[error("unknown command /{name}; try /help")]
+    UnknownCommand { name: String, span: Range<usize> },
+    #[error("unknown subcommand {name}; /{command} takes {expected}")]
+    UnknownSubcommand {
+        command: &'static str,
+        name: String,
+        expected: String,
+        span: Range<usize>,
+    },
+    #[error("missing {what}; see /help {command}")]
+    Missing {
+        command: &'static str,
+        what: &'static str,
+        span: Range<usize>,
+    },
+    #[error("invalid {what} {value:?}: {reason}")]
+    Invalid {
+        what: &'static str,
+        value: String,
+        reason: String,
+        span: Range<usize>,
+    },
+    #[error("/{command} doesn't take {key}:")]
+    UnknownOption {
+        command: &'static str,
+        key: String,
+        span: Range<usize>,
+    },
+    #[error("{key}: is given twice")]
+    DuplicateOption { key: String, span: Range<usize> },
+    #[error("{key}: can't be combined with {other}:")]
+    ConflictingOptions {
+        key: String,
+        other: &'static str,
+        span: Range<usize>,
+    },
+    #[error("unexpected argument {value:?}")]
+    Unexpected { value: String, span: Range<usize> },
+}
+
+impl ParseError {
+    /// Byte range of the input the error is about.
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            ParseError::NotACommand { span }
+            | ParseError::UnclosedQuote { span }
+            | ParseError::DanglingEscape { span }
+            | ParseError::UnknownCommand { span, .. }
+            | ParseError::UnknownSubcommand { span, .. }
+            | ParseError::Missing { span, .. }
+            | ParseError::Invalid { span, .. }
+            | ParseError::UnknownOption { span, .. }
+            | ParseError::DuplicateOption { span, .. }
+            | ParseError::ConflictingOptions { span, .. }
+            | ParseError::Unexpected { span, .. } => span.clone(),
+        }
+    }
+}
+
+/// One shell-like word of a command line, with quotes and escapes resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub text: String,
+    /// Set for `key:value` and `key:"several words"`; `text` is the value.
+    pub key: Option<String>,
+    /// Whether the key ended in `=` rather than `:`. Such a token is put
+    /// back together as a plain word for commands without that option.
+    pub equals: bool,
+    pub span: Range<usize>,
+}
+
+impl Token {
+    /// Parses the token's text, blaming the token if it doesn't parse.
+    pub fn parse<T>(&self, what: &'static str) -> Result<T, ParseError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.text
+            .parse()
+            .map_err(|err: T::Err| self.invalid(what, err))
+    }
+
+    pub fn invalid(&self, what: &'static str, reason: impl Display) -> ParseError {
+        ParseError::Invalid {
+            what,
+            value: self.text.clone(),
+            reason: reason.to_string(),
+            span: self.span.clone(),
+        }
+    }
+}
+
+/// Splits `input` into words at unquoted whitespace. Double quotes group
+/// words and may start mid-word (`key:"a b"`); a backslash escapes the next
+/// character, in or out of quotes. A word whose unquoted start is a name
+/// followed by `:` or `=` becomes a `key:value` token, so `desc="a b"`
+/// reads like `desc:"a b"` where the command takes `desc:`.
+pub fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut text = String::new();
+        let mut key = None;
+        let mut equals = false;
+        // Whether everything so far could still be an option name.
+        let mut plain = true;
+        let mut quote = None;
+        let mut end = input.len();
+        while let Some((at, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    let (_, escaped) = chars.next().ok_or(ParseError::DanglingEscape {
+                        span: at..input.len(),
+                    })?;
+                    text.push(escaped);
+                    plain = false;
+                }
+                '"' => {
+                    quote = match quote {
+                        Some(_) => None,
+                        None => Some(at),
+                    };
+                    plain = false;
+                }
+                c if quote.is_some() => text.push(c),
+                c if c.is_whitespace() => {
+                    end = at;
+                    break;
+                }
+                ':' | '=' if plain && key.is_none() && !text.is_empty() => {
+                    key = Some(std::mem::take(&mut text));
+                    equals = c == '=';
+                    plain = false;
+                }
+                c => {
+                    plain &= c.is_ascii_alphabetic() || c == '_';
+                    text.push(c);
+                }
+            }
+        }
+        if let Some(at) = quote {
+            return Err(ParseError::UnclosedQuote {
+                span: at..input.len(),
+            });
+        }
+        tokens.push(Token {
+            text,
+            key,
+            equals,
+            span: start..end,
+        });
+    }
+    Ok(tokens)
+}
+
+/// The arguments after a command name: positional words in order, and
+/// `key:value` options, each of which the command has to claim.
+pub struct Args {
+    spec: &'static CommandSpec,
+    words: VecDeque<Token>,
+    options: Vec<(Token, bool)>,
+    end: usize,
+}
+
+impl Args {
+    /// `end` is the length of the line, where missing arguments are shown.
+    pub fn new(
+        spec: &'static CommandSpec,
+        tokens: Vec<Token>,
+        end: usize,
+    ) -> Result<Self, ParseError> {
+        let mut words = VecDeque::new();
+        let mut options: Vec<(Token, bool)> = Vec::new();
+        for mut token in tokens {
+            if token.equals
+                && !spec
+                    .options
+                    .contains(&token.key.as_deref().unwrap_or_default())
+            {
+                let key = token.key.take().unwrap_or_default();
+                token.text = format!("{}={}", key, token.text);
+                token.equals = false;
+            }
+            match &token.key {
+                Some(key) => {
+                    if options
+                        .iter()
+                        .any(|(option, _)| option.key.as_ref() == Some(key))
+                    {
+                        return Err(ParseError::DuplicateOption {
+                            key: key.clone(),
+                            span: token.span,
+                        });
+                    }
+                    options.push((token, false));
+                }
+                None => words.push_back(token),
+            }
+        }
+        Ok(Self {
+            spec,
+            words,
+            options,
+            end,
+        })
+    }
+
+    pub fn optional(&mut self) -> Option<Token> {
+        self.words.pop_front()
+    }
+
+    pub fn required(&mut self, what: &'static str) -> Result<Token, ParseError> {
+        self.optional().ok_or_else(|| self.missing(what))
+    }
+
+    /// Every positional word not taken yet.
+    pub fn rest(&mut self) -> Vec<Token> {
+        self.words.drain(..).collect()
+    }
+
+    /// The value of `key:`, if given.
+    pub fn option(&mut self, key: &str) -> Option<Token> {
+        self.options
+            .iter_mut()
+            .find(|(option, _)| option.key.as_deref() == Some(key))
+            .map(|(option, used)| {
+                *used = true;
+                option.clone()
+            })
+    }
+
+    pub fn missing(&self, what: &'static str) -> ParseError {
+        ParseError::Missing {
+            command: self.spec.name,
+            what,
+            span: self.end..self.end,
+        }
+    }
+
+    /// For a subcommand word the command doesn't know.
+    pub fn unknown_subcommand(&self, token: &Token) -> ParseError {
+        ParseError::UnknownSubcommand {
+            command: self.spec.name,
+            name: token.text.clone(),
+            expected: self.spec.subcommands.join(", "),
+            span: token.span.clone(),
+        }
+    }
+
+    /// Fails on the first word or option nobody asked for.
+    pub fn finish(self) -> Result<(), ParseError> {
+        if let Some((option, _)) = self.options.into_iter().find(|(_, used)| !used) {
+            return Err(ParseError::UnknownOption {
+                command: self.spec.name,
+                key: option.key.unwrap_or_default(),
+                span: option.span,
+            });
+        }
+        match self.words.into_iter().next() {
+            Some(word) => Err(ParseError::Unexpected {
+                value: word.text,
+                span: word.span,
+            }),
+            None => Ok(()),
+        }
+    }
+}
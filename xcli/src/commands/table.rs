@@ -0,0 +1,755 @@
+use time::OffsetDateTime;
+
+use super::parse::{tokenize, Args, ParseError, Token};
+use super::Command;
+use crate::domain::schedule::{parse_at, parse_in};
+use crate::domain::{
+    normalize_tag, parse_due, AudienceScope, FeedFilter, NewTask, SearchQuery, TaskChanges,
+    TaskPriority, TaskQuery, TaskSort, TaskStatus,
+};
+
+/// One slash command: how it is written and how its arguments become a
+/// `Command`. `/help`, the help panel and completion are built from these.
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Short form shown in the help panel.
+    pub synopsis: &'static str,
+    /// Every form the command takes, for `/help <command>`.
+    pub usage: &'static [&'static str],
+    pub about: &'static str,
+    /// Words that pick a subcommand, as offered by completion.
+    pub subcommands: &'static [&'static str],
+    /// Option keys the command reads. `key=value` is only taken as an option
+    /// for these; otherwise it stays a plain word, as in `/settings set`.
+    pub options: &'static [&'static str],
+    parse: fn(&mut Args) -> Result<Command, ParseError>,
+}
+
+/// Every slash command, in the order the help panel lists them.
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "help",
+        synopsis: "/help [command]",
+        usage: &["/help", "/help <command>"],
+        about: "List the commands, or explain one",
+        subcommands: &[],
+        options: &[],
+        parse: help,
+    },
+    CommandSpec {
+        name: "login",
+        synopsis: "/login email: pw:",
+        usage: &["/login email:<email> pw:<password>"],
+        about: "Sign in with email and password",
+        subcommands: &[],
+        options: &["email", "pw"],
+        parse: login,
+    },
+    CommandSpec {
+        name: "passkey",
+        synopsis: "/passkey",
+        usage: &["/passkey"],
+        about: "Sign in through the browser with a passkey",
+        subcommands: &[],
+        options: &[],
+        parse: |_| Ok(Command::Passkey),
+    },
+    CommandSpec {
+        name: "account",
+        synopsis: "/account [switch|rm]",
+        usage: &[
+            "/account [list]",
+            "/account switch <email>",
+            "/account remove <email>",
+        ],
+        about: "List, switch or forget signed-in accounts",
+        subcommands: &["list", "switch", "remove"],
+        options: &[],
+        parse: account,
+    },
+    CommandSpec {
+        name: "logout",
+        synopsis: "/logout",
+        usage: &["/logout"],
+        about: "Sign out of the active account",
+        subcommands: &[],
+        options: &[],
+        parse: |_| Ok(Command::Logout),
+    },
+    CommandSpec {
+        name: "whoami",
+        synopsis: "/whoami",
+        usage: &["/whoami"],
+        about: "Show the signed-in account",
+        subcommands: &[],
+        options: &[],
+        parse: |_| Ok(Command::WhoAmI),
+    },
+    CommandSpec {
+        name: "post",
+        synopsis: "/post \"text\" audience:",
+        usage: &["/post \"text\" [audience:public|restrained|private] [at:<time>|in:<delay>]"],
+        about: "Publish a post, or schedule it with at: (local time) or in: (e.g. 2h)",
+        subcommands: &[],
+        options: &["audience", "at", "in"],
+        parse: post,
+    },
+    CommandSpec {
+        name: "compose",
+        synopsis: "/compose [\"text\"]",
+        usage: &["/compose [\"text\"] [audience:...]"],
+        about: "Open the multi-line composer",
+        subcommands: &[],
+        options: &["audience"],
+        parse: compose,
+    },
+    CommandSpec {
+        name: "drafts",
+        synopsis: "/drafts [open|send id]",
+        usage: &[
+            "/drafts [ls]",
+            "/drafts open <id>",
+            "/drafts send <id>",
+            "/drafts rm <id>",
+        ],
+        about: "List, reopen, send or delete saved drafts",
+        subcommands: &["ls", "open", "send", "rm"],
+        options: &[],
+        parse: drafts,
+    },
+    CommandSpec {
+        name: "scheduled",
+        synopsis: "/scheduled [edit id]",
+        usage: &[
+            "/scheduled [ls]",
+            "/scheduled edit <id> [\"text\"] [audience:...] [at:<time>|in:<delay>]",
+            "/scheduled cancel <id>",
+        ],
+        about: "List, change or cancel scheduled posts",
+        subcommands: &["ls", "edit", "cancel"],
+        options: &["audience", "at", "in"],
+        parse: scheduled,
+    },
+    CommandSpec {
+        name: "reply",
+        synopsis: "/reply <id> \"text\"",
+        usage: &["/reply <post id> \"text\" [audience:...]"],
+        about: "Reply to a post",
+        subcommands: &[],
+        options: &["audience"],
+        parse: reply,
+    },
+    CommandSpec {
+        name: "thread",
+        synopsis: "/thread <id>",
+        usage: &["/thread <post id>"],
+        about: "Show the conversation around a post",
+        subcommands: &[],
+        options: &[],
+        parse: |args| {
+            Ok(Command::Thread {
+                post_id: post_id(&args.required("post id")?)?,
+            })
+        },
+    },
+    CommandSpec {
+        name: "feed",
+        synopsis: "/feed [more] [filter]",
+        usage: &[
+            "/feed [global|following|me|mentions|#tag]",
+            "/feed more [filter]",
+        ],
+        about: "Load a feed, or the next older page of it",
+        subcommands: &["more"],
+        options: &[],
+        parse: feed,
+    },
+    CommandSpec {
+        name: "search",
+        synopsis: "/search \"words\" author:",
+        usage: &["/search \"words\" [author:@handle] [since:YYYY-MM-DD]"],
+        about: "Search posts",
+        subcommands: &[],
+        options: &["author", "since"],
+        parse: search,
+    },
+    CommandSpec {
+        name: "like",
+        synopsis: "/like <id>",
+        usage: &["/like <post id>"],
+        about: "Like a post",
+        subcommands: &[],
+        options: &[],
+        parse: |args| {
+            Ok(Command::Like {
+                post_id: post_id(&args.required("post id")?)?,
+            })
+        },
+    },
+    CommandSpec {
+        name: "unlike",
+        synopsis: "/unlike <id>",
+        usage: &["/unlike <post id>"],
+        about: "Take back a like",
+        subcommands: &[],
+        options: &[],
+        parse: |args| {
+            Ok(Command::Unlike {
+                post_id: post_id(&args.required("post id")?)?,
+            })
+        },
+    },
+    CommandSpec {
+        name: "follow",
+        synopsis: "/follow @h",
+        usage: &["/follow @handle"],
+        about: "Follow a user",
+        subcommands: &[],
+        options: &[],
+        parse: |args| {
+            Ok(Command::Follow {
+                handle: handle(&args.required("handle")?)?,
+            })
+        },
+    },
+    CommandSpec {
+        name: "unfollow",
+        synopsis: "/unfollow @h",
+        usage: &["/unfollow @handle"],
+        about: "Stop following a user",
+        subcommands: &[],
+        options: &[],
+        parse: |args| {
+            Ok(Command::Unfollow {
+                handle: handle(&args.required("handle")?)?,
+            })
+        },
+    },
+    CommandSpec {
+        name: "profile",
+        synopsis: "/profile @h | edit",
+        usage: &["/profile @handle", "/profile edit display_name:\"Name\""],
+        about: "Show a profile, or change your display name (empty clears it)",
+        subcommands: &["edit"],
+        options: &["display_name"],
+        parse: profile,
+    },
+    CommandSpec {
+        name: "who",
+        synopsis: "/who <query>",
+        usage: &["/who <query>"],
+        about: "Search the user directory by handle or name",
+        subcommands: &[],
+        options: &[],
+        parse: who,
+    },
+    CommandSpec {
+        name: "dm",
+        synopsis: "/dm @h [\"text\"]",
+        usage: &["/dm @handle [\"text\"]"],
+        about: "Send a direct message, or open the conversation",
+        subcommands: &[],
+        options: &[],
+        parse: |args| {
+            Ok(Command::Dm {
+                handle: handle(&args.required("handle")?)?,
+                text: args.optional().map(|token| token.text),
+            })
+        },
+    },
+    CommandSpec {
+        name: "inbox",
+        synopsis: "/inbox",
+        usage: &["/inbox"],
+        about: "List conversations",
+        subcommands: &[],
+        options: &[],
+        parse: |_| Ok(Command::Inbox),
+    },
+    CommandSpec {
+        name: "notifications",
+        synopsis: "/notifications [open]",
+        usage: &[
+            "/notifications [ls]",
+            "/notifications open <id>",
+            "/notifications read",
+        ],
+        about: "List notifications, open one or mark them all read",
+        subcommands: &["ls", "open", "read"],
+        options: &[],
+        parse: notifications,
+    },
+    CommandSpec {
+        name: "tasks",
+        synopsis: "/tasks add|ls|edit|done",
+        usage: &[
+            "/tasks add \"title\" [\"description\"] [priority:high] [due:3d] [tag:a,b]",
+            "/tasks ls [status] [tag:name] [priority:high] [due<7d] [sort:due|priority|updated]",
+            "/tasks edit <id> [title:..] [desc:..] [priority:..] [due:<when>|due:none]",
+            "/tasks start|done|cancel|reopen|rm <id>",
+            "/tasks tag|untag <id> <tag>...",
+        ],
+        about: "Track local tasks; completing one writes a report",
+        subcommands: &[
+            "add", "ls", "edit", "start", "done", "cancel", "reopen", "tag", "untag", "rm",
+        ],
+        options: &["title", "desc", "priority", "due", "tag", "sort"],
+        parse: tasks,
+    },
+    CommandSpec {
+        name: "report",
+        synopsis: "/report",
+        usage: &["/report"],
+        about: "List generated reports",
+        subcommands: &[],
+        options: &[],
+        parse: |_| Ok(Command::ReportSync),
+    },
+    CommandSpec {
+        name: "outbox",
+        synopsis: "/outbox [retry|drop id]",
+        usage: &["/outbox [ls]", "/outbox retry <id>", "/outbox drop <id>"],
+        about: "Inspect, retry or drop queued writes",
+        subcommands: &["ls", "retry", "drop"],
+        options: &[],
+        parse: outbox,
+    },
+    CommandSpec {
+        name: "settings",
+        synopsis: "/settings show|set",
+        usage: &["/settings show", "/settings set <key>=<value>"],
+        about: "Show or change settings",
+        subcommands: &["show", "set"],
+        options: &[],
+        parse: settings,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|spec| spec.name == name)
+}
+
+pub fn parse_command(input: &str) -> Result<Command, ParseError> {
+    let mut tokens = tokenize(input)?.into_iter();
+    let first = tokens.next().ok_or(ParseError::NotACommand {
+        span: 0..input.len(),
+    })?;
+    let name = match first.text.strip_prefix('/') {
+        Some(name) if first.key.is_none() => name,
+        _ => return Err(ParseError::NotACommand { span: first.span }),
+    };
+    let spec = find(name).ok_or_else(|| ParseError::UnknownCommand {
+        name: name.to_string(),
+        span: first.span.clone(),
+    })?;
+    let mut args = Args::new(spec, tokens.collect(), input.trim_end().len())?;
+    let command = (spec.parse)(&mut args)?;
+    args.finish()?;
+    Ok(command)
+}
+
+/// `/help` lists every command; `/help <command>` shows its forms.
+pub fn help_text(command: Option<&str>) -> String {
+    match command.and_then(find) {
+        Some(spec) => std::iter::once(spec.about.to_string())
+            .chain(spec.usage.iter().map(|usage| format!("  {}", usage)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => std::iter::once("Commands (/help <command> for details):".to_string())
+            .chain(
+                COMMANDS
+                    .iter()
+                    .map(|spec| format!("  {:<26} {}", spec.synopsis, spec.about)),
+            )
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+fn handle(token: &Token) -> Result<String, ParseError> {
+    let handle = token.text.trim_start_matches('@');
+    if handle.is_empty() {
+        return Err(token.invalid("handle", "it is empty"));
+    }
+    Ok(handle.to_string())
+}
+
+fn post_id(token: &Token) -> Result<String, ParseError> {
+    Ok(token.parse::<uuid::Uuid>("post id")?.to_string())
+}
+
+/// A row id such as `3` or `#3`.
+fn id(token: &Token) -> Result<i64, ParseError> {
+    token
+        .text
+        .trim_start_matches('#')
+        .parse()
+        .map_err(|err| token.invalid("id", err))
+}
+
+fn audience(args: &mut Args) -> Result<Option<AudienceScope>, ParseError> {
+    args.option("audience")
+        .map(|token| token.parse("audience"))
+        .transpose()
+}
+
+/// The time given by `at:` or `in:`, if any.
+fn send_at(args: &mut Args) -> Result<Option<OffsetDateTime>, ParseError> {
+    match (args.option("at"), args.option("in")) {
+        (Some(_), Some(delay)) => Err(ParseError::ConflictingOptions {
+            key: "in".into(),
+            other: "at",
+            span: delay.span,
+        }),
+        (Some(at), None) => parse_at(&at.text)
+            .map(Some)
+            .map_err(|err| at.invalid("time", err)),
+        (None, Some(delay)) => parse_in(&delay.text, OffsetDateTime::now_utc())
+            .map(Some)
+            .map_err(|err| delay.invalid("delay", err)),
+        (None, None) => Ok(None),
+    }
+}
+
+fn help(args: &mut Args) -> Result<Command, ParseError> {
+    let command = match args.optional() {
+        Some(token) => {
+            let name = token.text.trim_start_matches('/');
+            let spec = find(name).ok_or_else(|| ParseError::UnknownCommand {
+                name: name.to_string(),
+                span: token.span.clone(),
+            })?;
+            Some(spec.name.to_string())
+        }
+        None => None,
+    };
+    Ok(Command::Help { command })
+}
+
+fn login(args: &mut Args) -> Result<Command, ParseError> {
+    let email = args.option("email").ok_or_else(|| args.missing("email:"))?;
+    let password = args.option("pw").ok_or_else(|| args.missing("pw:"))?;
+    Ok(Command::Login {
+        email: email.text,
+        password: password.text,
+    })
+}
+
+fn account(args: &mut Args) -> Result<Command, ParseError> {
+    let Some(sub) = args.optional() else {
+        return Ok(Command::AccountList);
+    };
+    Ok(match sub.text.as_str() {
+        "list" | "ls" => Command::AccountList,
+        "switch" => Command::AccountSwitch {
+            name: args.required("account email")?.text,
+        },
+        "remove" | "rm" => Command::AccountRemove {
+            name: args.required("account email")?.text,
+        },
+        _ => return Err(args.unknown_subcommand(&sub)),
+    })
+}
+
+fn post(args: &mut Args) -> Result<Command, ParseError> {
+    let text = args.required("quoted text")?.text;
+    let audience = audience(args)?.unwrap_or_default();
+    Ok(match send_at(args)? {
+        Some(send_at) => Command::SchedulePost {
+            text,
+            audience,
+            send_at,
+        },
+        None => Command::Post { text, audience },
+    })
+}
+
+fn compose(args: &mut Args) -> Result<Command, ParseError> {
+    Ok(Command::Compose {
+        text: args.optional().map(|token| token.text),
+        audience: audience(args)?.unwrap_or_default(),
+    })
+}
+
+fn drafts(args: &mut Args) -> Result<Command, ParseError> {
+    let Some(sub) = args.optional() else {
+        return Ok(Command::Drafts);
+    };
+    Ok(match sub.text.as_str() {
+        "ls" => Command::Drafts,
+        "open" => Command::DraftOpen {
+            id: id(&args.required("draft id")?)?,
+        },
+        "send" => Command::DraftSend {
+            id: id(&args.required("draft id")?)?,
+        },
+        "rm" | "drop" => Command::DraftDelete {
+            id: id(&args.required("draft id")?)?,
+        },
+        _ => return Err(args.unknown_subcommand(&sub)),
+    })
+}
+
+fn scheduled(args: &mut Args) -> Result<Command, ParseError> {
+    let Some(sub) = args.optional() else {
+        return Ok(Command::Scheduled);
+    };
+    Ok(match sub.text.as_str() {
+        "ls" => Command::Scheduled,
+        "edit" => Command::ScheduledEdit {
+            id: id(&args.required("scheduled post id")?)?,
+            text: args.optional().map(|token| token.text),
+            audience: audience(args)?,
+            send_at: send_at(args)?,
+        },
+        "cancel" | "rm" => Command::ScheduledCancel {
+            id: id(&args.required("scheduled post id")?)?,
+        },
+        _ => return Err(args.unknown_subcommand(&sub)),
+    })
+}
+
+fn reply(args: &mut Args) -> Result<Command, ParseError> {
+    Ok(Command::Reply {
+        post_id: post_id(&args.required("post id")?)?,
+        text: args.required("quoted text")?.text,
+        audience: audience(args)?,
+    })
+}
+
+fn feed(args: &mut Args) -> Result<Command, ParseError> {
+    let filter = |token: Option<Token>| {
+        token
+            .map(|token| token.parse::<FeedFilter>("feed"))
+            .transpose()
+    };
+    match args.optional() {
+        Some(token) if token.text == "more" => Ok(Command::FeedMore {
+            filter: filter(args.optional())?,
+        }),
+        token => Ok(Command::Feed {
+            filter: filter(token)?.unwrap_or_default(),
+        }),
+    }
+}
+
+/// The words can be quoted as one phrase or left bare.
+fn search(args: &mut Args) -> Result<Command, ParseError> {
+    let mut query = SearchQuery {
+        text: args
+            .rest()
+            .into_iter()
+            .map(|token| token.text)
+            .collect::<Vec<_>>()
+            .join(" "),
+        ..Default::default()
+    };
+    if let Some(author) = args.option("author") {
+        query.author = Some(handle(&author)?);
+    }
+    if let Some(since) = args.option("since") {
+        query.since = Some(
+            SearchQuery::parse_since(&since.text)
+                .map_err(|_| since.invalid("date", "expected a date like 2026-01-01"))?,
+        );
+    }
+    if query.terms().is_empty() {
+        return Err(args.missing("search text"));
+    }
+    Ok(Command::Search { query })
+}
+
+fn profile(args: &mut Args) -> Result<Command, ParseError> {
+    let first = args.required("handle")?;
+    if first.text != "edit" {
+        return Ok(Command::Profile {
+            handle: handle(&first)?,
+        });
+    }
+    let name = args
+        .option("display_name")
+        .ok_or_else(|| args.missing("display_name:"))?;
+    let name = name.text.trim();
+    Ok(Command::ProfileEdit {
+        display_name: Some(name.to_string()).filter(|name| !name.is_empty()),
+    })
+}
+
+fn who(args: &mut Args) -> Result<Command, ParseError> {
+    let query = args
+        .rest()
+        .into_iter()
+        .map(|token| token.text)
+        .collect::<Vec<_>>()
+        .join(" ");
+    if query.trim().is_empty() {
+        return Err(args.missing("query"));
+    }
+    Ok(Command::Who {
+        query: query.trim().to_string(),
+    })
+}
+
+fn notifications(args: &mut Args) -> Result<Command, ParseError> {
+    let Some(sub) = args.optional() else {
+        return Ok(Command::Notifications);
+    };
+    Ok(match sub.text.as_str() {
+        "ls" => Command::Notifications,
+        "open" => Command::NotificationOpen {
+            id: id(&args.required("notification id")?)?,
+        },
+        "read" => Command::NotificationsRead,
+        _ => return Err(args.unknown_subcommand(&sub)),
+    })
+}
+
+fn tasks(args: &mut Args) -> Result<Command, ParseError> {
+    let sub = args.required("subcommand")?;
+    let now = OffsetDateTime::now_utc();
+    Ok(match sub.text.as_str() {
+        "add" => Command::TasksAdd {
+            task: NewTask {
+                title: args.required("title")?.text,
+                description: args.optional().map(|token| token.text).unwrap_or_default(),
+                priority: priority(args)?.unwrap_or_default(),
+                due_at: args
+                    .option("due")
+                    .map(|token| due(&token, now))
+                    .transpose()?,
+                tags: args
+                    .option("tag")
+                    .map(|token| tag_list(&token))
+                    .unwrap_or_default(),
+            },
+        },
+        "ls" => Command::TasksList {
+            query: task_query(args, now)?,
+        },
+        "edit" => {
+            let id = id(&args.required("task id")?)?;
+            let changes = TaskChanges {
+                title: args.option("title").map(|token| token.text),
+                description: args.option("desc").map(|token| token.text),
+                priority: priority(args)?,
+                due_at: args
+                    .option("due")
+                    .map(|token| match token.text.as_str() {
+                        "none" | "" => Ok(None),
+                        _ => due(&token, now).map(Some),
+                    })
+                    .transpose()?,
+            };
+            if changes.is_empty() {
+                return Err(args.missing("title:, desc:, priority: or due:"));
+            }
+            Command::TasksEdit { id, changes }
+        }
+        "start" | "cancel" | "reopen" => Command::TasksSetStatus {
+            id: id(&args.required("task id")?)?,
+            status: match sub.text.as_str() {
+                "start" => TaskStatus::InProgress,
+                "cancel" => TaskStatus::Cancelled,
+                _ => TaskStatus::Open,
+            },
+        },
+        "done" => Command::TasksDone {
+            id: id(&args.required("task id")?)?,
+        },
+        "tag" | "untag" => {
+            let id = id(&args.required("task id")?)?;
+            let tags = args.rest().iter().flat_map(tag_list).collect::<Vec<_>>();
+            if tags.is_empty() {
+                return Err(args.missing("tag"));
+            }
+            match sub.text.as_str() {
+                "tag" => Command::TasksTag { id, tags },
+                _ => Command::TasksUntag { id, tags },
+            }
+        }
+        "rm" => Command::TasksDelete {
+            id: id(&args.required("task id")?)?,
+        },
+        _ => return Err(args.unknown_subcommand(&sub)),
+    })
+}
+
+/// `/tasks ls` words: a status and `due<when>` in any order, plus the
+/// `tag:`, `priority:` and `sort:` options.
+fn task_query(args: &mut Args, now: OffsetDateTime) -> Result<TaskQuery, ParseError> {
+    let mut query = TaskQuery {
+        tag: args.option("tag").map(|token| token.text),
+        priority: priority(args)?,
+        sort: args
+            .option("sort")
+            .map(|token| token.parse::<TaskSort>("sort"))
+            .transpose()?
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+    for token in args.rest() {
+        match token.text.strip_prefix("due<") {
+            Some(when) => {
+                let at = parse_due(when, now).map_err(|err| token.invalid("due", err))?;
+                query.due_before = Some(at);
+            }
+            None => query.status = Some(token.parse::<TaskStatus>("status")?),
+        }
+    }
+    Ok(query)
+}
+
+fn priority(args: &mut Args) -> Result<Option<TaskPriority>, ParseError> {
+    args.option("priority")
+        .map(|token| token.parse::<TaskPriority>("priority"))
+        .transpose()
+}
+
+fn due(token: &Token, now: OffsetDateTime) -> Result<OffsetDateTime, ParseError> {
+    parse_due(&token.text, now).map_err(|err| token.invalid("due", err))
+}
+
+/// Tags separated by commas, `#` optional.
+fn tag_list(token: &Token) -> Vec<String> {
+    token
+        .text
+        .split(',')
+        .map(normalize_tag)
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+fn outbox(args: &mut Args) -> Result<Command, ParseError> {
+    let Some(sub) = args.optional() else {
+        return Ok(Command::OutboxList);
+    };
+    Ok(match sub.text.as_str() {
+        "ls" => Command::OutboxList,
+        "retry" => Command::OutboxRetry {
+            id: id(&args.required("outbox id")?)?,
+        },
+        "drop" => Command::OutboxDrop {
+            id: id(&args.required("outbox id")?)?,
+        },
+        _ => return Err(args.unknown_subcommand(&sub)),
+    })
+}
+
+fn settings(args: &mut Args) -> Result<Command, ParseError> {
+    let sub = args.required("subcommand")?;
+    Ok(match sub.text.as_str() {
+        "show" => Command::SettingsShow,
+        "set" => {
+            let pair = args.required("key=value")?;
+            let (key, value) = pair
+                .text
+                .split_once('=')
+                .ok_or_else(|| pair.invalid("setting", "expected key=value"))?;
+            Command::SettingsSet {
+                key: key.to_string(),
+                value: value.to_string(),
+            }
+        }
+        _ => return Err(args.unknown_subcommand(&sub)),
+    })
+}
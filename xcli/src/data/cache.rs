@@ -1,11 +1,16 @@
-use anyhow::Result;
-use rusqlite::params;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row, ToSql};
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 use uuid::Uuid;
 
-use crate::domain::{AudienceScope, Post, Profile};
+use crate::domain::{
+    parse_spans, AudienceScope, FeedCursor, FeedFilter, Post, Profile, SearchQuery,
+};
 
-use super::AppDatabase;
+use super::{format_ts, AppDatabase};
+
+/// Columns read by `map_post`, in order.
+const POST_COLUMNS: &str = "id, author, body, audience, created_at, author_handle, liked, like_count, reply_to, root_id, reply_to_handle";
 
 pub struct CacheDao<'a> {
     db: &'a AppDatabase,
@@ -16,6 +21,8 @@ impl<'a> CacheDao<'a> {
         Self { db }
     }
 
+    /// Caches `profile` and fills in its handle on cached posts that came
+    /// without one.
     pub fn upsert_profile(&self, profile: &Profile) -> Result<()> {
         let conn = self.db.connection();
         conn.execute(
@@ -25,64 +32,473 @@ impl<'a> CacheDao<'a> {
                 profile.id.to_string(),
                 &profile.handle,
                 profile.display_name.as_deref(),
-                profile
-                    .created_at
-                    .format(&Rfc3339)
-                    .unwrap_or_else(|_| profile.created_at.to_string())
+                format_ts(profile.created_at)
             ],
         )?;
+        conn.execute(
+            "UPDATE cache_posts SET author_handle=?2 WHERE author=?1 AND author_handle IS NULL",
+            params![profile.id.to_string(), &profile.handle],
+        )?;
+        Ok(())
+    }
+
+    /// Sets `author_handle` from cached profiles on posts that lack it, e.g.
+    /// ones delivered by Realtime.
+    pub fn fill_handles(&self, posts: &mut [Post]) -> Result<()> {
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare("SELECT handle FROM cache_profiles WHERE id=?1")?;
+        for post in posts.iter_mut().filter(|p| p.author_handle.is_none()) {
+            post.author_handle = stmt
+                .query_row(params![post.author.to_string()], |row| row.get(0))
+                .optional()?;
+        }
         Ok(())
     }
 
-    pub fn upsert_post(&self, post: &Post) -> Result<()> {
+    /// Caches `post` as seen by `viewer`, the signed-in account that fetched
+    /// it. Each account keeps its own copy so audiences and likes don't mix.
+    pub fn upsert_post(&self, viewer: &str, post: &Post) -> Result<()> {
+        self.upsert(viewer, post, true)
+    }
+
+    /// Caches a server search hit for `viewer`. It is searchable offline but
+    /// stays out of `list_feed` until a feed fetch returns it too.
+    pub fn upsert_search_hit(&self, viewer: &str, post: &Post) -> Result<()> {
+        self.upsert(viewer, post, false)
+    }
+
+    fn upsert(&self, viewer: &str, post: &Post, in_feed: bool) -> Result<()> {
         let conn = self.db.connection();
         conn.execute(
-            "INSERT INTO cache_posts(id, author, body, audience, created_at, author_handle, liked, like_count)
-             VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
-             ON CONFLICT(id) DO UPDATE SET body=excluded.body, audience=excluded.audience, created_at=excluded.created_at, author_handle=excluded.author_handle, liked=excluded.liked, like_count=excluded.like_count",
+            "INSERT INTO cache_posts(viewer, id, author, body, audience, created_at, author_handle, liked, like_count, reply_to, root_id, reply_to_handle, spans_indexed, in_feed)
+             VALUES(?9, ?1, ?2, ?3, ?4, ?5, COALESCE(?6, (SELECT handle FROM cache_profiles WHERE id=?2)), ?7, ?8, ?10, ?11, ?12, 1, ?13)
+             ON CONFLICT(viewer, id) DO UPDATE SET body=excluded.body, audience=excluded.audience, created_at=excluded.created_at, author_handle=COALESCE(excluded.author_handle, author_handle), liked=excluded.liked, like_count=excluded.like_count,
+             reply_to=excluded.reply_to, root_id=excluded.root_id, reply_to_handle=COALESCE(excluded.reply_to_handle, reply_to_handle), spans_indexed=1, in_feed=MAX(in_feed, excluded.in_feed)",
             params![
                 post.id.to_string(),
                 post.author.to_string(),
                 &post.body,
                 post.audience.to_string(),
-                post
-                    .created_at
-                    .format(&Rfc3339)
-                    .unwrap_or_else(|_| post.created_at.to_string()),
+                format_ts(post.created_at),
                 post.author_handle.as_deref(),
                 post.liked as i64,
-                post.like_count
+                post.like_count,
+                viewer,
+                post.reply_to.map(|id| id.to_string()),
+                post.root_id.map(|id| id.to_string()),
+                post.reply_to_handle.as_deref(),
+                in_feed as i64
+            ],
+        )?;
+        index_spans(&conn, viewer, &post.id.to_string(), &post.body)
+    }
+
+    /// Parses spans for posts cached before they were indexed. Returns how
+    /// many posts were indexed.
+    pub fn index_pending_spans(&self) -> Result<usize> {
+        let conn = self.db.connection();
+        let pending = {
+            let mut stmt =
+                conn.prepare("SELECT viewer, id, body FROM cache_posts WHERE spans_indexed=0")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        for (viewer, id, body) in &pending {
+            index_spans(&conn, viewer, id, body)?;
+            conn.execute(
+                "UPDATE cache_posts SET spans_indexed=1 WHERE viewer=?1 AND id=?2",
+                params![viewer, id],
+            )?;
+        }
+        Ok(pending.len())
+    }
+
+    pub fn get_post(&self, viewer: &str, id: &str) -> Result<Option<Post>> {
+        let conn = self.db.connection();
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM cache_posts WHERE viewer=?1 AND id=?2",
+                POST_COLUMNS
+            ),
+            params![viewer, id],
+            map_post,
+        )
+        .optional()
+        .context("cached post lookup")
+    }
+
+    pub fn list_posts(&self, viewer: &str, limit: usize) -> Result<Vec<Post>> {
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM cache_posts WHERE viewer=?1 ORDER BY created_at DESC LIMIT ?2",
+            POST_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![viewer, limit as i64], map_post)?;
+        let posts = rows.filter_map(Result::ok).collect();
+        Ok(posts)
+    }
+
+    /// Serves a feed from the cache when Supabase is unreachable. Only posts
+    /// cached for `user_id` by a feed fetch are considered, and it scopes the `following` and
+    /// `me` filters; `before` pages backwards.
+    pub fn list_feed(
+        &self,
+        filter: &FeedFilter,
+        user_id: &str,
+        before: Option<&FeedCursor>,
+        limit: usize,
+    ) -> Result<Vec<Post>> {
+        let conn = self.db.connection();
+        let clause = match filter {
+            FeedFilter::Global => "audience='public'",
+            FeedFilter::Following => {
+                "author=?1 OR author IN (SELECT followee FROM cache_follows WHERE follower=?1)"
+            }
+            FeedFilter::Me => "author=?1",
+            // Needs the account's own profile cached to know its handle.
+            FeedFilter::Mentions => {
+                "id IN (SELECT s.post_id FROM cache_post_spans s JOIN cache_profiles p ON p.id=?1
+                        WHERE s.viewer=?1 AND s.kind='mention' AND s.value=lower(p.handle))"
+            }
+            FeedFilter::Tag(_) => {
+                "id IN (SELECT post_id FROM cache_post_spans WHERE viewer=?1 AND kind='hashtag' AND value=?5)"
+            }
+        };
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM cache_posts
+             WHERE viewer=?1 AND in_feed=1 AND ({}) AND (?3 IS NULL OR created_at < ?3 OR (created_at = ?3 AND id < ?4))
+             ORDER BY created_at DESC, id DESC LIMIT ?2",
+            POST_COLUMNS, clause
+        ))?;
+        let limit = limit as i64;
+        let before_created_at = before.map(|c| format_ts(c.created_at));
+        let before_id = before.map(|c| c.id.to_string());
+        let mut args: Vec<&dyn ToSql> = vec![&user_id, &limit, &before_created_at, &before_id];
+        if let FeedFilter::Tag(tag) = filter {
+            args.push(tag);
+        }
+        let rows = stmt.query_map(args.as_slice(), map_post)?;
+        Ok(rows.filter_map(Result::ok).collect())
+    }
+
+    /// Full-text search over posts cached for `viewer`, best bm25 match
+    /// first. Body matches weigh more than author handle matches.
+    pub fn search_posts(
+        &self,
+        viewer: &str,
+        query: &SearchQuery,
+        limit: usize,
+    ) -> Result<Vec<Post>> {
+        let Some(expr) = query.match_expr() else {
+            return Ok(Vec::new());
+        };
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM cache_posts
+             JOIN (SELECT viewer AS hit_viewer, post_id, bm25(cache_posts_fts, 1.0, 0.5) AS rank
+                   FROM cache_posts_fts WHERE cache_posts_fts MATCH ?1)
+               ON hit_viewer = viewer AND post_id = id
+             WHERE viewer = ?2
+               AND (?3 IS NULL OR author_handle = ?3 COLLATE NOCASE)
+               AND (?4 IS NULL OR created_at >= ?4)
+             ORDER BY rank LIMIT ?5",
+            POST_COLUMNS
+        ))?;
+        let rows = stmt.query_map(
+            params![
+                expr,
+                viewer,
+                query.author.as_deref(),
+                query.since.map(|d| format!("{}T00:00:00.000000Z", d)),
+                limit as i64
+            ],
+            map_post,
+        )?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("searching cached posts")
+    }
+
+    /// The newest posts by `author` cached for `viewer`.
+    pub fn posts_by_author(&self, viewer: &str, author: &str, limit: usize) -> Result<Vec<Post>> {
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM cache_posts WHERE viewer=?1 AND author=?2
+             ORDER BY created_at DESC, id DESC LIMIT ?3",
+            POST_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![viewer, author, limit as i64], map_post)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("cached posts by author")
+    }
+
+    /// Every cached post of the conversation started by `root`, oldest
+    /// first.
+    pub fn thread(&self, viewer: &str, root: &str) -> Result<Vec<Post>> {
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM cache_posts WHERE viewer=?1 AND (id=?2 OR root_id=?2)
+             ORDER BY created_at, id",
+            POST_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![viewer, root], map_post)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("cached thread")
+    }
+
+    /// Remembers where the last page served for `filter` ended, or clears it
+    /// once the feed is exhausted.
+    pub fn set_feed_cursor(
+        &self,
+        viewer: &str,
+        filter: &FeedFilter,
+        cursor: Option<&FeedCursor>,
+    ) -> Result<()> {
+        let conn = self.db.connection();
+        conn.execute(
+            "INSERT INTO feed_cursors(viewer, filter, before_created_at, before_id, updated_at) VALUES(?5, ?1, ?2, ?3, ?4)
+             ON CONFLICT(viewer, filter) DO UPDATE SET before_created_at=excluded.before_created_at,
+             before_id=excluded.before_id, updated_at=excluded.updated_at",
+            params![
+                filter.to_string(),
+                cursor.map(|c| format_ts(c.created_at)),
+                cursor.map(|c| c.id.to_string()),
+                format_ts(OffsetDateTime::now_utc()),
+                viewer
             ],
         )?;
         Ok(())
     }
 
-    pub fn list_posts(&self, limit: usize) -> Result<Vec<Post>> {
+    pub fn feed_cursor(&self, viewer: &str, filter: &FeedFilter) -> Result<Option<FeedCursor>> {
         let conn = self.db.connection();
+        let row: Option<(Option<String>, Option<String>)> = conn
+            .query_row(
+                "SELECT before_created_at, before_id FROM feed_cursors WHERE viewer=?1 AND filter=?2",
+                params![viewer, filter.to_string()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(row.and_then(|(created_at, id)| {
+            Some(FeedCursor {
+                created_at: OffsetDateTime::parse(&created_at?, &Rfc3339).ok()?,
+                id: Uuid::parse_str(&id?).ok()?,
+            })
+        }))
+    }
+
+    /// The filter whose page was served most recently, used by `/feed more`.
+    pub fn last_feed_filter(&self, viewer: &str) -> Result<Option<FeedFilter>> {
+        let conn = self.db.connection();
+        let raw: Option<String> = conn
+            .query_row(
+                "SELECT filter FROM feed_cursors WHERE viewer=?1 ORDER BY updated_at DESC LIMIT 1",
+                params![viewer],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(raw.and_then(|s| s.parse().ok()))
+    }
+
+    pub fn mark_feed_synced(&self, viewer: &str, filter: &FeedFilter) -> Result<()> {
+        let now = OffsetDateTime::now_utc();
+        let conn = self.db.connection();
+        conn.execute(
+            "INSERT INTO feed_sync(viewer, filter, synced_at) VALUES(?1, ?2, ?3)
+             ON CONFLICT(viewer, filter) DO UPDATE SET synced_at=excluded.synced_at",
+            params![viewer, filter.to_string(), format_ts(now)],
+        )?;
+        Ok(())
+    }
+
+    pub fn feed_synced_at(
+        &self,
+        viewer: &str,
+        filter: &FeedFilter,
+    ) -> Result<Option<OffsetDateTime>> {
+        let conn = self.db.connection();
+        let raw: Option<String> = conn
+            .query_row(
+                "SELECT synced_at FROM feed_sync WHERE viewer=?1 AND filter=?2",
+                params![viewer, filter.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(raw.and_then(|s| OffsetDateTime::parse(&s, &Rfc3339).ok()))
+    }
+
+    pub fn profile_by_handle(&self, handle: &str) -> Result<Option<Profile>> {
+        let conn = self.db.connection();
+        conn.query_row(
+            "SELECT id, handle, display_name, created_at FROM cache_profiles WHERE handle=?1",
+            params![handle],
+            map_profile,
+        )
+        .optional()
+        .context("cached profile lookup")
+    }
+
+    /// Handles of cached profiles starting with `prefix`, for tab completion.
+    pub fn handles(&self, prefix: &str, limit: usize) -> Result<Vec<String>> {
+        let conn = self.db.connection();
+        let pattern = format!(
+            "{}%",
+            prefix
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
         let mut stmt = conn.prepare(
-            "SELECT id, author, body, audience, created_at, author_handle, liked, like_count FROM cache_posts ORDER BY created_at DESC LIMIT ?1",
+            "SELECT handle FROM cache_profiles WHERE handle LIKE ?1 ESCAPE '\\'
+             ORDER BY handle LIMIT ?2",
         )?;
-        let rows = stmt.query_map(params![limit as i64], |row| {
-            let audience: String = row.get(3)?;
-            let created_at = row
-                .get::<_, String>(4)
-                .ok()
-                .and_then(|s| OffsetDateTime::parse(&s, &Rfc3339).ok())
-                .unwrap_or_else(|| OffsetDateTime::now_utc());
-            Ok(Post {
-                id: Uuid::parse_str(row.get::<_, String>(0)?.as_str())
-                    .unwrap_or_else(|_| Uuid::nil()),
-                author: Uuid::parse_str(row.get::<_, String>(1)?.as_str())
-                    .unwrap_or_else(|_| Uuid::nil()),
-                body: row.get(2)?,
-                audience: audience.parse().unwrap_or(AudienceScope::Public),
-                created_at,
-                author_handle: row.get(5).ok(),
-                liked: row.get::<_, i64>(6)? != 0,
-                like_count: row.get(7)?,
-            })
-        })?;
-        let posts = rows.filter_map(Result::ok).collect();
-        Ok(posts)
+        let rows = stmt.query_map(params![pattern, limit as i64], |row| row.get(0))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("listing cached handles")
     }
+
+    /// Cached profiles whose handle or display name contains `query`, the
+    /// offline fallback for `/who`.
+    pub fn search_profiles(&self, query: &str, limit: usize) -> Result<Vec<Profile>> {
+        let conn = self.db.connection();
+        let pattern = format!(
+            "%{}%",
+            query
+                .trim_start_matches('@')
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        let mut stmt = conn.prepare(
+            "SELECT id, handle, display_name, created_at FROM cache_profiles
+             WHERE handle LIKE ?1 ESCAPE '\\' OR display_name LIKE ?1 ESCAPE '\\'
+             ORDER BY handle LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![pattern, limit as i64], map_profile)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("searching cached profiles")
+    }
+
+    /// Mirrors a like or unlike into `cache_likes` and adjusts the cached
+    /// post's `liked`/`like_count` in `user_id`'s cache, returning the post if
+    /// that account has it cached.
+    pub fn set_like(&self, user_id: &str, post_id: &str, liked: bool) -> Result<Option<Post>> {
+        let now = OffsetDateTime::now_utc();
+        {
+            let conn = self.db.connection();
+            if liked {
+                conn.execute(
+                    "INSERT OR IGNORE INTO cache_likes(user_id, post_id, created_at) VALUES(?1, ?2, ?3)",
+                    params![
+                        user_id,
+                        post_id,
+                        format_ts(now)
+                    ],
+                )?;
+                conn.execute(
+                    "UPDATE cache_posts SET liked=1, like_count=like_count+1 WHERE viewer=?1 AND id=?2 AND liked=0",
+                    params![user_id, post_id],
+                )?;
+            } else {
+                conn.execute(
+                    "DELETE FROM cache_likes WHERE user_id=?1 AND post_id=?2",
+                    params![user_id, post_id],
+                )?;
+                conn.execute(
+                    "UPDATE cache_posts SET liked=0, like_count=MAX(like_count-1, 0) WHERE viewer=?1 AND id=?2 AND liked=1",
+                    params![user_id, post_id],
+                )?;
+            }
+        }
+        self.get_post(user_id, post_id)
+    }
+
+    pub fn set_follow(&self, follower: &str, followee: &str, following: bool) -> Result<()> {
+        let conn = self.db.connection();
+        if following {
+            let now = OffsetDateTime::now_utc();
+            conn.execute(
+                "INSERT OR IGNORE INTO cache_follows(follower, followee, created_at) VALUES(?1, ?2, ?3)",
+                params![
+                    follower,
+                    followee,
+                    format_ts(now)
+                ],
+            )?;
+        } else {
+            conn.execute(
+                "DELETE FROM cache_follows WHERE follower=?1 AND followee=?2",
+                params![follower, followee],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the spans stored for one cached post.
+fn index_spans(conn: &Connection, viewer: &str, post_id: &str, body: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM cache_post_spans WHERE viewer=?1 AND post_id=?2",
+        params![viewer, post_id],
+    )?;
+    for span in parse_spans(body) {
+        conn.execute(
+            "INSERT INTO cache_post_spans(viewer, post_id, kind, span_start, span_end, value)
+             VALUES(?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                viewer,
+                post_id,
+                span.kind.to_string(),
+                span.start as i64,
+                span.end as i64,
+                &span.value
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn map_profile(row: &Row<'_>) -> rusqlite::Result<Profile> {
+    let created_at: String = row.get(3)?;
+    Ok(Profile {
+        id: Uuid::parse_str(row.get::<_, String>(0)?.as_str()).unwrap_or_else(|_| Uuid::nil()),
+        handle: row.get(1)?,
+        display_name: row.get(2)?,
+        created_at: OffsetDateTime::parse(&created_at, &Rfc3339)
+            .unwrap_or_else(|_| OffsetDateTime::now_utc()),
+    })
+}
+
+fn map_post(row: &Row<'_>) -> rusqlite::Result<Post> {
+    let audience: String = row.get(3)?;
+    let created_at = row
+        .get::<_, String>(4)
+        .ok()
+        .and_then(|s| OffsetDateTime::parse(&s, &Rfc3339).ok())
+        .unwrap_or_else(OffsetDateTime::now_utc);
+    Ok(Post {
+        id: Uuid::parse_str(row.get::<_, String>(0)?.as_str()).unwrap_or_else(|_| Uuid::nil()),
+        author: Uuid::parse_str(row.get::<_, String>(1)?.as_str()).unwrap_or_else(|_| Uuid::nil()),
+        body: row.get(2)?,
+        audience: audience.parse().unwrap_or(AudienceScope::Public),
+        created_at,
+        author_handle: row.get(5).ok(),
+        liked: row.get::<_, i64>(6)? != 0,
+        like_count: row.get(7)?,
+        reply_to: uuid_column(row, 8)?,
+        root_id: uuid_column(row, 9)?,
+        reply_to_handle: row.get(10)?,
+    })
+}
+
+fn uuid_column(row: &Row<'_>, index: usize) -> rusqlite::Result<Option<Uuid>> {
+    Ok(row
+        .get::<_, Option<String>>(index)?
+        .and_then(|id| Uuid::parse_str(&id).ok()))
 }
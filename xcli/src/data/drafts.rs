@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, OptionalExtension, Row};
+use std::str::FromStr;
+
+use crate::domain::{AudienceScope, Draft};
+
+use super::{format_ts, parse_ts, AppDatabase};
+
+/// Drafts written before accounts were tracked, or while signed out, have
+/// no owner and show up for whichever account is active.
+const OWNED_BY: &str = "user_id IN (?1, '')";
+
+pub struct DraftDao<'a> {
+    db: &'a AppDatabase,
+}
+
+impl<'a> DraftDao<'a> {
+    pub fn new(db: &'a AppDatabase) -> Self {
+        Self { db }
+    }
+
+    /// Inserts `draft` for `user_id`, or updates it when it already has an
+    /// id. Returns the id, which is a new one if the draft now belongs to
+    /// another account.
+    pub fn save(&self, user_id: &str, draft: &Draft) -> Result<i64> {
+        let conn = self.db.connection();
+        let updated_at = format_ts(draft.updated_at);
+        if let Some(id) = draft.id {
+            let updated = conn.execute(
+                &format!(
+                    "UPDATE drafts SET body=?3, audience=?4, updated_at=?5 WHERE {} AND id=?2",
+                    OWNED_BY
+                ),
+                params![
+                    user_id,
+                    id,
+                    &draft.body,
+                    draft.audience.to_string(),
+                    updated_at
+                ],
+            )?;
+            if updated > 0 {
+                return Ok(id);
+            }
+            // Removed elsewhere (e.g. `/drafts rm`) while still open.
+            let inserted = conn.execute(
+                "INSERT INTO drafts(id, user_id, body, audience, updated_at) VALUES(?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(id) DO NOTHING",
+                params![
+                    id,
+                    user_id,
+                    &draft.body,
+                    draft.audience.to_string(),
+                    updated_at
+                ],
+            )?;
+            if inserted > 0 {
+                return Ok(id);
+            }
+        }
+        conn.execute(
+            "INSERT INTO drafts(user_id, body, audience, updated_at) VALUES(?1, ?2, ?3, ?4)",
+            params![user_id, &draft.body, draft.audience.to_string(), updated_at],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Drafts of `user_id`, most recently edited first.
+    pub fn list(&self, user_id: &str) -> Result<Vec<Draft>> {
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, body, audience, updated_at FROM drafts WHERE {}
+             ORDER BY updated_at DESC, id DESC",
+            OWNED_BY
+        ))?;
+        let rows = stmt.query_map(params![user_id], map_draft)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("listing drafts")
+    }
+
+    pub fn get(&self, user_id: &str, id: i64) -> Result<Option<Draft>> {
+        let conn = self.db.connection();
+        conn.query_row(
+            &format!(
+                "SELECT id, body, audience, updated_at FROM drafts WHERE {} AND id=?2",
+                OWNED_BY
+            ),
+            params![user_id, id],
+            map_draft,
+        )
+        .optional()
+        .context("draft lookup")
+    }
+
+    /// Returns whether `user_id` had such a draft.
+    pub fn delete(&self, user_id: &str, id: i64) -> Result<bool> {
+        let conn = self.db.connection();
+        let rows = conn.execute(
+            &format!("DELETE FROM drafts WHERE {} AND id=?2", OWNED_BY),
+            params![user_id, id],
+        )?;
+        Ok(rows > 0)
+    }
+}
+
+fn map_draft(row: &Row<'_>) -> rusqlite::Result<Draft> {
+    let audience: String = row.get(2)?;
+    let updated_at: String = row.get(3)?;
+    Ok(Draft {
+        id: Some(row.get(0)?),
+        body: row.get(1)?,
+        audience: AudienceScope::from_str(&audience).unwrap_or_default(),
+        updated_at: parse_ts(&updated_at),
+    })
+}
@@ -0,0 +1,59 @@
+use anyhow::{Context, Result};
+use rusqlite::params;
+use time::OffsetDateTime;
+
+use super::{format_ts, AppDatabase};
+
+/// Most lines kept; older ones are dropped as new ones come in.
+pub const HISTORY_LIMIT: usize = 1000;
+
+pub struct HistoryDao<'a> {
+    db: &'a AppDatabase,
+}
+
+impl<'a> HistoryDao<'a> {
+    pub fn new(db: &'a AppDatabase) -> Self {
+        Self { db }
+    }
+
+    /// Records an entered command line. Blank lines, repeats of the previous
+    /// line and `/login` lines, which carry a password, are not stored.
+    /// Returns whether the line was kept.
+    pub fn push(&self, line: &str, at: OffsetDateTime) -> Result<bool> {
+        if line.trim().is_empty() || line.trim_start().starts_with("/login") {
+            return Ok(false);
+        }
+        let conn = self.db.connection();
+        let last: Option<String> = conn
+            .query_row(
+                "SELECT line FROM command_history ORDER BY id DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .ok();
+        if last.as_deref() == Some(line) {
+            return Ok(false);
+        }
+        conn.execute(
+            "INSERT INTO command_history(line, created_at) VALUES(?1, ?2)",
+            params![line, format_ts(at)],
+        )?;
+        conn.execute(
+            "DELETE FROM command_history WHERE id <= (SELECT MAX(id) FROM command_history) - ?1",
+            params![HISTORY_LIMIT as i64],
+        )?;
+        Ok(true)
+    }
+
+    /// The newest `limit` lines, oldest first.
+    pub fn recent(&self, limit: usize) -> Result<Vec<String>> {
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare(
+            "SELECT line FROM (SELECT id, line FROM command_history ORDER BY id DESC LIMIT ?1)
+             ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![limit as i64], |row| row.get(0))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("loading command history")
+    }
+}
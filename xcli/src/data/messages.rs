@@ -0,0 +1,151 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, OptionalExtension, Row};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::domain::{Conversation, Message};
+
+use super::{format_ts, parse_ts, parse_uuid, AppDatabase};
+
+const COLUMNS: &str =
+    "id, sender, recipient, body, created_at, read_at, sender_handle, recipient_handle";
+
+pub struct MessageDao<'a> {
+    db: &'a AppDatabase,
+}
+
+impl<'a> MessageDao<'a> {
+    pub fn new(db: &'a AppDatabase) -> Self {
+        Self { db }
+    }
+
+    /// Caches `message` for `viewer`. A read mark or handle already cached
+    /// is kept when the incoming copy lacks one.
+    pub fn upsert(&self, viewer: &str, message: &Message) -> Result<()> {
+        let viewer_id = Uuid::parse_str(viewer).unwrap_or_default();
+        let conn = self.db.connection();
+        conn.execute(
+            "INSERT INTO cache_messages(viewer, id, peer, sender, recipient, body, created_at, read_at, sender_handle, recipient_handle)
+             VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT(viewer, id) DO UPDATE SET body=excluded.body,
+             read_at=COALESCE(excluded.read_at, read_at),
+             sender_handle=COALESCE(excluded.sender_handle, sender_handle),
+             recipient_handle=COALESCE(excluded.recipient_handle, recipient_handle)",
+            params![
+                viewer,
+                message.id.to_string(),
+                message.peer(viewer_id).to_string(),
+                message.sender.to_string(),
+                message.recipient.to_string(),
+                &message.body,
+                format_ts(message.created_at),
+                message.read_at.map(format_ts),
+                message.sender_handle.as_deref(),
+                message.recipient_handle.as_deref()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Creation time of the newest cached message, where an incremental
+    /// sync picks up.
+    pub fn latest(&self, viewer: &str) -> Result<Option<OffsetDateTime>> {
+        let conn = self.db.connection();
+        let latest: Option<String> = conn.query_row(
+            "SELECT MAX(created_at) FROM cache_messages WHERE viewer=?1",
+            params![viewer],
+            |row| row.get(0),
+        )?;
+        Ok(latest.and_then(|s| OffsetDateTime::parse(&s, &Rfc3339).ok()))
+    }
+
+    /// One entry per peer with the latest message and how many of theirs
+    /// are unread, most recent conversation first.
+    pub fn conversations(&self, viewer: &str) -> Result<Vec<Conversation>> {
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare(
+            "SELECT m.peer,
+                    CASE WHEN m.sender = ?1 THEN m.recipient_handle ELSE m.sender_handle END,
+                    m.body, m.created_at,
+                    (SELECT COUNT(*) FROM cache_messages u
+                     WHERE u.viewer = ?1 AND u.peer = m.peer AND u.recipient = ?1 AND u.read_at IS NULL)
+             FROM cache_messages m
+             WHERE m.viewer = ?1 AND m.created_at =
+                 (SELECT MAX(created_at) FROM cache_messages l WHERE l.viewer = ?1 AND l.peer = m.peer)
+             GROUP BY m.peer
+             ORDER BY m.created_at DESC",
+        )?;
+        let rows = stmt.query_map(params![viewer], |row| {
+            Ok(Conversation {
+                peer: parse_uuid(&row.get::<_, String>(0)?),
+                peer_handle: row.get(1)?,
+                last_body: row.get(2)?,
+                last_at: parse_ts(&row.get::<_, String>(3)?),
+                unread: row.get(4)?,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("listing conversations")
+    }
+
+    /// The latest `limit` messages exchanged with `peer`, oldest first.
+    pub fn conversation(&self, viewer: &str, peer: &str, limit: usize) -> Result<Vec<Message>> {
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT * FROM (SELECT {} FROM cache_messages WHERE viewer=?1 AND peer=?2
+             ORDER BY created_at DESC, id DESC LIMIT ?3) ORDER BY created_at, id",
+            COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![viewer, peer, limit as i64], map_message)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("loading conversation")
+    }
+
+    /// Marks everything `peer` sent to `viewer` as read. Returns how many
+    /// messages were unread.
+    pub fn mark_read(&self, viewer: &str, peer: &str, at: OffsetDateTime) -> Result<usize> {
+        let conn = self.db.connection();
+        Ok(conn.execute(
+            "UPDATE cache_messages SET read_at=?3
+             WHERE viewer=?1 AND peer=?2 AND recipient=?1 AND read_at IS NULL",
+            params![viewer, peer, format_ts(at)],
+        )?)
+    }
+
+    pub fn unread_count(&self, viewer: &str) -> Result<i64> {
+        let conn = self.db.connection();
+        Ok(conn.query_row(
+            "SELECT COUNT(*) FROM cache_messages WHERE viewer=?1 AND recipient=?1 AND read_at IS NULL",
+            params![viewer],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Finds a cached peer by handle so a conversation opens offline.
+    pub fn peer_by_handle(&self, viewer: &str, handle: &str) -> Result<Option<Uuid>> {
+        let conn = self.db.connection();
+        conn.query_row(
+            "SELECT peer FROM cache_messages
+             WHERE viewer=?1 AND ((sender=?1 AND recipient_handle=?2) OR (recipient=?1 AND sender_handle=?2))
+             LIMIT 1",
+            params![viewer, handle],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map(|peer| peer.as_deref().map(parse_uuid))
+        .context("looking up conversation peer")
+    }
+}
+
+fn map_message(row: &Row<'_>) -> rusqlite::Result<Message> {
+    Ok(Message {
+        id: parse_uuid(&row.get::<_, String>(0)?),
+        sender: parse_uuid(&row.get::<_, String>(1)?),
+        recipient: parse_uuid(&row.get::<_, String>(2)?),
+        body: row.get(3)?,
+        created_at: parse_ts(&row.get::<_, String>(4)?),
+        read_at: row.get::<_, Option<String>>(5)?.as_deref().map(parse_ts),
+        sender_handle: row.get(6)?,
+        recipient_handle: row.get(7)?,
+    })
+}
@@ -1,15 +1,231 @@
-use anyhow::{Context, Result};
-use rusqlite::{Connection, TransactionBehavior};
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, TransactionBehavior};
+use sha2::{Digest, Sha256};
+use strum_macros::Display;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 
+/// A numbered script from `migrations/sqlite/`, embedded at build time by
+/// `build.rs`.
+#[derive(Debug)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: Option<&'static str>,
+}
+
+include!(concat!(env!("OUT_DIR"), "/migrations.rs"));
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        hex::encode(Sha256::digest(self.up.as_bytes()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the embedded script no longer matches the recorded checksum.
+    Modified,
+    /// Recorded in the database but unknown to this build.
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: u32,
+    pub name: String,
+    pub state: MigrationState,
+    pub applied_at: Option<String>,
+}
+
+struct AppliedRow {
+    version: u32,
+    name: String,
+    checksum: String,
+    applied_at: String,
+}
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Brings the schema up to the newest embedded migration.
 pub fn apply(conn: &mut Connection) -> Result<()> {
-    let current_version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
-    if current_version >= 1 {
-        return Ok(());
-    }
-    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
-    tx.execute_batch(include_str!("../../migrations/sqlite/001_init.sql"))
-        .context("applying 001_init.sql")?;
-    tx.pragma_update(None, "user_version", &1)?;
-    tx.commit()?;
+    for status in status(conn)? {
+        if status.state == MigrationState::Modified {
+            tracing::warn!(
+                version = status.version,
+                name = %status.name,
+                "applied migration has been edited since it ran"
+            );
+        }
+    }
+    migrate_to(conn, latest_version())?;
+    Ok(())
+}
+
+pub fn current_version(conn: &Connection) -> Result<u32> {
+    ensure_table(conn)?;
+    let version: Option<u32> =
+        conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+            row.get(0)
+        })?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Applies pending migrations up to `target`, or runs down scripts in reverse
+/// when `target` is below the current version. Each step runs in its own
+/// transaction. Returns a line per step taken.
+pub fn migrate_to(conn: &mut Connection, target: u32) -> Result<Vec<String>> {
+    if target > latest_version() {
+        bail!("no migration {} (latest is {})", target, latest_version());
+    }
+    let applied = applied_rows(conn)?;
+    let is_applied = |version: u32| applied.iter().any(|row| row.version == version);
+    let mut log = Vec::new();
+
+    let current = current_version(conn)?;
+    if target < current {
+        let to_revert = applied
+            .iter()
+            .rev()
+            .filter(|row| row.version > target)
+            .collect::<Vec<_>>();
+        for row in &to_revert {
+            let has_down = MIGRATIONS
+                .iter()
+                .any(|m| m.version == row.version && m.down.is_some());
+            if !has_down {
+                bail!("migration {} has no down script", row.name);
+            }
+        }
+        for row in to_revert {
+            let migration = MIGRATIONS
+                .iter()
+                .find(|m| m.version == row.version)
+                .expect("checked above");
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            tx.execute_batch(migration.down.unwrap_or_default())
+                .with_context(|| format!("reverting {}", migration.name))?;
+            tx.execute(
+                "DELETE FROM schema_migrations WHERE version=?1",
+                params![migration.version],
+            )?;
+            tx.pragma_update(None, "user_version", migration.version - 1)?;
+            tx.commit()?;
+            log.push(format!("reverted {}", migration.name));
+        }
+        return Ok(log);
+    }
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|m| m.version <= target && !is_applied(m.version))
+    {
+        let now = OffsetDateTime::now_utc();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute_batch(migration.up)
+            .with_context(|| format!("applying {}", migration.name))?;
+        tx.execute(
+            "INSERT INTO schema_migrations(version, name, checksum, applied_at) VALUES(?1, ?2, ?3, ?4)",
+            params![
+                migration.version,
+                migration.name,
+                migration.checksum(),
+                now.format(&Rfc3339).unwrap_or_else(|_| now.to_string())
+            ],
+        )?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+        log.push(format!("applied {}", migration.name));
+    }
+    Ok(log)
+}
+
+pub fn status(conn: &Connection) -> Result<Vec<MigrationStatus>> {
+    let applied = applied_rows(conn)?;
+    let mut out = MIGRATIONS
+        .iter()
+        .map(|migration| {
+            let row = applied.iter().find(|row| row.version == migration.version);
+            let state = match row {
+                None => MigrationState::Pending,
+                Some(row) if row.checksum != migration.checksum() => MigrationState::Modified,
+                Some(_) => MigrationState::Applied,
+            };
+            MigrationStatus {
+                version: migration.version,
+                name: migration.name.to_string(),
+                state,
+                applied_at: row.map(|row| row.applied_at.clone()),
+            }
+        })
+        .collect::<Vec<_>>();
+    for row in &applied {
+        if !MIGRATIONS.iter().any(|m| m.version == row.version) {
+            out.push(MigrationStatus {
+                version: row.version,
+                name: row.name.clone(),
+                state: MigrationState::Unknown,
+                applied_at: Some(row.applied_at.clone()),
+            });
+        }
+    }
+    out.sort_by_key(|status| status.version);
+    Ok(out)
+}
+
+fn applied_rows(conn: &Connection) -> Result<Vec<AppliedRow>> {
+    ensure_table(conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT version, name, checksum, applied_at FROM schema_migrations ORDER BY version",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(AppliedRow {
+                version: row.get(0)?,
+                name: row.get(1)?,
+                checksum: row.get(2)?,
+                applied_at: row.get(3)?,
+            })
+        })?
+        .filter_map(Result::ok)
+        .collect();
+    Ok(rows)
+}
+
+/// Creates the bookkeeping table. Databases created before it existed only
+/// carry `user_version`; those migrations are recorded as applied with the
+/// checksum of the script embedded in this build.
+fn ensure_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        );",
+    )?;
+    let recorded: i64 = conn.query_row("SELECT COUNT(*) FROM schema_migrations", [], |row| {
+        row.get(0)
+    })?;
+    let legacy_version: u32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if recorded == 0 && legacy_version > 0 {
+        let now = OffsetDateTime::now_utc();
+        for migration in MIGRATIONS.iter().filter(|m| m.version <= legacy_version) {
+            conn.execute(
+                "INSERT INTO schema_migrations(version, name, checksum, applied_at) VALUES(?1, ?2, ?3, ?4)",
+                params![
+                    migration.version,
+                    migration.name,
+                    migration.checksum(),
+                    now.format(&Rfc3339).unwrap_or_else(|_| now.to_string())
+                ],
+            )?;
+        }
+    }
     Ok(())
 }
@@ -1,7 +1,37 @@
 pub mod cache;
+pub mod drafts;
+pub mod history;
+pub mod messages;
 pub mod migrations;
+pub mod notifications;
+pub mod outbox;
 pub mod reports;
+pub mod scheduled;
 pub mod sqlite;
 pub mod tasks;
 
 pub use sqlite::{AppDatabase, DatabaseConfig};
+
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::{OffsetDateTime, UtcOffset};
+use uuid::Uuid;
+
+/// Timestamps are stored as fixed-width UTC RFC 3339 text with
+/// microseconds, e.g. `2026-10-18T09:00:00.500000Z`, so comparing them as
+/// strings in SQL orders them by time.
+pub(crate) fn format_ts(ts: OffsetDateTime) -> String {
+    let format =
+        format_description!("[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:6]Z");
+    let utc = ts.to_offset(UtcOffset::UTC);
+    utc.format(format).unwrap_or_else(|_| utc.to_string())
+}
+
+/// Unreadable timestamps read as now rather than failing the whole row.
+pub(crate) fn parse_ts(value: &str) -> OffsetDateTime {
+    OffsetDateTime::parse(value, &Rfc3339).unwrap_or_else(|_| OffsetDateTime::now_utc())
+}
+
+pub(crate) fn parse_uuid(value: &str) -> Uuid {
+    Uuid::parse_str(value).unwrap_or_else(|_| Uuid::nil())
+}
@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, OptionalExtension, Row};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::domain::{Notification, NotificationKind};
+
+use super::{format_ts, parse_ts, parse_uuid, AppDatabase};
+
+const COLUMNS: &str = "id, key, kind, actor, actor_handle, post_id, excerpt, created_at, read_at";
+
+pub struct NotificationDao<'a> {
+    db: &'a AppDatabase,
+}
+
+impl<'a> NotificationDao<'a> {
+    pub fn new(db: &'a AppDatabase) -> Self {
+        Self { db }
+    }
+
+    /// Stores `notification` for `viewer` unless it's already there, so
+    /// read marks and local ids survive later polls. Returns whether it
+    /// was new.
+    pub fn insert(&self, viewer: &str, notification: &Notification) -> Result<bool> {
+        let conn = self.db.connection();
+        let inserted = conn.execute(
+            "INSERT INTO notifications(viewer, key, kind, actor, actor_handle, post_id, excerpt, created_at)
+             VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(viewer, key) DO NOTHING",
+            params![
+                viewer,
+                &notification.key,
+                notification.kind.to_string(),
+                notification.actor.to_string(),
+                notification.actor_handle.as_deref(),
+                notification.post_id.map(|id| id.to_string()),
+                notification.excerpt.as_deref(),
+                format_ts(notification.created_at)
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Creation time of the newest stored notification, where the next poll
+    /// picks up.
+    pub fn latest(&self, viewer: &str) -> Result<Option<OffsetDateTime>> {
+        let conn = self.db.connection();
+        let latest: Option<String> = conn.query_row(
+            "SELECT MAX(created_at) FROM notifications WHERE viewer=?1",
+            params![viewer],
+            |row| row.get(0),
+        )?;
+        Ok(latest.and_then(|s| OffsetDateTime::parse(&s, &Rfc3339).ok()))
+    }
+
+    /// Newest first.
+    pub fn list(&self, viewer: &str, limit: usize) -> Result<Vec<Notification>> {
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM notifications WHERE viewer=?1 ORDER BY created_at DESC, id DESC LIMIT ?2",
+            COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![viewer, limit as i64], map_notification)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("listing notifications")
+    }
+
+    pub fn get(&self, viewer: &str, id: i64) -> Result<Option<Notification>> {
+        let conn = self.db.connection();
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM notifications WHERE viewer=?1 AND id=?2",
+                COLUMNS
+            ),
+            params![viewer, id],
+            map_notification,
+        )
+        .optional()
+        .context("notification lookup")
+    }
+
+    pub fn unread_count(&self, viewer: &str) -> Result<i64> {
+        let conn = self.db.connection();
+        Ok(conn.query_row(
+            "SELECT COUNT(*) FROM notifications WHERE viewer=?1 AND read_at IS NULL",
+            params![viewer],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Marks one notification read, or all of `viewer`'s when `id` is
+    /// `None`. Returns how many were unread.
+    pub fn mark_read(&self, viewer: &str, id: Option<i64>, at: OffsetDateTime) -> Result<usize> {
+        let conn = self.db.connection();
+        Ok(conn.execute(
+            "UPDATE notifications SET read_at=?3
+             WHERE viewer=?1 AND (?2 IS NULL OR id=?2) AND read_at IS NULL",
+            params![viewer, id, format_ts(at)],
+        )?)
+    }
+}
+
+fn map_notification(row: &Row<'_>) -> rusqlite::Result<Notification> {
+    let kind: String = row.get(2)?;
+    Ok(Notification {
+        id: row.get(0)?,
+        key: row.get(1)?,
+        kind: kind.parse().unwrap_or(NotificationKind::Mention),
+        actor: parse_uuid(&row.get::<_, String>(3)?),
+        actor_handle: row.get(4)?,
+        post_id: row.get::<_, Option<String>>(5)?.as_deref().map(parse_uuid),
+        excerpt: row.get(6)?,
+        created_at: parse_ts(&row.get::<_, String>(7)?),
+        read_at: row.get::<_, Option<String>>(8)?.as_deref().map(parse_ts),
+    })
+}
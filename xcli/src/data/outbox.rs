@@ -0,0 +1,239 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, OptionalExtension, Row};
+use time::OffsetDateTime;
+
+use crate::domain::{OutboxItem, OutboxPayload, OutboxStatus};
+
+use super::{format_ts, parse_ts, AppDatabase};
+
+const COLUMNS: &str = "id, payload, status, attempts, last_error, created_at, next_attempt_at";
+/// Items queued before accounts were tracked have no owner and go out with
+/// whichever account is active.
+const OWNED_BY: &str = "user_id IN (?1, '')";
+
+pub struct OutboxDao<'a> {
+    db: &'a AppDatabase,
+}
+
+impl<'a> OutboxDao<'a> {
+    pub fn new(db: &'a AppDatabase) -> Self {
+        Self { db }
+    }
+
+    /// Queues `payload` on behalf of `user_id`; only that account's session
+    /// will deliver it.
+    pub fn enqueue(&self, user_id: &str, payload: &OutboxPayload) -> Result<OutboxItem> {
+        let now = OffsetDateTime::now_utc();
+        let conn = self.db.connection();
+        conn.execute(
+            "INSERT INTO outbox(kind, payload, created_at, status, next_attempt_at, updated_at, user_id)
+             VALUES(?1, ?2, ?3, 'pending', ?4, ?3, ?5)",
+            params![
+                payload.kind(),
+                serde_json::to_string(payload)?,
+                format_ts(now),
+                now.unix_timestamp(),
+                user_id
+            ],
+        )?;
+        Ok(OutboxItem {
+            id: conn.last_insert_rowid(),
+            payload: payload.clone(),
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            created_at: now,
+            next_attempt_at: now,
+        })
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<OutboxItem>> {
+        let conn = self.db.connection();
+        conn.query_row(
+            &format!("SELECT {} FROM outbox WHERE id=?1", COLUMNS),
+            params![id],
+            map_row,
+        )
+        .optional()
+        .context("outbox lookup")
+    }
+
+    /// Most recent items of `user_id` first, sent ones included.
+    pub fn list(&self, user_id: &str, limit: usize) -> Result<Vec<OutboxItem>> {
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM outbox WHERE {} ORDER BY id DESC LIMIT ?2",
+            COLUMNS, OWNED_BY
+        ))?;
+        let items = stmt.query_map(params![user_id, limit as i64], map_row)?;
+        items
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("listing outbox items")
+    }
+
+    /// Items of `user_id` still waiting to go out.
+    pub fn pending_count(&self, user_id: &str) -> Result<i64> {
+        let conn = self.db.connection();
+        let count = conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM outbox WHERE {} AND status IN ('pending','sending')",
+                OWNED_BY
+            ),
+            params![user_id],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// Marks a single pending item of `user_id` as `sending` and stamps the
+    /// claim; returns `None` if another flush already owns it, it is not
+    /// pending or it was queued by a different account.
+    pub fn claim(&self, user_id: &str, id: i64) -> Result<Option<OutboxItem>> {
+        let now = OffsetDateTime::now_utc();
+        let rows = {
+            let conn = self.db.connection();
+            conn.execute(
+                &format!(
+                    "UPDATE outbox SET status='sending', claimed_at=?3, updated_at=?4 WHERE {} AND id=?2 AND status='pending'",
+                    OWNED_BY
+                ),
+                params![user_id, id, now.unix_timestamp(), format_ts(now)],
+            )?
+        };
+        if rows == 0 {
+            return Ok(None);
+        }
+        self.get(id)
+    }
+
+    /// Claims every pending item of `user_id` whose backoff has elapsed,
+    /// oldest first.
+    pub fn claim_due(
+        &self,
+        user_id: &str,
+        now: OffsetDateTime,
+        limit: usize,
+    ) -> Result<Vec<OutboxItem>> {
+        let ids = {
+            let conn = self.db.connection();
+            let mut stmt = conn.prepare(&format!(
+                "SELECT id FROM outbox WHERE {} AND status='pending' AND next_attempt_at<=?2 ORDER BY id LIMIT ?3",
+                OWNED_BY
+            ))?;
+            let ids = stmt
+                .query_map(
+                    params![user_id, now.unix_timestamp(), limit as i64],
+                    |row| row.get::<_, i64>(0),
+                )?
+                .filter_map(Result::ok)
+                .collect::<Vec<_>>();
+            ids
+        };
+        let mut claimed = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(item) = self.claim(user_id, id)? {
+                claimed.push(item);
+            }
+        }
+        Ok(claimed)
+    }
+
+    pub fn mark_sent(&self, id: i64) -> Result<()> {
+        let conn = self.db.connection();
+        conn.execute(
+            "UPDATE outbox SET status='sent', attempts=attempts+1, last_error=NULL, updated_at=?2 WHERE id=?1",
+            params![id, format_ts(OffsetDateTime::now_utc())],
+        )?;
+        Ok(())
+    }
+
+    pub fn mark_retry(&self, id: i64, error: &str, next_attempt_at: OffsetDateTime) -> Result<()> {
+        let conn = self.db.connection();
+        conn.execute(
+            "UPDATE outbox SET status='pending', attempts=attempts+1, last_error=?2, next_attempt_at=?3, updated_at=?4 WHERE id=?1",
+            params![
+                id,
+                error,
+                next_attempt_at.unix_timestamp(),
+                format_ts(OffsetDateTime::now_utc())
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn mark_failed(&self, id: i64, error: &str) -> Result<()> {
+        let conn = self.db.connection();
+        conn.execute(
+            "UPDATE outbox SET status='failed', attempts=attempts+1, last_error=?2, updated_at=?3 WHERE id=?1",
+            params![id, error, format_ts(OffsetDateTime::now_utc())],
+        )?;
+        Ok(())
+    }
+
+    /// Puts a failed or backed-off item of `user_id` back at the front of
+    /// the queue.
+    pub fn retry(&self, user_id: &str, id: i64) -> Result<bool> {
+        let now = OffsetDateTime::now_utc();
+        let conn = self.db.connection();
+        let rows = conn.execute(
+            &format!(
+                "UPDATE outbox SET status='pending', attempts=0, next_attempt_at=?3, updated_at=?4
+                 WHERE {} AND id=?2 AND status IN ('pending','failed')",
+                OWNED_BY
+            ),
+            params![user_id, id, now.unix_timestamp(), format_ts(now)],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Deletes an item of `user_id` that isn't being delivered and returns
+    /// it.
+    pub fn remove(&self, user_id: &str, id: i64) -> Result<Option<OutboxItem>> {
+        let conn = self.db.connection();
+        conn.query_row(
+            &format!(
+                "DELETE FROM outbox WHERE {} AND id=?2 AND status<>'sending' RETURNING {}",
+                OWNED_BY, COLUMNS
+            ),
+            params![user_id, id],
+            map_row,
+        )
+        .optional()
+        .context("removing outbox item")
+    }
+
+    /// Releases items left in `sending` by a process that exited
+    /// mid-delivery: those claimed before `claimed_before`. Newer claims may
+    /// still be in flight in another process and are left alone.
+    pub fn requeue_stale(&self, claimed_before: OffsetDateTime) -> Result<usize> {
+        let conn = self.db.connection();
+        let rows = conn.execute(
+            "UPDATE outbox SET status='pending', claimed_at=NULL, updated_at=?2
+             WHERE status='sending' AND COALESCE(claimed_at, 0)<?1",
+            params![
+                claimed_before.unix_timestamp(),
+                format_ts(OffsetDateTime::now_utc())
+            ],
+        )?;
+        Ok(rows)
+    }
+}
+
+fn map_row(row: &Row<'_>) -> rusqlite::Result<OutboxItem> {
+    let payload: String = row.get(1)?;
+    let status: String = row.get(2)?;
+    let created_at: String = row.get(5)?;
+    let next_attempt_at: i64 = row.get(6)?;
+    Ok(OutboxItem {
+        id: row.get(0)?,
+        payload: serde_json::from_str(&payload).map_err(|err| {
+            rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(err))
+        })?,
+        status: status.parse().unwrap_or(OutboxStatus::Pending),
+        attempts: row.get(3)?,
+        last_error: row.get(4)?,
+        created_at: parse_ts(&created_at),
+        next_attempt_at: OffsetDateTime::from_unix_timestamp(next_attempt_at)
+            .unwrap_or_else(|_| OffsetDateTime::now_utc()),
+    })
+}
@@ -4,7 +4,7 @@ use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 
 use crate::domain::Report;
 
-use super::AppDatabase;
+use super::{format_ts, AppDatabase};
 
 pub struct ReportDao<'a> {
     db: &'a AppDatabase,
@@ -24,10 +24,7 @@ impl<'a> ReportDao<'a> {
                 report.task_id,
                 &report.path,
                 &report.summary,
-                report
-                    .created_at
-                    .format(&Rfc3339)
-                    .unwrap_or_else(|_| report.created_at.to_string())
+                format_ts(report.created_at)
             ],
         )?;
         Ok(())
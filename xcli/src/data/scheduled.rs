@@ -0,0 +1,226 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, OptionalExtension, Row};
+use std::str::FromStr;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::domain::{AudienceScope, ScheduledPost, ScheduledStatus};
+
+use super::{format_ts, parse_ts, parse_uuid, AppDatabase};
+
+const COLUMNS: &str =
+    "id, body, audience, send_at, status, attempts, last_error, created_at, post_id";
+
+pub struct ScheduledDao<'a> {
+    db: &'a AppDatabase,
+}
+
+impl<'a> ScheduledDao<'a> {
+    pub fn new(db: &'a AppDatabase) -> Self {
+        Self { db }
+    }
+
+    /// Holds a post of `user_id` back until `send_at`.
+    pub fn add(
+        &self,
+        user_id: &str,
+        body: &str,
+        audience: AudienceScope,
+        send_at: OffsetDateTime,
+    ) -> Result<ScheduledPost> {
+        let now = OffsetDateTime::now_utc();
+        let post_id = Uuid::new_v4();
+        let conn = self.db.connection();
+        conn.execute(
+            "INSERT INTO scheduled_posts(user_id, body, audience, send_at, next_attempt_at, created_at, post_id)
+             VALUES(?1, ?2, ?3, ?4, ?4, ?5, ?6)",
+            params![
+                user_id,
+                body,
+                audience.to_string(),
+                send_at.unix_timestamp(),
+                format_ts(now),
+                post_id.to_string()
+            ],
+        )?;
+        Ok(ScheduledPost {
+            id: conn.last_insert_rowid(),
+            post_id,
+            body: body.to_string(),
+            audience,
+            send_at,
+            status: ScheduledStatus::Waiting,
+            attempts: 0,
+            last_error: None,
+            created_at: now,
+        })
+    }
+
+    pub fn get(&self, user_id: &str, id: i64) -> Result<Option<ScheduledPost>> {
+        let conn = self.db.connection();
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM scheduled_posts WHERE user_id=?1 AND id=?2",
+                COLUMNS
+            ),
+            params![user_id, id],
+            map_row,
+        )
+        .optional()
+        .context("scheduled post lookup")
+    }
+
+    /// Every post `user_id` has waiting or failed, soonest first.
+    pub fn list(&self, user_id: &str) -> Result<Vec<ScheduledPost>> {
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM scheduled_posts WHERE user_id=?1 ORDER BY send_at, id",
+            COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![user_id], map_row)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("listing scheduled posts")
+    }
+
+    /// Rewrites a post that isn't being sent; `None` keeps the old value.
+    /// A failed post is armed again with its attempts reset. Returns the
+    /// updated post, or `None` if there is no such post or it is in flight.
+    pub fn update(
+        &self,
+        user_id: &str,
+        id: i64,
+        body: Option<&str>,
+        audience: Option<AudienceScope>,
+        send_at: Option<OffsetDateTime>,
+    ) -> Result<Option<ScheduledPost>> {
+        let rows = {
+            let conn = self.db.connection();
+            conn.execute(
+                "UPDATE scheduled_posts SET
+                    body=COALESCE(?3, body),
+                    audience=COALESCE(?4, audience),
+                    send_at=COALESCE(?5, send_at),
+                    next_attempt_at=COALESCE(?5, send_at),
+                    status='waiting', attempts=0, last_error=NULL
+                 WHERE user_id=?1 AND id=?2 AND status<>'sending'",
+                params![
+                    user_id,
+                    id,
+                    body,
+                    audience.map(|a| a.to_string()),
+                    send_at.map(|at| at.unix_timestamp())
+                ],
+            )?
+        };
+        if rows == 0 {
+            return Ok(None);
+        }
+        self.get(user_id, id)
+    }
+
+    /// Returns whether a post that wasn't in flight was removed.
+    pub fn cancel(&self, user_id: &str, id: i64) -> Result<bool> {
+        let conn = self.db.connection();
+        let rows = conn.execute(
+            "DELETE FROM scheduled_posts WHERE user_id=?1 AND id=?2 AND status<>'sending'",
+            params![user_id, id],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Marks every waiting post of `user_id` that is due at `now` as
+    /// `sending`, claimed at `now`, and returns them, oldest `send_at` first.
+    pub fn claim_due(
+        &self,
+        user_id: &str,
+        now: OffsetDateTime,
+        limit: usize,
+    ) -> Result<Vec<ScheduledPost>> {
+        let ids = {
+            let conn = self.db.connection();
+            let mut stmt = conn.prepare(
+                "SELECT id FROM scheduled_posts
+                 WHERE user_id=?1 AND status='waiting' AND next_attempt_at<=?2
+                 ORDER BY send_at, id LIMIT ?3",
+            )?;
+            let ids = stmt
+                .query_map(
+                    params![user_id, now.unix_timestamp(), limit as i64],
+                    |row| row.get::<_, i64>(0),
+                )?
+                .filter_map(Result::ok)
+                .collect::<Vec<_>>();
+            ids
+        };
+        let mut claimed = Vec::with_capacity(ids.len());
+        for id in ids {
+            let rows = self.db.connection().execute(
+                "UPDATE scheduled_posts SET status='sending', claimed_at=?2 WHERE id=?1 AND status='waiting'",
+                params![id, now.unix_timestamp()],
+            )?;
+            if rows > 0 {
+                if let Some(post) = self.get(user_id, id)? {
+                    claimed.push(post);
+                }
+            }
+        }
+        Ok(claimed)
+    }
+
+    /// Drops a post once it is live.
+    pub fn mark_published(&self, id: i64) -> Result<()> {
+        let conn = self.db.connection();
+        conn.execute("DELETE FROM scheduled_posts WHERE id=?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn mark_retry(&self, id: i64, error: &str, next_attempt_at: OffsetDateTime) -> Result<()> {
+        let conn = self.db.connection();
+        conn.execute(
+            "UPDATE scheduled_posts SET status='waiting', attempts=attempts+1, last_error=?2, next_attempt_at=?3 WHERE id=?1",
+            params![id, error, next_attempt_at.unix_timestamp()],
+        )?;
+        Ok(())
+    }
+
+    pub fn mark_failed(&self, id: i64, error: &str) -> Result<()> {
+        let conn = self.db.connection();
+        conn.execute(
+            "UPDATE scheduled_posts SET status='failed', attempts=attempts+1, last_error=?2 WHERE id=?1",
+            params![id, error],
+        )?;
+        Ok(())
+    }
+
+    /// Releases posts left in `sending` by a process that exited
+    /// mid-publish: those claimed before `claimed_before`. Newer claims may
+    /// still be in flight in another process and are left alone.
+    pub fn requeue_stale(&self, claimed_before: OffsetDateTime) -> Result<usize> {
+        let conn = self.db.connection();
+        let rows = conn.execute(
+            "UPDATE scheduled_posts SET status='waiting', claimed_at=NULL
+             WHERE status='sending' AND COALESCE(claimed_at, 0)<?1",
+            params![claimed_before.unix_timestamp()],
+        )?;
+        Ok(rows)
+    }
+}
+
+fn map_row(row: &Row<'_>) -> rusqlite::Result<ScheduledPost> {
+    let audience: String = row.get(2)?;
+    let send_at: i64 = row.get(3)?;
+    let status: String = row.get(4)?;
+    let created_at: String = row.get(7)?;
+    Ok(ScheduledPost {
+        id: row.get(0)?,
+        post_id: parse_uuid(&row.get::<_, String>(8)?),
+        body: row.get(1)?,
+        audience: AudienceScope::from_str(&audience).unwrap_or_default(),
+        send_at: OffsetDateTime::from_unix_timestamp(send_at)
+            .unwrap_or_else(|_| OffsetDateTime::now_utc()),
+        status: status.parse().unwrap_or(ScheduledStatus::Waiting),
+        attempts: row.get(5)?,
+        last_error: row.get(6)?,
+        created_at: parse_ts(&created_at),
+    })
+}
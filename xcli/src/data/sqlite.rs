@@ -31,12 +31,19 @@ impl DatabaseConfig {
 
 impl AppDatabase {
     pub fn open(cfg: &DatabaseConfig) -> Result<Self> {
-        let mut conn = Connection::open_with_flags(
+        let db = Self::open_unmigrated(cfg)?;
+        migrations::apply(&mut db.connection())?;
+        Ok(db)
+    }
+
+    /// Opens the database without touching the schema, for `db status` and
+    /// `db migrate`.
+    pub fn open_unmigrated(cfg: &DatabaseConfig) -> Result<Self> {
+        let conn = Connection::open_with_flags(
             &cfg.path,
             OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
         )?;
-        conn.pragma_update(None, "foreign_keys", &1)?;
-        migrations::apply(&mut conn)?;
+        conn.pragma_update(None, "foreign_keys", 1)?;
         Ok(Self {
             conn: Arc::new(Mutex::new(conn)),
         })
@@ -1,10 +1,15 @@
 use anyhow::{Context, Result};
-use rusqlite::{params, OptionalExtension};
-use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use time::OffsetDateTime;
 
-use crate::domain::{Task, TaskStatus};
+use crate::domain::{
+    normalize_tag, NewTask, Task, TaskChanges, TaskPriority, TaskQuery, TaskSort, TaskStatus,
+};
 
-use super::AppDatabase;
+use super::{format_ts, parse_ts, AppDatabase};
+
+const COLUMNS: &str =
+    "id, title, description, status, priority, due_at, created_at, updated_at, done_at";
 
 pub struct TaskDao<'a> {
     db: &'a AppDatabase,
@@ -16,104 +21,243 @@ impl<'a> TaskDao<'a> {
     }
 
     pub fn add(&self, title: &str, description: &str) -> Result<Task> {
-        let created_at = OffsetDateTime::now_utc();
-        let conn = self.db.connection();
-        conn.execute(
-            "INSERT INTO tasks(title, description, status, created_at) VALUES(?1, ?2, 'open', ?3)",
-            params![
-                title,
-                description,
-                created_at
-                    .format(&Rfc3339)
-                    .unwrap_or_else(|_| created_at.to_string())
-            ],
-        )?;
-        let id = conn.last_insert_rowid();
-        Ok(Task {
-            id,
+        self.insert(&NewTask {
             title: title.to_string(),
             description: description.to_string(),
-            status: TaskStatus::Open,
-            created_at,
-            done_at: None,
+            ..Default::default()
         })
     }
 
+    pub fn insert(&self, task: &NewTask) -> Result<Task> {
+        let now = format_ts(OffsetDateTime::now_utc());
+        let id = {
+            let mut conn = self.db.connection();
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT INTO tasks(title, description, status, priority, due_at, created_at, updated_at)
+                 VALUES(?1, ?2, 'open', ?3, ?4, ?5, ?5)",
+                params![
+                    task.title,
+                    task.description,
+                    task.priority.rank(),
+                    task.due_at.map(OffsetDateTime::unix_timestamp),
+                    now
+                ],
+            )?;
+            let id = tx.last_insert_rowid();
+            insert_tags(&tx, id, &task.tags)?;
+            tx.commit()?;
+            id
+        };
+        self.get(id)
+    }
+
+    /// Every task, or those with `filter` status, newest first.
     pub fn list(&self, filter: Option<TaskStatus>) -> Result<Vec<Task>> {
-        let conn = self.db.connection();
-        let mut stmt = match filter {
-            Some(TaskStatus::Open) => conn.prepare("SELECT id, title, description, status, created_at, done_at FROM tasks WHERE status='open' ORDER BY created_at DESC")?,
-            Some(TaskStatus::Done) => conn.prepare("SELECT id, title, description, status, created_at, done_at FROM tasks WHERE status='done' ORDER BY done_at DESC")?,
-            None => conn.prepare("SELECT id, title, description, status, created_at, done_at FROM tasks ORDER BY created_at DESC")?,
+        self.query(&TaskQuery {
+            status: filter,
+            ..Default::default()
+        })
+    }
+
+    pub fn query(&self, query: &TaskQuery) -> Result<Vec<Task>> {
+        let order = match query.sort {
+            TaskSort::Created => "created_at DESC, id DESC",
+            TaskSort::Due => "due_at IS NULL, due_at, priority DESC, id",
+            TaskSort::Priority => "priority DESC, due_at IS NULL, due_at, id",
+            TaskSort::Updated => "updated_at DESC, id DESC",
         };
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {COLUMNS} FROM tasks
+             WHERE (?1 IS NULL OR status = ?1)
+               AND (?2 IS NULL OR priority >= ?2)
+               AND (?3 IS NULL OR (due_at < ?3 AND status IN ('open', 'in_progress')))
+               AND (?4 IS NULL OR id IN (SELECT task_id FROM task_tags WHERE tag = ?4))
+             ORDER BY {order}"
+        ))?;
         let tasks = stmt
-            .query_map([], |row| {
-                let status: String = row.get(3)?;
-                let created_at: String = row.get(4)?;
-                let done_at_raw: Option<String> = row.get(5)?;
-                Ok(Task {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    description: row.get(2)?,
-                    status: match status.as_str() {
-                        "done" => TaskStatus::Done,
-                        _ => TaskStatus::Open,
-                    },
-                    created_at: OffsetDateTime::parse(&created_at, &Rfc3339)
-                        .unwrap_or_else(|_| OffsetDateTime::now_utc()),
-                    done_at: done_at_raw.and_then(|s| OffsetDateTime::parse(&s, &Rfc3339).ok()),
-                })
-            })?
-            .filter_map(Result::ok)
-            .collect();
-        Ok(tasks)
+            .query_map(
+                params![
+                    query.status.map(|status| status.as_str()),
+                    query.priority.map(|priority| priority.rank()),
+                    query.due_before.map(OffsetDateTime::unix_timestamp),
+                    query.tag.as_deref().map(normalize_tag),
+                ],
+                row_to_task,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        tasks
+            .into_iter()
+            .map(|mut task| {
+                task.tags = tags_of(&conn, task.id)?;
+                Ok(task)
+            })
+            .collect()
     }
 
     pub fn mark_done(&self, id: i64) -> Result<Option<Task>> {
-        let done_at = OffsetDateTime::now_utc();
+        self.set_status(id, TaskStatus::Done)
+    }
+
+    /// Puts a done or cancelled task back to open.
+    pub fn reopen(&self, id: i64) -> Result<Option<Task>> {
+        self.set_status(id, TaskStatus::Open)
+    }
+
+    /// `done_at` is set when the task becomes done and cleared otherwise.
+    pub fn set_status(&self, id: i64, status: TaskStatus) -> Result<Option<Task>> {
+        let now = OffsetDateTime::now_utc();
+        let done_at = (status == TaskStatus::Done).then(|| format_ts(now));
+        let rows = self.db.connection().execute(
+            "UPDATE tasks SET status=?2, done_at=?3, updated_at=?4 WHERE id=?1",
+            params![id, status.as_str(), done_at, format_ts(now)],
+        )?;
+        self.updated(id, rows)
+    }
+
+    pub fn edit(&self, id: i64, changes: &TaskChanges) -> Result<Option<Task>> {
+        let rows = self.db.connection().execute(
+            "UPDATE tasks SET
+                title = COALESCE(?2, title),
+                description = COALESCE(?3, description),
+                priority = COALESCE(?4, priority),
+                due_at = CASE WHEN ?5 THEN ?6 ELSE due_at END,
+                updated_at = ?7
+             WHERE id=?1",
+            params![
+                id,
+                changes.title,
+                changes.description,
+                changes.priority.map(|priority| priority.rank()),
+                changes.due_at.is_some(),
+                changes.due_at.flatten().map(OffsetDateTime::unix_timestamp),
+                format_ts(OffsetDateTime::now_utc())
+            ],
+        )?;
+        self.updated(id, rows)
+    }
+
+    /// Removes the task and its tags. Returns false if there was no such
+    /// task.
+    pub fn delete(&self, id: i64) -> Result<bool> {
+        let rows = self
+            .db
+            .connection()
+            .execute("DELETE FROM tasks WHERE id=?1", params![id])?;
+        Ok(rows > 0)
+    }
+
+    pub fn add_tags(&self, id: i64, tags: &[String]) -> Result<Option<Task>> {
         let rows = {
-            let conn = self.db.connection();
-            conn.execute(
-                "UPDATE tasks SET status='done', done_at=?2 WHERE id=?1",
-                params![
-                    id,
-                    done_at
-                        .format(&Rfc3339)
-                        .unwrap_or_else(|_| done_at.to_string())
-                ],
-            )?
+            let mut conn = self.db.connection();
+            let tx = conn.transaction()?;
+            let rows = touch(&tx, id)?;
+            if rows > 0 {
+                insert_tags(&tx, id, tags)?;
+            }
+            tx.commit()?;
+            rows
         };
+        self.updated(id, rows)
+    }
+
+    pub fn remove_tags(&self, id: i64, tags: &[String]) -> Result<Option<Task>> {
+        let rows = {
+            let mut conn = self.db.connection();
+            let tx = conn.transaction()?;
+            let rows = touch(&tx, id)?;
+            for tag in tags {
+                tx.execute(
+                    "DELETE FROM task_tags WHERE task_id=?1 AND tag=?2",
+                    params![id, normalize_tag(tag)],
+                )?;
+            }
+            tx.commit()?;
+            rows
+        };
+        self.updated(id, rows)
+    }
+
+    /// Every tag in use, alphabetically.
+    pub fn tags(&self) -> Result<Vec<String>> {
+        let conn = self.db.connection();
+        let mut stmt = conn.prepare("SELECT DISTINCT tag FROM task_tags ORDER BY tag")?;
+        let tags = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(tags)
+    }
+
+    pub fn get(&self, id: i64) -> Result<Task> {
+        let conn = self.db.connection();
+        let mut task = conn
+            .query_row(
+                &format!("SELECT {COLUMNS} FROM tasks WHERE id=?1"),
+                params![id],
+                row_to_task,
+            )
+            .optional()
+            .context("task lookup")?
+            .context("task not found")?;
+        task.tags = tags_of(&conn, id)?;
+        Ok(task)
+    }
+
+    /// The task after an update that touched `rows` rows.
+    fn updated(&self, id: i64, rows: usize) -> Result<Option<Task>> {
         if rows == 0 {
             return Ok(None);
         }
         self.get(id).map(Some)
     }
+}
 
-    pub fn get(&self, id: i64) -> Result<Task> {
-        let conn = self.db.connection();
-        conn.query_row(
-            "SELECT id, title, description, status, created_at, done_at FROM tasks WHERE id=?1",
-            params![id],
-            |row| {
-                let status: String = row.get(3)?;
-                let created_at: String = row.get(4)?;
-                let done_at_raw: Option<String> = row.get(5)?;
-                Ok(Task {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    description: row.get(2)?,
-                    status: match status.as_str() {
-                        "done" => TaskStatus::Done,
-                        _ => TaskStatus::Open,
-                    },
-                    created_at: OffsetDateTime::parse(&created_at, &Rfc3339)
-                        .unwrap_or_else(|_| OffsetDateTime::now_utc()),
-                    done_at: done_at_raw.and_then(|s| OffsetDateTime::parse(&s, &Rfc3339).ok()),
-                })
-            },
-        )
-        .optional()
-        .context("task lookup")?
-        .context("task not found")
+fn touch(conn: &Connection, id: i64) -> Result<usize> {
+    Ok(conn.execute(
+        "UPDATE tasks SET updated_at=?2 WHERE id=?1",
+        params![id, format_ts(OffsetDateTime::now_utc())],
+    )?)
+}
+
+fn insert_tags(conn: &Connection, id: i64, tags: &[String]) -> Result<()> {
+    for tag in tags {
+        let tag = normalize_tag(tag);
+        if !tag.is_empty() {
+            conn.execute(
+                "INSERT OR IGNORE INTO task_tags(task_id, tag) VALUES(?1, ?2)",
+                params![id, tag],
+            )?;
+        }
     }
+    Ok(())
+}
+
+fn tags_of(conn: &Connection, id: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT tag FROM task_tags WHERE task_id=?1 ORDER BY tag")?;
+    let tags = stmt
+        .query_map(params![id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(tags)
+}
+
+/// Tags are filled in separately.
+fn row_to_task(row: &Row) -> rusqlite::Result<Task> {
+    let status: String = row.get(3)?;
+    let due_at: Option<i64> = row.get(5)?;
+    let created_at: String = row.get(6)?;
+    let updated_at: String = row.get(7)?;
+    let done_at: Option<String> = row.get(8)?;
+    Ok(Task {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        description: row.get(2)?,
+        status: status.parse().unwrap_or(TaskStatus::Open),
+        priority: TaskPriority::from_rank(row.get(4)?),
+        due_at: due_at.and_then(|at| OffsetDateTime::from_unix_timestamp(at).ok()),
+        tags: Vec::new(),
+        created_at: parse_ts(&created_at),
+        updated_at: parse_ts(&updated_at),
+        done_at: done_at.map(|at| parse_ts(&at)),
+    })
 }
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use super::AudienceScope;
+
+/// Longest post the composer will send, in characters.
+pub const POST_MAX_CHARS: usize = 280;
+
+/// An unsent post kept by the composer. `id` is `None` until it is first
+/// saved.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Draft {
+    pub id: Option<i64>,
+    pub body: String,
+    pub audience: AudienceScope,
+    pub updated_at: OffsetDateTime,
+}
+
+impl Draft {
+    pub fn new(body: impl Into<String>, audience: AudienceScope) -> Self {
+        Self {
+            id: None,
+            body: body.into(),
+            audience,
+            updated_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    /// The first line, cut to `max` characters, for draft lists.
+    pub fn preview(&self, max: usize) -> String {
+        let first = self.body.lines().next().unwrap_or_default();
+        let cut: String = first.chars().take(max).collect();
+        if cut.len() < first.len() || self.body.lines().nth(1).is_some() {
+            format!("{}…", cut)
+        } else {
+            cut
+        }
+    }
+}
@@ -1,17 +1,83 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
-use strum_macros::{Display, EnumString};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::{normalize_tag, Post};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, EnumString, Display)]
+/// Posts requested per feed page.
+pub const FEED_PAGE_SIZE: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
 #[serde(rename_all = "snake_case")]
-#[strum(serialize_all = "snake_case")]
 pub enum FeedFilter {
+    #[default]
     Global,
     Following,
     Me,
+    /// Posts mentioning the signed-in user.
+    Mentions,
+    /// Posts carrying a hashtag, stored lowercased without the `#`.
+    Tag(String),
+}
+
+impl FeedFilter {
+    pub fn tag(tag: &str) -> Self {
+        FeedFilter::Tag(normalize_tag(tag))
+    }
+}
+
+/// `global`, `following`, `me`, `mentions` or `#tag`; the same strings key
+/// feed cursors and sync times in the cache.
+impl fmt::Display for FeedFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedFilter::Global => write!(f, "global"),
+            FeedFilter::Following => write!(f, "following"),
+            FeedFilter::Me => write!(f, "me"),
+            FeedFilter::Mentions => write!(f, "mentions"),
+            FeedFilter::Tag(tag) => write!(f, "#{}", tag),
+        }
+    }
+}
+
+impl FromStr for FeedFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "global" => Ok(FeedFilter::Global),
+            "following" => Ok(FeedFilter::Following),
+            "me" => Ok(FeedFilter::Me),
+            "mentions" => Ok(FeedFilter::Mentions),
+            tag if tag.len() > 1 && tag.starts_with('#') => {
+                let tag = normalize_tag(tag);
+                if !tag.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    return Err(anyhow!("invalid hashtag #{}", tag));
+                }
+                Ok(FeedFilter::Tag(tag))
+            }
+            other => Err(anyhow!("unknown feed {}", other)),
+        }
+    }
+}
+
+/// Keyset position in a feed: the next page holds posts strictly older than
+/// (`created_at`, `id`).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FeedCursor {
+    pub created_at: OffsetDateTime,
+    pub id: Uuid,
 }
 
-impl Default for FeedFilter {
-    fn default() -> Self {
-        FeedFilter::Global
+impl FeedCursor {
+    pub fn after(post: &Post) -> Self {
+        Self {
+            created_at: post.created_at,
+            id: post.id,
+        }
     }
 }
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// A direct message between two users.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Message {
+    pub id: Uuid,
+    pub sender: Uuid,
+    pub recipient: Uuid,
+    pub body: String,
+    pub created_at: OffsetDateTime,
+    /// When the recipient opened the conversation; unset while unread.
+    pub read_at: Option<OffsetDateTime>,
+    pub sender_handle: Option<String>,
+    pub recipient_handle: Option<String>,
+}
+
+impl Message {
+    /// The other participant from `viewer`'s side.
+    pub fn peer(&self, viewer: Uuid) -> Uuid {
+        if self.sender == viewer {
+            self.recipient
+        } else {
+            self.sender
+        }
+    }
+
+    pub fn peer_handle(&self, viewer: Uuid) -> Option<&str> {
+        if self.sender == viewer {
+            self.recipient_handle.as_deref()
+        } else {
+            self.sender_handle.as_deref()
+        }
+    }
+}
+
+/// One line of `/inbox`: the latest message exchanged with a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversation {
+    pub peer: Uuid,
+    pub peer_handle: Option<String>,
+    pub last_body: String,
+    pub last_at: OffsetDateTime,
+    pub unread: i64,
+}
+
+/// What the messages pane shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mailbox {
+    Inbox(Vec<Conversation>),
+    Conversation {
+        /// Unknown when the conversation was opened offline for a handle
+        /// never messaged before.
+        peer: Option<Uuid>,
+        peer_handle: String,
+        messages: Vec<Message>,
+    },
+}
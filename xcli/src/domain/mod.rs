@@ -1,15 +1,33 @@
+pub mod draft;
 pub mod feed;
+pub mod message;
+pub mod notification;
+pub mod outbox;
 pub mod post;
 pub mod profile;
 pub mod report;
+pub mod schedule;
 pub mod scope;
+pub mod search;
 pub mod session;
+pub mod span;
 pub mod task;
+pub mod thread;
 
-pub use feed::FeedFilter;
+pub use draft::{Draft, POST_MAX_CHARS};
+pub use feed::{FeedCursor, FeedFilter, FEED_PAGE_SIZE};
+pub use message::{Conversation, Mailbox, Message};
+pub use notification::{Notification, NotificationKind, NOTIFICATION_LIMIT};
+pub use outbox::{OutboxItem, OutboxPayload, OutboxStatus};
 pub use post::Post;
-pub use profile::Profile;
+pub use profile::{Profile, ProfileCard, ProfileStats, DIRECTORY_LIMIT, PROFILE_POSTS};
 pub use report::Report;
+pub use schedule::{ScheduledPost, ScheduledStatus};
 pub use scope::AudienceScope;
-pub use session::{Session, SessionTokens};
-pub use task::{Task, TaskStatus};
+pub use search::{SearchQuery, SEARCH_LIMIT};
+pub use session::{Account, Session, SessionTokens};
+pub use span::{normalize_tag, parse_spans, SpanKind, TextSpan};
+pub use task::{
+    parse_due, NewTask, Task, TaskChanges, TaskPriority, TaskQuery, TaskSort, TaskStatus,
+};
+pub use thread::{Thread, ThreadEntry};
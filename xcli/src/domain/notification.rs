@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, EnumString, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum NotificationKind {
+    Mention,
+    Reply,
+    Like,
+    Follow,
+}
+
+/// Someone interacting with the signed-in user. `id` is the local row id
+/// used by `/notifications open`; `key` identifies the item on the server.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Notification {
+    pub id: i64,
+    pub key: String,
+    pub kind: NotificationKind,
+    pub actor: Uuid,
+    pub actor_handle: Option<String>,
+    /// The post mentioned, replied to or liked; `None` for follows.
+    pub post_id: Option<Uuid>,
+    pub excerpt: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub read_at: Option<OffsetDateTime>,
+}
+
+impl Notification {
+    pub fn is_unread(&self) -> bool {
+        self.read_at.is_none()
+    }
+
+    /// `@alice liked your post: "..."` and the like.
+    pub fn describe(&self) -> String {
+        let actor = match &self.actor_handle {
+            Some(handle) => format!("@{}", handle),
+            None => self.actor.to_string()[..8].to_string(),
+        };
+        let action = match self.kind {
+            NotificationKind::Mention => "mentioned you",
+            NotificationKind::Reply => "replied",
+            NotificationKind::Like => "liked your post",
+            NotificationKind::Follow => "followed you",
+        };
+        match &self.excerpt {
+            Some(excerpt) => format!("{} {}: \"{}\"", actor, action, excerpt),
+            None => format!("{} {}", actor, action),
+        }
+    }
+}
+
+/// Most notifications listed by `/notifications`.
+pub const NOTIFICATION_LIMIT: usize = 50;
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::AudienceScope;
+
+/// A mutating action waiting to be delivered to Supabase.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OutboxPayload {
+    /// `id` is chosen when the post is queued and sent with every attempt,
+    /// so Supabase ignores a retry whose earlier attempt got through. Items
+    /// queued before ids existed got one from migration 016.
+    Post {
+        id: Uuid,
+        text: String,
+        audience: AudienceScope,
+        /// Set when the post is a reply.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reply_to: Option<String>,
+    },
+    Like {
+        post_id: String,
+    },
+    Unlike {
+        post_id: String,
+    },
+    Follow {
+        handle: String,
+    },
+    Unfollow {
+        handle: String,
+    },
+    /// `id` works as for `Post`.
+    Message {
+        id: Uuid,
+        handle: String,
+        text: String,
+    },
+}
+
+impl OutboxPayload {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OutboxPayload::Post { .. } => "post",
+            OutboxPayload::Like { .. } => "like",
+            OutboxPayload::Unlike { .. } => "unlike",
+            OutboxPayload::Follow { .. } => "follow",
+            OutboxPayload::Unfollow { .. } => "unfollow",
+            OutboxPayload::Message { .. } => "message",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            OutboxPayload::Post {
+                text,
+                audience,
+                reply_to,
+                ..
+            } => {
+                let preview: String = text.chars().take(30).collect();
+                let ellipsis = if text.chars().count() > 30 { "…" } else { "" };
+                match reply_to {
+                    Some(parent) => format!(
+                        "reply to {} \"{}{}\" [{}]",
+                        parent, preview, ellipsis, audience
+                    ),
+                    None => format!("post \"{}{}\" [{}]", preview, ellipsis, audience),
+                }
+            }
+            OutboxPayload::Like { post_id } => format!("like {}", post_id),
+            OutboxPayload::Unlike { post_id } => format!("unlike {}", post_id),
+            OutboxPayload::Follow { handle } => format!("follow @{}", handle),
+            OutboxPayload::Unfollow { handle } => format!("unfollow @{}", handle),
+            OutboxPayload::Message { handle, text, .. } => {
+                let preview: String = text.chars().take(30).collect();
+                let ellipsis = if text.chars().count() > 30 { "…" } else { "" };
+                format!("message @{} \"{}{}\"", handle, preview, ellipsis)
+            }
+        }
+    }
+}
+
+/// `Sending` marks an item claimed by a delivery attempt so two flushes never
+/// send it twice.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, EnumString, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum OutboxStatus {
+    Pending,
+    Sending,
+    Sent,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OutboxItem {
+    pub id: i64,
+    pub payload: OutboxPayload,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub next_attempt_at: OffsetDateTime,
+}
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 use uuid::Uuid;
 
-use super::AudienceScope;
+use super::{parse_spans, AudienceScope, TextSpan};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Post {
@@ -10,10 +10,19 @@ pub struct Post {
     pub author: Uuid,
     pub body: String,
     pub audience: AudienceScope,
+    #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
     pub author_handle: Option<String>,
     pub liked: bool,
     pub like_count: i64,
+    /// The post this one answers, if it is a reply.
+    #[serde(default)]
+    pub reply_to: Option<Uuid>,
+    /// The post that started the conversation; unset on top-level posts.
+    #[serde(default)]
+    pub root_id: Option<Uuid>,
+    #[serde(default)]
+    pub reply_to_handle: Option<String>,
 }
 
 impl Post {
@@ -27,6 +36,19 @@ impl Post {
             author_handle: None,
             liked: false,
             like_count: 0,
+            reply_to: None,
+            root_id: None,
+            reply_to_handle: None,
         }
     }
+
+    /// The id of the conversation this post belongs to.
+    pub fn thread_root(&self) -> Uuid {
+        self.root_id.or(self.reply_to).unwrap_or(self.id)
+    }
+
+    /// Mentions, hashtags and URLs in the body.
+    pub fn spans(&self) -> Vec<TextSpan> {
+        parse_spans(&self.body)
+    }
 }
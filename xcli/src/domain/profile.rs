@@ -2,6 +2,13 @@ use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 use uuid::Uuid;
 
+use super::Post;
+
+/// Recent posts shown under a profile.
+pub const PROFILE_POSTS: usize = 20;
+/// Most profiles listed by `/who`.
+pub const DIRECTORY_LIMIT: usize = 20;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Profile {
     pub id: Uuid,
@@ -9,3 +16,42 @@ pub struct Profile {
     pub display_name: Option<String>,
     pub created_at: OffsetDateTime,
 }
+
+impl Profile {
+    /// `Display Name (@handle)`, or just `@handle` without a display name.
+    pub fn label(&self) -> String {
+        match &self.display_name {
+            Some(name) => format!("{} (@{})", name, self.handle),
+            None => format!("@{}", self.handle),
+        }
+    }
+
+    /// One line with the label, join date and, when known, follow counts.
+    pub fn summary(&self, stats: Option<ProfileStats>) -> String {
+        let mut line = format!("{} · joined {}", self.label(), self.created_at.date());
+        if let Some(stats) = stats {
+            line.push_str(&format!(
+                " · {} follower{} · {} following",
+                stats.followers,
+                if stats.followers == 1 { "" } else { "s" },
+                stats.following
+            ));
+        }
+        line
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileStats {
+    pub followers: i64,
+    pub following: i64,
+}
+
+/// What `/profile` shows. `stats` is unknown when the card was built from
+/// the cache while offline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileCard {
+    pub profile: Profile,
+    pub stats: Option<ProfileStats>,
+    pub posts: Vec<Post>,
+}
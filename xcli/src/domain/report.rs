@@ -7,5 +7,6 @@ pub struct Report {
     pub task_id: Option<i64>,
     pub path: String,
     pub summary: String,
+    #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
 }
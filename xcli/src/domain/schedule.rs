@@ -0,0 +1,136 @@
+use anyhow::{anyhow, Result};
+use chrono::{Local, LocalResult, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use time::{format_description::well_known::Rfc3339, Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use super::AudienceScope;
+
+/// `Sending` marks a post claimed by a publish attempt so the TUI and
+/// `xcli flush-scheduled` never send it twice.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, EnumString, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ScheduledStatus {
+    Waiting,
+    Sending,
+    Failed,
+}
+
+/// A post held back locally until `send_at`. Rows are deleted once
+/// published.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ScheduledPost {
+    pub id: i64,
+    /// Id the post is inserted with, the same on every publish attempt.
+    pub post_id: Uuid,
+    pub body: String,
+    pub audience: AudienceScope,
+    pub send_at: OffsetDateTime,
+    pub status: ScheduledStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: OffsetDateTime,
+}
+
+impl ScheduledPost {
+    /// One line for `/scheduled`.
+    pub fn describe(&self, now: OffsetDateTime) -> String {
+        let preview: String = self.body.chars().take(30).collect();
+        let ellipsis = if self.body.chars().count() > 30 {
+            "…"
+        } else {
+            ""
+        };
+        let when = if self.send_at > now {
+            format!("in {}", format_duration(self.send_at - now))
+        } else {
+            "due".to_string()
+        };
+        let mut line = format!(
+            "#{} {} ({}) [{}] \"{}{}\"",
+            self.id,
+            local_label(self.send_at),
+            when,
+            self.audience,
+            preview.replace('\n', " "),
+            ellipsis
+        );
+        match (self.status, &self.last_error) {
+            (ScheduledStatus::Failed, Some(err)) => line.push_str(&format!(" failed: {}", err)),
+            (_, Some(err)) => line.push_str(&format!(" retrying: {}", err)),
+            _ => {}
+        }
+        line
+    }
+}
+
+/// Parses the value of `at:`: RFC 3339 with an offset, or
+/// `YYYY-MM-DDTHH:MM[:SS]` in local time.
+pub fn parse_at(value: &str) -> Result<OffsetDateTime> {
+    if let Ok(at) = OffsetDateTime::parse(value, &Rfc3339) {
+        return Ok(at);
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .map_err(|_| anyhow!("at: expects a time like 2026-10-19T09:00"))?;
+    let local = match Local.from_local_datetime(&naive) {
+        LocalResult::Single(at) | LocalResult::Ambiguous(at, _) => at,
+        LocalResult::None => return Err(anyhow!("{} doesn't exist in local time", value)),
+    };
+    Ok(OffsetDateTime::from_unix_timestamp(local.timestamp())?)
+}
+
+/// Parses the value of `in:`, e.g. `2h`, `45m` or `1d12h`, as a delay
+/// after `now`.
+pub fn parse_in(value: &str, now: OffsetDateTime) -> Result<OffsetDateTime> {
+    let usage = || anyhow!("in: expects a delay like 2h, 45m or 1d12h");
+    let mut total = Duration::ZERO;
+    let mut digits = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let amount = digits.parse::<i64>().map_err(|_| usage())?;
+        total += match c {
+            's' => Duration::seconds(amount),
+            'm' => Duration::minutes(amount),
+            'h' => Duration::hours(amount),
+            'd' => Duration::days(amount),
+            'w' => Duration::weeks(amount),
+            _ => return Err(usage()),
+        };
+        digits.clear();
+    }
+    if !digits.is_empty() || total <= Duration::ZERO {
+        return Err(usage());
+    }
+    Ok(now + total)
+}
+
+/// `send_at` in local time, for lists and messages.
+pub fn local_label(at: OffsetDateTime) -> String {
+    match Local.timestamp_opt(at.unix_timestamp(), 0) {
+        LocalResult::Single(local) | LocalResult::Ambiguous(local, _) => {
+            local.format("%Y-%m-%d %H:%M").to_string()
+        }
+        LocalResult::None => at.to_string(),
+    }
+}
+
+/// A coarse delay such as `2h 5m` or `40s`.
+fn format_duration(delay: Duration) -> String {
+    let (days, hours, minutes) = (
+        delay.whole_days(),
+        delay.whole_hours() % 24,
+        delay.whole_minutes() % 60,
+    );
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{}s", delay.whole_seconds()),
+        (0, 0, m) => format!("{}m", m),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, _) => format!("{}d {}h", d, h),
+    }
+}
@@ -1,10 +1,13 @@
 use serde::{Deserialize, Serialize};
 use strum_macros::{Display, EnumString};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, EnumString, Display)]
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, EnumString, Display, Default,
+)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum AudienceScope {
+    #[default]
     Public,
     Restrained,
     Private,
@@ -19,9 +22,3 @@ impl AudienceScope {
         ]
     }
 }
-
-impl Default for AudienceScope {
-    fn default() -> Self {
-        AudienceScope::Public
-    }
-}
@@ -0,0 +1,58 @@
+use time::{macros::format_description, Date};
+
+/// Most results a `/search` returns.
+pub const SEARCH_LIMIT: usize = 50;
+
+/// A `/search` request: free text, optionally narrowed to one author and to
+/// posts on or after a date.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SearchQuery {
+    pub text: String,
+    pub author: Option<String>,
+    pub since: Option<Date>,
+}
+
+impl SearchQuery {
+    /// Lowercased words of `text`, split the way the FTS tokenizer splits
+    /// them. These are what gets matched and highlighted.
+    pub fn terms(&self) -> Vec<String> {
+        self.text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    }
+
+    /// An FTS5 `MATCH` expression requiring every term as a prefix. Terms are
+    /// quoted so user input can't smuggle in FTS operators.
+    pub fn match_expr(&self) -> Option<String> {
+        let terms = self.terms();
+        if terms.is_empty() {
+            return None;
+        }
+        Some(
+            terms
+                .iter()
+                .map(|term| format!("\"{}\"*", term))
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+
+    pub fn parse_since(value: &str) -> Result<Date, time::error::Parse> {
+        Date::parse(value, format_description!("[year]-[month]-[day]"))
+    }
+}
+
+impl std::fmt::Display for SearchQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\"", self.text)?;
+        if let Some(author) = &self.author {
+            write!(f, " by @{}", author)?;
+        }
+        if let Some(since) = self.since {
+            write!(f, " since {}", since)?;
+        }
+        Ok(())
+    }
+}
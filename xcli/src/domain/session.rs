@@ -21,6 +21,14 @@ pub struct Session {
     pub tokens: SessionTokens,
 }
 
+/// A stored sign-in as listed by `/account list`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    pub user_id: String,
+    pub email: String,
+    pub active: bool,
+}
+
 impl Session {
     pub fn is_valid(&self) -> bool {
         !self.tokens.is_expired()
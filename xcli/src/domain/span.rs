@@ -0,0 +1,87 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+static TOKEN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?P<url>https?://[^\s<>]+)|(?P<mention>@\w+)|(?P<hashtag>#\w+)").unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, EnumString, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SpanKind {
+    Mention,
+    Hashtag,
+    Url,
+}
+
+/// A mention, hashtag or URL inside a post body, as byte offsets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextSpan {
+    pub kind: SpanKind,
+    pub start: usize,
+    pub end: usize,
+    /// What the span refers to: the handle or tag lowercased without its
+    /// sigil, or the URL as written.
+    pub value: String,
+}
+
+impl TextSpan {
+    pub fn text<'a>(&self, body: &'a str) -> &'a str {
+        &body[self.start..self.end]
+    }
+}
+
+/// Finds the mentions, hashtags and URLs in `body`, in order. A sigil only
+/// counts at the start of a word, so `me@example.com` and `a#b` are plain
+/// text, and tags made of digits alone (`#1`) are ignored.
+pub fn parse_spans(body: &str) -> Vec<TextSpan> {
+    TOKEN
+        .captures_iter(body)
+        .filter_map(|cap| {
+            let whole = cap.get(0)?;
+            let (start, mut end) = (whole.start(), whole.end());
+            let kind = if cap.name("url").is_some() {
+                // Trailing punctuation usually ends the sentence, not the URL.
+                let trimmed = whole
+                    .as_str()
+                    .trim_end_matches(|c: char| ".,;:!?'\")".contains(c));
+                end = start + trimmed.len();
+                SpanKind::Url
+            } else {
+                let glued = body[..start]
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '@' || c == '#');
+                if glued {
+                    return None;
+                }
+                if cap.name("mention").is_some() {
+                    SpanKind::Mention
+                } else if whole.as_str()[1..].chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                } else {
+                    SpanKind::Hashtag
+                }
+            };
+            let text = &body[start..end];
+            let value = match kind {
+                SpanKind::Url => text.to_string(),
+                _ => text[1..].to_lowercase(),
+            };
+            Some(TextSpan {
+                kind,
+                start,
+                end,
+                value,
+            })
+        })
+        .collect()
+}
+
+/// Lowercases a tag and drops a leading `#`, as stored in spans.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim_start_matches('#').to_lowercase()
+}
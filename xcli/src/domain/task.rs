@@ -1,20 +1,108 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+use super::schedule::{local_label, parse_at, parse_in};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum TaskStatus {
     Open,
+    InProgress,
     Done,
+    Cancelled,
 }
 
 impl TaskStatus {
+    pub const ALL: [TaskStatus; 4] = [
+        TaskStatus::Open,
+        TaskStatus::InProgress,
+        TaskStatus::Done,
+        TaskStatus::Cancelled,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             TaskStatus::Open => "open",
+            TaskStatus::InProgress => "in_progress",
             TaskStatus::Done => "done",
+            TaskStatus::Cancelled => "cancelled",
         }
     }
+
+    /// Open and in-progress tasks still need doing.
+    pub fn is_active(&self) -> bool {
+        matches!(self, TaskStatus::Open | TaskStatus::InProgress)
+    }
+}
+
+impl FromStr for TaskStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "open" => Ok(TaskStatus::Open),
+            "in_progress" | "in-progress" => Ok(TaskStatus::InProgress),
+            "done" => Ok(TaskStatus::Done),
+            "cancelled" | "canceled" => Ok(TaskStatus::Cancelled),
+            _ => Err(anyhow!("expected open, in_progress, done or cancelled")),
+        }
+    }
+}
+
+/// Stored as its rank, so tasks sort by it directly.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl TaskPriority {
+    pub const ALL: [TaskPriority; 4] = [
+        TaskPriority::Low,
+        TaskPriority::Normal,
+        TaskPriority::High,
+        TaskPriority::Urgent,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskPriority::Low => "low",
+            TaskPriority::Normal => "normal",
+            TaskPriority::High => "high",
+            TaskPriority::Urgent => "urgent",
+        }
+    }
+
+    pub fn rank(&self) -> i64 {
+        *self as i64
+    }
+
+    pub fn from_rank(rank: i64) -> Self {
+        match rank {
+            i64::MIN..=0 => TaskPriority::Low,
+            1 => TaskPriority::Normal,
+            2 => TaskPriority::High,
+            _ => TaskPriority::Urgent,
+        }
+    }
+}
+
+impl FromStr for TaskPriority {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        TaskPriority::ALL
+            .into_iter()
+            .find(|priority| priority.as_str() == s.to_lowercase())
+            .ok_or_else(|| anyhow!("expected low, normal, high or urgent"))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -23,6 +111,117 @@ pub struct Task {
     pub title: String,
     pub description: String,
     pub status: TaskStatus,
+    pub priority: TaskPriority,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub due_at: Option<OffsetDateTime>,
+    /// Lowercase, without `#`, in alphabetical order.
+    pub tags: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
     pub done_at: Option<OffsetDateTime>,
 }
+
+impl Task {
+    /// Still to do and past its due time.
+    pub fn is_overdue(&self, now: OffsetDateTime) -> bool {
+        self.status.is_active() && self.due_at.is_some_and(|due| due < now)
+    }
+
+    /// One line for `/tasks ls`.
+    pub fn describe(&self, now: OffsetDateTime) -> String {
+        let mut line = format!("#{} [{}]", self.id, self.status.as_str());
+        if self.priority != TaskPriority::Normal {
+            line.push_str(&format!(" !{}", self.priority.as_str()));
+        }
+        line.push_str(&format!(" {}", self.title));
+        if let Some(due) = self.due_at {
+            line.push_str(&format!(" due {}", local_label(due)));
+            if self.is_overdue(now) {
+                line.push_str(" (overdue)");
+            }
+        }
+        for tag in &self.tags {
+            line.push_str(&format!(" #{}", tag));
+        }
+        line
+    }
+}
+
+/// A task to create.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NewTask {
+    pub title: String,
+    pub description: String,
+    pub priority: TaskPriority,
+    pub due_at: Option<OffsetDateTime>,
+    pub tags: Vec<String>,
+}
+
+/// Fields to change on a task; `None` leaves one as it is.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskChanges {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub priority: Option<TaskPriority>,
+    /// `Some(None)` clears the due time.
+    pub due_at: Option<Option<OffsetDateTime>>,
+}
+
+impl TaskChanges {
+    pub fn is_empty(&self) -> bool {
+        *self == TaskChanges::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TaskSort {
+    /// Newest first.
+    #[default]
+    Created,
+    /// Soonest due first; tasks without a due time last.
+    Due,
+    /// Most urgent first, then soonest due.
+    Priority,
+    /// Most recently changed first.
+    Updated,
+}
+
+impl FromStr for TaskSort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "created" => Ok(TaskSort::Created),
+            "due" => Ok(TaskSort::Due),
+            "priority" => Ok(TaskSort::Priority),
+            "updated" => Ok(TaskSort::Updated),
+            _ => Err(anyhow!("expected created, due, priority or updated")),
+        }
+    }
+}
+
+/// Which tasks `/tasks ls` shows, and in what order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskQuery {
+    /// Every status when `None`.
+    pub status: Option<TaskStatus>,
+    pub tag: Option<String>,
+    /// This priority or a more urgent one.
+    pub priority: Option<TaskPriority>,
+    /// Open or in-progress tasks due before this time, overdue ones
+    /// included.
+    pub due_before: Option<OffsetDateTime>,
+    pub sort: TaskSort,
+}
+
+/// Parses a due time: a delay from `now` such as `3d`, a time accepted by
+/// `at:`, or a date, meaning the end of that day in local time.
+pub fn parse_due(value: &str, now: OffsetDateTime) -> Result<OffsetDateTime> {
+    parse_in(value, now)
+        .or_else(|_| parse_at(value))
+        .or_else(|_| parse_at(&format!("{}T23:59:59", value)))
+        .map_err(|_| anyhow!("expected a delay like 3d, a date like 2026-11-01 or a time"))
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use super::Post;
+
+/// A conversation laid out for display: every post after its parent,
+/// replies in the order they were written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thread {
+    pub root: Uuid,
+    pub entries: Vec<ThreadEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadEntry {
+    /// 0 for the root, 1 for direct replies to it, and so on.
+    pub depth: usize,
+    pub post: Post,
+}
+
+impl Thread {
+    /// Arranges the posts of one conversation depth first. Replies whose
+    /// parent wasn't loaded hang directly under the root.
+    pub fn build(root: Uuid, mut posts: Vec<Post>) -> Self {
+        posts.sort_by_key(|post| (post.created_at, post.id));
+        let known = posts.iter().map(|post| post.id).collect::<Vec<_>>();
+        let mut children: HashMap<Option<Uuid>, Vec<Post>> = HashMap::new();
+        for post in posts {
+            let parent = if post.id == root {
+                None
+            } else {
+                Some(
+                    post.reply_to
+                        .filter(|id| known.contains(id))
+                        .unwrap_or(root),
+                )
+            };
+            children.entry(parent).or_default().push(post);
+        }
+
+        let mut entries = Vec::new();
+        let mut stack = children
+            .remove(&None)
+            .unwrap_or_default()
+            .into_iter()
+            .rev()
+            .map(|post| (0, post))
+            .collect::<Vec<_>>();
+        if stack.is_empty() {
+            // The root itself wasn't loaded; show its replies top level.
+            stack = children
+                .remove(&Some(root))
+                .unwrap_or_default()
+                .into_iter()
+                .rev()
+                .map(|post| (0, post))
+                .collect();
+        }
+        while let Some((depth, post)) = stack.pop() {
+            if let Some(replies) = children.remove(&Some(post.id)) {
+                stack.extend(replies.into_iter().rev().map(|reply| (depth + 1, reply)));
+            }
+            entries.push(ThreadEntry { depth, post });
+        }
+        Self { root, entries }
+    }
+
+    pub fn depths(&self) -> HashMap<Uuid, usize> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.post.id, entry.depth))
+            .collect()
+    }
+
+    pub fn posts(&self) -> Vec<Post> {
+        self.entries
+            .iter()
+            .map(|entry| entry.post.clone())
+            .collect()
+    }
+}
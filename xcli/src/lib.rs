@@ -1,9 +1,12 @@
 pub mod app;
 pub mod cfg;
+pub mod cli;
 pub mod commands;
 pub mod data;
 pub mod domain;
+pub mod realtime;
 pub mod reports;
 pub mod supabase;
+pub mod sync;
 pub mod telemetry;
 pub mod ui;
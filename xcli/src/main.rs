@@ -1,49 +1,88 @@
-mod app;
-mod cfg;
-mod commands;
-mod data;
-mod domain;
-mod reports;
-mod supabase;
-mod telemetry;
-mod ui;
-
-use anyhow::Result;
+use std::io::Read;
+use std::process::ExitCode;
+
+use anyhow::{bail, Context, Result};
 use clap::Parser;
 use tracing::info;
 
-use app::App;
-use data::{AppDatabase, DatabaseConfig};
-use supabase::{SessionStore, SupabaseClient, SupabaseConfig};
-
-#[derive(Parser, Debug)]
-#[command(author, version, about = "Terminal Twitter client powered by Supabase")]
-struct Cli {
-    /// Run command in non-interactive mode
-    #[arg(long)]
-    command: Option<String>,
-}
+use cli_twitter::app::App;
+use cli_twitter::cli::{
+    self, render, render_error, script, Action, Cli, DbAction, Failure, OutputFormat,
+};
+use cli_twitter::commands::{self, CommandContext};
+use cli_twitter::data::{migrations, AppDatabase, DatabaseConfig};
+use cli_twitter::supabase::{LoginRequired, SessionStore, SupabaseClient, SupabaseConfig};
+use cli_twitter::sync::{scheduled, SyncEvent};
+use cli_twitter::{cfg, telemetry};
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     telemetry::init();
     let cli = Cli::parse();
+    let format = cli.output;
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", render_error(&err, format));
+            ExitCode::from(Failure::of(&err).code())
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
     let cfg = cfg::load_config()?;
     if let Some(feed) = cfg.default_feed.as_deref() {
         info!("default_feed" = %feed, "configuration loaded");
     }
     let db_cfg = DatabaseConfig::resolve()?;
+    let flush_scheduled = matches!(cli.action, Some(Action::FlushScheduled));
+    let mut script_run = None;
+    // Arguments are checked before touching the session or network.
+    let one_shot = match cli.action {
+        Some(Action::Db { action }) => return run_db(&db_cfg, action),
+        Some(Action::Run {
+            path,
+            dry_run,
+            stop_on_error,
+        }) => {
+            let steps = script::parse_script(&read_script(&path)?)?;
+            if dry_run {
+                let count = script::check(&steps)?;
+                println!("{} command{} OK", count, if count == 1 { "" } else { "s" });
+                return Ok(());
+            }
+            script_run = Some((steps, stop_on_error));
+            None
+        }
+        Some(Action::Client(action)) => {
+            let limit = action.limit();
+            Some((action.to_command()?, limit))
+        }
+        _ => None,
+    };
     let db = AppDatabase::open(&db_cfg)?;
-    let session_store = SessionStore::new()?;
+    let session_store = SessionStore::new(cfg.session_backend);
     let sb_cfg = SupabaseConfig::from_env()?;
     let supabase = SupabaseClient::new(sb_cfg, session_store)?;
+    let ctx = CommandContext::new(&db, &supabase);
+
+    if flush_scheduled {
+        return run_flush_scheduled(&db, &supabase).await;
+    }
+    if let Some((steps, stop_on_error)) = script_run {
+        return run_script(ctx, &steps, stop_on_error, cli.output).await;
+    }
+    if let Some((command, limit)) = one_shot {
+        let output = cli::execute(ctx, command, limit).await?;
+        println!("{}", render(&output, cli.output)?);
+        return Ok(());
+    }
 
     if let Some(cmd) = cli.command {
         if cmd.starts_with('/') {
             let command = commands::parse_command(&cmd)?;
-            let output =
-                commands::execute(commands::CommandContext::new(&db, &supabase), command).await?;
-            println!("{}", output.message);
+            let output = commands::execute(ctx, command).await?;
+            println!("{}", render(&output, cli.output)?);
             return Ok(());
         }
     }
@@ -51,3 +90,90 @@ async fn main() -> Result<()> {
     let app = App::new(&db, &supabase)?;
     app.run().await
 }
+
+/// Prints one line per scheduled post handled and fails if any of them
+/// could not be published.
+async fn run_flush_scheduled(db: &AppDatabase, supabase: &SupabaseClient) -> Result<()> {
+    supabase.restore_session().await?.ok_or(LoginRequired)?;
+    let events = scheduled::publish_due(db, supabase).await?;
+    if events.is_empty() {
+        println!("No scheduled posts due");
+    }
+    for event in &events {
+        println!("{}", event);
+    }
+    let failed = events
+        .iter()
+        .filter(|event| matches!(event, SyncEvent::ScheduledFailed { .. }))
+        .count();
+    if failed > 0 {
+        bail!(
+            "{} scheduled post{} not published",
+            failed,
+            if failed == 1 { "" } else { "s" }
+        );
+    }
+    Ok(())
+}
+
+fn read_script(path: &str) -> Result<String> {
+    if path == "-" {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source)?;
+        return Ok(source);
+    }
+    std::fs::read_to_string(path).with_context(|| format!("reading {}", path))
+}
+
+/// Prints each command's output as it finishes and, unless the script
+/// stops there, each failure to stderr.
+async fn run_script(
+    ctx: CommandContext<'_>,
+    steps: &[script::Step],
+    stop_on_error: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    script::run(ctx, steps, stop_on_error, |result| {
+        match result.as_ref().map(|output| render(output, format)) {
+            Ok(Ok(rendered)) => println!("{}", rendered),
+            Ok(Err(err)) => eprintln!("{}", render_error(&err, format)),
+            Err(err) => eprintln!("{}", render_error(err, format)),
+        }
+    })
+    .await
+}
+
+fn run_db(cfg: &DatabaseConfig, action: DbAction) -> Result<()> {
+    let db = AppDatabase::open_unmigrated(cfg)?;
+    let mut conn = db.connection();
+    match action {
+        DbAction::Status => {
+            println!(
+                "{} (schema version {}, latest {})",
+                cfg.path.display(),
+                migrations::current_version(&conn)?,
+                migrations::latest_version()
+            );
+            for status in migrations::status(&conn)? {
+                println!(
+                    "{:>4}  {:<24} {:<9} {}",
+                    status.version,
+                    status.name,
+                    status.state,
+                    status.applied_at.unwrap_or_default()
+                );
+            }
+        }
+        DbAction::Migrate { to } => {
+            let target = to.unwrap_or_else(migrations::latest_version);
+            let log = migrations::migrate_to(&mut conn, target)?;
+            if log.is_empty() {
+                println!("Already at version {}", target);
+            }
+            for line in log {
+                println!("{}", line);
+            }
+        }
+    }
+    Ok(())
+}
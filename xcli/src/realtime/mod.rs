@@ -0,0 +1,185 @@
+pub mod protocol;
+
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use futures::{SinkExt, StreamExt};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, sleep, MissedTickBehavior};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use crate::supabase::{LoginRequired, SupabaseClient, SupabaseConfig};
+use crate::sync::SyncEvent;
+
+use protocol::{Frame, Incoming};
+
+#[derive(Debug, Clone)]
+pub struct RealtimeConfig {
+    /// Full websocket URL including `apikey` and `vsn` query parameters.
+    pub url: String,
+    pub heartbeat_interval: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RealtimeConfig {
+    pub fn from_supabase(cfg: &SupabaseConfig) -> Self {
+        let base = cfg.url.trim_end_matches('/');
+        let ws = if let Some(rest) = base.strip_prefix("https://") {
+            format!("wss://{}", rest)
+        } else if let Some(rest) = base.strip_prefix("http://") {
+            format!("ws://{}", rest)
+        } else {
+            base.to_string()
+        };
+        Self {
+            url: format!(
+                "{}/realtime/v1/websocket?apikey={}&vsn=1.0.0",
+                ws, cfg.anon_key
+            ),
+            heartbeat_interval: Duration::from_secs(25),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Keeps a Realtime subscription to new posts open, reconnecting with
+/// backoff, and forwards each insert as `SyncEvent::NewPost`. Aborted when
+/// dropped.
+pub struct RealtimeWorker {
+    handle: JoinHandle<()>,
+}
+
+impl RealtimeWorker {
+    pub fn spawn(
+        supabase: SupabaseClient,
+        cfg: RealtimeConfig,
+        events: UnboundedSender<SyncEvent>,
+    ) -> Self {
+        let handle = tokio::spawn(async move {
+            let mut failures = 0u32;
+            loop {
+                match run_session(&supabase, &cfg, &events, &mut failures).await {
+                    Ok(()) => tracing::debug!("realtime connection closed"),
+                    Err(err) => {
+                        failures += 1;
+                        tracing::debug!(?err, failures, "realtime connection failed");
+                        // Report only the first failure of a streak.
+                        if failures == 1 {
+                            let error = format!("{:#}", err);
+                            if events.send(SyncEvent::RealtimeDown { error }).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+                if events.is_closed() {
+                    return;
+                }
+                sleep(backoff(failures, cfg.max_backoff)).await;
+            }
+        });
+        Self { handle }
+    }
+}
+
+impl Drop for RealtimeWorker {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+fn backoff(failures: u32, max: Duration) -> Duration {
+    let secs = 1u64 << failures.min(6);
+    Duration::from_secs(secs).min(max)
+}
+
+/// One connection's lifetime: join, then pump heartbeats, token refreshes and
+/// incoming frames until the socket closes or a heartbeat goes unanswered.
+/// `failures` is reset once the join is acknowledged.
+async fn run_session(
+    supabase: &SupabaseClient,
+    cfg: &RealtimeConfig,
+    events: &UnboundedSender<SyncEvent>,
+    failures: &mut u32,
+) -> Result<()> {
+    let session = supabase.ensure_session().await?.ok_or(LoginRequired)?;
+    let mut token = session.tokens.access_token;
+
+    let (socket, _) = connect_async(cfg.url.as_str()).await?;
+    let (mut sink, mut stream) = socket.split();
+    let mut refs = 0u64;
+    let mut next_ref = move || {
+        refs += 1;
+        refs.to_string()
+    };
+
+    let join_ref = next_ref();
+    sink.send(Message::Text(Frame::join(&join_ref, &token).encode()?))
+        .await?;
+
+    let mut heartbeat = interval(cfg.heartbeat_interval);
+    heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    heartbeat.tick().await;
+    let mut pending_heartbeat: Option<String> = None;
+
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                if pending_heartbeat.is_some() {
+                    bail!("heartbeat timed out");
+                }
+                if let Some(session) = supabase.ensure_session().await? {
+                    if session.tokens.access_token != token {
+                        token = session.tokens.access_token;
+                        let frame = Frame::access_token(&next_ref(), &token);
+                        sink.send(Message::Text(frame.encode()?)).await?;
+                    }
+                }
+                let reference = next_ref();
+                sink.send(Message::Text(Frame::heartbeat(&reference).encode()?)).await?;
+                pending_heartbeat = Some(reference);
+            }
+            message = stream.next() => {
+                let Some(message) = message else {
+                    return Ok(());
+                };
+                let text = match message? {
+                    Message::Text(text) => text,
+                    Message::Ping(data) => {
+                        sink.send(Message::Pong(data)).await?;
+                        continue;
+                    }
+                    Message::Close(_) => return Ok(()),
+                    _ => continue,
+                };
+                let frame = match Frame::decode(&text) {
+                    Ok(frame) => frame,
+                    Err(err) => {
+                        tracing::warn!(?err, "ignoring realtime frame");
+                        continue;
+                    }
+                };
+                let event = match Incoming::from(frame) {
+                    Incoming::Reply { reference, .. } if reference == pending_heartbeat => {
+                        pending_heartbeat = None;
+                        continue;
+                    }
+                    Incoming::Reply { reference, ok } if reference.as_deref() == Some(&join_ref) => {
+                        if !ok {
+                            bail!("subscription rejected");
+                        }
+                        *failures = 0;
+                        SyncEvent::RealtimeConnected
+                    }
+                    Incoming::Insert(post) => SyncEvent::NewPost(post),
+                    Incoming::Closed(reason) => bail!(reason),
+                    Incoming::Reply { .. } | Incoming::Other => continue,
+                };
+                if events.send(event).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::domain::Post;
+use crate::supabase::auth::FeedRow;
+
+/// Channel carrying inserts on `public.posts`.
+pub const POSTS_TOPIC: &str = "realtime:public:posts";
+const PHOENIX_TOPIC: &str = "phoenix";
+
+/// A Phoenix channel frame in the JSON (v1) serialization Supabase Realtime
+/// speaks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Frame {
+    pub topic: String,
+    pub event: String,
+    #[serde(default)]
+    pub payload: Value,
+    #[serde(rename = "ref", default)]
+    pub reference: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub join_ref: Option<String>,
+}
+
+impl Frame {
+    pub fn join(reference: &str, access_token: &str) -> Self {
+        Self {
+            topic: POSTS_TOPIC.to_string(),
+            event: "phx_join".to_string(),
+            payload: json!({
+                "config": {
+                    "broadcast": { "self": false },
+                    "presence": { "key": "" },
+                    "postgres_changes": [
+                        { "event": "INSERT", "schema": "public", "table": "posts" }
+                    ]
+                },
+                "access_token": access_token,
+            }),
+            reference: Some(reference.to_string()),
+            join_ref: Some(reference.to_string()),
+        }
+    }
+
+    pub fn heartbeat(reference: &str) -> Self {
+        Self {
+            topic: PHOENIX_TOPIC.to_string(),
+            event: "heartbeat".to_string(),
+            payload: json!({}),
+            reference: Some(reference.to_string()),
+            join_ref: None,
+        }
+    }
+
+    pub fn access_token(reference: &str, access_token: &str) -> Self {
+        Self {
+            topic: POSTS_TOPIC.to_string(),
+            event: "access_token".to_string(),
+            payload: json!({ "access_token": access_token }),
+            reference: Some(reference.to_string()),
+            join_ref: None,
+        }
+    }
+
+    pub fn encode(&self) -> Result<String> {
+        serde_json::to_string(self).context("encoding realtime frame")
+    }
+
+    pub fn decode(text: &str) -> Result<Self> {
+        serde_json::from_str(text).context("decoding realtime frame")
+    }
+}
+
+/// What the worker cares about in an incoming frame.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Incoming {
+    Reply {
+        reference: Option<String>,
+        ok: bool,
+    },
+    Insert(Post),
+    /// The server closed or errored the channel; reconnect.
+    Closed(String),
+    Other,
+}
+
+impl From<Frame> for Incoming {
+    fn from(frame: Frame) -> Self {
+        match frame.event.as_str() {
+            "phx_reply" => Incoming::Reply {
+                ok: frame.payload.get("status").and_then(Value::as_str) == Some("ok"),
+                reference: frame.reference,
+            },
+            "postgres_changes" => {
+                let data = &frame.payload["data"];
+                if data.get("type").and_then(Value::as_str) != Some("INSERT") {
+                    return Incoming::Other;
+                }
+                match serde_json::from_value::<FeedRow>(data["record"].clone()) {
+                    Ok(row) => Incoming::Insert(row.into_post()),
+                    Err(err) => {
+                        tracing::warn!(?err, "unreadable realtime record");
+                        Incoming::Other
+                    }
+                }
+            }
+            "phx_error" | "phx_close" | "system" if is_failure(&frame) => {
+                Incoming::Closed(format!("{} on {}", frame.event, frame.topic))
+            }
+            _ => Incoming::Other,
+        }
+    }
+}
+
+fn is_failure(frame: &Frame) -> bool {
+    match frame.event.as_str() {
+        "system" => frame.payload.get("status").and_then(Value::as_str) == Some("error"),
+        _ => true,
+    }
+}
@@ -7,7 +7,9 @@ use crate::data::cache::CacheDao;
 use crate::data::AppDatabase;
 use crate::domain::{Report, Task};
 
-pub fn generate_report(db: &AppDatabase, task: &Task) -> Result<Report> {
+/// `viewer` is the signed-in account whose cached posts are summarised, if
+/// any.
+pub fn generate_report(db: &AppDatabase, viewer: Option<&str>, task: &Task) -> Result<Report> {
     let now = OffsetDateTime::now_utc();
     std::fs::create_dir_all("reports").context("creating reports directory")?;
     let filename = format!(
@@ -22,12 +24,16 @@ pub fn generate_report(db: &AppDatabase, task: &Task) -> Result<Report> {
         .open(&filename)
         .with_context(|| format!("creating report {}", filename))?;
 
-    let cache = CacheDao::new(db);
-    let posts = cache.list_posts(50)?;
+    let posts = match viewer {
+        Some(viewer) => CacheDao::new(db).list_posts(viewer, 50)?,
+        None => Vec::new(),
+    };
     let summary = format!(
-        "Task #{id} \nStatus: {status}\nTitle: {title}\nDescription: {desc}\nCompleted at: {done:?}\nCached posts: {count}\n",
+        "Task #{id} \nStatus: {status}\nPriority: {priority}\nTags: {tags}\nTitle: {title}\nDescription: {desc}\nCompleted at: {done:?}\nCached posts: {count}\n",
         id = task.id,
         status = task.status.as_str(),
+        priority = task.priority.as_str(),
+        tags = task.tags.join(", "),
         title = task.title,
         desc = task.description,
         done = task.done_at,
@@ -1,11 +1,16 @@
 use anyhow::{anyhow, Context, Result};
-use reqwest::{Client, StatusCode};
+use jsonwebtoken::{DecodingKey, Validation};
+use reqwest::{Client, Method, Response};
 use serde::{Deserialize, Serialize};
 use time::{format_description::well_known::Rfc3339, Duration, OffsetDateTime};
+use uuid::Uuid;
 
-use crate::domain::{AudienceScope, FeedFilter, Post, Session, SessionTokens};
+use crate::domain::{
+    AudienceScope, FeedCursor, FeedFilter, Post, SearchQuery, Session, SessionTokens,
+};
 
-use super::client::SupabaseConfig;
+use super::client::{SupabaseClient, SupabaseConfig};
+use super::error::SupabaseError;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthResponse {
@@ -23,13 +28,19 @@ pub struct AuthUser {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct PostgrestPostRequest<'a> {
+    id: Uuid,
     author: &'a str,
     body: &'a str,
     audience: &'a str,
+    /// `root_id` is filled in by a trigger from the parent post.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<&'a str>,
 }
 
+/// A post as returned by the feed RPCs, PostgREST inserts and Realtime
+/// change records.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct FeedRow {
+pub(crate) struct FeedRow {
     id: String,
     author: String,
     body: String,
@@ -38,6 +49,9 @@ struct FeedRow {
     author_handle: Option<String>,
     liked: Option<bool>,
     like_count: Option<i64>,
+    reply_to: Option<String>,
+    root_id: Option<String>,
+    reply_to_handle: Option<String>,
 }
 
 pub async fn login_with_email(
@@ -58,13 +72,9 @@ pub async fn login_with_email(
         .header("apikey", &cfg.anon_key)
         .json(&Request { email, password })
         .send()
-        .await?;
-
-    if resp.status() == StatusCode::UNAUTHORIZED {
-        return Err(anyhow!("invalid credentials"));
-    }
-
-    let body: AuthResponse = resp.json().await.context("parsing auth response")?;
+        .await
+        .map_err(SupabaseError::from)?;
+    let body: AuthResponse = auth_response(resp).await?;
     let expires_at = OffsetDateTime::now_utc() + Duration::seconds(body.expires_in);
     let email = body.user.email.clone().unwrap_or_else(|| email.to_string());
     Ok(Session {
@@ -78,6 +88,84 @@ pub async fn login_with_email(
     })
 }
 
+/// Trades a PKCE authorization code from the browser login for tokens.
+pub async fn exchange_code(
+    client: &Client,
+    cfg: &SupabaseConfig,
+    auth_code: &str,
+    code_verifier: &str,
+) -> Result<Session> {
+    #[derive(Serialize)]
+    struct Request<'a> {
+        auth_code: &'a str,
+        code_verifier: &'a str,
+    }
+
+    let url = format!("{}/auth/v1/token?grant_type=pkce", cfg.url);
+    let resp = client
+        .post(url)
+        .header("apikey", &cfg.anon_key)
+        .json(&Request {
+            auth_code,
+            code_verifier,
+        })
+        .send()
+        .await
+        .map_err(SupabaseError::from)?;
+    let body: AuthResponse = auth_response(resp).await?;
+    let mut session = session_from_jwt(&body.access_token, &body.refresh_token)?;
+    if session.email.is_empty() {
+        session.email = body.user.email.unwrap_or_default();
+    }
+    Ok(session)
+}
+
+/// Decodes a token endpoint response. Rejections there mean the
+/// credentials or refresh token are no good.
+async fn auth_response(resp: Response) -> Result<AuthResponse> {
+    if !resp.status().is_success() {
+        return Err(SupabaseError::from_response(resp)
+            .await
+            .auth_rejected()
+            .into());
+    }
+    Ok(resp.json().await.map_err(SupabaseError::from)?)
+}
+
+#[derive(Debug, Deserialize)]
+struct AccessClaims {
+    sub: String,
+    #[serde(default)]
+    email: Option<String>,
+    exp: i64,
+}
+
+/// Builds a session from the claims in a Supabase access token. The
+/// signature isn't checked here; the server verifies it on every request.
+pub fn session_from_jwt(access_token: &str, refresh_token: &str) -> Result<Session> {
+    let mut validation = Validation::default();
+    validation.insecure_disable_signature_validation();
+    validation.validate_aud = false;
+    let claims = jsonwebtoken::decode::<AccessClaims>(
+        access_token,
+        &DecodingKey::from_secret(&[]),
+        &validation,
+    )
+    .context("decoding access token")?
+    .claims;
+    let expires_at =
+        OffsetDateTime::from_unix_timestamp(claims.exp).context("access token expiry")?;
+    Ok(Session {
+        user_id: claims.sub,
+        email: claims.email.unwrap_or_default(),
+        tokens: SessionTokens {
+            access_token: access_token.to_string(),
+            refresh_token: refresh_token.to_string(),
+            expires_at,
+        },
+    })
+}
+
 pub async fn refresh_session(
     client: &Client,
     cfg: &SupabaseConfig,
@@ -94,13 +182,9 @@ pub async fn refresh_session(
         .header("apikey", &cfg.anon_key)
         .json(&Request { refresh_token })
         .send()
-        .await?;
-
-    if resp.status() != StatusCode::OK {
-        return Err(anyhow!("refresh failed"));
-    }
-
-    let body: AuthResponse = resp.json().await?;
+        .await
+        .map_err(SupabaseError::from)?;
+    let body: AuthResponse = auth_response(resp).await?;
     Ok(SessionTokens {
         access_token: body.access_token,
         refresh_token: body.refresh_token,
@@ -108,37 +192,39 @@ pub async fn refresh_session(
     })
 }
 
+/// Inserts post `id`. Sending the same id again is ignored rather than
+/// creating a second post, and returns the one already stored.
 pub async fn post_message(
-    client: &Client,
-    cfg: &SupabaseConfig,
+    supabase: &SupabaseClient,
     session: &Session,
+    id: Uuid,
     text: &str,
     scope: AudienceScope,
+    reply_to: Option<&str>,
 ) -> Result<Post> {
-    let url = format!("{}/rest/v1/posts", cfg.url);
-    let req = PostgrestPostRequest {
-        author: &session.user_id,
-        body: text,
-        audience: &scope.to_string(),
-    };
-
-    let resp = client
-        .post(url)
-        .header("apikey", &cfg.anon_key)
+    let mut rows: Vec<FeedRow> = supabase
+        .rest(Method::POST, "posts")
         .header(
-            "Authorization",
-            format!("Bearer {}", mask(&session.tokens.access_token)),
+            "Prefer",
+            "return=representation,resolution=ignore-duplicates",
         )
-        .json(&req)
-        .send()
+        .json(&PostgrestPostRequest {
+            id,
+            author: &session.user_id,
+            body: text,
+            audience: &scope.to_string(),
+            reply_to,
+        })
+        .fetch(session)
         .await?;
-
-    if resp.status() != StatusCode::CREATED {
-        let text = resp.text().await.unwrap_or_default();
-        return Err(anyhow!("failed to post message: {}", text));
+    if rows.is_empty() {
+        // An ignored duplicate comes back empty.
+        rows = supabase
+            .rest(Method::GET, "posts")
+            .query("id", format!("eq.{}", id))
+            .fetch(session)
+            .await?;
     }
-
-    let rows: Vec<FeedRow> = resp.json().await?;
     rows.into_iter()
         .next()
         .map(|row| row.into_post())
@@ -146,41 +232,75 @@ pub async fn post_message(
 }
 
 pub async fn fetch_feed(
-    client: &Client,
-    cfg: &SupabaseConfig,
+    supabase: &SupabaseClient,
     session: &Session,
-    filter: FeedFilter,
+    filter: &FeedFilter,
+    before: Option<&FeedCursor>,
+    page_size: usize,
 ) -> Result<Vec<Post>> {
-    let url = match filter {
-        FeedFilter::Global => format!("{}/rest/v1/rpc/feed_global", cfg.url),
-        FeedFilter::Following => format!("{}/rest/v1/rpc/feed_following", cfg.url),
-        FeedFilter::Me => format!("{}/rest/v1/rpc/feed_me", cfg.url),
+    let rpc = match filter {
+        FeedFilter::Global => "rpc/feed_global",
+        FeedFilter::Following => "rpc/feed_following",
+        FeedFilter::Me => "rpc/feed_me",
+        FeedFilter::Mentions => "rpc/feed_mentions",
+        FeedFilter::Tag(_) => "rpc/feed_tag",
     };
-    let resp = client
-        .post(url)
-        .header("apikey", &cfg.anon_key)
-        .header(
-            "Authorization",
-            format!("Bearer {}", mask(&session.tokens.access_token)),
-        )
-        .json(&serde_json::json!({ "uid": session.user_id }))
-        .send()
+    let mut args = serde_json::json!({
+        "uid": session.user_id,
+        "before_created_at": before.map(|c| c.created_at.format(&Rfc3339).unwrap_or_default()),
+        "before_id": before.map(|c| c.id),
+        "page_size": page_size,
+    });
+    if let FeedFilter::Tag(tag) = filter {
+        args["tag"] = serde_json::Value::from(tag.as_str());
+    }
+    let rows: Vec<FeedRow> = supabase
+        .rest(Method::POST, rpc)
+        .json(&args)
+        .fetch(session)
         .await?;
+    Ok(rows.into_iter().map(|row| row.into_post()).collect())
+}
 
-    if resp.status() == StatusCode::UNAUTHORIZED {
-        return Err(anyhow!("session expired"));
-    }
+/// Every post in the conversation `post_id` belongs to that `session` may
+/// see, through the `thread` RPC.
+pub async fn fetch_thread(
+    supabase: &SupabaseClient,
+    session: &Session,
+    post_id: &str,
+) -> Result<Vec<Post>> {
+    let rows: Vec<FeedRow> = supabase
+        .rest(Method::POST, "rpc/thread")
+        .json(&serde_json::json!({ "uid": session.user_id, "post_id": post_id }))
+        .fetch(session)
+        .await?;
+    Ok(rows.into_iter().map(|row| row.into_post()).collect())
+}
 
-    if resp.status() != StatusCode::OK {
-        let text = resp.text().await.unwrap_or_default();
-        return Err(anyhow!("feed fetch failed: {}", text));
-    }
-    let rows: Vec<FeedRow> = resp.json().await?;
+/// Server-side full-text search through the `search_posts` RPC, which only
+/// returns posts `session` may see.
+pub async fn search_posts(
+    supabase: &SupabaseClient,
+    session: &Session,
+    query: &SearchQuery,
+    limit: usize,
+) -> Result<Vec<Post>> {
+    let rows: Vec<FeedRow> = supabase
+        .rest(Method::POST, "rpc/search_posts")
+        .json(&serde_json::json!({
+            "uid": session.user_id,
+            "query": query.text,
+            "author_handle": query.author,
+            "since": query.since.map(|d| d.to_string()),
+            "max_results": limit,
+        }))
+        .fetch(session)
+        .await?;
     Ok(rows.into_iter().map(|row| row.into_post()).collect())
 }
 
 impl FeedRow {
-    fn into_post(self) -> Post {
+    pub(crate) fn into_post(self) -> Post {
         Post {
             id: self.id.parse().unwrap_or_default(),
             author: self.author.parse().unwrap_or_default(),
@@ -194,13 +314,9 @@ impl FeedRow {
             author_handle: self.author_handle,
             liked: self.liked.unwrap_or(false),
             like_count: self.like_count.unwrap_or(0),
+            reply_to: self.reply_to.and_then(|id| id.parse().ok()),
+            root_id: self.root_id.and_then(|id| id.parse().ok()),
+            reply_to_handle: self.reply_to_handle,
         }
     }
 }
-
-fn mask(token: &str) -> String {
-    if token.len() <= 12 {
-        return token.to_string();
-    }
-    format!("{}...{}", &token[..4], &token[token.len() - 4..])
-}
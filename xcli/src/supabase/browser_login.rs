@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{timeout, Instant};
+use url::Url;
+
+use crate::domain::Session;
+
+use super::client::SupabaseClient;
+
+/// Largest request head accepted on the callback listener.
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+/// How long a single browser connection may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+const CALLBACK_PATH: &str = "/callback";
+
+/// A browser-based (passkey) login in progress: a localhost listener waiting
+/// for the login page to redirect back with a PKCE authorization code.
+pub struct BrowserLogin {
+    listener: TcpListener,
+    state: String,
+    verifier: String,
+    timeout: Duration,
+}
+
+/// The parts of an HTTP request line the callback handler looks at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallbackRequest {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+}
+
+impl BrowserLogin {
+    pub async fn bind(timeout: Duration) -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .context("binding login callback listener")?;
+        Ok(Self {
+            listener,
+            state: random_string(32),
+            verifier: random_string(64),
+            timeout,
+        })
+    }
+
+    /// Where the login page should send the browser back to. Carries the
+    /// state parameter so any redirect that drops it is rejected.
+    pub fn redirect_uri(&self) -> Result<String> {
+        let port = self.listener.local_addr()?.port();
+        Ok(format!(
+            "http://127.0.0.1:{}{}?state={}",
+            port, CALLBACK_PATH, self.state
+        ))
+    }
+
+    /// The page to open in the browser: `base` (the Supabase authorize
+    /// endpoint or a hosted passkey page) with redirect and PKCE parameters.
+    pub fn authorize_url(&self, base: &str) -> Result<String> {
+        let mut url = Url::parse(base).with_context(|| format!("invalid login url {}", base))?;
+        url.query_pairs_mut()
+            .append_pair("redirect_to", &self.redirect_uri()?)
+            .append_pair("code_challenge", &code_challenge(&self.verifier))
+            .append_pair("code_challenge_method", "s256");
+        Ok(url.into())
+    }
+
+    /// Waits for the callback, exchanges the code and stores the session.
+    /// The browser is shown a success or failure page either way.
+    pub async fn complete(self, supabase: &SupabaseClient) -> Result<Session> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let (mut socket, _) = timeout(remaining, self.listener.accept())
+                .await
+                .map_err(|_| anyhow!("timed out waiting for the browser login"))??;
+            // Browsers open speculative connections and ask for favicons;
+            // anything that isn't the callback is answered and skipped.
+            let request = match read_request(&mut socket).await {
+                Ok(request) => request,
+                Err(err) => {
+                    tracing::debug!(?err, "ignoring login listener connection");
+                    respond(&mut socket, 400, "Bad Request", "").await.ok();
+                    continue;
+                }
+            };
+            if request.method != "GET" || request.path != CALLBACK_PATH {
+                respond(&mut socket, 404, "Not Found", "").await.ok();
+                continue;
+            }
+
+            let result = self.handle_callback(supabase, &request).await;
+            let (status, reason, page) = match &result {
+                Ok(session) => (200, "OK", success_page(&session.email)),
+                Err(err) => (400, "Bad Request", failure_page(&format!("{:#}", err))),
+            };
+            if let Err(err) = respond(&mut socket, status, reason, &page).await {
+                tracing::warn!(?err, "failed to answer login callback");
+            }
+            return result;
+        }
+    }
+
+    async fn handle_callback(
+        &self,
+        supabase: &SupabaseClient,
+        request: &CallbackRequest,
+    ) -> Result<Session> {
+        if request.query.get("state") != Some(&self.state) {
+            bail!("login state mismatch; start the login again");
+        }
+        if let Some(error) = request.query.get("error") {
+            let description = request
+                .query
+                .get("error_description")
+                .unwrap_or(error)
+                .clone();
+            bail!("login was not completed: {}", description);
+        }
+        let code = request
+            .query
+            .get("code")
+            .ok_or_else(|| anyhow!("callback carried no authorization code"))?;
+        supabase.login_with_code(code, &self.verifier).await
+    }
+}
+
+/// Parses an HTTP/1.x request head (request line plus headers) and splits the
+/// query string of its target.
+pub fn parse_request(head: &str) -> Result<CallbackRequest> {
+    let mut lines = head.split("\r\n");
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        bail!("malformed request line {:?}", request_line);
+    };
+    if !version.starts_with("HTTP/1.") {
+        bail!("unsupported protocol {}", version);
+    }
+    for header in lines.take_while(|line| !line.is_empty()) {
+        if !header.contains(':') {
+            bail!("malformed header {:?}", header);
+        }
+    }
+    let url = Url::parse("http://localhost")?
+        .join(target)
+        .with_context(|| format!("malformed request target {}", target))?;
+    Ok(CallbackRequest {
+        method: method.to_string(),
+        path: url.path().to_string(),
+        query: url.query_pairs().into_owned().collect(),
+    })
+}
+
+async fn read_request(socket: &mut TcpStream) -> Result<CallbackRequest> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    loop {
+        let n = timeout(READ_TIMEOUT, socket.read(&mut chunk))
+            .await
+            .map_err(|_| anyhow!("request read timed out"))??;
+        if n == 0 {
+            bail!("connection closed before request was complete");
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(end) = buf.windows(4).position(|window| window == b"\r\n\r\n") {
+            let head = std::str::from_utf8(&buf[..end]).context("request is not UTF-8")?;
+            return parse_request(head);
+        }
+        if buf.len() > MAX_REQUEST_BYTES {
+            bail!("request head too large");
+        }
+    }
+}
+
+async fn respond(socket: &mut TcpStream, status: u16, reason: &str, body: &str) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\nCache-Control: no-store\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await.ok();
+    Ok(())
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+fn success_page(email: &str) -> String {
+    page(
+        "Signed in",
+        &format!(
+            "You are signed in to cli-twitter as <strong>{}</strong>. You can close this window and return to the terminal.",
+            escape_html(email)
+        ),
+    )
+}
+
+fn failure_page(reason: &str) -> String {
+    page(
+        "Sign-in failed",
+        &format!(
+            "{}<br>Return to the terminal and run <code>/passkey</code> to try again.",
+            escape_html(reason)
+        ),
+    )
+}
+
+fn page(title: &str, message: &str) -> String {
+    format!(
+        "<!doctype html><html><head><meta charset=\"utf-8\"><title>cli-twitter: {title}</title>\
+<style>body{{font-family:system-ui,sans-serif;max-width:32rem;margin:4rem auto;color:#222}}</style>\
+</head><body><h1>{title}</h1><p>{message}</p></body></html>"
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,19 +1,42 @@
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
-use reqwest::Client;
+use parking_lot::Mutex;
+use rand::Rng;
+use reqwest::{Client, Method};
+use time::OffsetDateTime;
 use tokio::time::sleep;
 use tracing::instrument;
+use uuid::Uuid;
 
-use crate::domain::{AudienceScope, FeedFilter, Post, Session};
+use crate::domain::{
+    AudienceScope, FeedCursor, FeedFilter, Message, Notification, Post, Profile, ProfileCard,
+    SearchQuery, Session, SessionTokens, PROFILE_POSTS,
+};
 
 use super::auth;
+use super::error::SupabaseError;
+use super::messages;
+use super::notifications;
+use super::rest::RestRequest;
 use super::session_store::SessionStore;
+use super::social;
+
+const MAX_RETRIES: u32 = 3;
+const RETRY_BASE: Duration = Duration::from_millis(200);
+/// Longest `Retry-After` worth waiting out inside a single command.
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Clone)]
 pub struct SupabaseConfig {
     pub url: String,
     pub anon_key: String,
+    /// Page that runs the browser (passkey) login and redirects back with a
+    /// PKCE code. Supabase's own authorize endpoint needs a `provider`, so
+    /// there is no usable default and `/passkey` requires this.
+    pub login_url: Option<String>,
 }
 
 impl SupabaseConfig {
@@ -21,14 +44,30 @@ impl SupabaseConfig {
         dotenvy::dotenv().ok();
         let url = std::env::var("SUPABASE_URL").context("SUPABASE_URL missing")?;
         let anon_key = std::env::var("SUPABASE_ANON_KEY").context("SUPABASE_ANON_KEY missing")?;
-        Ok(Self { url, anon_key })
+        let login_url = std::env::var("SUPABASE_LOGIN_URL").ok();
+        Ok(Self {
+            url,
+            anon_key,
+            login_url,
+        })
+    }
+
+    pub fn login_url(&self) -> Result<&str> {
+        self.login_url.as_deref().context(
+            "SUPABASE_LOGIN_URL is not set; /passkey needs a hosted login page that \
+             redirects back with a PKCE code",
+        )
     }
 }
 
+#[derive(Clone)]
 pub struct SupabaseClient {
     pub client: Client,
     pub cfg: SupabaseConfig,
     pub sessions: SessionStore,
+    /// Newest tokens seen per account, so a caller still holding a
+    /// [`Session`] from before a refresh doesn't send a stale access token.
+    latest: Arc<Mutex<HashMap<String, SessionTokens>>>,
 }
 
 impl SupabaseClient {
@@ -41,6 +80,7 @@ impl SupabaseClient {
             client,
             cfg,
             sessions,
+            latest: Arc::default(),
         })
     }
 
@@ -50,40 +90,221 @@ impl SupabaseClient {
         Ok(session)
     }
 
+    /// Finishes a browser login by exchanging its authorization code.
+    pub async fn login_with_code(&self, code: &str, verifier: &str) -> Result<Session> {
+        let session = auth::exchange_code(&self.client, &self.cfg, code, verifier).await?;
+        self.sessions.save(&session).await?;
+        Ok(session)
+    }
+
     pub async fn restore_session(&self) -> Result<Option<Session>> {
         self.sessions.load().await
     }
 
     pub async fn ensure_session(&self) -> Result<Option<Session>> {
-        if let Some(mut session) = self.sessions.load().await? {
-            if session.tokens.is_expired() {
-                let tokens =
-                    auth::refresh_session(&self.client, &self.cfg, &session.tokens.refresh_token)
-                        .await?;
-                session.tokens = tokens;
-                self.sessions.save(&session).await?;
+        match self.sessions.load().await? {
+            Some(session) if session.tokens.is_expired() => self.refresh(&session).await.map(Some),
+            session => Ok(session),
+        }
+    }
+
+    /// Trades `session`'s refresh token for new tokens and stores them. The
+    /// active account stays as it is, even if `session` belongs to another.
+    /// If another request or process has already rotated that refresh
+    /// token, its stored tokens are used instead.
+    pub async fn refresh(&self, session: &Session) -> Result<Session> {
+        if let Some(stored) = self.sessions.get(&session.user_id).await? {
+            if stored.tokens.refresh_token != session.tokens.refresh_token
+                && !stored.tokens.is_expired()
+            {
+                self.remember(&stored);
+                return Ok(stored);
             }
-            return Ok(Some(session));
         }
-        Ok(None)
+        let tokens =
+            auth::refresh_session(&self.client, &self.cfg, &session.tokens.refresh_token).await?;
+        let session = Session {
+            tokens,
+            ..session.clone()
+        };
+        self.sessions.save_tokens(&session).await?;
+        self.remember(&session);
+        Ok(session)
+    }
+
+    /// `session` with the newest tokens this client has seen for its
+    /// account.
+    pub fn current(&self, session: &Session) -> Session {
+        match self.latest.lock().get(&session.user_id) {
+            Some(tokens) => Session {
+                tokens: tokens.clone(),
+                ..session.clone()
+            },
+            None => session.clone(),
+        }
+    }
+
+    fn remember(&self, session: &Session) {
+        self.latest
+            .lock()
+            .insert(session.user_id.clone(), session.tokens.clone());
+    }
+
+    /// Starts an authenticated PostgREST request for `path` under
+    /// `/rest/v1/`, e.g. `posts` or `rpc/feed_global`.
+    pub fn rest(&self, method: Method, path: &str) -> RestRequest<'_> {
+        RestRequest::new(self, method, path)
+    }
+
+    #[instrument(skip_all, fields(filter = %filter))]
+    pub async fn fetch_feed(
+        &self,
+        session: &Session,
+        filter: &FeedFilter,
+        before: Option<FeedCursor>,
+        page_size: usize,
+    ) -> Result<Vec<Post>> {
+        retry_async(|| auth::fetch_feed(self, session, filter, before.as_ref(), page_size)).await
+    }
+
+    /// Not retried here: the outbox retries with the same `id`.
+    #[instrument(skip_all)]
+    pub async fn send_message(
+        &self,
+        session: &Session,
+        id: Uuid,
+        recipient: &str,
+        body: &str,
+    ) -> Result<Message> {
+        messages::send_message(self, session, id, recipient, body).await
+    }
+
+    #[instrument(skip_all)]
+    pub async fn fetch_messages(
+        &self,
+        session: &Session,
+        since: Option<OffsetDateTime>,
+    ) -> Result<Vec<Message>> {
+        retry_async(|| messages::fetch_messages(self, session, since)).await
+    }
+
+    #[instrument(skip_all, fields(peer = %peer))]
+    pub async fn mark_messages_read(
+        &self,
+        session: &Session,
+        peer: &str,
+        at: OffsetDateTime,
+    ) -> Result<()> {
+        retry_async(|| messages::mark_read(self, session, peer, at)).await
+    }
+
+    #[instrument(skip_all)]
+    pub async fn fetch_notifications(
+        &self,
+        session: &Session,
+        since: Option<OffsetDateTime>,
+    ) -> Result<Vec<Notification>> {
+        retry_async(|| notifications::fetch_notifications(self, session, since)).await
     }
 
-    #[instrument(skip_all, fields(filter = ?filter))]
-    pub async fn fetch_feed(&self, session: &Session, filter: FeedFilter) -> Result<Vec<Post>> {
-        retry_async(|| auth::fetch_feed(&self.client, &self.cfg, session, filter)).await
+    #[instrument(skip_all, fields(post_id = %post_id))]
+    pub async fn fetch_thread(&self, session: &Session, post_id: &str) -> Result<Vec<Post>> {
+        retry_async(|| auth::fetch_thread(self, session, post_id)).await
+    }
+
+    #[instrument(skip_all, fields(query = %query))]
+    pub async fn search_posts(
+        &self,
+        session: &Session,
+        query: &SearchQuery,
+        limit: usize,
+    ) -> Result<Vec<Post>> {
+        retry_async(|| auth::search_posts(self, session, query, limit)).await
     }
 
+    /// Not retried here: the outbox and the scheduled queue retry with the
+    /// same `id`.
     #[instrument(skip_all, fields(scope = %scope))]
     pub async fn create_post(
         &self,
         session: &Session,
+        id: Uuid,
         text: &str,
         scope: AudienceScope,
+        reply_to: Option<&str>,
     ) -> Result<Post> {
-        retry_async(|| auth::post_message(&self.client, &self.cfg, session, text, scope)).await
+        auth::post_message(self, session, id, text, scope, reply_to).await
+    }
+
+    #[instrument(skip_all, fields(handle = %handle))]
+    pub async fn find_profile(&self, session: &Session, handle: &str) -> Result<Profile> {
+        retry_async(|| social::find_profile(self, session, handle)).await
+    }
+
+    /// `handle`'s profile, follow counts and newest posts.
+    #[instrument(skip_all, fields(handle = %handle))]
+    pub async fn profile(&self, session: &Session, handle: &str) -> Result<ProfileCard> {
+        let (profile, stats) = retry_async(|| social::profile_card(self, session, handle)).await?;
+        let author = profile.id.to_string();
+        let posts =
+            retry_async(|| social::profile_posts(self, session, &author, PROFILE_POSTS)).await?;
+        Ok(ProfileCard {
+            profile,
+            stats: Some(stats),
+            posts,
+        })
+    }
+
+    #[instrument(skip_all)]
+    pub async fn update_profile(
+        &self,
+        session: &Session,
+        display_name: Option<&str>,
+    ) -> Result<Profile> {
+        retry_async(|| social::update_profile(self, session, display_name)).await
+    }
+
+    #[instrument(skip_all, fields(query = %query))]
+    pub async fn search_profiles(
+        &self,
+        session: &Session,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<Profile>> {
+        retry_async(|| social::search_profiles(self, session, query, limit)).await
+    }
+
+    /// Resolves `handle` and follows that profile, returning it for caching.
+    pub async fn follow(&self, session: &Session, handle: &str) -> Result<Profile> {
+        let profile = self.find_profile(session, handle).await?;
+        let followee = profile.id.to_string();
+        retry_async(|| social::follow(self, session, &followee)).await?;
+        Ok(profile)
+    }
+
+    pub async fn unfollow(&self, session: &Session, handle: &str) -> Result<Profile> {
+        let profile = self.find_profile(session, handle).await?;
+        let followee = profile.id.to_string();
+        retry_async(|| social::unfollow(self, session, &followee)).await?;
+        Ok(profile)
+    }
+
+    #[instrument(skip_all, fields(post_id = %post_id))]
+    pub async fn like_post(&self, session: &Session, post_id: &str) -> Result<()> {
+        retry_async(|| social::like(self, session, post_id)).await
+    }
+
+    #[instrument(skip_all, fields(post_id = %post_id))]
+    pub async fn unlike_post(&self, session: &Session, post_id: &str) -> Result<()> {
+        retry_async(|| social::unlike(self, session, post_id)).await
     }
 }
 
+/// Retries `f` while it fails with a transient [`SupabaseError`], backing
+/// off exponentially with jitter. Only for requests that are safe to repeat:
+/// reads, PATCH, DELETE and inserts that treat a conflict as success. A
+/// `Retry-After` longer than the backoff is waited out, unless it's too long
+/// to block a command on.
 async fn retry_async<F, Fut, T>(mut f: F) -> Result<T>
 where
     F: FnMut() -> Fut,
@@ -91,15 +312,31 @@ where
 {
     let mut attempts = 0;
     loop {
-        match f().await {
+        let err = match f().await {
             Ok(res) => return Ok(res),
-            Err(err) if attempts < 3 => {
-                attempts += 1;
-                let backoff = Duration::from_millis(150 * attempts * attempts);
-                tracing::warn!(?err, attempts, "retrying request");
-                sleep(backoff).await;
-            }
-            Err(err) => return Err(err),
+            Err(err) => err,
+        };
+        let Some(error) = SupabaseError::find(&err).filter(|e| e.is_transient()) else {
+            return Err(err);
+        };
+        let wait = backoff(attempts).max(error.retry_after().unwrap_or_default());
+        if attempts >= MAX_RETRIES || wait > MAX_RETRY_WAIT {
+            return Err(err);
         }
+        attempts += 1;
+        tracing::warn!(%error, attempts, ?wait, "retrying request");
+        sleep(wait).await;
     }
 }
+
+/// 200ms, 400ms, 800ms, ... with each step jittered down by up to half.
+fn backoff(attempts: u32) -> Duration {
+    let base = RETRY_BASE.as_millis() as u64 * 2u64.pow(attempts.min(8));
+    Duration::from_millis(rand::thread_rng().gen_range(base / 2..=base))
+}
+
+/// True when `err` came from failing to reach Supabase at all (DNS, refused
+/// connection, timeout) rather than from a response we didn't like.
+pub fn is_offline(err: &anyhow::Error) -> bool {
+    SupabaseError::find(err).is_some_and(SupabaseError::is_offline)
+}
@@ -0,0 +1,224 @@
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Response, StatusCode};
+use serde_json::Value;
+use thiserror::Error;
+use time::{format_description::well_known::Rfc2822, OffsetDateTime};
+
+/// An error body from PostgREST or Supabase Auth, e.g.
+/// `{"code":"23505","message":"duplicate key ...","details":null,"hint":null}`.
+/// Bodies that aren't JSON keep their text as `message`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    pub status: u16,
+    pub code: Option<String>,
+    pub message: String,
+    pub details: Option<String>,
+    pub hint: Option<String>,
+}
+
+impl ApiError {
+    pub async fn from_response(resp: Response) -> Self {
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        Self::parse(status.as_u16(), &text)
+    }
+
+    /// Reads PostgREST's `code`/`message`/`details`/`hint` as well as the
+    /// `error_code`/`msg`/`error_description` fields Supabase Auth uses.
+    pub fn parse(status: u16, body: &str) -> Self {
+        let json = serde_json::from_str::<Value>(body).unwrap_or_default();
+        let field = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| json.get(*name).and_then(Value::as_str))
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let message =
+            field(&["message", "msg", "error_description"]).unwrap_or_else(|| match body.trim() {
+                text if !text.is_empty() && !json.is_object() => text.to_string(),
+                _ => StatusCode::from_u16(status)
+                    .ok()
+                    .and_then(|s| s.canonical_reason())
+                    .unwrap_or("request failed")
+                    .to_string(),
+            });
+        ApiError {
+            status,
+            code: field(&["code", "error_code", "error"]),
+            message,
+            details: field(&["details"]),
+            hint: field(&["hint"]),
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(code) = &self.code {
+            write!(f, " ({})", code)?;
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, "; hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// A command that needs an account ran while none is signed in.
+#[derive(Debug, Error)]
+#[error("login required")]
+pub struct LoginRequired;
+
+/// Why a call to Supabase failed, sorted by what the caller can do about it.
+#[derive(Debug, Error)]
+pub enum SupabaseError {
+    #[error("can't reach Supabase: {0}")]
+    Network(String),
+    #[error("Supabase did not answer in time")]
+    Timeout,
+    #[error("not signed in: {0}")]
+    Unauthorized(ApiError),
+    /// Usually a row-level security policy rejecting the row.
+    #[error("forbidden: {0}")]
+    Forbidden(ApiError),
+    #[error("conflict: {0}")]
+    Conflict(ApiError),
+    #[error("rate limited by Supabase")]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("Supabase server error: {0}")]
+    Server(ApiError),
+    /// Any other 4xx, e.g. a malformed filter or a missing column.
+    #[error("request rejected: {0}")]
+    Rejected(ApiError),
+    #[error("unexpected response from Supabase: {0}")]
+    Decode(String),
+}
+
+impl SupabaseError {
+    pub async fn from_response(resp: Response) -> Self {
+        let retry_after = retry_after(resp.headers());
+        let api = ApiError::from_response(resp).await;
+        Self::from_status(api, retry_after)
+    }
+
+    pub fn from_status(api: ApiError, retry_after: Option<Duration>) -> Self {
+        match StatusCode::from_u16(api.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR) {
+            StatusCode::UNAUTHORIZED => Self::Unauthorized(api),
+            StatusCode::FORBIDDEN => Self::Forbidden(api),
+            StatusCode::CONFLICT => Self::Conflict(api),
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited { retry_after },
+            StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => Self::Timeout,
+            status if status.is_server_error() => Self::Server(api),
+            _ => Self::Rejected(api),
+        }
+    }
+
+    /// Finds the Supabase error anywhere in `err`'s chain.
+    pub fn find(err: &anyhow::Error) -> Option<&SupabaseError> {
+        err.chain().find_map(|cause| cause.downcast_ref())
+    }
+
+    /// Whether the same request may succeed if sent again later.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::Network(_) | Self::Timeout | Self::RateLimited { .. } | Self::Server(_)
+        )
+    }
+
+    pub fn is_offline(&self) -> bool {
+        matches!(self, Self::Network(_) | Self::Timeout)
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// A status line telling the user what to do next.
+    pub fn advice(&self) -> String {
+        match self {
+            Self::Network(_) => {
+                "Can't reach Supabase. Check your connection; queued changes are sent once it's back."
+                    .to_string()
+            }
+            Self::Timeout => "Supabase timed out. Try again in a moment.".to_string(),
+            Self::Unauthorized(api) => format!(
+                "{}. Sign in again with /login or /passkey.",
+                api.message.trim_end_matches('.')
+            ),
+            Self::Forbidden(api) => format!(
+                "Not allowed: {}. Check you're on the right account (/account) and that the RLS policies are applied.",
+                api.message.trim_end_matches('.')
+            ),
+            Self::Conflict(api) => format!("Already exists: {}.", api.message.trim_end_matches('.')),
+            Self::RateLimited {
+                retry_after: Some(wait),
+            } => format!(
+                "Rate limited by Supabase. Wait {}s before trying again.",
+                wait.as_secs().max(1)
+            ),
+            Self::RateLimited { retry_after: None } => {
+                "Rate limited by Supabase. Wait a little before trying again.".to_string()
+            }
+            Self::Server(api) => format!(
+                "Supabase had a server error ({}). Try again later.",
+                api.status
+            ),
+            Self::Rejected(api) => format!("Supabase rejected the request: {}", api),
+            Self::Decode(_) => {
+                "Unexpected response from Supabase. Is the schema in supabase/remote.sql applied?"
+                    .to_string()
+            }
+        }
+    }
+
+    /// Supabase Auth answers bad credentials or a revoked refresh token with
+    /// a 400; treat those as a sign-in problem rather than a bad request.
+    pub fn auth_rejected(self) -> Self {
+        match self {
+            Self::Rejected(api) => Self::Unauthorized(api),
+            other => other,
+        }
+    }
+}
+
+impl From<reqwest::Error> for SupabaseError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Self::Timeout
+        } else if err.is_decode() {
+            Self::Decode(err.to_string())
+        } else {
+            Self::Network(err.to_string())
+        }
+    }
+}
+
+/// The status line for a failed command: Supabase errors get advice, any
+/// other error its own message.
+pub fn user_message(err: &anyhow::Error) -> String {
+    match SupabaseError::find(err) {
+        Some(error) => error.advice(),
+        None => err.to_string(),
+    }
+}
+
+/// `Retry-After` as either delay seconds or an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = OffsetDateTime::parse(value, &Rfc2822).ok()?;
+    let wait = at - OffsetDateTime::now_utc();
+    Some(Duration::try_from(wait).unwrap_or_default())
+}
@@ -0,0 +1,130 @@
+use anyhow::{anyhow, Result};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::domain::{Message, Session};
+use uuid::Uuid;
+
+use super::client::SupabaseClient;
+
+/// Embeds both participants' handles through the two foreign keys.
+const SELECT: &str = "id,sender,recipient,body,created_at,read_at,\
+sender_profile:profiles!messages_sender_fkey(handle),\
+recipient_profile:profiles!messages_recipient_fkey(handle)";
+/// Most messages fetched by one sync.
+const SYNC_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MessageRow {
+    id: String,
+    sender: String,
+    recipient: String,
+    body: String,
+    created_at: String,
+    read_at: Option<String>,
+    sender_profile: Option<HandleRow>,
+    recipient_profile: Option<HandleRow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HandleRow {
+    handle: String,
+}
+
+/// Inserts message `id`; as with posts, sending the same id again returns
+/// the stored message instead of a duplicate.
+pub async fn send_message(
+    supabase: &SupabaseClient,
+    session: &Session,
+    id: Uuid,
+    recipient: &str,
+    body: &str,
+) -> Result<Message> {
+    let mut rows: Vec<MessageRow> = supabase
+        .rest(Method::POST, "messages")
+        .query("select", SELECT)
+        .header(
+            "Prefer",
+            "return=representation,resolution=ignore-duplicates",
+        )
+        .json(&serde_json::json!({
+            "id": id,
+            "sender": session.user_id,
+            "recipient": recipient,
+            "body": body,
+        }))
+        .fetch(session)
+        .await?;
+    if rows.is_empty() {
+        rows = supabase
+            .rest(Method::GET, "messages")
+            .query("select", SELECT)
+            .query("id", format!("eq.{}", id))
+            .fetch(session)
+            .await?;
+    }
+    rows.into_iter()
+        .next()
+        .map(MessageRow::into_message)
+        .ok_or_else(|| anyhow!("empty response"))
+}
+
+/// Messages sent or received by `session` after `since`, oldest first.
+pub async fn fetch_messages(
+    supabase: &SupabaseClient,
+    session: &Session,
+    since: Option<OffsetDateTime>,
+) -> Result<Vec<Message>> {
+    let mut req = supabase
+        .rest(Method::GET, "messages")
+        .query("select", SELECT)
+        .query(
+            "or",
+            format!("(sender.eq.{0},recipient.eq.{0})", session.user_id),
+        )
+        .query("order", "created_at.asc")
+        .query("limit", SYNC_LIMIT.to_string());
+    if let Some(since) = since {
+        req = req.query(
+            "created_at",
+            format!("gt.{}", since.format(&Rfc3339).unwrap_or_default()),
+        );
+    }
+    let rows: Vec<MessageRow> = req.fetch(session).await?;
+    Ok(rows.into_iter().map(MessageRow::into_message).collect())
+}
+
+/// Marks what `peer` sent to `session` as read on the server.
+pub async fn mark_read(
+    supabase: &SupabaseClient,
+    session: &Session,
+    peer: &str,
+    at: OffsetDateTime,
+) -> Result<()> {
+    supabase
+        .rest(Method::PATCH, "messages")
+        .query("recipient", format!("eq.{}", session.user_id))
+        .query("sender", format!("eq.{}", peer))
+        .query("read_at", "is.null")
+        .header("Prefer", "return=minimal")
+        .json(&serde_json::json!({ "read_at": at.format(&Rfc3339).unwrap_or_default() }))
+        .execute(session)
+        .await
+}
+
+impl MessageRow {
+    fn into_message(self) -> Message {
+        let ts = |s: &str| OffsetDateTime::parse(s, &Rfc3339).ok();
+        Message {
+            id: self.id.parse().unwrap_or_default(),
+            sender: self.sender.parse().unwrap_or_default(),
+            recipient: self.recipient.parse().unwrap_or_default(),
+            created_at: ts(&self.created_at).unwrap_or_else(OffsetDateTime::now_utc),
+            read_at: self.read_at.as_deref().and_then(ts),
+            body: self.body,
+            sender_handle: self.sender_profile.map(|p| p.handle),
+            recipient_handle: self.recipient_profile.map(|p| p.handle),
+        }
+    }
+}
@@ -1,6 +1,15 @@
 pub mod auth;
+pub mod browser_login;
 pub mod client;
+pub mod error;
+pub mod messages;
+pub mod notifications;
+pub mod rest;
+pub mod secrets;
 pub mod session_store;
+pub mod social;
 
-pub use client::{SupabaseClient, SupabaseConfig};
+pub use browser_login::BrowserLogin;
+pub use client::{is_offline, SupabaseClient, SupabaseConfig};
+pub use error::{ApiError, LoginRequired, SupabaseError};
 pub use session_store::SessionStore;
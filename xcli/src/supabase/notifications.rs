@@ -0,0 +1,62 @@
+use anyhow::Result;
+use reqwest::Method;
+use serde::Deserialize;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::domain::{Notification, NotificationKind, Session};
+
+use super::client::SupabaseClient;
+
+/// Most notifications fetched by one poll.
+const SYNC_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Deserialize)]
+struct NotificationRow {
+    key: String,
+    kind: String,
+    actor: String,
+    actor_handle: Option<String>,
+    post_id: Option<String>,
+    excerpt: Option<String>,
+    created_at: String,
+}
+
+/// Mentions, replies, likes and follows for `session` newer than `since`,
+/// oldest first and at most [`SYNC_LIMIT`], through the `notifications` RPC.
+/// Items of kinds this version doesn't know are skipped.
+pub async fn fetch_notifications(
+    supabase: &SupabaseClient,
+    session: &Session,
+    since: Option<OffsetDateTime>,
+) -> Result<Vec<Notification>> {
+    let rows: Vec<NotificationRow> = supabase
+        .rest(Method::POST, "rpc/notifications")
+        .json(&serde_json::json!({
+            "uid": session.user_id,
+            "since": since.map(|ts| ts.format(&Rfc3339).unwrap_or_default()),
+            "max_results": SYNC_LIMIT,
+        }))
+        .fetch(session)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(NotificationRow::into_notification)
+        .collect())
+}
+
+impl NotificationRow {
+    fn into_notification(self) -> Option<Notification> {
+        Some(Notification {
+            id: 0,
+            kind: self.kind.parse::<NotificationKind>().ok()?,
+            key: self.key,
+            actor: self.actor.parse().unwrap_or_default(),
+            actor_handle: self.actor_handle,
+            post_id: self.post_id.and_then(|id| id.parse().ok()),
+            excerpt: self.excerpt,
+            created_at: OffsetDateTime::parse(&self.created_at, &Rfc3339)
+                .unwrap_or_else(|_| OffsetDateTime::now_utc()),
+            read_at: None,
+        })
+    }
+}
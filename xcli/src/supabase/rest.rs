@@ -0,0 +1,116 @@
+use anyhow::{Context, Result};
+use reqwest::{Method, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::domain::Session;
+
+use super::client::SupabaseClient;
+use super::error::SupabaseError;
+
+/// An authenticated PostgREST request (`/rest/v1/...`). It is only built into
+/// a `reqwest` request when sent, so it can be replayed with a fresh token
+/// when the first attempt comes back 401.
+pub struct RestRequest<'a> {
+    supabase: &'a SupabaseClient,
+    method: Method,
+    url: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(&'static str, String)>,
+    body: Option<serde_json::Result<Value>>,
+    accept: Vec<StatusCode>,
+}
+
+impl<'a> RestRequest<'a> {
+    pub(crate) fn new(supabase: &'a SupabaseClient, method: Method, path: &str) -> Self {
+        Self {
+            url: format!(
+                "{}/rest/v1/{}",
+                supabase.cfg.url.trim_end_matches('/'),
+                path.trim_start_matches('/')
+            ),
+            supabase,
+            method,
+            query: Vec::new(),
+            headers: Vec::new(),
+            body: None,
+            accept: Vec::new(),
+        }
+    }
+
+    pub fn query(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.query.push((key.to_string(), value.into()));
+        self
+    }
+
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    pub fn json(mut self, body: &impl Serialize) -> Self {
+        self.body = Some(serde_json::to_value(body));
+        self
+    }
+
+    /// Treats `status` as success, e.g. 409 for an insert whose row exists.
+    pub fn accept(mut self, status: StatusCode) -> Self {
+        self.accept.push(status);
+        self
+    }
+
+    /// Sends with the newest access token known for `session`'s account. A
+    /// 401 refreshes the session once and replays the request; any other
+    /// failure becomes a [`SupabaseError`].
+    pub async fn send(mut self, session: &Session) -> Result<Response> {
+        let session = self.supabase.current(session);
+        let body = self
+            .body
+            .take()
+            .transpose()
+            .context("encoding request body")?;
+        let mut resp = self
+            .dispatch(&session.tokens.access_token, body.as_ref())
+            .await?;
+        if resp.status() == StatusCode::UNAUTHORIZED {
+            tracing::debug!(url = %self.url, "access token rejected; refreshing session");
+            let refreshed = self.supabase.refresh(&session).await?;
+            resp = self
+                .dispatch(&refreshed.tokens.access_token, body.as_ref())
+                .await?;
+        }
+        if resp.status().is_success() || self.accept.contains(&resp.status()) {
+            return Ok(resp);
+        }
+        Err(SupabaseError::from_response(resp).await.into())
+    }
+
+    /// Sends and decodes a JSON response body.
+    pub async fn fetch<T: DeserializeOwned>(self, session: &Session) -> Result<T> {
+        let resp = self.send(session).await?;
+        Ok(resp.json().await.map_err(SupabaseError::from)?)
+    }
+
+    /// Sends and discards the response body.
+    pub async fn execute(self, session: &Session) -> Result<()> {
+        self.send(session).await.map(|_| ())
+    }
+
+    async fn dispatch(&self, token: &str, body: Option<&Value>) -> Result<Response> {
+        let mut req = self
+            .supabase
+            .client
+            .request(self.method.clone(), &self.url)
+            .header("apikey", &self.supabase.cfg.anon_key)
+            .bearer_auth(token)
+            .query(&self.query);
+        for (name, value) in &self.headers {
+            req = req.header(*name, value);
+        }
+        if let Some(body) = body {
+            req = req.json(body);
+        }
+        Ok(req.send().await.map_err(SupabaseError::from)?)
+    }
+}
@@ -0,0 +1,242 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use parking_lot::Mutex;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+const KEYRING_SERVICE: &str = "cli-twitter";
+const KEYRING_ACCOUNT: &str = "sessions";
+const SALT_LEN: usize = 16;
+
+/// Where session secrets are kept. Calls block and are made from
+/// `spawn_blocking`.
+pub trait SecretBackend: Send + Sync {
+    fn describe(&self) -> String;
+    fn load(&self) -> Result<Option<String>>;
+    fn store(&self, secret: &str) -> Result<()>;
+    fn delete(&self) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Display, EnumString, Deserialize)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// The OS keyring if one answers, otherwise the encrypted file.
+    #[default]
+    Auto,
+    Keyring,
+    File,
+}
+
+pub struct KeyringBackend {
+    entry: keyring::Entry,
+}
+
+impl KeyringBackend {
+    pub fn new() -> Self {
+        Self {
+            entry: keyring::Entry::new(KEYRING_SERVICE, KEYRING_ACCOUNT),
+        }
+    }
+
+    /// Whether a keyring service is reachable at all; an empty entry counts.
+    pub fn is_available(&self) -> bool {
+        matches!(
+            self.entry.get_password(),
+            Ok(_) | Err(keyring::Error::NoEntry)
+        )
+    }
+}
+
+impl Default for KeyringBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecretBackend for KeyringBackend {
+    fn describe(&self) -> String {
+        "OS keyring".to_string()
+    }
+
+    fn load(&self) -> Result<Option<String>> {
+        match self.entry.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(anyhow!(err).context("reading keyring")),
+        }
+    }
+
+    fn store(&self, secret: &str) -> Result<()> {
+        self.entry
+            .set_password(secret)
+            .map_err(|err| anyhow!(err).context("writing keyring"))
+    }
+
+    fn delete(&self) -> Result<()> {
+        match self.entry.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(anyhow!(err).context("clearing keyring")),
+        }
+    }
+}
+
+/// A file sealed with XChaCha20-Poly1305 under a key derived from a
+/// passphrase with Argon2id. The file is always written with mode 0600.
+pub struct EncryptedFileBackend {
+    path: PathBuf,
+    passphrase: String,
+    /// The last derived key and its salt, so each save doesn't rerun Argon2.
+    key: Mutex<Option<([u8; SALT_LEN], Key)>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    version: u8,
+    kdf: String,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl EncryptedFileBackend {
+    pub fn new(path: PathBuf, passphrase: impl Into<String>) -> Self {
+        Self {
+            path,
+            passphrase: passphrase.into(),
+            key: Mutex::new(None),
+        }
+    }
+
+    fn key_for(&self, salt: [u8; SALT_LEN]) -> Result<Key> {
+        let mut cached = self.key.lock();
+        if let Some((cached_salt, key)) = cached.as_ref() {
+            if *cached_salt == salt {
+                return Ok(*key);
+            }
+        }
+        let mut key = Key::default();
+        Argon2::default()
+            .hash_password_into(self.passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|err| anyhow!("deriving session key: {}", err))?;
+        *cached = Some((salt, key));
+        Ok(key)
+    }
+
+    fn current_salt(&self) -> [u8; SALT_LEN] {
+        if let Some((salt, _)) = self.key.lock().as_ref() {
+            return *salt;
+        }
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        salt
+    }
+}
+
+impl SecretBackend for EncryptedFileBackend {
+    fn describe(&self) -> String {
+        format!("encrypted file {}", self.path.display())
+    }
+
+    fn load(&self) -> Result<Option<String>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        restrict_permissions(&self.path)?;
+        let raw =
+            fs::read(&self.path).with_context(|| format!("reading {}", self.path.display()))?;
+        if raw.is_empty() {
+            return Ok(None);
+        }
+        let envelope: Envelope = serde_json::from_slice(&raw).context("parsing session file")?;
+        if envelope.version != 1 {
+            bail!("unsupported session file version {}", envelope.version);
+        }
+        let salt: [u8; SALT_LEN] = STANDARD
+            .decode(&envelope.salt)?
+            .try_into()
+            .map_err(|_| anyhow!("bad salt in session file"))?;
+        let nonce = STANDARD.decode(&envelope.nonce)?;
+        if nonce.len() != 24 {
+            bail!("bad nonce in session file");
+        }
+        let ciphertext = STANDARD.decode(&envelope.ciphertext)?;
+        let cipher = XChaCha20Poly1305::new(&self.key_for(salt)?);
+        let plain = cipher
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| anyhow!("cannot decrypt session file: wrong passphrase or corrupted"))?;
+        Ok(Some(
+            String::from_utf8(plain).context("session file is not UTF-8")?,
+        ))
+    }
+
+    fn store(&self, secret: &str) -> Result<()> {
+        let salt = self.current_salt();
+        let cipher = XChaCha20Poly1305::new(&self.key_for(salt)?);
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, secret.as_bytes())
+            .map_err(|_| anyhow!("encrypting session"))?;
+        let envelope = Envelope {
+            version: 1,
+            kdf: "argon2id".to_string(),
+            salt: STANDARD.encode(salt),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        };
+        write_private(&self.path, &serde_json::to_vec_pretty(&envelope)?)
+    }
+
+    fn delete(&self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)
+                .with_context(|| format!("removing {}", self.path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes through a 0600 temp file and renames it into place so a crash
+/// never leaves a half-written or world-readable file behind.
+fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut options = OpenOptions::new();
+    options.create(true).write(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&tmp)
+        .with_context(|| format!("writing {}", tmp.display()))?;
+    restrict_permissions(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path).with_context(|| format!("replacing {}", path.display()))?;
+    Ok(())
+}
+
+/// Tightens a secret file to 0600 if something loosened it.
+pub fn restrict_permissions(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(path)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            tracing::warn!(path = %path.display(), mode = format!("{:o}", mode & 0o777), "session file was readable by others; restricting to 0600");
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
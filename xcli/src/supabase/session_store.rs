@@ -1,18 +1,29 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use directories::ProjectDirs;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
-use std::fs::OpenOptions;
-use std::io::Read;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::Path;
 use std::sync::Arc;
 use time::OffsetDateTime;
 use tokio::task;
 
-use crate::domain::Session;
+use crate::domain::{Account, Session};
 
+use super::secrets::{BackendKind, EncryptedFileBackend, KeyringBackend, SecretBackend};
+
+/// Plaintext file written by earlier builds; moved into the backend on start.
+const LEGACY_FILE: &str = "sessions.json";
+const ENCRYPTED_FILE: &str = "sessions.enc";
+const PASSPHRASE_ENV: &str = "CLI_TWITTER_PASSPHRASE";
+
+/// Signed-in accounts, kept in a [`SecretBackend`]. The backend is only
+/// picked, and a passphrase asked for, the first time a session is read or
+/// written, so commands that never touch one work without a keyring.
+#[derive(Clone)]
 pub struct SessionStore {
-    path: PathBuf,
+    kind: BackendKind,
+    backend: Arc<Mutex<Option<Arc<dyn SecretBackend>>>>,
     mutex: Arc<Mutex<()>>,
 }
 
@@ -22,72 +33,313 @@ struct PersistedSession {
     saved_at: OffsetDateTime,
 }
 
+/// Every signed-in account, keyed by user id, plus which one is in use.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedAccounts {
+    active: Option<String>,
+    accounts: Vec<PersistedSession>,
+}
+
+impl PersistedAccounts {
+    /// Accepts the single-session format written before accounts existed.
+    fn parse(secret: &str) -> Result<Self> {
+        if secret.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        if let Ok(accounts) = serde_json::from_str::<Self>(secret) {
+            return Ok(accounts);
+        }
+        let single: PersistedSession =
+            serde_json::from_str(secret).context("parsing stored sessions")?;
+        Ok(Self {
+            active: Some(single.session.user_id.clone()),
+            accounts: vec![single],
+        })
+    }
+
+    fn active(&self) -> Option<&Session> {
+        let active = self.active.as_deref()?;
+        self.accounts
+            .iter()
+            .map(|entry| &entry.session)
+            .find(|session| session.user_id == active)
+    }
+
+    /// Resolves `name` (an email, its local part or a user id) to an index.
+    fn find(&self, name: &str) -> Result<usize> {
+        let name = name.trim_start_matches('@');
+        let matches = |session: &Session| {
+            session.email.eq_ignore_ascii_case(name)
+                || session.user_id == name
+                || session
+                    .email
+                    .split('@')
+                    .next()
+                    .is_some_and(|local| local.eq_ignore_ascii_case(name))
+        };
+        let found = self
+            .accounts
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| matches(&entry.session))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        match found.as_slice() {
+            [index] => Ok(*index),
+            [] => bail!("no signed-in account matches {}", name),
+            _ => bail!("{} matches several accounts; use the full email", name),
+        }
+    }
+}
+
 impl SessionStore {
-    pub fn new() -> Result<Self> {
+    /// A store over the backend `kind` resolves to, opened on first use.
+    pub fn new(kind: BackendKind) -> Self {
+        Self {
+            kind,
+            backend: Arc::new(Mutex::new(None)),
+            mutex: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// A store over an explicit backend, e.g. an encrypted file in a temp
+    /// dir in tests.
+    pub fn with_backend(backend: Arc<dyn SecretBackend>) -> Self {
+        Self {
+            kind: BackendKind::default(),
+            backend: Arc::new(Mutex::new(Some(backend))),
+            mutex: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn describe(&self) -> Result<String> {
+        Ok(self.backend()?.describe())
+    }
+
+    /// The backend, picking it and moving an old plaintext session file
+    /// into it on first use. Blocks while a passphrase is typed.
+    fn backend(&self) -> Result<Arc<dyn SecretBackend>> {
+        let mut backend = self.backend.lock();
+        if let Some(backend) = backend.as_ref() {
+            return Ok(backend.clone());
+        }
         let proj = ProjectDirs::from("com", "OpenAI", "cli-twitter").context("project dirs")?;
         let dir = proj.data_dir();
         std::fs::create_dir_all(dir).context("session dir")?;
-        let path = dir.join("sessions.json");
-        Ok(Self {
-            path,
-            mutex: Arc::new(Mutex::new(())),
-        })
+        let opened = select_backend(self.kind, dir)?;
+        migrate_plaintext(&dir.join(LEGACY_FILE), opened.as_ref())?;
+        *backend = Some(opened.clone());
+        Ok(opened)
     }
 
+    /// Stores `session`, replacing an earlier one for the same user, and
+    /// makes it the active account.
     pub async fn save(&self, session: &Session) -> Result<()> {
-        let path = self.path.clone();
         let session = session.clone();
-        let guard = self.mutex.clone();
-        task::spawn_blocking(move || {
-            let _lock = guard.lock();
-            let persisted = PersistedSession {
+        self.update(move |accounts| {
+            accounts
+                .accounts
+                .retain(|entry| entry.session.user_id != session.user_id);
+            accounts.active = Some(session.user_id.clone());
+            accounts.accounts.push(PersistedSession {
                 session,
                 saved_at: OffsetDateTime::now_utc(),
-            };
-            let file = OpenOptions::new()
-                .create(true)
-                .write(true)
-                .truncate(true)
-                .open(&path)?;
-            serde_json::to_writer_pretty(file, &persisted)?;
-            Ok::<_, anyhow::Error>(())
+            });
+            Ok(())
+        })
+        .await
+    }
+
+    /// Stores refreshed tokens for `session`'s account without changing
+    /// which account is active, so a refresh that raced `/account switch`
+    /// doesn't switch back. An account signed out meanwhile stays signed out.
+    pub async fn save_tokens(&self, session: &Session) -> Result<()> {
+        let session = session.clone();
+        self.update(move |accounts| {
+            if let Some(entry) = accounts
+                .accounts
+                .iter_mut()
+                .find(|entry| entry.session.user_id == session.user_id)
+            {
+                entry.session = session;
+                entry.saved_at = OffsetDateTime::now_utc();
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// The stored session of `user_id`, active or not.
+    pub async fn get(&self, user_id: &str) -> Result<Option<Session>> {
+        let user_id = user_id.to_string();
+        self.read(move |accounts| {
+            accounts
+                .accounts
+                .iter()
+                .map(|entry| &entry.session)
+                .find(|session| session.user_id == user_id)
+                .cloned()
         })
-        .await??;
-        Ok(())
+        .await
     }
 
+    /// The active account's session.
     pub async fn load(&self) -> Result<Option<Session>> {
-        let path = self.path.clone();
-        let guard = self.mutex.clone();
-        let result = task::spawn_blocking(move || {
-            let _lock = guard.lock();
-            if !path.exists() {
-                return Ok::<Option<Session>, anyhow::Error>(None);
+        self.read(|accounts| accounts.active().cloned()).await
+    }
+
+    /// Signs the active account out; other accounts stay stored.
+    pub async fn clear(&self) -> Result<()> {
+        self.update(|accounts| {
+            if let Some(active) = accounts.active.take() {
+                accounts
+                    .accounts
+                    .retain(|entry| entry.session.user_id != active);
             }
-            let mut buf = Vec::new();
-            let mut file = OpenOptions::new().read(true).open(&path)?;
-            file.read_to_end(&mut buf)?;
-            if buf.is_empty() {
-                return Ok(None);
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn accounts(&self) -> Result<Vec<Account>> {
+        self.read(|accounts| {
+            accounts
+                .accounts
+                .iter()
+                .map(|entry| Account {
+                    user_id: entry.session.user_id.clone(),
+                    email: entry.session.email.clone(),
+                    active: accounts.active.as_deref() == Some(entry.session.user_id.as_str()),
+                })
+                .collect()
+        })
+        .await
+    }
+
+    /// Makes the account matching `name` active and returns its session.
+    pub async fn switch(&self, name: &str) -> Result<Session> {
+        let name = name.to_string();
+        self.update(move |accounts| {
+            let index = accounts.find(&name)?;
+            let session = accounts.accounts[index].session.clone();
+            accounts.active = Some(session.user_id.clone());
+            Ok(session)
+        })
+        .await
+    }
+
+    /// Forgets the account matching `name`. Removing the active account
+    /// leaves no account active.
+    pub async fn remove(&self, name: &str) -> Result<Account> {
+        let name = name.to_string();
+        self.update(move |accounts| {
+            let index = accounts.find(&name)?;
+            let removed = accounts.accounts.remove(index).session;
+            let active = accounts.active.as_deref() == Some(removed.user_id.as_str());
+            if active {
+                accounts.active = None;
             }
-            let persisted: PersistedSession = serde_json::from_slice(&buf)?;
-            Ok(Some(persisted.session))
+            Ok(Account {
+                user_id: removed.user_id,
+                email: removed.email,
+                active,
+            })
         })
-        .await??;
-        Ok(result)
+        .await
     }
 
-    pub async fn clear(&self) -> Result<()> {
-        let path = self.path.clone();
-        let guard = self.mutex.clone();
+    async fn read<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&PersistedAccounts) -> T + Send + 'static,
+    {
+        let store = self.clone();
         task::spawn_blocking(move || {
-            let _lock = guard.lock();
-            if path.exists() {
-                std::fs::remove_file(path).ok();
+            let backend = store.backend()?;
+            let _lock = store.mutex.lock();
+            let secret = backend.load()?.unwrap_or_default();
+            Ok(f(&PersistedAccounts::parse(&secret)?))
+        })
+        .await?
+    }
+
+    /// Read-modify-write under the store lock. Nothing is written if `f`
+    /// fails; the secret is deleted once no account is left.
+    async fn update<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut PersistedAccounts) -> Result<T> + Send + 'static,
+    {
+        let store = self.clone();
+        task::spawn_blocking(move || {
+            let backend = store.backend()?;
+            let _lock = store.mutex.lock();
+            let secret = backend.load()?.unwrap_or_default();
+            let mut accounts = PersistedAccounts::parse(&secret)?;
+            let out = f(&mut accounts)?;
+            if accounts.accounts.is_empty() {
+                backend.delete()?;
+            } else {
+                backend.store(&serde_json::to_string(&accounts)?)?;
             }
-            Ok::<_, anyhow::Error>(())
+            Ok(out)
         })
-        .await??;
-        Ok(())
+        .await?
+    }
+}
+
+fn select_backend(kind: BackendKind, dir: &Path) -> Result<Arc<dyn SecretBackend>> {
+    if kind != BackendKind::File {
+        let keyring = KeyringBackend::new();
+        if keyring.is_available() {
+            return Ok(Arc::new(keyring));
+        }
+        if kind == BackendKind::Keyring {
+            bail!("session_backend is \"keyring\" but no OS keyring is reachable");
+        }
+        tracing::info!("no OS keyring reachable; using encrypted session file");
+    }
+    let passphrase = passphrase()?;
+    Ok(Arc::new(EncryptedFileBackend::new(
+        dir.join(ENCRYPTED_FILE),
+        passphrase,
+    )))
+}
+
+fn passphrase() -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        if !passphrase.is_empty() {
+            return Ok(passphrase);
+        }
+    }
+    if !std::io::stdin().is_terminal() {
+        bail!(
+            "no OS keyring available; set {} to encrypt the session file",
+            PASSPHRASE_ENV
+        );
+    }
+    let passphrase = rpassword::prompt_password("Session passphrase: ")?;
+    if passphrase.is_empty() {
+        bail!("an empty passphrase can't protect the session file");
+    }
+    Ok(passphrase)
+}
+
+/// Moves a plaintext session file from an older build into `backend` and
+/// deletes it. An existing secret in the backend wins over the old file.
+/// Returns whether a file was found.
+pub fn migrate_plaintext(legacy: &Path, backend: &dyn SecretBackend) -> Result<bool> {
+    if !legacy.exists() {
+        return Ok(false);
+    }
+    let contents =
+        std::fs::read_to_string(legacy).with_context(|| format!("reading {}", legacy.display()))?;
+    if !contents.trim().is_empty() && backend.load()?.is_none() {
+        serde_json::from_str::<PersistedSession>(&contents)
+            .context("parsing plaintext session file")?;
+        backend.store(&contents)?;
     }
+    std::fs::remove_file(legacy).with_context(|| format!("removing {}", legacy.display()))?;
+    tracing::info!(to = %backend.describe(), "moved plaintext session file");
+    Ok(true)
 }
@@ -0,0 +1,185 @@
+use anyhow::{anyhow, Result};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::domain::{Post, Profile, ProfileStats, Session};
+
+use super::auth::FeedRow;
+use super::client::SupabaseClient;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProfileRow {
+    id: String,
+    handle: String,
+    display_name: Option<String>,
+    created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ProfileCardRow {
+    #[serde(flatten)]
+    profile: ProfileRow,
+    followers: i64,
+    following: i64,
+}
+
+const PROFILE_SELECT: &str = "id,handle,display_name,created_at";
+
+pub async fn find_profile(
+    supabase: &SupabaseClient,
+    session: &Session,
+    handle: &str,
+) -> Result<Profile> {
+    let rows: Vec<ProfileRow> = supabase
+        .rest(Method::GET, "profiles")
+        .query("select", PROFILE_SELECT)
+        .query("handle", format!("eq.{}", handle))
+        .fetch(session)
+        .await?;
+    rows.into_iter()
+        .next()
+        .map(ProfileRow::into_profile)
+        .ok_or_else(|| anyhow!("unknown handle @{}", handle))
+}
+
+/// `handle`'s profile with its follow counts, through the `profile_card` RPC.
+pub async fn profile_card(
+    supabase: &SupabaseClient,
+    session: &Session,
+    handle: &str,
+) -> Result<(Profile, ProfileStats)> {
+    let rows: Vec<ProfileCardRow> = supabase
+        .rest(Method::POST, "rpc/profile_card")
+        .json(&serde_json::json!({ "p_handle": handle }))
+        .fetch(session)
+        .await?;
+    let row = rows
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("unknown handle @{}", handle))?;
+    let stats = ProfileStats {
+        followers: row.followers,
+        following: row.following,
+    };
+    Ok((row.profile.into_profile(), stats))
+}
+
+/// The newest posts by `author` that `session` may see.
+pub async fn profile_posts(
+    supabase: &SupabaseClient,
+    session: &Session,
+    author: &str,
+    limit: usize,
+) -> Result<Vec<Post>> {
+    let rows: Vec<FeedRow> = supabase
+        .rest(Method::POST, "rpc/profile_posts")
+        .json(&serde_json::json!({
+            "uid": session.user_id,
+            "author_id": author,
+            "page_size": limit,
+        }))
+        .fetch(session)
+        .await?;
+    Ok(rows.into_iter().map(FeedRow::into_post).collect())
+}
+
+/// Sets the signed-in user's display name; `None` clears it.
+pub async fn update_profile(
+    supabase: &SupabaseClient,
+    session: &Session,
+    display_name: Option<&str>,
+) -> Result<Profile> {
+    let rows: Vec<ProfileRow> = supabase
+        .rest(Method::PATCH, "profiles")
+        .query("id", format!("eq.{}", session.user_id))
+        .query("select", PROFILE_SELECT)
+        .header("Prefer", "return=representation")
+        .json(&serde_json::json!({ "display_name": display_name }))
+        .fetch(session)
+        .await?;
+    rows.into_iter()
+        .next()
+        .map(ProfileRow::into_profile)
+        .ok_or_else(|| anyhow!("no profile for this account"))
+}
+
+/// Profiles whose handle or display name contains `query`, by handle.
+pub async fn search_profiles(
+    supabase: &SupabaseClient,
+    session: &Session,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<Profile>> {
+    // Characters with a meaning inside a PostgREST `or` filter are dropped.
+    let needle: String = query
+        .trim_start_matches('@')
+        .chars()
+        .filter(|c| !matches!(c, ',' | '(' | ')' | '*' | '"' | '\\'))
+        .collect();
+    let rows: Vec<ProfileRow> = supabase
+        .rest(Method::GET, "profiles")
+        .query("select", PROFILE_SELECT)
+        .query(
+            "or",
+            format!("(handle.ilike.*{0}*,display_name.ilike.*{0}*)", needle),
+        )
+        .query("order", "handle.asc")
+        .query("limit", limit.to_string())
+        .fetch(session)
+        .await?;
+    Ok(rows.into_iter().map(ProfileRow::into_profile).collect())
+}
+
+pub async fn follow(supabase: &SupabaseClient, session: &Session, followee: &str) -> Result<()> {
+    supabase
+        .rest(Method::POST, "follows")
+        .header("Prefer", "return=minimal")
+        .json(&serde_json::json!({ "follower": session.user_id, "followee": followee }))
+        // 409 means the row already exists, which is the state we wanted.
+        .accept(StatusCode::CONFLICT)
+        .execute(session)
+        .await
+}
+
+pub async fn unfollow(supabase: &SupabaseClient, session: &Session, followee: &str) -> Result<()> {
+    supabase
+        .rest(Method::DELETE, "follows")
+        .query("follower", format!("eq.{}", session.user_id))
+        .query("followee", format!("eq.{}", followee))
+        .execute(session)
+        .await
+}
+
+pub async fn like(supabase: &SupabaseClient, session: &Session, post_id: &str) -> Result<()> {
+    supabase
+        .rest(Method::POST, "likes")
+        .header("Prefer", "return=minimal")
+        .json(&serde_json::json!({ "user_id": session.user_id, "post_id": post_id }))
+        .accept(StatusCode::CONFLICT)
+        .execute(session)
+        .await
+}
+
+pub async fn unlike(supabase: &SupabaseClient, session: &Session, post_id: &str) -> Result<()> {
+    supabase
+        .rest(Method::DELETE, "likes")
+        .query("user_id", format!("eq.{}", session.user_id))
+        .query("post_id", format!("eq.{}", post_id))
+        .execute(session)
+        .await
+}
+
+impl ProfileRow {
+    fn into_profile(self) -> Profile {
+        Profile {
+            id: self.id.parse().unwrap_or_default(),
+            handle: self.handle,
+            display_name: self.display_name,
+            created_at: self
+                .created_at
+                .and_then(|s| OffsetDateTime::parse(&s, &Rfc3339).ok())
+                .unwrap_or_else(OffsetDateTime::now_utc),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+
+use crate::data::{cache::CacheDao, AppDatabase};
+use crate::domain::{FeedCursor, FeedFilter, Post, FEED_PAGE_SIZE};
+use crate::supabase::{LoginRequired, SupabaseClient};
+
+use super::SyncEvent;
+
+/// Fetches the newest page of `filter` from Supabase, caches every post and
+/// records the sync time used by the offline marker.
+pub async fn refresh(
+    db: &AppDatabase,
+    supabase: &SupabaseClient,
+    filter: &FeedFilter,
+) -> Result<Vec<Post>> {
+    let (viewer, posts) = fetch_page(db, supabase, filter, None).await?;
+    CacheDao::new(db).mark_feed_synced(&viewer, filter)?;
+    Ok(posts)
+}
+
+/// Fetches the page of `filter` older than `before` (or the newest page),
+/// caches it for the active account and moves that account's `/feed more`
+/// cursor to its last post. Returns the account's user id with the page.
+pub async fn fetch_page(
+    db: &AppDatabase,
+    supabase: &SupabaseClient,
+    filter: &FeedFilter,
+    before: Option<FeedCursor>,
+) -> Result<(String, Vec<Post>)> {
+    let session = supabase.ensure_session().await?.ok_or(LoginRequired)?;
+    let mut posts = supabase
+        .fetch_feed(&session, filter, before, FEED_PAGE_SIZE)
+        .await?;
+    let cache = CacheDao::new(db);
+    cache.fill_handles(&mut posts)?;
+    for post in &posts {
+        cache.upsert_post(&session.user_id, post)?;
+    }
+    remember_cursor(db, &session.user_id, filter, before, &posts)?;
+    Ok((session.user_id, posts))
+}
+
+/// Stores where a served page ended. An empty follow-up page keeps the
+/// previous cursor so `/feed more` can be retried later.
+pub fn remember_cursor(
+    db: &AppDatabase,
+    viewer: &str,
+    filter: &FeedFilter,
+    before: Option<FeedCursor>,
+    page: &[Post],
+) -> Result<()> {
+    let cursor = page.last().map(FeedCursor::after).or(before);
+    CacheDao::new(db).set_feed_cursor(viewer, filter, cursor.as_ref())
+}
+
+/// One-shot background retry used while the TUI is showing a cached feed.
+/// Sends `FeedRefreshed` only on success so the cached view stays put while
+/// still offline.
+pub fn spawn_probe(
+    db: AppDatabase,
+    supabase: SupabaseClient,
+    filter: FeedFilter,
+    events: UnboundedSender<SyncEvent>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        match refresh(&db, &supabase, &filter).await {
+            Ok(posts) => {
+                events.send(SyncEvent::FeedRefreshed { filter, posts }).ok();
+            }
+            Err(err) => tracing::debug!(?err, "feed still unreachable"),
+        }
+    })
+}
@@ -0,0 +1,80 @@
+use anyhow::Result;
+use time::OffsetDateTime;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+
+use crate::data::{messages::MessageDao, AppDatabase};
+use crate::domain::Session;
+use crate::supabase::SupabaseClient;
+
+use super::SyncEvent;
+
+/// Pulls messages newer than the latest cached one for `session`'s account.
+/// Returns how many arrived.
+pub async fn refresh(
+    db: &AppDatabase,
+    supabase: &SupabaseClient,
+    session: &Session,
+) -> Result<usize> {
+    let dao = MessageDao::new(db);
+    let messages = supabase
+        .fetch_messages(session, dao.latest(&session.user_id)?)
+        .await?;
+    for message in &messages {
+        dao.upsert(&session.user_id, message)?;
+    }
+    Ok(messages.len())
+}
+
+/// Marks `peer`'s messages read locally and, if any were unread, on the
+/// server too. A failed server update is only logged; the next open of the
+/// conversation tries again.
+pub async fn mark_read(
+    db: &AppDatabase,
+    supabase: &SupabaseClient,
+    session: &Session,
+    peer: &str,
+) -> Result<usize> {
+    let now = OffsetDateTime::now_utc();
+    let unread = MessageDao::new(db).mark_read(&session.user_id, peer, now)?;
+    if unread > 0 {
+        if let Err(err) = supabase.mark_messages_read(session, peer, now).await {
+            tracing::warn!(?err, "failed to mark messages read on the server");
+        }
+    }
+    Ok(unread)
+}
+
+/// One-shot background fetch of new messages. Sends `MessagesReceived` when
+/// something arrived for the account that is still active.
+pub fn spawn_poll(
+    db: AppDatabase,
+    supabase: SupabaseClient,
+    events: UnboundedSender<SyncEvent>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let session = match supabase.ensure_session().await {
+            Ok(Some(session)) => session,
+            Ok(None) => return,
+            Err(err) => {
+                tracing::debug!(?err, "message poll skipped");
+                return;
+            }
+        };
+        match refresh(&db, &supabase, &session).await {
+            Ok(0) => {}
+            Ok(_) => {
+                let unread = MessageDao::new(&db)
+                    .unread_count(&session.user_id)
+                    .unwrap_or_default();
+                events
+                    .send(SyncEvent::MessagesReceived {
+                        user_id: session.user_id,
+                        unread,
+                    })
+                    .ok();
+            }
+            Err(err) => tracing::debug!(?err, "message poll failed"),
+        }
+    })
+}
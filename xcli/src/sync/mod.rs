@@ -0,0 +1,122 @@
+pub mod feed;
+pub mod messages;
+pub mod notifications;
+pub mod outbox;
+pub mod scheduled;
+
+use std::fmt;
+
+use crate::domain::{FeedFilter, Post};
+
+pub use outbox::{OutboxWorker, MAX_ATTEMPTS};
+pub use scheduled::ScheduledWorker;
+
+/// Outcome of background work, forwarded to the TUI status panel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncEvent {
+    Delivered {
+        id: i64,
+        summary: String,
+        /// The post created by a post or reply.
+        post: Option<Post>,
+    },
+    Retrying {
+        id: i64,
+        attempts: u32,
+        error: String,
+    },
+    Failed {
+        id: i64,
+        error: String,
+    },
+    /// A cached feed could be fetched again after being offline.
+    FeedRefreshed {
+        filter: FeedFilter,
+        posts: Vec<Post>,
+    },
+    /// A post inserted since the feed was loaded, pushed over Realtime.
+    NewPost(Post),
+    RealtimeConnected,
+    RealtimeDown {
+        error: String,
+    },
+    /// New direct messages were fetched for `user_id`.
+    MessagesReceived {
+        user_id: String,
+        unread: i64,
+    },
+    /// New notifications were stored for `user_id`.
+    NotificationsReceived {
+        user_id: String,
+        added: usize,
+        unread: i64,
+    },
+    /// Scheduled post `id` went out as `post_id`.
+    ScheduledPublished {
+        id: i64,
+        post_id: String,
+    },
+    /// Publishing scheduled post `id` failed; it is tried again later unless
+    /// `retrying` is false.
+    ScheduledFailed {
+        id: i64,
+        error: String,
+        retrying: bool,
+    },
+}
+
+impl fmt::Display for SyncEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncEvent::Delivered { summary, .. } => write!(f, "{}", summary),
+            SyncEvent::Retrying {
+                id,
+                attempts,
+                error,
+            } => write!(
+                f,
+                "Outbox #{} queued (attempt {}/{}): {}",
+                id, attempts, MAX_ATTEMPTS, error
+            ),
+            SyncEvent::Failed { id, error } => {
+                write!(f, "Outbox #{} failed, /outbox retry {}: {}", id, id, error)
+            }
+            SyncEvent::FeedRefreshed { filter, posts } => {
+                write!(f, "Back online: fetched {} {} posts", posts.len(), filter)
+            }
+            SyncEvent::NewPost(post) => write!(
+                f,
+                "New post from @{}",
+                post.author_handle
+                    .clone()
+                    .unwrap_or_else(|| post.author.to_string())
+            ),
+            SyncEvent::RealtimeConnected => write!(f, "Live updates connected"),
+            SyncEvent::RealtimeDown { error } => {
+                write!(f, "Live updates disconnected, reconnecting: {}", error)
+            }
+            SyncEvent::MessagesReceived { unread, .. } => {
+                write!(f, "New messages: {} unread, /inbox", unread)
+            }
+            SyncEvent::NotificationsReceived { added, .. } => write!(
+                f,
+                "{} new notification{}, /notifications",
+                added,
+                if *added == 1 { "" } else { "s" }
+            ),
+            SyncEvent::ScheduledPublished { id, post_id } => {
+                write!(f, "Scheduled post #{} published as {}", id, post_id)
+            }
+            SyncEvent::ScheduledFailed {
+                id,
+                error,
+                retrying: true,
+            } => write!(f, "Scheduled post #{} not sent, retrying: {}", id, error),
+            SyncEvent::ScheduledFailed { id, error, .. } => write!(
+                f,
+                "Scheduled post #{} failed, /scheduled edit {} to retry: {}",
+                id, id, error
+            ),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+
+use crate::data::{notifications::NotificationDao, AppDatabase};
+use crate::domain::Session;
+use crate::supabase::SupabaseClient;
+
+use super::SyncEvent;
+
+/// Stores notifications newer than the latest one kept for `session`'s
+/// account. Returns how many were new.
+pub async fn refresh(
+    db: &AppDatabase,
+    supabase: &SupabaseClient,
+    session: &Session,
+) -> Result<usize> {
+    let dao = NotificationDao::new(db);
+    let notifications = supabase
+        .fetch_notifications(session, dao.latest(&session.user_id)?)
+        .await?;
+    let mut added = 0;
+    for notification in &notifications {
+        if dao.insert(&session.user_id, notification)? {
+            added += 1;
+        }
+    }
+    Ok(added)
+}
+
+/// One-shot background poll. Sends `NotificationsReceived` when something
+/// new arrived.
+pub fn spawn_poll(
+    db: AppDatabase,
+    supabase: SupabaseClient,
+    events: UnboundedSender<SyncEvent>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let session = match supabase.ensure_session().await {
+            Ok(Some(session)) => session,
+            Ok(None) => return,
+            Err(err) => {
+                tracing::debug!(?err, "notification poll skipped");
+                return;
+            }
+        };
+        match refresh(&db, &supabase, &session).await {
+            Ok(0) => {}
+            Ok(added) => {
+                let unread = NotificationDao::new(&db)
+                    .unread_count(&session.user_id)
+                    .unwrap_or_default();
+                events
+                    .send(SyncEvent::NotificationsReceived {
+                        user_id: session.user_id,
+                        added,
+                        unread,
+                    })
+                    .ok();
+            }
+            Err(err) => tracing::debug!(?err, "notification poll failed"),
+        }
+    })
+}
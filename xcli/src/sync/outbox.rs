@@ -0,0 +1,247 @@
+use std::time::Duration as StdDuration;
+
+use anyhow::Result;
+use rand::Rng;
+use time::{Duration, OffsetDateTime};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+
+use crate::data::{cache::CacheDao, messages::MessageDao, outbox::OutboxDao, AppDatabase};
+use crate::domain::{OutboxItem, OutboxPayload, OutboxStatus, Post, Session};
+use crate::supabase::error::user_message;
+use crate::supabase::{LoginRequired, SupabaseClient, SupabaseError};
+
+use super::SyncEvent;
+
+/// Deliveries after which an item is parked as `failed` until retried by hand.
+pub const MAX_ATTEMPTS: u32 = 8;
+const BATCH_SIZE: usize = 20;
+/// How long a claim holds before the item counts as abandoned by a process
+/// that died mid-delivery. Comfortably longer than a batch takes to send.
+pub const CLAIM_TIMEOUT: Duration = Duration::minutes(15);
+
+/// Exponential backoff starting at 5s and capped at 10 minutes, with up to
+/// 20% jitter so a reconnect doesn't fire every item at once.
+pub fn backoff(attempts: u32) -> Duration {
+    let base = 5i64 << attempts.saturating_sub(1).min(7);
+    let secs = base.min(600);
+    let jitter = rand::thread_rng().gen_range(0..=secs / 5);
+    Duration::seconds(secs + jitter)
+}
+
+/// Claims and delivers every due item. Does nothing while signed out.
+pub async fn drain(db: &AppDatabase, supabase: &SupabaseClient) -> Result<Vec<SyncEvent>> {
+    let Some(session) = supabase.ensure_session().await? else {
+        return Ok(Vec::new());
+    };
+    let items =
+        OutboxDao::new(db).claim_due(&session.user_id, OffsetDateTime::now_utc(), BATCH_SIZE)?;
+    let mut events = Vec::with_capacity(items.len());
+    for item in items {
+        events.push(process(db, supabase, &session, item).await?);
+    }
+    Ok(events)
+}
+
+/// Attempts a single item right away, e.g. just after a command queued it.
+/// Returns `None` when the item is not pending or already being sent.
+pub async fn flush_one(
+    db: &AppDatabase,
+    supabase: &SupabaseClient,
+    id: i64,
+) -> Result<Option<SyncEvent>> {
+    let session = supabase.ensure_session().await?.ok_or(LoginRequired)?;
+    let Some(item) = OutboxDao::new(db).claim(&session.user_id, id)? else {
+        return Ok(None);
+    };
+    process(db, supabase, &session, item).await.map(Some)
+}
+
+async fn process(
+    db: &AppDatabase,
+    supabase: &SupabaseClient,
+    session: &Session,
+    item: OutboxItem,
+) -> Result<SyncEvent> {
+    let dao = OutboxDao::new(db);
+    match deliver(db, supabase, session, &item.payload).await {
+        Ok((summary, post)) => {
+            dao.mark_sent(item.id)?;
+            Ok(SyncEvent::Delivered {
+                id: item.id,
+                summary,
+                post,
+            })
+        }
+        Err(err) => {
+            let attempts = item.attempts + 1;
+            tracing::warn!(id = item.id, attempts, error = %format!("{:#}", err), "outbox delivery failed");
+            let error = user_message(&err);
+            // Resending won't fix a rejected request, so only transient
+            // failures use up the remaining attempts.
+            let supabase_error = SupabaseError::find(&err);
+            let permanent = supabase_error.is_some_and(|e| !e.is_transient());
+            if attempts >= MAX_ATTEMPTS || permanent {
+                dao.mark_failed(item.id, &error)?;
+                mirror(db, &session.user_id, &item.payload, false)?;
+                Ok(SyncEvent::Failed { id: item.id, error })
+            } else {
+                let wait = supabase_error
+                    .and_then(SupabaseError::retry_after)
+                    .and_then(|wait| Duration::try_from(wait).ok())
+                    .map_or_else(|| backoff(attempts), |wait| wait.max(backoff(attempts)));
+                dao.mark_retry(item.id, &error, OffsetDateTime::now_utc() + wait)?;
+                Ok(SyncEvent::Retrying {
+                    id: item.id,
+                    attempts,
+                    error,
+                })
+            }
+        }
+    }
+}
+
+async fn deliver(
+    db: &AppDatabase,
+    supabase: &SupabaseClient,
+    session: &Session,
+    payload: &OutboxPayload,
+) -> Result<(String, Option<Post>)> {
+    match payload {
+        OutboxPayload::Post {
+            id,
+            text,
+            audience,
+            reply_to,
+        } => {
+            let post = supabase
+                .create_post(session, *id, text, *audience, reply_to.as_deref())
+                .await?;
+            CacheDao::new(db).upsert_post(&session.user_id, &post)?;
+            let summary = match reply_to {
+                Some(parent) => format!("Replied to {} with {}", parent, post.id),
+                None => format!("Posted {}", post.id),
+            };
+            Ok((summary, Some(post)))
+        }
+        OutboxPayload::Like { post_id } => {
+            supabase.like_post(session, post_id).await?;
+            CacheDao::new(db).set_like(&session.user_id, post_id, true)?;
+            Ok((format!("Liked {}", post_id), None))
+        }
+        OutboxPayload::Unlike { post_id } => {
+            supabase.unlike_post(session, post_id).await?;
+            CacheDao::new(db).set_like(&session.user_id, post_id, false)?;
+            Ok((format!("Unliked {}", post_id), None))
+        }
+        OutboxPayload::Follow { handle } => {
+            let profile = supabase.follow(session, handle).await?;
+            let cache = CacheDao::new(db);
+            cache.upsert_profile(&profile)?;
+            cache.set_follow(&session.user_id, &profile.id.to_string(), true)?;
+            Ok((format!("Following @{}", handle), None))
+        }
+        OutboxPayload::Unfollow { handle } => {
+            let profile = supabase.unfollow(session, handle).await?;
+            let cache = CacheDao::new(db);
+            cache.upsert_profile(&profile)?;
+            cache.set_follow(&session.user_id, &profile.id.to_string(), false)?;
+            Ok((format!("Unfollowed @{}", handle), None))
+        }
+        OutboxPayload::Message { id, handle, text } => {
+            let profile = supabase.find_profile(session, handle).await?;
+            let mut message = supabase
+                .send_message(session, *id, &profile.id.to_string(), text)
+                .await?;
+            message
+                .recipient_handle
+                .get_or_insert_with(|| profile.handle.clone());
+            MessageDao::new(db).upsert(&session.user_id, &message)?;
+            Ok((format!("Message sent to @{}", handle), None))
+        }
+    }
+}
+
+/// Applies a queued action to the local cache ahead of delivery so the UI
+/// reflects it immediately; `applied = false` rolls it back. Follows are only
+/// mirrored when the handle is already cached, otherwise delivery fills them
+/// in. Returns the cached post whose like state changed, if any.
+pub fn mirror(
+    db: &AppDatabase,
+    user_id: &str,
+    payload: &OutboxPayload,
+    applied: bool,
+) -> Result<Option<Post>> {
+    let cache = CacheDao::new(db);
+    match payload {
+        OutboxPayload::Post { .. } | OutboxPayload::Message { .. } => Ok(None),
+        OutboxPayload::Like { post_id } => cache.set_like(user_id, post_id, applied),
+        OutboxPayload::Unlike { post_id } => cache.set_like(user_id, post_id, !applied),
+        OutboxPayload::Follow { handle } | OutboxPayload::Unfollow { handle } => {
+            let following = matches!(payload, OutboxPayload::Follow { .. }) == applied;
+            if let Some(profile) = cache.profile_by_handle(handle)? {
+                cache.set_follow(user_id, &profile.id.to_string(), following)?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Drops an outbox item that isn't being delivered. A pending like or follow
+/// has already been mirrored into the cache and will now never be sent, so
+/// the mirror is rolled back; failed items were rolled back when they
+/// failed. Returns whether there was such an item.
+pub fn drop_item(db: &AppDatabase, user_id: &str, id: i64) -> Result<bool> {
+    let Some(item) = OutboxDao::new(db).remove(user_id, id)? else {
+        return Ok(false);
+    };
+    if item.status == OutboxStatus::Pending {
+        mirror(db, user_id, &item.payload, false)?;
+    }
+    Ok(true)
+}
+
+/// Background task that drains the outbox on a fixed interval and reports
+/// each delivery outcome over `events`. Aborted when dropped.
+pub struct OutboxWorker {
+    handle: JoinHandle<()>,
+}
+
+impl OutboxWorker {
+    pub fn spawn(
+        db: AppDatabase,
+        supabase: SupabaseClient,
+        interval: StdDuration,
+        events: UnboundedSender<SyncEvent>,
+    ) -> Self {
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let stale = OffsetDateTime::now_utc() - CLAIM_TIMEOUT;
+                match OutboxDao::new(&db).requeue_stale(stale) {
+                    Ok(0) => {}
+                    Ok(n) => tracing::info!(n, "requeued interrupted outbox items"),
+                    Err(err) => tracing::warn!(?err, "failed to requeue outbox items"),
+                }
+                match drain(&db, &supabase).await {
+                    Ok(batch) => {
+                        for event in batch {
+                            if events.send(event).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(err) => tracing::debug!(?err, "outbox drain skipped"),
+                }
+            }
+        });
+        Self { handle }
+    }
+}
+
+impl Drop for OutboxWorker {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::Duration as StdDuration;
+
+use anyhow::Result;
+use time::{Duration, OffsetDateTime};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+
+use crate::data::{cache::CacheDao, scheduled::ScheduledDao, AppDatabase};
+use crate::domain::{ScheduledPost, Session};
+use crate::supabase::error::user_message;
+use crate::supabase::{SupabaseClient, SupabaseError};
+
+use super::outbox::{backoff, CLAIM_TIMEOUT, MAX_ATTEMPTS};
+use super::SyncEvent;
+
+const BATCH_SIZE: usize = 20;
+
+/// Publishes every scheduled post of the active account that is due,
+/// first taking back claims abandoned by a process that died mid-publish.
+/// Does nothing while signed out.
+pub async fn publish_due(db: &AppDatabase, supabase: &SupabaseClient) -> Result<Vec<SyncEvent>> {
+    let Some(session) = supabase.ensure_session().await? else {
+        return Ok(Vec::new());
+    };
+    let dao = ScheduledDao::new(db);
+    let now = OffsetDateTime::now_utc();
+    let requeued = dao.requeue_stale(now - CLAIM_TIMEOUT)?;
+    if requeued > 0 {
+        tracing::info!(requeued, "requeued interrupted scheduled posts");
+    }
+    let posts = dao.claim_due(&session.user_id, now, BATCH_SIZE)?;
+    let mut events = Vec::with_capacity(posts.len());
+    for post in posts {
+        events.push(publish(db, supabase, &session, post).await?);
+    }
+    Ok(events)
+}
+
+async fn publish(
+    db: &AppDatabase,
+    supabase: &SupabaseClient,
+    session: &Session,
+    scheduled: ScheduledPost,
+) -> Result<SyncEvent> {
+    let dao = ScheduledDao::new(db);
+    match supabase
+        .create_post(
+            session,
+            scheduled.post_id,
+            &scheduled.body,
+            scheduled.audience,
+            None,
+        )
+        .await
+    {
+        Ok(post) => {
+            dao.mark_published(scheduled.id)?;
+            CacheDao::new(db).upsert_post(&session.user_id, &post)?;
+            Ok(SyncEvent::ScheduledPublished {
+                id: scheduled.id,
+                post_id: post.id.to_string(),
+            })
+        }
+        Err(err) => {
+            let attempts = scheduled.attempts + 1;
+            tracing::warn!(id = scheduled.id, attempts, error = %format!("{:#}", err), "scheduled post failed");
+            let error = user_message(&err);
+            let supabase_error = SupabaseError::find(&err);
+            let permanent = supabase_error.is_some_and(|e| !e.is_transient());
+            if attempts >= MAX_ATTEMPTS || permanent {
+                dao.mark_failed(scheduled.id, &error)?;
+                Ok(SyncEvent::ScheduledFailed {
+                    id: scheduled.id,
+                    error,
+                    retrying: false,
+                })
+            } else {
+                let wait = supabase_error
+                    .and_then(SupabaseError::retry_after)
+                    .and_then(|wait| Duration::try_from(wait).ok())
+                    .map_or_else(|| backoff(attempts), |wait| wait.max(backoff(attempts)));
+                dao.mark_retry(scheduled.id, &error, OffsetDateTime::now_utc() + wait)?;
+                Ok(SyncEvent::ScheduledFailed {
+                    id: scheduled.id,
+                    error,
+                    retrying: true,
+                })
+            }
+        }
+    }
+}
+
+/// Background task that publishes due scheduled posts on a fixed interval
+/// and reports each outcome over `events`. Aborted when dropped.
+pub struct ScheduledWorker {
+    handle: JoinHandle<()>,
+}
+
+impl ScheduledWorker {
+    pub fn spawn(
+        db: AppDatabase,
+        supabase: SupabaseClient,
+        interval: StdDuration,
+        events: UnboundedSender<SyncEvent>,
+    ) -> Self {
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match publish_due(&db, &supabase).await {
+                    Ok(batch) => {
+                        for event in batch {
+                            if events.send(event).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(err) => tracing::debug!(?err, "scheduled publish skipped"),
+                }
+            }
+        });
+        Self { handle }
+    }
+}
+
+impl Drop for ScheduledWorker {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
@@ -4,7 +4,8 @@ pub fn init() {
     let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
     tracing_subscriber::registry()
         .with(env_filter)
-        .with(fmt::layer())
+        // stdout is reserved for command output that scripts parse.
+        .with(fmt::layer().with_writer(std::io::stderr))
         .init();
     std::panic::set_hook(Box::new(|panic_info| {
         eprintln!("panic: {}", panic_info);
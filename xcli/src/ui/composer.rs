@@ -0,0 +1,185 @@
+use unicode_width::UnicodeWidthStr;
+
+use crate::domain::{AudienceScope, Draft};
+
+/// The multi-line post editor behind compose mode. `col` is a byte offset
+/// into the current line that always sits on a char boundary.
+#[derive(Debug)]
+pub struct Composer {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+    audience: AudienceScope,
+    /// Set once the text has been saved as a draft.
+    pub draft_id: Option<i64>,
+    /// Changed since the last autosave.
+    dirty: bool,
+}
+
+impl Composer {
+    /// Opens `draft` with the cursor at the end of its text.
+    pub fn new(draft: Draft) -> Self {
+        let mut composer = Self {
+            lines: vec![String::new()],
+            row: 0,
+            col: 0,
+            audience: draft.audience,
+            draft_id: draft.id,
+            dirty: false,
+        };
+        composer.set_text(&draft.body);
+        composer.dirty = false;
+        composer
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.iter().all(|line| line.trim().is_empty())
+    }
+
+    pub fn char_count(&self) -> usize {
+        self.lines
+            .iter()
+            .map(|line| line.chars().count())
+            .sum::<usize>()
+            + self.lines.len()
+            - 1
+    }
+
+    pub fn audience(&self) -> AudienceScope {
+        self.audience
+    }
+
+    /// Steps to the next audience in `AudienceScope::all()`, wrapping round.
+    pub fn cycle_audience(&mut self) {
+        let all = AudienceScope::all();
+        let current = all.iter().position(|a| *a == self.audience).unwrap_or(0);
+        self.audience = all[(current + 1) % all.len()];
+        self.dirty = true;
+    }
+
+    /// The current text as a draft to save.
+    pub fn draft(&self) -> Draft {
+        let mut draft = Draft::new(self.text(), self.audience);
+        draft.id = self.draft_id;
+        draft
+    }
+
+    /// Whether anything changed since the last call.
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Replaces the text, e.g. with what came back from `$EDITOR`.
+    pub fn set_text(&mut self, text: &str) {
+        self.lines = text.split('\n').map(str::to_string).collect();
+        self.row = self.lines.len() - 1;
+        self.col = self.lines[self.row].len();
+        self.dirty = true;
+    }
+
+    /// Line and terminal column of the cursor.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.lines[self.row][..self.col].width())
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.lines[self.row].insert(self.col, c);
+        self.col += c.len_utf8();
+        self.dirty = true;
+    }
+
+    pub fn newline(&mut self) {
+        let rest = self.lines[self.row].split_off(self.col);
+        self.row += 1;
+        self.lines.insert(self.row, rest);
+        self.col = 0;
+        self.dirty = true;
+    }
+
+    /// Deletes before the cursor, joining with the previous line at its
+    /// start.
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.lines[self.row][..self.col].chars().next_back() {
+            self.col -= c.len_utf8();
+            self.lines[self.row].remove(self.col);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.lines[self.row].len();
+            self.lines[self.row].push_str(&line);
+        } else {
+            return;
+        }
+        self.dirty = true;
+    }
+
+    /// Deletes under the cursor, joining with the next line at its end.
+    pub fn delete(&mut self) {
+        if self.col < self.lines[self.row].len() {
+            self.lines[self.row].remove(self.col);
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+        } else {
+            return;
+        }
+        self.dirty = true;
+    }
+
+    pub fn left(&mut self) {
+        if let Some(c) = self.lines[self.row][..self.col].chars().next_back() {
+            self.col -= c.len_utf8();
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.lines[self.row].len();
+        }
+    }
+
+    pub fn right(&mut self) {
+        if let Some(c) = self.lines[self.row][self.col..].chars().next() {
+            self.col += c.len_utf8();
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    pub fn up(&mut self) {
+        if self.row > 0 {
+            self.move_to_row(self.row - 1);
+        }
+    }
+
+    pub fn down(&mut self) {
+        if self.row + 1 < self.lines.len() {
+            self.move_to_row(self.row + 1);
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.col = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.col = self.lines[self.row].len();
+    }
+
+    /// Keeps the cursor in about the same column on another line.
+    fn move_to_row(&mut self, row: usize) {
+        let chars = self.lines[self.row][..self.col].chars().count();
+        self.row = row;
+        self.col = self.lines[row]
+            .char_indices()
+            .nth(chars)
+            .map(|(i, _)| i)
+            .unwrap_or(self.lines[row].len());
+    }
+}
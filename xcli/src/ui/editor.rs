@@ -0,0 +1,308 @@
+use unicode_width::UnicodeWidthStr;
+
+/// The command line: a single-line buffer with a cursor, history recall and
+/// reverse search. `cursor` is a byte offset that always sits on a char
+/// boundary.
+#[derive(Debug, Default)]
+pub struct LineEditor {
+    buffer: String,
+    cursor: usize,
+    /// Earlier commands, oldest first.
+    history: Vec<String>,
+    /// Index into `history` while Up/Down browse it.
+    recall: Option<usize>,
+    /// What was typed before browsing started, restored by Down.
+    draft: String,
+    search: Option<HistorySearch>,
+}
+
+/// State of a Ctrl-R search: the typed query and the history entry it
+/// currently matches.
+#[derive(Debug)]
+struct HistorySearch {
+    query: String,
+    found: Option<usize>,
+    /// The line to restore if the search is cancelled.
+    original: String,
+}
+
+impl LineEditor {
+    pub fn new(history: Vec<String>) -> Self {
+        Self {
+            history,
+            ..Default::default()
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.buffer
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Terminal columns before the cursor.
+    pub fn cursor_column(&self) -> usize {
+        self.buffer[..self.cursor].width()
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.buffer = text.to_string();
+        self.cursor = self.buffer.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.cursor = 0;
+        self.recall = None;
+        self.search = None;
+    }
+
+    /// Returns the line and empties the editor.
+    pub fn take(&mut self) -> String {
+        let line = std::mem::take(&mut self.buffer);
+        self.clear();
+        line
+    }
+
+    /// Adds `line` to the history unless it is blank or repeats the
+    /// previous entry.
+    pub fn remember(&mut self, line: &str) {
+        if !line.trim().is_empty() && self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+        }
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.buffer.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.buffer[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.buffer.remove(self.cursor);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.buffer.len() {
+            self.buffer.remove(self.cursor);
+        }
+    }
+
+    pub fn left(&mut self) {
+        if let Some(c) = self.buffer[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn right(&mut self) {
+        if let Some(c) = self.buffer[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.buffer.len();
+    }
+
+    /// Moves to the start of the current or previous word.
+    pub fn word_left(&mut self) {
+        self.cursor = self.word_start();
+    }
+
+    /// Moves past the end of the current or next word.
+    pub fn word_right(&mut self) {
+        let rest = &self.buffer[self.cursor..];
+        let word = rest.len() - rest.trim_start().len();
+        let end = rest[word..]
+            .find(char::is_whitespace)
+            .map(|i| word + i)
+            .unwrap_or(rest.len());
+        self.cursor += end;
+    }
+
+    /// Deletes from the start of the word before the cursor up to it
+    /// (Ctrl-W).
+    pub fn delete_word(&mut self) {
+        let start = self.word_start();
+        self.buffer.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Deletes everything before the cursor (Ctrl-U).
+    pub fn delete_to_start(&mut self) {
+        self.buffer.replace_range(..self.cursor, "");
+        self.cursor = 0;
+    }
+
+    /// Deletes everything after the cursor (Ctrl-K).
+    pub fn delete_to_end(&mut self) {
+        self.buffer.truncate(self.cursor);
+    }
+
+    fn word_start(&self) -> usize {
+        let before = self.buffer[..self.cursor].trim_end();
+        before
+            .rfind(char::is_whitespace)
+            .map(|i| i + 1)
+            .unwrap_or(0)
+    }
+
+    /// Replaces `start..cursor` with the completion: the only candidate
+    /// followed by a space, or else the longest prefix all candidates share.
+    pub fn complete(&mut self, start: usize, candidates: &[String]) {
+        let replacement = match candidates {
+            [] => return,
+            [only] => format!("{} ", only),
+            [first, rest @ ..] => rest.iter().fold(first.clone(), |prefix, candidate| {
+                let shared = prefix
+                    .char_indices()
+                    .zip(candidate.chars())
+                    .find(|((_, a), b)| a != b)
+                    .map(|((i, _), _)| i)
+                    .unwrap_or(prefix.len().min(candidate.len()));
+                prefix[..shared].to_string()
+            }),
+        };
+        if replacement.len() < self.cursor - start {
+            return;
+        }
+        self.buffer.replace_range(start..self.cursor, &replacement);
+        self.cursor = start + replacement.len();
+    }
+
+    /// Steps back through history (Up).
+    pub fn history_prev(&mut self) {
+        let index = match self.recall {
+            Some(0) => return,
+            Some(index) => index - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.buffer.clone();
+                self.history.len() - 1
+            }
+        };
+        self.recall = Some(index);
+        self.set_text(&self.history[index].clone());
+    }
+
+    /// Steps forward through history, ending at the line being typed (Down).
+    pub fn history_next(&mut self) {
+        let Some(index) = self.recall else {
+            return;
+        };
+        if index + 1 < self.history.len() {
+            self.recall = Some(index + 1);
+            self.set_text(&self.history[index + 1].clone());
+        } else {
+            self.recall = None;
+            let draft = std::mem::take(&mut self.draft);
+            self.set_text(&draft);
+        }
+    }
+
+    /// Starts a Ctrl-R search, or on a second press looks for an older
+    /// match of the same query.
+    pub fn search_older(&mut self) {
+        match &mut self.search {
+            None => {
+                self.search = Some(HistorySearch {
+                    query: String::new(),
+                    found: None,
+                    original: self.buffer.clone(),
+                });
+            }
+            Some(search) => {
+                let before = search.found.unwrap_or(self.history.len());
+                let query = search.query.clone();
+                if let Some(found) = self.find(&query, before) {
+                    self.show_match(found);
+                }
+            }
+        }
+    }
+
+    pub fn search_push(&mut self, c: char) {
+        if let Some(search) = &mut self.search {
+            search.query.push(c);
+            self.research();
+        }
+    }
+
+    pub fn search_backspace(&mut self) {
+        if let Some(search) = &mut self.search {
+            search.query.pop();
+            self.research();
+        }
+    }
+
+    /// Keeps the matched line in the editor and leaves search mode.
+    pub fn accept_search(&mut self) {
+        self.search = None;
+    }
+
+    /// Leaves search mode and restores the line from before it.
+    pub fn cancel_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.set_text(&search.original);
+        }
+    }
+
+    /// The query while a Ctrl-R search is active.
+    pub fn search_query(&self) -> Option<&str> {
+        self.search.as_ref().map(|search| search.query.as_str())
+    }
+
+    /// Whether the active search found nothing for its query.
+    pub fn search_failed(&self) -> bool {
+        self.search
+            .as_ref()
+            .map(|search| !search.query.is_empty() && search.found.is_none())
+            .unwrap_or(false)
+    }
+
+    /// Searches again from the newest entry after the query changed.
+    fn research(&mut self) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        let query = search.query.clone();
+        match self.find(&query, self.history.len()) {
+            Some(found) => self.show_match(found),
+            None => {
+                if let Some(search) = &mut self.search {
+                    search.found = None;
+                }
+            }
+        }
+    }
+
+    /// Newest history entry before `before` that contains `query`.
+    fn find(&self, query: &str, before: usize) -> Option<usize> {
+        if query.is_empty() {
+            return None;
+        }
+        self.history[..before.min(self.history.len())]
+            .iter()
+            .rposition(|line| line.contains(query))
+    }
+
+    fn show_match(&mut self, found: usize) {
+        let line = self.history[found].clone();
+        let at = line.find(self.search_query().unwrap_or_default());
+        if let Some(search) = &mut self.search {
+            search.found = Some(found);
+        }
+        self.set_text(&line);
+        if let Some(at) = at {
+            self.cursor = at;
+        }
+    }
+}
@@ -1,25 +1,188 @@
-use ratatui::style::{Color, Style};
+pub mod composer;
+pub mod editor;
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span, Text};
-use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use ratatui::widgets::block::{Position, Title};
+use ratatui::widgets::{
+    Block, Borders, HighlightSpacing, List, ListItem, ListState, Paragraph, Wrap,
+};
+use time::OffsetDateTime;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use uuid::Uuid;
+
+pub use composer::Composer;
+pub use editor::LineEditor;
 
-use crate::domain::Post;
+use crate::commands::table::COMMANDS;
+use crate::domain::schedule::local_label;
+use crate::domain::{
+    Draft, Mailbox, Notification, Post, Profile, ProfileStats, SearchQuery, SpanKind, Task,
+    TaskPriority, TextSpan, POST_MAX_CHARS,
+};
 
-pub fn nav_panel() -> Paragraph<'static> {
-    let nav_text = Text::from(vec![
-        Line::from("Commands:"),
-        Line::from("/login email: pw:"),
-        Line::from("/post \"text\" audience:public"),
-        Line::from("/feed global"),
-        Line::from("/tasks add \"title\" \"desc\""),
+/// Command help from the command table, topped by the unread notification
+/// badge.
+pub fn nav_panel(notifications: i64) -> Paragraph<'static> {
+    let badge = if notifications > 0 {
+        Line::from(Span::styled(
+            format!(
+                "● {} notification{}",
+                notifications,
+                if notifications == 1 { "" } else { "s" }
+            ),
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        ))
+    } else {
+        Line::from(Span::styled(
+            "No new notifications",
+            Style::default().fg(Color::DarkGray),
+        ))
+    };
+    let mut lines = vec![badge, Line::from("Commands:")];
+    lines.extend(COMMANDS.iter().map(|spec| Line::from(spec.synopsis)));
+    lines.extend([
+        Line::from("m: toggle lower pane"),
+        Line::from("Feed keys:"),
+        Line::from("j/k, PgUp/PgDn, g/G: move"),
+        Line::from("l: like  r: reply"),
+        Line::from("o: open  y: copy id"),
+        Line::from("c: compose"),
     ]);
-    Paragraph::new(nav_text).block(Block::default().title("Help").borders(Borders::ALL))
+    Paragraph::new(Text::from(lines)).block(Block::default().title("Help").borders(Borders::ALL))
+}
+
+/// What the feed panel is showing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FeedView {
+    #[default]
+    Feed,
+    Search(SearchQuery),
+    /// A conversation; replies are indented by their depth below the root.
+    Thread {
+        root: Uuid,
+        depths: HashMap<Uuid, usize>,
+    },
+    /// A user's recent posts, with their profile in the title.
+    Profile {
+        profile: Profile,
+        stats: Option<ProfileStats>,
+    },
+}
+
+/// Width of the marker drawn in front of the selected post.
+pub const SELECTION_WIDTH: u16 = 2;
+
+/// Cursor and scroll offset of the feed list. ratatui moves the offset while
+/// rendering so the selected post stays in view, including after a resize.
+#[derive(Debug, Default)]
+pub struct FeedList {
+    pub state: ListState,
+    /// Posts that fit on screen from the current offset, as of the last draw.
+    page: usize,
+}
+
+impl FeedList {
+    /// Selects the first of `len` posts and scrolls to the top.
+    pub fn reset(&mut self, len: usize) {
+        self.state = ListState::default().with_selected((len > 0).then_some(0));
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.state.selected()
+    }
+
+    /// Moves the cursor by `delta` posts. Returns true when it is already on
+    /// the last of `len` posts and asked to go further down, i.e. an older
+    /// page should be loaded.
+    pub fn move_by(&mut self, delta: isize, len: usize) -> bool {
+        if len == 0 {
+            self.state.select(None);
+            return false;
+        }
+        let current = self.state.selected().unwrap_or(0).min(len - 1);
+        if delta > 0 && current == len - 1 {
+            return true;
+        }
+        self.state
+            .select(Some(current.saturating_add_signed(delta).min(len - 1)));
+        false
+    }
+
+    pub fn first(&mut self, len: usize) {
+        self.state.select((len > 0).then_some(0));
+    }
+
+    pub fn last(&mut self, len: usize) {
+        self.state.select(len.checked_sub(1));
+    }
+
+    /// Keeps the cursor on a post after the list changed length.
+    pub fn clamp(&mut self, len: usize) {
+        let selected = match self.state.selected() {
+            Some(index) => len.checked_sub(1).map(|last| index.min(last)),
+            None => (len > 0).then_some(0),
+        };
+        self.state.select(selected);
+    }
+
+    /// Records how many of `items` fit in `rows` lines from the current
+    /// offset; PgUp/PgDn move by that many posts.
+    pub fn measure(&mut self, items: &[ListItem], rows: usize) {
+        let mut used = 0;
+        self.page = items
+            .iter()
+            .skip(self.state.offset())
+            .take_while(|item| {
+                used += item.height();
+                used <= rows
+            })
+            .count();
+    }
+
+    pub fn page(&self) -> usize {
+        self.page.max(1)
+    }
 }
 
-pub fn feed_panel(posts: &[Post]) -> Paragraph<'_> {
-    let feed_lines = posts
+/// One list item per post, word-wrapped to `width` columns.
+pub fn feed_items(posts: &[Post], view: &FeedView, width: u16) -> Vec<ListItem<'static>> {
+    let terms = match view {
+        FeedView::Search(query) => query.terms(),
+        _ => Vec::new(),
+    };
+    let muted = Style::default().fg(Color::DarkGray);
+    posts
         .iter()
         .map(|post| {
-            Line::from(vec![
+            let mut lines = Vec::with_capacity(2);
+            let mut spans = Vec::new();
+            match view {
+                FeedView::Thread { depths, .. } => {
+                    let depth = depths.get(&post.id).copied().unwrap_or_default();
+                    if depth > 0 {
+                        spans.push(Span::styled("│ ".repeat(depth), muted));
+                    }
+                }
+                _ => {
+                    if let Some(parent) = post.reply_to {
+                        let handle = post
+                            .reply_to_handle
+                            .clone()
+                            .unwrap_or_else(|| parent.to_string()[..8].to_string());
+                        lines.push(Line::from(Span::styled(
+                            format!("↳ replying to @{}", handle),
+                            muted,
+                        )));
+                    }
+                }
+            }
+            spans.extend([
                 Span::styled(
                     format!(
                         "@{}",
@@ -30,28 +193,558 @@ pub fn feed_panel(posts: &[Post]) -> Paragraph<'_> {
                     Style::default().fg(Color::Cyan),
                 ),
                 Span::raw(" "),
-                Span::raw(post.body.clone()),
+            ]);
+            spans.extend(styled_body(&post.body, &post.spans(), &terms));
+            spans.extend([
                 Span::raw(format!(" [{}]", post.audience)),
-            ])
+                Span::styled(
+                    format!(" {}{}", if post.liked { "♥" } else { "♡" }, post.like_count),
+                    Style::default().fg(if post.liked {
+                        Color::Red
+                    } else {
+                        Color::DarkGray
+                    }),
+                ),
+            ]);
+            lines.extend(wrap_line(Line::from(spans), width as usize));
+            ListItem::new(lines)
         })
-        .collect::<Vec<_>>();
-    Paragraph::new(feed_lines)
+        .collect()
+}
+
+pub fn feed_panel<'a>(
+    items: Vec<ListItem<'a>>,
+    offline: Option<&str>,
+    incoming: usize,
+    view: &FeedView,
+) -> List<'a> {
+    let mut title = vec![match view {
+        FeedView::Feed => Span::raw("Feed"),
+        FeedView::Search(query) => Span::raw(format!("Search {}", query)),
+        FeedView::Thread { depths, .. } => Span::raw(format!(
+            "Thread ({} repl{})",
+            depths.len().saturating_sub(1),
+            if depths.len() == 2 { "y" } else { "ies" }
+        )),
+        FeedView::Profile { profile, stats } => Span::raw(profile.summary(*stats)),
+    }];
+    if let Some(label) = offline {
+        title.push(Span::styled(
+            format!(" [{}]", label),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if incoming > 0 {
+        title.push(Span::styled(
+            format!(
+                " [{} new post{}, press n]",
+                incoming,
+                if incoming == 1 { "" } else { "s" }
+            ),
+            Style::default().fg(Color::Green),
+        ));
+    }
+    let title = Line::from(title);
+    List::new(items)
+        .highlight_symbol("▌ ")
+        .highlight_spacing(HighlightSpacing::Always)
+        .highlight_style(Style::default().bg(Color::Indexed(236)))
+        .block(Block::default().title(title).borders(Borders::ALL))
+}
+
+/// Word-wraps `line` to `width` columns, keeping each span's style. Words
+/// longer than a whole line are split.
+pub fn wrap_line(line: Line<'static>, width: usize) -> Vec<Line<'static>> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut current = Vec::new();
+    let mut used = 0;
+    for span in line.spans {
+        for mut word in span.content.split_inclusive(' ') {
+            if used > 0 && used + word.trim_end().width() > width {
+                lines.push(Line::from(std::mem::take(&mut current)));
+                used = 0;
+            }
+            if used == 0 {
+                word = word.trim_start();
+            }
+            while word.trim_end().width() > width.saturating_sub(used) {
+                let mut split = 0;
+                for (index, c) in word.char_indices() {
+                    if used + word[..index].width() + c.width().unwrap_or(0) > width {
+                        break;
+                    }
+                    split = index + c.len_utf8();
+                }
+                if split == 0 {
+                    // A single character wider than the line.
+                    split = word.chars().next().map(char::len_utf8).unwrap_or(0);
+                }
+                current.push(Span::styled(word[..split].to_string(), span.style));
+                lines.push(Line::from(std::mem::take(&mut current)));
+                used = 0;
+                word = &word[split..];
+            }
+            if !word.is_empty() {
+                current.push(Span::styled(word.to_string(), span.style));
+                used += word.width();
+            }
+        }
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(Line::from(current));
+    }
+    lines
+}
+
+/// Styles the mentions, hashtags and URLs in `body`; the text between them
+/// gets search highlighting.
+fn styled_body(body: &str, entities: &[TextSpan], terms: &[String]) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut pos = 0;
+    for entity in entities {
+        spans.extend(highlight(&body[pos..entity.start], terms));
+        let style = match entity.kind {
+            SpanKind::Mention => Style::default().fg(Color::Cyan),
+            SpanKind::Hashtag => Style::default().fg(Color::Magenta),
+            SpanKind::Url => Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::UNDERLINED),
+        };
+        spans.push(Span::styled(entity.text(body).to_string(), style));
+        pos = entity.end;
+    }
+    spans.extend(highlight(&body[pos..], terms));
+    spans.retain(|span| !span.content.is_empty());
+    spans
+}
+
+/// Splits `body` into spans, marking words that start with one of `terms`
+/// (the same prefix match `/search` uses).
+fn highlight(body: &str, terms: &[String]) -> Vec<Span<'static>> {
+    if terms.is_empty() {
+        return vec![Span::raw(body.to_string())];
+    }
+    let marked = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut rest = body;
+    while let Some(start) = rest.find(char::is_alphanumeric) {
+        plain.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(rest.len());
+        let word = &rest[..end];
+        let lower = word.to_lowercase();
+        if terms.iter().any(|term| lower.starts_with(term.as_str())) {
+            if !plain.is_empty() {
+                spans.push(Span::raw(std::mem::take(&mut plain)));
+            }
+            spans.push(Span::styled(word.to_string(), marked));
+        } else {
+            plain.push_str(word);
+        }
+        rest = &rest[end..];
+    }
+    plain.push_str(rest);
+    if !plain.is_empty() {
+        spans.push(Span::raw(plain));
+    }
+    spans
+}
+
+/// What the pane below the feed shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pane {
+    Mailbox(Mailbox),
+    Notifications(Vec<Notification>),
+    Drafts(Vec<Draft>),
+    Tasks(Vec<Task>),
+}
+
+pub fn pane_panel(pane: &Pane, height: u16) -> Paragraph<'_> {
+    match pane {
+        Pane::Mailbox(mailbox) => messages_panel(mailbox, height),
+        Pane::Notifications(notifications) => notifications_panel(notifications),
+        Pane::Drafts(drafts) => drafts_panel(drafts),
+        Pane::Tasks(tasks) => tasks_panel(tasks, OffsetDateTime::now_utc()),
+    }
+}
+
+/// The last `/tasks ls`, in its order. Overdue tasks are red; finished
+/// ones are dimmed.
+pub fn tasks_panel(tasks: &[Task], now: OffsetDateTime) -> Paragraph<'_> {
+    let muted = Style::default().fg(Color::DarkGray);
+    let lines = if tasks.is_empty() {
+        vec![Line::from(Span::styled("No tasks", muted))]
+    } else {
+        tasks
+            .iter()
+            .map(|task| {
+                let overdue = task.is_overdue(now);
+                let title = if overdue {
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+                } else if task.status.is_active() {
+                    Style::default()
+                } else {
+                    muted
+                };
+                let mut spans = vec![
+                    Span::styled(format!("#{} ", task.id), Style::default().fg(Color::Yellow)),
+                    Span::styled(format!("[{}] ", task.status.as_str()), muted),
+                ];
+                let priority = match task.priority {
+                    TaskPriority::Urgent => Some(Color::Red),
+                    TaskPriority::High => Some(Color::Magenta),
+                    TaskPriority::Low => Some(Color::DarkGray),
+                    TaskPriority::Normal => None,
+                };
+                if let Some(color) = priority {
+                    spans.push(Span::styled(
+                        format!("!{} ", task.priority.as_str()),
+                        Style::default().fg(color),
+                    ));
+                }
+                spans.push(Span::styled(task.title.clone(), title));
+                if let Some(due) = task.due_at {
+                    let label = if overdue {
+                        format!(" overdue since {}", local_label(due))
+                    } else {
+                        format!(" due {}", local_label(due))
+                    };
+                    spans.push(Span::styled(
+                        label,
+                        if overdue {
+                            Style::default().fg(Color::Red)
+                        } else {
+                            muted
+                        },
+                    ));
+                }
+                for tag in &task.tags {
+                    spans.push(Span::styled(
+                        format!(" #{}", tag),
+                        Style::default().fg(Color::Cyan),
+                    ));
+                }
+                Line::from(spans)
+            })
+            .collect()
+    };
+    let overdue = tasks.iter().filter(|task| task.is_overdue(now)).count();
+    let title = match overdue {
+        0 => format!("Tasks ({}) · /tasks edit|done|rm <id>", tasks.len()),
+        n => format!(
+            "Tasks ({}, {} overdue) · /tasks edit|done|rm <id>",
+            tasks.len(),
+            n
+        ),
+    };
+    Paragraph::new(lines)
         .wrap(Wrap { trim: true })
-        .block(Block::default().title("Feed").borders(Borders::ALL))
+        .block(Block::default().title(title).borders(Borders::ALL))
 }
 
-pub fn status_panel(lines: Vec<String>) -> Paragraph<'static> {
-    let status_lines = lines.into_iter().map(Line::from).collect::<Vec<_>>();
+/// Saved drafts, most recently edited first, with the ids `/drafts` takes.
+pub fn drafts_panel(drafts: &[Draft]) -> Paragraph<'_> {
+    let lines = if drafts.is_empty() {
+        vec![Line::from(Span::styled(
+            "No drafts",
+            Style::default().fg(Color::DarkGray),
+        ))]
+    } else {
+        drafts
+            .iter()
+            .map(|draft| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("#{} ", draft.id.unwrap_or_default()),
+                        Style::default().fg(Color::Yellow),
+                    ),
+                    Span::styled(
+                        format!("[{}] ", draft.audience),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::raw(draft.preview(60)),
+                ])
+            })
+            .collect()
+    };
+    Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .title(format!(
+                "Drafts ({}) · /drafts open|send|rm <id>",
+                drafts.len()
+            ))
+            .borders(Borders::ALL),
+    )
+}
+
+/// The composer with its audience and a live character count. Long lines
+/// wrap at the panel edge. Returns the widget and the cursor's row and
+/// column inside the border.
+pub fn compose_panel(composer: &Composer, width: u16, height: u16) -> (Paragraph<'_>, (u16, u16)) {
+    let columns = width.saturating_sub(2).max(1) as usize;
+    let rows = height.saturating_sub(2).max(1) as usize;
+    let (cursor_line, cursor_column) = composer.cursor();
+    let mut cursor = (0, 0);
+    let mut lines = Vec::new();
+    for (index, text) in composer.lines().iter().enumerate() {
+        if index == cursor_line {
+            cursor = (
+                lines.len() + cursor_column / columns,
+                cursor_column % columns,
+            );
+        }
+        lines.extend(chunk(text, columns).into_iter().map(Line::from));
+    }
+    let scroll = cursor.0.saturating_sub(rows - 1);
+    let count = composer.char_count();
+    let count_style = if count > POST_MAX_CHARS {
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    let title = Line::from(vec![
+        Span::raw("Compose · "),
+        Span::styled(
+            composer.audience().to_string(),
+            Style::default().fg(Color::Cyan),
+        ),
+        Span::raw(" · "),
+        Span::styled(format!("{}/{}", count, POST_MAX_CHARS), count_style),
+    ]);
+    let hints = Title::from(Span::styled(
+        "Ctrl-S send · Tab audience · Ctrl-E $EDITOR · Esc keep draft",
+        Style::default().fg(Color::DarkGray),
+    ))
+    .position(Position::Bottom);
+    let panel = Paragraph::new(lines)
+        .scroll((scroll.min(u16::MAX as usize) as u16, 0))
+        .block(
+            Block::default()
+                .title(title)
+                .title(hints)
+                .borders(Borders::ALL),
+        );
+    let row = (cursor.0 - scroll).min(u16::MAX as usize) as u16;
+    (panel, (row, cursor.1 as u16))
+}
+
+/// Splits `text` into pieces at most `width` columns wide; an empty line
+/// stays one empty piece.
+fn chunk(text: &str, width: usize) -> Vec<String> {
+    let mut chunks = vec![String::new()];
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used > 0 && used + w > width {
+            chunks.push(String::new());
+            used = 0;
+        }
+        if let Some(last) = chunks.last_mut() {
+            last.push(c);
+        }
+        used += w;
+    }
+    chunks
+}
+
+/// Newest first; unread items are bold and each starts with the id
+/// `/notifications open` takes.
+pub fn notifications_panel(notifications: &[Notification]) -> Paragraph<'_> {
+    let unread = notifications.iter().filter(|n| n.is_unread()).count();
+    let lines = if notifications.is_empty() {
+        vec![Line::from(Span::styled(
+            "Nothing yet",
+            Style::default().fg(Color::DarkGray),
+        ))]
+    } else {
+        notifications
+            .iter()
+            .map(|notification| {
+                let style = if notification.is_unread() {
+                    Style::default().add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::DarkGray)
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!("#{} ", notification.id),
+                        Style::default().fg(Color::Yellow),
+                    ),
+                    Span::styled(notification.describe(), style),
+                ])
+            })
+            .collect()
+    };
+    Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .title(format!("Notifications ({} unread)", unread))
+            .borders(Borders::ALL),
+    )
+}
+
+/// The inbox or one conversation; newest messages at the bottom.
+pub fn messages_panel(mailbox: &Mailbox, height: u16) -> Paragraph<'_> {
+    let muted = Style::default().fg(Color::DarkGray);
+    let (title, lines) = match mailbox {
+        Mailbox::Inbox(conversations) => {
+            let unread: i64 = conversations.iter().map(|c| c.unread).sum();
+            let lines = if conversations.is_empty() {
+                vec![Line::from(Span::styled("No messages yet", muted))]
+            } else {
+                conversations
+                    .iter()
+                    .map(|conversation| {
+                        let handle = conversation
+                            .peer_handle
+                            .clone()
+                            .unwrap_or_else(|| conversation.peer.to_string());
+                        let mut spans = vec![Span::styled(
+                            format!("@{}", handle),
+                            Style::default().fg(Color::Cyan),
+                        )];
+                        if conversation.unread > 0 {
+                            spans.push(Span::styled(
+                                format!(" ({})", conversation.unread),
+                                Style::default()
+                                    .fg(Color::Green)
+                                    .add_modifier(Modifier::BOLD),
+                            ));
+                        }
+                        spans.push(Span::raw(format!(" {}", conversation.last_body)));
+                        Line::from(spans)
+                    })
+                    .collect()
+            };
+            (format!("Inbox ({} unread)", unread), lines)
+        }
+        Mailbox::Conversation {
+            peer,
+            peer_handle,
+            messages,
+        } => {
+            let lines = if messages.is_empty() {
+                vec![Line::from(Span::styled(
+                    format!("No messages with @{} yet", peer_handle),
+                    muted,
+                ))]
+            } else {
+                messages
+                    .iter()
+                    .map(|message| {
+                        let (who, style) = if Some(message.sender) == *peer {
+                            (
+                                format!("@{}", peer_handle),
+                                Style::default().fg(Color::Cyan),
+                            )
+                        } else {
+                            ("you".to_string(), Style::default().fg(Color::Green))
+                        };
+                        Line::from(vec![
+                            Span::styled(who, style),
+                            Span::raw(format!(" {}", message.body)),
+                        ])
+                    })
+                    .collect()
+            };
+            (format!("Messages with @{}", peer_handle), lines)
+        }
+    };
+    // Keep the newest message in view; wrapped lines may still push it down.
+    let hidden = lines
+        .len()
+        .saturating_sub(height.saturating_sub(2) as usize);
+    Paragraph::new(lines)
+        .wrap(Wrap { trim: true })
+        .scroll((hidden.min(u16::MAX as usize) as u16, 0))
+        .block(Block::default().title(title).borders(Borders::ALL))
+}
+
+pub fn status_panel(account: Option<&str>, unread: i64, lines: Vec<String>) -> Paragraph<'static> {
+    let account = match account {
+        Some(email) => {
+            let mut spans = vec![Span::styled(
+                email.to_string(),
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )];
+            if unread > 0 {
+                spans.push(Span::styled(
+                    format!(" ✉ {}", unread),
+                    Style::default().fg(Color::Green),
+                ));
+            }
+            Line::from(spans)
+        }
+        None => Line::from(Span::styled(
+            "not signed in",
+            Style::default().fg(Color::DarkGray),
+        )),
+    };
+    let status_lines = std::iter::once(account)
+        .chain(lines.into_iter().map(Line::from))
+        .collect::<Vec<_>>();
     Paragraph::new(status_lines).block(Block::default().title("Status").borders(Borders::ALL))
 }
 
-pub fn command_panel<'a>(input: &'a str, editing: bool) -> Paragraph<'a> {
+/// The command line, scrolled sideways to keep the cursor in view, with the
+/// byte range `error` underlined (an empty range marks where something is
+/// missing). Returns the widget and the cursor's column inside the border.
+pub fn command_panel(
+    editor: &LineEditor,
+    editing: bool,
+    error: Option<Range<usize>>,
+    width: u16,
+) -> (Paragraph<'_>, u16) {
     let style = if editing {
         Style::default().fg(Color::Green)
     } else {
         Style::default()
     };
-    Paragraph::new(input.to_string())
-        .style(style)
-        .block(Block::default().title("Command").borders(Borders::ALL))
+    let (prompt, title) = match editor.search_query() {
+        Some(query) => (
+            format!(
+                "({}search) '{}': ",
+                if editor.search_failed() {
+                    "failing "
+                } else {
+                    ""
+                },
+                query
+            ),
+            "History search (Ctrl-R older, Esc cancel)",
+        ),
+        None => (String::new(), "Command"),
+    };
+    let column = prompt.width() + editor.cursor_column();
+    let inner = width.saturating_sub(2).max(1) as usize;
+    let scroll = (column + 1).saturating_sub(inner);
+    let mut spans = vec![Span::styled(prompt, Style::default().fg(Color::Yellow))];
+    let text = editor.text();
+    match error.filter(|span| text.get(span.clone()).is_some()) {
+        Some(span) => {
+            let bad = Style::default()
+                .fg(Color::Red)
+                .add_modifier(Modifier::UNDERLINED);
+            spans.push(Span::styled(&text[..span.start], style));
+            if span.is_empty() {
+                spans.push(Span::styled(" ", bad.bg(Color::Red)));
+            } else {
+                spans.push(Span::styled(&text[span.clone()], bad));
+            }
+            spans.push(Span::styled(&text[span.end..], style));
+        }
+        None => spans.push(Span::styled(text, style)),
+    }
+    let line = Line::from(spans);
+    let panel = Paragraph::new(line)
+        .scroll((0, scroll.min(u16::MAX as usize) as u16))
+        .block(Block::default().title(title).borders(Borders::ALL));
+    (panel, (column - scroll).min(u16::MAX as usize) as u16)
 }
@@ -17,6 +17,30 @@ create table if not exists posts (
     created_at timestamptz default now()
 );
 
+-- Replies point at the post they answer; root_id is the post that started
+-- the conversation and is filled in from the parent on insert.
+alter table posts add column if not exists reply_to uuid references posts(id) on delete set null;
+alter table posts add column if not exists root_id uuid references posts(id) on delete cascade;
+create index if not exists posts_root_id on posts(root_id);
+
+create or replace function public.set_post_root()
+returns trigger
+language plpgsql
+as $$
+begin
+    if new.reply_to is not null then
+        select coalesce(p.root_id, p.id) into new.root_id from posts p where p.id = new.reply_to;
+    else
+        new.root_id := null;
+    end if;
+    return new;
+end;
+$$;
+
+drop trigger if exists posts_set_root on posts;
+create trigger posts_set_root before insert on posts
+    for each row execute function public.set_post_root();
+
 create table if not exists follows (
     follower uuid references profiles(id) on delete cascade,
     followee uuid references profiles(id) on delete cascade,
@@ -31,12 +55,24 @@ create table if not exists likes (
     primary key (user_id, post_id)
 );
 
+create table if not exists messages (
+    id uuid primary key default gen_random_uuid(),
+    sender uuid not null references profiles(id) on delete cascade,
+    recipient uuid not null references profiles(id) on delete cascade,
+    body text not null check (char_length(body) between 1 and 2000),
+    created_at timestamptz default now(),
+    read_at timestamptz
+);
+create index if not exists messages_recipient on messages(recipient, created_at);
+create index if not exists messages_sender on messages(sender, created_at);
+
 create table if not exists restraints (
     allowed uuid references profiles(id) on delete cascade,
     post_id uuid references posts(id) on delete cascade,
     primary key (allowed, post_id)
 );
 
+drop view if exists public.v_feed_public cascade;
 create or replace view public.v_feed_public as
 select p.id,
        p.author,
@@ -44,23 +80,53 @@ select p.id,
        p.audience,
        p.created_at,
        prof.handle as author_handle,
-       coalesce(lc.count, 0) as like_count
+       coalesce(lc.count, 0) as like_count,
+       p.reply_to,
+       p.root_id,
+       parent_prof.handle as reply_to_handle
 from posts p
 left join profiles prof on prof.id = p.author
+left join posts parent on parent.id = p.reply_to
+left join profiles parent_prof on parent_prof.id = parent.author
 left join (
     select post_id, count(*) as count from likes group by post_id
 ) lc on lc.post_id = p.id
 where p.audience = 'public'
 order by p.created_at desc;
 
-create or replace function public.feed_global(uid uuid)
+-- Feed RPCs page backwards with a keyset cursor: pass the created_at and id of
+-- the last post already shown to get the next older page.
+drop function if exists public.feed_global(uuid);
+drop function if exists public.feed_me(uuid);
+drop function if exists public.feed_following(uuid);
+-- Reply columns changed the return types of these.
+drop function if exists public.feed_global(uuid, timestamptz, uuid, int);
+drop function if exists public.feed_me(uuid, timestamptz, uuid, int);
+drop function if exists public.feed_following(uuid, timestamptz, uuid, int);
+drop function if exists public.search_posts(uuid, text, text, date, int);
+
+create or replace function public.feed_global(
+    uid uuid,
+    before_created_at timestamptz default null,
+    before_id uuid default null,
+    page_size int default 50
+)
 returns setof v_feed_public
 language sql stable
 as $$
-    select * from v_feed_public limit 200;
+    select * from v_feed_public v
+    where before_created_at is null
+       or (v.created_at, v.id) < (before_created_at, before_id)
+    order by v.created_at desc, v.id desc
+    limit least(page_size, 200);
 $$;
 
-create or replace function public.feed_me(uid uuid)
+create or replace function public.feed_me(
+    uid uuid,
+    before_created_at timestamptz default null,
+    before_id uuid default null,
+    page_size int default 50
+)
 returns table (
     id uuid,
     author uuid,
@@ -69,7 +135,10 @@ returns table (
     created_at timestamptz,
     author_handle text,
     like_count bigint,
-    liked boolean
+    liked boolean,
+    reply_to uuid,
+    root_id uuid,
+    reply_to_handle text
 )
 language sql stable
 as $$
@@ -80,18 +149,29 @@ as $$
            p.created_at,
            prof.handle,
            coalesce(lc.count,0) as like_count,
-           exists(select 1 from likes l where l.post_id = p.id and l.user_id = uid) as liked
+           exists(select 1 from likes l where l.post_id = p.id and l.user_id = uid) as liked,
+           p.reply_to,
+           p.root_id,
+           parent_prof.handle
     from posts p
+    left join posts parent on parent.id = p.reply_to
+    left join profiles parent_prof on parent_prof.id = parent.author
     left join profiles prof on prof.id = p.author
     left join (
         select post_id, count(*) as count from likes group by post_id
     ) lc on lc.post_id = p.id
     where p.author = uid
-    order by p.created_at desc
-    limit 200;
+      and (before_created_at is null or (p.created_at, p.id) < (before_created_at, before_id))
+    order by p.created_at desc, p.id desc
+    limit least(page_size, 200);
 $$;
 
-create or replace function public.feed_following(uid uuid)
+create or replace function public.feed_following(
+    uid uuid,
+    before_created_at timestamptz default null,
+    before_id uuid default null,
+    page_size int default 50
+)
 returns table (
     id uuid,
     author uuid,
@@ -100,7 +180,10 @@ returns table (
     created_at timestamptz,
     author_handle text,
     like_count bigint,
-    liked boolean
+    liked boolean,
+    reply_to uuid,
+    root_id uuid,
+    reply_to_handle text
 )
 language sql stable
 as $$
@@ -111,18 +194,349 @@ as $$
            p.created_at,
            prof.handle,
            coalesce(lc.count,0) as like_count,
-           exists(select 1 from likes l where l.post_id = p.id and l.user_id = uid) as liked
+           exists(select 1 from likes l where l.post_id = p.id and l.user_id = uid) as liked,
+           p.reply_to,
+           p.root_id,
+           parent_prof.handle
     from posts p
+    left join posts parent on parent.id = p.reply_to
+    left join profiles parent_prof on parent_prof.id = parent.author
     join follows f on f.followee = p.author
     left join profiles prof on prof.id = p.author
     left join (
         select post_id, count(*) as count from likes group by post_id
     ) lc on lc.post_id = p.id
-    where f.follower = uid
+    where (f.follower = uid
        or p.author = uid
        or p.audience = 'public'
        or (p.audience = 'restrained' and exists(select 1 from restraints r where r.post_id = p.id and r.allowed = uid))
-       or (p.audience = 'private' and p.author = uid)
-    order by p.created_at desc
-    limit 200;
+       or (p.audience = 'private' and p.author = uid))
+      and (before_created_at is null or (p.created_at, p.id) < (before_created_at, before_id))
+    order by p.created_at desc, p.id desc
+    limit least(page_size, 200);
+$$;
+
+-- Stream post inserts to Realtime subscribers; RLS decides who sees them.
+alter publication supabase_realtime add table posts;
+
+-- Full-text search for /search. Returns the posts uid may see that match
+-- every word of query, best match first.
+create index if not exists posts_body_fts on posts using gin (to_tsvector('simple', body));
+
+create or replace function public.search_posts(
+    uid uuid,
+    query text,
+    author_handle text default null,
+    since date default null,
+    max_results int default 50
+)
+returns table (
+    id uuid,
+    author uuid,
+    body text,
+    audience text,
+    created_at timestamptz,
+    author_handle text,
+    like_count bigint,
+    liked boolean,
+    reply_to uuid,
+    root_id uuid,
+    reply_to_handle text
+)
+language sql stable
+as $$
+    select p.id,
+           p.author,
+           p.body,
+           p.audience,
+           p.created_at,
+           prof.handle,
+           coalesce(lc.count,0) as like_count,
+           exists(select 1 from likes l where l.post_id = p.id and l.user_id = uid) as liked,
+           p.reply_to,
+           p.root_id,
+           parent_prof.handle
+    from posts p
+    left join posts parent on parent.id = p.reply_to
+    left join profiles parent_prof on parent_prof.id = parent.author
+    left join profiles prof on prof.id = p.author
+    left join (
+        select post_id, count(*) as count from likes group by post_id
+    ) lc on lc.post_id = p.id
+    where to_tsvector('simple', p.body) @@ plainto_tsquery('simple', query)
+      and (p.author = uid
+       or p.audience = 'public'
+       or (p.audience = 'restrained' and exists(select 1 from restraints r where r.post_id = p.id and r.allowed = uid)))
+      and (search_posts.author_handle is null or prof.handle = search_posts.author_handle)
+      and (since is null or p.created_at >= since)
+    order by ts_rank(to_tsvector('simple', p.body), plainto_tsquery('simple', query)) desc, p.created_at desc
+    limit least(max_results, 200);
+$$;
+
+-- The whole conversation post_id belongs to, oldest first, limited to the
+-- posts uid may see.
+create or replace function public.thread(uid uuid, post_id uuid)
+returns table (
+    id uuid,
+    author uuid,
+    body text,
+    audience text,
+    created_at timestamptz,
+    author_handle text,
+    like_count bigint,
+    liked boolean,
+    reply_to uuid,
+    root_id uuid,
+    reply_to_handle text
+)
+language sql stable
+as $$
+    with target as (
+        select coalesce(t.root_id, t.id) as root from posts t where t.id = thread.post_id
+    )
+    select p.id,
+           p.author,
+           p.body,
+           p.audience,
+           p.created_at,
+           prof.handle,
+           coalesce(lc.count,0) as like_count,
+           exists(select 1 from likes l where l.post_id = p.id and l.user_id = uid) as liked,
+           p.reply_to,
+           p.root_id,
+           parent_prof.handle
+    from posts p
+    join target on p.id = target.root or p.root_id = target.root
+    left join profiles prof on prof.id = p.author
+    left join posts parent on parent.id = p.reply_to
+    left join profiles parent_prof on parent_prof.id = parent.author
+    left join (
+        select post_id, count(*) as count from likes group by post_id
+    ) lc on lc.post_id = p.id
+    where p.author = uid
+       or p.audience = 'public'
+       or (p.audience = 'restrained' and exists(select 1 from restraints r where r.post_id = p.id and r.allowed = uid))
+    order by p.created_at, p.id
+    limit 500;
+$$;
+
+-- A profile with its follower and following counts.
+create or replace function public.profile_card(p_handle text)
+returns table (
+    id uuid,
+    handle text,
+    display_name text,
+    created_at timestamptz,
+    followers bigint,
+    following bigint
+)
+language sql stable
+as $$
+    select prof.id,
+           prof.handle,
+           prof.display_name,
+           prof.created_at,
+           (select count(*) from follows f where f.followee = prof.id) as followers,
+           (select count(*) from follows f where f.follower = prof.id) as following
+    from profiles prof
+    where prof.handle = p_handle;
+$$;
+
+-- The newest posts by author_id that uid may see.
+create or replace function public.profile_posts(uid uuid, author_id uuid, page_size int default 20)
+returns table (
+    id uuid,
+    author uuid,
+    body text,
+    audience text,
+    created_at timestamptz,
+    author_handle text,
+    like_count bigint,
+    liked boolean,
+    reply_to uuid,
+    root_id uuid,
+    reply_to_handle text
+)
+language sql stable
+as $$
+    select p.id,
+           p.author,
+           p.body,
+           p.audience,
+           p.created_at,
+           prof.handle,
+           coalesce(lc.count,0) as like_count,
+           exists(select 1 from likes l where l.post_id = p.id and l.user_id = uid) as liked,
+           p.reply_to,
+           p.root_id,
+           parent_prof.handle
+    from posts p
+    left join profiles prof on prof.id = p.author
+    left join posts parent on parent.id = p.reply_to
+    left join profiles parent_prof on parent_prof.id = parent.author
+    left join (
+        select post_id, count(*) as count from likes group by post_id
+    ) lc on lc.post_id = p.id
+    where p.author = author_id
+      and (p.author = uid
+       or p.audience = 'public'
+       or (p.audience = 'restrained' and exists(select 1 from restraints r where r.post_id = p.id and r.allowed = uid)))
+    order by p.created_at desc, p.id desc
+    limit least(page_size, 200);
+$$;
+
+-- Posts mentioning uid's handle that uid may see, newest first.
+drop function if exists public.feed_mentions(uuid, timestamptz, uuid, int);
+create or replace function public.feed_mentions(
+    uid uuid,
+    before_created_at timestamptz default null,
+    before_id uuid default null,
+    page_size int default 50
+)
+returns table (
+    id uuid,
+    author uuid,
+    body text,
+    audience text,
+    created_at timestamptz,
+    author_handle text,
+    like_count bigint,
+    liked boolean,
+    reply_to uuid,
+    root_id uuid,
+    reply_to_handle text
+)
+language sql stable
+as $$
+    select p.id,
+           p.author,
+           p.body,
+           p.audience,
+           p.created_at,
+           prof.handle,
+           coalesce(lc.count,0) as like_count,
+           exists(select 1 from likes l where l.post_id = p.id and l.user_id = uid) as liked,
+           p.reply_to,
+           p.root_id,
+           parent_prof.handle
+    from posts p
+    join profiles me on me.id = uid
+    left join profiles prof on prof.id = p.author
+    left join posts parent on parent.id = p.reply_to
+    left join profiles parent_prof on parent_prof.id = parent.author
+    left join (
+        select post_id, count(*) as count from likes group by post_id
+    ) lc on lc.post_id = p.id
+    where p.body ~* ('(^|[^[:alnum:]_@#])@' || me.handle || '([^[:alnum:]_]|$)')
+      and (p.author = uid
+       or p.audience = 'public'
+       or (p.audience = 'restrained' and exists(select 1 from restraints r where r.post_id = p.id and r.allowed = uid)))
+      and (before_created_at is null or (p.created_at, p.id) < (before_created_at, before_id))
+    order by p.created_at desc, p.id desc
+    limit least(page_size, 200);
+$$;
+
+-- Posts tagged #tag (matched case-insensitively) that uid may see.
+drop function if exists public.feed_tag(uuid, text, timestamptz, uuid, int);
+create or replace function public.feed_tag(
+    uid uuid,
+    tag text,
+    before_created_at timestamptz default null,
+    before_id uuid default null,
+    page_size int default 50
+)
+returns table (
+    id uuid,
+    author uuid,
+    body text,
+    audience text,
+    created_at timestamptz,
+    author_handle text,
+    like_count bigint,
+    liked boolean,
+    reply_to uuid,
+    root_id uuid,
+    reply_to_handle text
+)
+language sql stable
+as $$
+    select p.id,
+           p.author,
+           p.body,
+           p.audience,
+           p.created_at,
+           prof.handle,
+           coalesce(lc.count,0) as like_count,
+           exists(select 1 from likes l where l.post_id = p.id and l.user_id = uid) as liked,
+           p.reply_to,
+           p.root_id,
+           parent_prof.handle
+    from posts p
+    left join profiles prof on prof.id = p.author
+    left join posts parent on parent.id = p.reply_to
+    left join profiles parent_prof on parent_prof.id = parent.author
+    left join (
+        select post_id, count(*) as count from likes group by post_id
+    ) lc on lc.post_id = p.id
+    where p.body ~* ('(^|[^[:alnum:]_@#])#' || regexp_replace(tag, '\W', '', 'g') || '([^[:alnum:]_]|$)')
+      and (p.author = uid
+       or p.audience = 'public'
+       or (p.audience = 'restrained' and exists(select 1 from restraints r where r.post_id = p.id and r.allowed = uid)))
+      and (before_created_at is null or (p.created_at, p.id) < (before_created_at, before_id))
+    order by p.created_at desc, p.id desc
+    limit least(page_size, 200);
+$$;
+
+-- Notifications for uid newer than `since`: mentions of uid's handle, replies
+-- to and likes of uid's posts, and new followers. `key` identifies an item
+-- across polls. Oldest first, so when more than `max_results` are waiting the
+-- client's next `since` (the newest it stored) picks up where this page ended.
+drop function if exists public.notifications(uuid, timestamptz, int);
+create or replace function public.notifications(
+    uid uuid,
+    since timestamptz default null,
+    max_results int default 100
+)
+returns table (
+    key text,
+    kind text,
+    actor uuid,
+    actor_handle text,
+    post_id uuid,
+    excerpt text,
+    created_at timestamptz
+)
+language sql stable
+as $$
+    with me as (select handle from profiles where id = uid),
+    items as (
+        select 'mention:' || p.id, 'mention', p.author, p.id, p.body, p.created_at
+        from posts p, me
+        where p.author <> uid
+          and p.body ~* ('(^|[^[:alnum:]_@#])@' || me.handle || '([^[:alnum:]_]|$)')
+          and (p.audience = 'public'
+           or (p.audience = 'restrained' and exists(select 1 from restraints r where r.post_id = p.id and r.allowed = uid)))
+        union all
+        select 'reply:' || p.id, 'reply', p.author, p.id, p.body, p.created_at
+        from posts p
+        join posts parent on parent.id = p.reply_to
+        where parent.author = uid and p.author <> uid
+          and (p.audience = 'public'
+           or (p.audience = 'restrained' and exists(select 1 from restraints r where r.post_id = p.id and r.allowed = uid)))
+        union all
+        select 'like:' || l.post_id || ':' || l.user_id, 'like', l.user_id, l.post_id, p.body, l.created_at
+        from likes l
+        join posts p on p.id = l.post_id
+        where p.author = uid and l.user_id <> uid
+        union all
+        select 'follow:' || f.follower, 'follow', f.follower, null, null, f.created_at
+        from follows f
+        where f.followee = uid
+    )
+    select i.key, i.kind, i.actor, prof.handle, i.post_id, left(i.excerpt, 80), i.created_at
+    from items i (key, kind, actor, post_id, excerpt, created_at)
+    left join profiles prof on prof.id = i.actor
+    where since is null or i.created_at > since
+    order by i.created_at, i.key
+    limit least(max_results, 500);
 $$;
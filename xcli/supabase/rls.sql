@@ -52,3 +52,17 @@ create policy "likes_rw" on likes
 create policy "restraints_rw" on restraints
     for all using (allowed = auth.uid() or exists(select 1 from posts p where p.id = restraints.post_id and p.author = auth.uid()))
     with check (allowed = auth.uid() or exists(select 1 from posts p where p.id = restraints.post_id and p.author = auth.uid()));
+
+-- Direct messages: only the two participants can read a message, only the
+-- sender can write it, and only the recipient can mark it read.
+alter table messages enable row level security;
+
+create policy "messages_select" on messages
+    for select using (sender = auth.uid() or recipient = auth.uid());
+
+create policy "messages_insert" on messages
+    for insert with check (sender = auth.uid());
+
+create policy "messages_mark_read" on messages
+    for update using (recipient = auth.uid())
+    with check (recipient = auth.uid());
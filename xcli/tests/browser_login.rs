@@ -0,0 +1,197 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use cli_twitter::supabase::browser_login::parse_request;
+use cli_twitter::supabase::secrets::EncryptedFileBackend;
+use cli_twitter::supabase::{BrowserLogin, SessionStore, SupabaseClient, SupabaseConfig};
+use jsonwebtoken::{EncodingKey, Header};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+fn client_for(port: u16) -> SupabaseClient {
+    let path =
+        std::env::temp_dir().join(format!("cli-twitter-login-{}.json", uuid::Uuid::new_v4()));
+    let cfg = SupabaseConfig {
+        url: format!("http://127.0.0.1:{}", port),
+        anon_key: "anon".into(),
+        login_url: Some(format!("http://127.0.0.1:{}/cli-login", port)),
+    };
+    SupabaseClient::new(
+        cfg,
+        SessionStore::with_backend(Arc::new(EncryptedFileBackend::new(path, "test"))),
+    )
+    .unwrap()
+}
+
+fn access_token(user_id: &str, email: &str, exp: i64) -> String {
+    jsonwebtoken::encode(
+        &Header::default(),
+        &json!({ "sub": user_id, "email": email, "exp": exp, "aud": "authenticated" }),
+        &EncodingKey::from_secret(b"not-checked"),
+    )
+    .unwrap()
+}
+
+/// Answers one `POST /auth/v1/token?grant_type=pkce`, checking the verifier
+/// against the challenge sent to the login page.
+fn token_endpoint(listener: TcpListener, challenge: String, token: String) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 1024];
+        let (head, body) = loop {
+            let n = socket.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+            let text = String::from_utf8_lossy(&buf).to_string();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|line| {
+                        line.to_ascii_lowercase()
+                            .strip_prefix("content-length: ")
+                            .map(str::to_string)
+                    })
+                    .and_then(|len| len.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    break (head.to_string(), body.to_string());
+                }
+            }
+        };
+        assert!(head.starts_with("POST /auth/v1/token?grant_type=pkce "));
+        let request: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(request["auth_code"], "the-code");
+        let verifier = request["code_verifier"].as_str().unwrap();
+        assert_eq!(
+            URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())),
+            challenge
+        );
+
+        let response = json!({
+            "access_token": token,
+            "refresh_token": "refresh",
+            "expires_in": 3600,
+            "user": { "id": "ignored", "email": null },
+        })
+        .to_string();
+        let reply = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            response.len(),
+            response
+        );
+        socket.write_all(reply.as_bytes()).await.unwrap();
+    })
+}
+
+fn query_param(url: &str, name: &str) -> String {
+    url::Url::parse(url)
+        .unwrap()
+        .query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+        .unwrap()
+}
+
+#[test]
+fn parses_request_line_and_query() {
+    let request = parse_request(
+        "GET /callback?state=abc&code=x%2By HTTP/1.1\r\nHost: 127.0.0.1:4000\r\nAccept: */*",
+    )
+    .unwrap();
+    assert_eq!(request.method, "GET");
+    assert_eq!(request.path, "/callback");
+    assert_eq!(request.query["state"], "abc");
+    assert_eq!(request.query["code"], "x+y");
+
+    assert!(parse_request("GET /callback").is_err());
+    assert!(parse_request("GET /callback SPDY/3").is_err());
+    assert!(parse_request("GET / HTTP/1.1\r\nnot a header").is_err());
+}
+
+#[test]
+fn passkey_login_requires_a_login_url() {
+    let cfg = SupabaseConfig {
+        url: "http://127.0.0.1:9".into(),
+        anon_key: "anon".into(),
+        login_url: None,
+    };
+    let err = cfg.login_url().unwrap_err();
+    assert!(err.to_string().contains("SUPABASE_LOGIN_URL"));
+}
+
+#[tokio::test]
+async fn browser_login_exchanges_code_and_saves_session() {
+    let server = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+    let supabase = client_for(server.local_addr().unwrap().port());
+    let user_id = uuid::Uuid::new_v4().to_string();
+    let exp = time::OffsetDateTime::now_utc().unix_timestamp() + 3600;
+
+    let login = BrowserLogin::bind(Duration::from_secs(5)).await.unwrap();
+    let authorize = login
+        .authorize_url(supabase.cfg.login_url().unwrap())
+        .unwrap();
+    assert!(authorize.starts_with(&format!("{}/cli-login?", supabase.cfg.url)));
+    assert_eq!(query_param(&authorize, "code_challenge_method"), "s256");
+    let endpoint = token_endpoint(
+        server,
+        query_param(&authorize, "code_challenge"),
+        access_token(&user_id, "me@example.com", exp),
+    );
+    let redirect = query_param(&authorize, "redirect_to");
+
+    let task = {
+        let supabase = supabase.clone();
+        tokio::spawn(async move { login.complete(&supabase).await })
+    };
+    let browser = reqwest::Client::new();
+    let favicon = redirect.replace("/callback", "/favicon.ico");
+    assert_eq!(browser.get(favicon).send().await.unwrap().status(), 404);
+    let page = browser
+        .get(format!("{}&code=the-code", redirect))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(page.status(), 200);
+    assert!(page.text().await.unwrap().contains("me@example.com"));
+
+    let session = task.await.unwrap().unwrap();
+    endpoint.await.unwrap();
+    assert_eq!(session.user_id, user_id);
+    assert_eq!(session.email, "me@example.com");
+    assert_eq!(session.tokens.expires_at.unix_timestamp(), exp);
+    assert_eq!(supabase.restore_session().await.unwrap(), Some(session));
+}
+
+#[tokio::test]
+async fn browser_login_rejects_wrong_state() {
+    let supabase = client_for(9);
+    let login = BrowserLogin::bind(Duration::from_secs(5)).await.unwrap();
+    let redirect = login.redirect_uri().unwrap();
+    let forged = format!(
+        "{}?state=forged&code=the-code",
+        redirect.split('?').next().unwrap()
+    );
+
+    let task = {
+        let supabase = supabase.clone();
+        tokio::spawn(async move { login.complete(&supabase).await })
+    };
+    let page = reqwest::get(forged).await.unwrap();
+    assert_eq!(page.status(), 400);
+    assert!(page.text().await.unwrap().contains("Sign-in failed"));
+    let err = task.await.unwrap().unwrap_err();
+    assert!(err.to_string().contains("state"));
+    assert_eq!(supabase.restore_session().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn browser_login_times_out() {
+    let supabase = client_for(9);
+    let login = BrowserLogin::bind(Duration::from_millis(50)).await.unwrap();
+    let err = login.complete(&supabase).await.unwrap_err();
+    assert!(err.to_string().contains("timed out"));
+}
@@ -0,0 +1,406 @@
+mod support;
+
+use cli_twitter::data::cache::CacheDao;
+use cli_twitter::domain::{
+    AudienceScope, FeedCursor, FeedFilter, Post, Profile, SearchQuery, Thread,
+};
+use support::temp_db;
+use time::macros::{datetime, offset};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+#[test]
+fn like_mirroring_updates_cached_post() {
+    let db = temp_db();
+    let cache = CacheDao::new(&db);
+    let mut post = Post::new(Uuid::new_v4(), "hello", AudienceScope::Public);
+    post.like_count = 2;
+    let user = Uuid::new_v4().to_string();
+    cache.upsert_post(&user, &post).unwrap();
+    let id = post.id.to_string();
+
+    let liked = cache.set_like(&user, &id, true).unwrap().unwrap();
+    assert!(liked.liked);
+    assert_eq!(liked.like_count, 3);
+    // Liking twice must not double count.
+    let again = cache.set_like(&user, &id, true).unwrap().unwrap();
+    assert_eq!(again.like_count, 3);
+
+    let unliked = cache.set_like(&user, &id, false).unwrap().unwrap();
+    assert!(!unliked.liked);
+    assert_eq!(unliked.like_count, 2);
+}
+
+#[test]
+fn offline_feed_respects_filter() {
+    let db = temp_db();
+    let cache = CacheDao::new(&db);
+    let me = Uuid::new_v4();
+    let friend = Uuid::new_v4();
+    let stranger = Uuid::new_v4();
+    let viewer = me.to_string();
+    cache
+        .upsert_post(&viewer, &Post::new(me, "mine", AudienceScope::Private))
+        .unwrap();
+    cache
+        .upsert_post(&viewer, &Post::new(friend, "friend", AudienceScope::Public))
+        .unwrap();
+    cache
+        .upsert_post(
+            &viewer,
+            &Post::new(stranger, "stranger", AudienceScope::Public),
+        )
+        .unwrap();
+    cache
+        .set_follow(&me.to_string(), &friend.to_string(), true)
+        .unwrap();
+
+    let bodies = |filter| {
+        let mut bodies = cache
+            .list_feed(&filter, &me.to_string(), None, 10)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.body)
+            .collect::<Vec<_>>();
+        bodies.sort();
+        bodies
+    };
+    assert_eq!(bodies(FeedFilter::Global), vec!["friend", "stranger"]);
+    assert_eq!(bodies(FeedFilter::Following), vec!["friend", "mine"]);
+    assert_eq!(bodies(FeedFilter::Me), vec!["mine"]);
+
+    assert!(cache
+        .feed_synced_at(&viewer, &FeedFilter::Me)
+        .unwrap()
+        .is_none());
+    cache.mark_feed_synced(&viewer, &FeedFilter::Me).unwrap();
+    assert!(cache
+        .feed_synced_at(&viewer, &FeedFilter::Me)
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+fn offline_feed_pages_with_cursor() {
+    let db = temp_db();
+    let cache = CacheDao::new(&db);
+    let author = Uuid::new_v4();
+    let user = author.to_string();
+    for minutes in 0..5 {
+        let mut post = Post::new(author, format!("post {}", minutes), AudienceScope::Public);
+        post.created_at =
+            OffsetDateTime::now_utc().replace_nanosecond(0).unwrap() - Duration::minutes(minutes);
+        cache.upsert_post(&user, &post).unwrap();
+    }
+    let first = cache
+        .list_feed(&FeedFilter::Global, &user, None, 2)
+        .unwrap();
+    assert_eq!(first.len(), 2);
+    assert_eq!(first[0].body, "post 0");
+
+    let cursor = FeedCursor::after(first.last().unwrap());
+    cache
+        .set_feed_cursor(&user, &FeedFilter::Global, Some(&cursor))
+        .unwrap();
+    assert_eq!(
+        cache.feed_cursor(&user, &FeedFilter::Global).unwrap(),
+        Some(cursor)
+    );
+    assert_eq!(
+        cache.last_feed_filter(&user).unwrap(),
+        Some(FeedFilter::Global)
+    );
+
+    let second = cache
+        .list_feed(&FeedFilter::Global, &user, Some(&cursor), 10)
+        .unwrap();
+    let bodies = second.into_iter().map(|p| p.body).collect::<Vec<_>>();
+    assert_eq!(bodies, vec!["post 2", "post 3", "post 4"]);
+}
+
+#[test]
+fn cached_posts_are_scoped_to_the_viewer() {
+    let db = temp_db();
+    let cache = CacheDao::new(&db);
+    let team = Uuid::new_v4().to_string();
+    let personal = Uuid::new_v4().to_string();
+    let post = Post::new(Uuid::new_v4(), "team only", AudienceScope::Restrained);
+    let id = post.id.to_string();
+    cache.upsert_post(&team, &post).unwrap();
+    cache.set_like(&team, &id, true).unwrap();
+    cache.mark_feed_synced(&team, &FeedFilter::Global).unwrap();
+
+    assert!(cache.get_post(&personal, &id).unwrap().is_none());
+    assert!(cache
+        .list_feed(&FeedFilter::Global, &personal, None, 10)
+        .unwrap()
+        .is_empty());
+    assert!(cache.list_posts(&personal, 10).unwrap().is_empty());
+    assert!(cache
+        .feed_synced_at(&personal, &FeedFilter::Global)
+        .unwrap()
+        .is_none());
+
+    // The same post fetched by the other account keeps its own like state.
+    cache.upsert_post(&personal, &post).unwrap();
+    assert!(!cache.get_post(&personal, &id).unwrap().unwrap().liked);
+    assert!(cache.get_post(&team, &id).unwrap().unwrap().liked);
+}
+
+#[test]
+fn search_ranks_and_follows_cache_changes() {
+    let db = temp_db();
+    let cache = CacheDao::new(&db);
+    let viewer = Uuid::new_v4().to_string();
+    let now = OffsetDateTime::now_utc();
+    let post = |body: &str, handle: &str, age_days: i64| {
+        let mut post = Post::new(Uuid::new_v4(), body, AudienceScope::Public);
+        post.author_handle = Some(handle.into());
+        post.created_at = now - Duration::days(age_days);
+        post
+    };
+    let mut strong = post("rust rust rust and more rust", "alice", 1);
+    let weak = post(
+        "a long post that mentions rust once among many other words",
+        "bob",
+        2,
+    );
+    let old = post("rusty old post", "alice", 400);
+    let other = post("nothing to see here", "carol", 1);
+    for p in [&strong, &weak, &old, &other] {
+        cache.upsert_post(&viewer, p).unwrap();
+    }
+    cache
+        .upsert_post(
+            &Uuid::new_v4().to_string(),
+            &post("rust elsewhere", "dave", 1),
+        )
+        .unwrap();
+
+    let query = |text: &str| SearchQuery {
+        text: text.into(),
+        ..Default::default()
+    };
+    let ids = |posts: Vec<Post>| posts.into_iter().map(|p| p.id).collect::<Vec<_>>();
+    let found = ids(cache.search_posts(&viewer, &query("Rust"), 10).unwrap());
+    assert_eq!(found.len(), 3);
+    assert_eq!(found[0], strong.id);
+    assert!(found.contains(&weak.id) && found.contains(&old.id));
+    // FTS syntax in user input is matched literally, not interpreted.
+    assert!(cache
+        .search_posts(&viewer, &query("rust OR NEAR("), 10)
+        .unwrap()
+        .is_empty());
+
+    let filtered = SearchQuery {
+        author: Some("ALICE".into()),
+        since: Some((now - Duration::days(30)).date()),
+        ..query("rust")
+    };
+    assert_eq!(
+        ids(cache.search_posts(&viewer, &filtered, 10).unwrap()),
+        [strong.id]
+    );
+
+    strong.body = "now about go".into();
+    cache.upsert_post(&viewer, &strong).unwrap();
+    assert_eq!(
+        ids(cache.search_posts(&viewer, &query("go"), 10).unwrap()),
+        [strong.id]
+    );
+    assert!(!ids(cache.search_posts(&viewer, &query("rust"), 10).unwrap()).contains(&strong.id));
+}
+
+#[test]
+fn search_hits_stay_out_of_the_offline_feed() {
+    let db = temp_db();
+    let cache = CacheDao::new(&db);
+    let viewer = Uuid::new_v4().to_string();
+    let hit = Post::new(Uuid::new_v4(), "found by search", AudienceScope::Public);
+    cache.upsert_search_hit(&viewer, &hit).unwrap();
+
+    let query = SearchQuery {
+        text: "search".into(),
+        ..Default::default()
+    };
+    assert_eq!(cache.search_posts(&viewer, &query, 10).unwrap().len(), 1);
+    assert!(cache
+        .list_feed(&FeedFilter::Global, &viewer, None, 10)
+        .unwrap()
+        .is_empty());
+
+    // A feed fetch of the same post makes it part of the feed, and a later
+    // search hit doesn't take it out again.
+    cache.upsert_post(&viewer, &hit).unwrap();
+    cache.upsert_search_hit(&viewer, &hit).unwrap();
+    let feed = cache
+        .list_feed(&FeedFilter::Global, &viewer, None, 10)
+        .unwrap();
+    assert_eq!(feed.len(), 1);
+    assert_eq!(feed[0].id, hit.id);
+}
+
+#[test]
+fn thread_is_loaded_and_nested_under_root() {
+    let db = temp_db();
+    let cache = CacheDao::new(&db);
+    let viewer = Uuid::new_v4().to_string();
+    let start = OffsetDateTime::now_utc() - Duration::hours(1);
+    let mut at = 0;
+    let mut post = |body: &str, parent: Option<&Post>| {
+        at += 1;
+        let mut post = Post::new(Uuid::new_v4(), body, AudienceScope::Public);
+        post.created_at = start + Duration::minutes(at);
+        if let Some(parent) = parent {
+            post.reply_to = Some(parent.id);
+            post.root_id = Some(parent.thread_root());
+            post.reply_to_handle = Some("alice".into());
+        }
+        post
+    };
+    let root = post("standup: what's blocking you?", None);
+    let first = post("waiting on review", Some(&root));
+    let second = post("nothing today", Some(&root));
+    let nested = post("I'll review it now", Some(&first));
+    let unrelated = post("lunch?", None);
+    for p in [&nested, &second, &root, &unrelated, &first] {
+        cache.upsert_post(&viewer, p).unwrap();
+    }
+
+    let cached = cache
+        .get_post(&viewer, &nested.id.to_string())
+        .unwrap()
+        .unwrap();
+    assert_eq!(cached.reply_to, Some(first.id));
+    assert_eq!(cached.root_id, Some(root.id));
+    assert_eq!(cached.reply_to_handle.as_deref(), Some("alice"));
+
+    let posts = cache.thread(&viewer, &root.id.to_string()).unwrap();
+    assert_eq!(posts.len(), 4);
+    let thread = Thread::build(cached.thread_root(), posts);
+    let layout = thread
+        .entries
+        .iter()
+        .map(|entry| (entry.post.id, entry.depth))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        layout,
+        [(root.id, 0), (first.id, 1), (nested.id, 2), (second.id, 1)]
+    );
+}
+
+#[test]
+fn cached_profiles_fill_in_missing_author_handles() {
+    let db = temp_db();
+    let cache = CacheDao::new(&db);
+    let viewer = Uuid::new_v4().to_string();
+    let alice = Uuid::new_v4();
+    let early = Post::new(alice, "before the profile", AudienceScope::Public);
+    cache.upsert_post(&viewer, &early).unwrap();
+
+    cache
+        .upsert_profile(&Profile {
+            id: alice,
+            handle: "alice".into(),
+            display_name: None,
+            created_at: OffsetDateTime::now_utc(),
+        })
+        .unwrap();
+    let backfilled = cache
+        .get_post(&viewer, &early.id.to_string())
+        .unwrap()
+        .unwrap();
+    assert_eq!(backfilled.author_handle.as_deref(), Some("alice"));
+
+    let late = Post::new(alice, "after the profile", AudienceScope::Public);
+    cache.upsert_post(&viewer, &late).unwrap();
+    let posts = cache
+        .posts_by_author(&viewer, &alice.to_string(), 10)
+        .unwrap();
+    assert_eq!(posts.len(), 2);
+    assert!(posts
+        .iter()
+        .all(|p| p.author_handle.as_deref() == Some("alice")));
+
+    let mut live = [Post::new(alice, "from realtime", AudienceScope::Public)];
+    cache.fill_handles(&mut live).unwrap();
+    assert_eq!(live[0].author_handle.as_deref(), Some("alice"));
+    assert_eq!(cache.search_profiles("ali", 5).unwrap().len(), 1);
+    assert!(cache.search_profiles("a_i", 5).unwrap().is_empty());
+}
+
+#[test]
+fn tag_and_mention_feeds_use_indexed_spans() {
+    let db = temp_db();
+    let cache = CacheDao::new(&db);
+    let me = Uuid::new_v4();
+    let viewer = me.to_string();
+    let other = Uuid::new_v4();
+    for body in [
+        "learning #Rust today",
+        "hey @me, seen #rustlang?",
+        "email me@example.com",
+    ] {
+        cache
+            .upsert_post(&viewer, &Post::new(other, body, AudienceScope::Public))
+            .unwrap();
+    }
+    cache
+        .upsert_profile(&Profile {
+            id: me,
+            handle: "Me".into(),
+            display_name: None,
+            created_at: OffsetDateTime::now_utc(),
+        })
+        .unwrap();
+
+    let bodies = |filter: FeedFilter| {
+        cache
+            .list_feed(&filter, &viewer, None, 10)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.body)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        bodies(FeedFilter::tag("#rust")),
+        vec!["learning #Rust today"]
+    );
+    assert_eq!(
+        bodies(FeedFilter::Mentions),
+        vec!["hey @me, seen #rustlang?"]
+    );
+    assert_eq!(cache.index_pending_spans().unwrap(), 0);
+}
+
+#[test]
+fn feed_orders_sub_second_and_offset_timestamps() {
+    let db = temp_db();
+    let cache = CacheDao::new(&db);
+    let author = Uuid::new_v4();
+    let user = author.to_string();
+    let base = datetime!(2026-10-18 09:00 UTC);
+    for (body, at) in [
+        ("whole", base),
+        ("half", base + Duration::milliseconds(500)),
+        (
+            "offset",
+            (base + Duration::seconds(1)).to_offset(offset!(+2)),
+        ),
+    ] {
+        let mut post = Post::new(author, body, AudienceScope::Public);
+        post.created_at = at;
+        cache.upsert_post(&user, &post).unwrap();
+    }
+    let mut seen = Vec::new();
+    let mut cursor = None;
+    while let Some(post) = cache
+        .list_feed(&FeedFilter::Global, &user, cursor.as_ref(), 1)
+        .unwrap()
+        .pop()
+    {
+        cursor = Some(FeedCursor::after(&post));
+        seen.push(post.body);
+    }
+    assert_eq!(seen, vec!["offset", "half", "whole"]);
+}
@@ -0,0 +1,254 @@
+mod support;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use clap::Parser;
+use cli_twitter::cli::{self, render, Action, Cli, Failure, Invalid, OutputFormat};
+use cli_twitter::commands::{Command, CommandContext, CommandOutput};
+use cli_twitter::data::{reports::ReportDao, tasks::TaskDao};
+use cli_twitter::domain::{
+    AudienceScope, FeedFilter, NewTask, Post, Task, TaskPriority, TaskQuery, TaskStatus,
+};
+use cli_twitter::supabase::{ApiError, LoginRequired, SupabaseError};
+use serde_json::{json, Value};
+use support::{client_for, session, temp_db, Reply, StubServer};
+use time::macros::datetime;
+use uuid::Uuid;
+
+fn command(args: &[&str]) -> (Command, Option<usize>) {
+    let cli = Cli::try_parse_from(std::iter::once("xcli").chain(args.iter().copied())).unwrap();
+    match cli.action {
+        Some(Action::Client(action)) => {
+            let limit = action.limit();
+            (action.to_command().unwrap(), limit)
+        }
+        other => panic!("not a client command: {:?}", other),
+    }
+}
+
+#[test]
+fn subcommands_map_onto_commands() {
+    assert_eq!(
+        command(&["feed", "--filter", "following", "--limit", "5"]),
+        (
+            Command::Feed {
+                filter: FeedFilter::Following
+            },
+            Some(5)
+        )
+    );
+    assert_eq!(
+        command(&["tasks", "ls", "--status", "open"]).0,
+        Command::TasksList {
+            query: TaskQuery {
+                status: Some(TaskStatus::Open),
+                ..Default::default()
+            }
+        }
+    );
+    assert_eq!(
+        command(&[
+            "tasks",
+            "add",
+            "ship",
+            "--priority",
+            "high",
+            "--tag",
+            "infra",
+            "--tag",
+            "ops"
+        ])
+        .0,
+        Command::TasksAdd {
+            task: NewTask {
+                title: "ship".into(),
+                priority: TaskPriority::High,
+                tags: vec!["infra".into(), "ops".into()],
+                ..Default::default()
+            }
+        }
+    );
+    assert_eq!(
+        command(&["post", "hello", "--audience", "private"]).0,
+        Command::Post {
+            text: "hello".into(),
+            audience: AudienceScope::Private
+        }
+    );
+    assert!(matches!(
+        command(&["post", "later", "--in", "2h"]).0,
+        Command::SchedulePost { .. }
+    ));
+    assert_eq!(command(&["whoami"]).0, Command::WhoAmI);
+
+    let cli = Cli::try_parse_from(["xcli", "whoami", "--output", "ndjson"]).unwrap();
+    assert_eq!(cli.output, OutputFormat::Ndjson);
+    assert!(Cli::try_parse_from(["xcli", "feed", "--filter", "nope"]).is_err());
+    let Some(Action::Client(action)) = Cli::try_parse_from(["xcli", "like", "not-a-uuid"])
+        .unwrap()
+        .action
+    else {
+        panic!("expected like");
+    };
+    let err = action.to_command().unwrap_err();
+    assert_eq!(Failure::of(&err), Failure::Invalid);
+}
+
+#[test]
+fn json_and_ndjson_carry_posts_tasks_and_reports() {
+    let mut post = Post::new(Uuid::new_v4(), "hello\nworld", AudienceScope::Public);
+    post.created_at = datetime!(2026-10-18 09:00 UTC);
+    let task = Task {
+        id: 7,
+        title: "write docs".into(),
+        description: String::new(),
+        status: TaskStatus::Open,
+        priority: TaskPriority::Normal,
+        due_at: None,
+        tags: Vec::new(),
+        created_at: datetime!(2026-10-18 08:00 UTC),
+        updated_at: datetime!(2026-10-18 08:00 UTC),
+        done_at: None,
+    };
+    let output = CommandOutput {
+        message: "Fetched 1 posts".into(),
+        feed: Some(vec![post.clone()]),
+        tasks: Some(vec![task]),
+        ..Default::default()
+    };
+
+    let text = render(&output, OutputFormat::Text).unwrap();
+    assert_eq!(text.lines().count(), 2);
+    assert!(text.ends_with("hello world"));
+
+    let json: Value = serde_json::from_str(&render(&output, OutputFormat::Json).unwrap()).unwrap();
+    assert_eq!(json["message"], "Fetched 1 posts");
+    assert_eq!(json["posts"][0]["id"], post.id.to_string());
+    assert_eq!(json["posts"][0]["created_at"], "2026-10-18T09:00:00Z");
+    assert_eq!(json["tasks"][0]["status"], "open");
+    assert!(json.get("reports").is_none());
+
+    let lines = render(&output, OutputFormat::Ndjson)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["type"], "post");
+    assert_eq!(lines[1]["type"], "task");
+    assert_eq!(lines[1]["id"], 7);
+
+    let empty = render(
+        &CommandOutput {
+            message: "Signed in as me@example.com".into(),
+            ..Default::default()
+        },
+        OutputFormat::Ndjson,
+    )
+    .unwrap();
+    assert_eq!(
+        serde_json::from_str::<Value>(&empty).unwrap(),
+        json!({"type": "message", "message": "Signed in as me@example.com"})
+    );
+}
+
+#[test]
+fn failures_map_to_distinct_exit_codes() {
+    let failure = |err: anyhow::Error| Failure::of(&err).code();
+    assert_eq!(failure(Invalid("bad".into()).into()), 2);
+    assert_eq!(failure(LoginRequired.into()), 3);
+    assert_eq!(
+        failure(
+            anyhow::Error::from(SupabaseError::Network("refused".into())).context("fetching feed")
+        ),
+        4
+    );
+    let rejected = |code: &str| {
+        SupabaseError::Rejected(ApiError::parse(
+            400,
+            &json!({ "error_code": code, "msg": "nope" }).to_string(),
+        ))
+    };
+    assert_eq!(failure(rejected("invalid_credentials").into()), 3);
+    assert_eq!(failure(rejected("PGRST100").into()), 2);
+    assert_eq!(failure(anyhow::anyhow!("disk full")), 1);
+}
+
+#[tokio::test]
+async fn posts_scheduled_in_the_past_exit_as_invalid() {
+    let me = session("access-1");
+    let server = StubServer::start(|_| Reply::json(200, json!([]))).await;
+    let client = client_for(&server, &me).await;
+    let db = temp_db();
+
+    let (cmd, limit) = command(&["post", "x", "--at", "2020-01-01T09:00"]);
+    let err = cli::execute(CommandContext::new(&db, &client), cmd, limit)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().ends_with("is in the past"));
+    assert_eq!(Failure::of(&err).code(), 2);
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn finishing_a_done_task_again_is_invalid() {
+    let me = session("access-1");
+    let server = StubServer::start(|_| Reply::json(200, json!([]))).await;
+    let client = client_for(&server, &me).await;
+    let db = temp_db();
+    let dao = TaskDao::new(&db);
+    let task = dao.add("ship", "").unwrap();
+    let done = dao.mark_done(task.id).unwrap().unwrap();
+
+    let (cmd, limit) = command(&["tasks", "done", &task.id.to_string()]);
+    let err = cli::execute(CommandContext::new(&db, &client), cmd, limit)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), format!("task {} is already done", task.id));
+    assert_eq!(Failure::of(&err).code(), 2);
+    assert_eq!(dao.get(task.id).unwrap().done_at, done.done_at);
+    assert!(ReportDao::new(&db).latest(10).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn feed_limit_loads_further_pages() {
+    let me = session("access-1");
+    let calls = AtomicUsize::new(0);
+    let server = StubServer::start(move |req| match req.path() {
+        "/rest/v1/rpc/feed_following" => {
+            let page = calls.fetch_add(1, Ordering::SeqCst);
+            let posts = (0..if page < 2 { 50 } else { 0 })
+                .map(|i| {
+                    json!({
+                        "id": Uuid::new_v4(),
+                        "author": Uuid::new_v4(),
+                        "body": format!("page {} post {}", page, i),
+                        "audience": "public",
+                        "created_at": format!("2026-10-{:02}T{:02}:{:02}:00Z", 18 - page, 23 - i / 60, 59 - i % 60)
+                    })
+                })
+                .collect::<Vec<_>>();
+            Reply::json(200, json!(posts))
+        }
+        _ => Reply::json(200, json!([])),
+    })
+    .await;
+    let client = client_for(&server, &me).await;
+    let db = temp_db();
+    let ctx = CommandContext::new(&db, &client);
+
+    let (command, limit) = command(&["feed", "--filter", "following", "--limit", "60"]);
+    let output = cli::execute(ctx, command, limit).await.unwrap();
+    let feed = output.feed.unwrap();
+    assert_eq!(feed.len(), 60);
+    assert_eq!(feed[59].body, "page 1 post 9");
+    assert_eq!(output.message, "Fetched 60 following posts");
+    assert_eq!(
+        server
+            .requests()
+            .iter()
+            .filter(|r| r.path() == "/rest/v1/rpc/feed_following")
+            .count(),
+        2
+    );
+}
@@ -1,5 +1,9 @@
-use cli_twitter::commands::{self, Command};
-use cli_twitter::domain::AudienceScope;
+use cli_twitter::commands::parse::tokenize;
+use cli_twitter::commands::table::{help_text, COMMANDS};
+use cli_twitter::commands::{self, Command, ParseError};
+use cli_twitter::domain::{AudienceScope, FeedFilter, NewTask, TaskPriority, TaskSort, TaskStatus};
+use time::macros::datetime;
+use time::{Duration, OffsetDateTime};
 
 #[test]
 fn parse_post_command() {
@@ -24,3 +28,475 @@ fn parse_login_command() {
         _ => panic!("unexpected command"),
     }
 }
+
+#[test]
+fn parse_unlike_requires_uuid() {
+    let id = uuid::Uuid::new_v4();
+    let cmd = commands::parse_command(&format!("/unlike {}", id)).unwrap();
+    assert_eq!(
+        cmd,
+        Command::Unlike {
+            post_id: id.to_string()
+        }
+    );
+    assert!(commands::parse_command("/unlike not-a-post").is_err());
+    assert_eq!(
+        commands::parse_command("/unfollow @alice").unwrap(),
+        Command::Unfollow {
+            handle: "alice".into()
+        }
+    );
+}
+
+#[test]
+fn parse_feed_more() {
+    assert_eq!(
+        commands::parse_command("/feed more").unwrap(),
+        Command::FeedMore { filter: None }
+    );
+    assert_eq!(
+        commands::parse_command("/feed more following").unwrap(),
+        Command::FeedMore {
+            filter: Some(FeedFilter::Following)
+        }
+    );
+}
+
+#[test]
+fn parse_account_commands() {
+    assert_eq!(
+        commands::parse_command("/account").unwrap(),
+        Command::AccountList
+    );
+    assert_eq!(
+        commands::parse_command("/account switch me@example.com").unwrap(),
+        Command::AccountSwitch {
+            name: "me@example.com".into()
+        }
+    );
+    assert_eq!(
+        commands::parse_command("/account remove work").unwrap(),
+        Command::AccountRemove {
+            name: "work".into()
+        }
+    );
+    assert!(commands::parse_command("/account switch").is_err());
+}
+
+#[test]
+fn parse_search_command() {
+    let Command::Search { query } =
+        commands::parse_command("/search \"offline mode\" author:@alice since:2026-01-01").unwrap()
+    else {
+        panic!("expected search");
+    };
+    assert_eq!(query.text, "offline mode");
+    assert_eq!(query.author.as_deref(), Some("alice"));
+    assert_eq!(query.since.unwrap().to_string(), "2026-01-01");
+    assert_eq!(query.match_expr().unwrap(), "\"offline\"* \"mode\"*");
+
+    let Command::Search { query } = commands::parse_command("/search rust tui").unwrap() else {
+        panic!("expected search");
+    };
+    assert_eq!(query.terms(), ["rust", "tui"]);
+    assert!(commands::parse_command("/search since:2026-13-01 x").is_err());
+    assert!(commands::parse_command("/search author:@bob").is_err());
+}
+
+#[test]
+fn parse_reply_and_thread() {
+    let id = uuid::Uuid::new_v4();
+    assert_eq!(
+        commands::parse_command(&format!("/reply {} \"done, shipping today\"", id)).unwrap(),
+        Command::Reply {
+            post_id: id.to_string(),
+            text: "done, shipping today".into(),
+            audience: None,
+        }
+    );
+    assert_eq!(
+        commands::parse_command(&format!("/reply {} \"team only\" audience:restrained", id))
+            .unwrap(),
+        Command::Reply {
+            post_id: id.to_string(),
+            text: "team only".into(),
+            audience: Some(AudienceScope::Restrained),
+        }
+    );
+    assert!(commands::parse_command(&format!("/reply {}", id)).is_err());
+    assert_eq!(
+        commands::parse_command(&format!("/thread {}", id)).unwrap(),
+        Command::Thread {
+            post_id: id.to_string()
+        }
+    );
+}
+
+#[test]
+fn parses_direct_message_commands() {
+    assert_eq!(
+        commands::parse_command("/dm @bob \"lunch?\"").unwrap(),
+        Command::Dm {
+            handle: "bob".into(),
+            text: Some("lunch?".into())
+        }
+    );
+    assert_eq!(
+        commands::parse_command("/dm bob").unwrap(),
+        Command::Dm {
+            handle: "bob".into(),
+            text: None
+        }
+    );
+    assert!(commands::parse_command("/dm").is_err());
+    assert_eq!(commands::parse_command("/inbox").unwrap(), Command::Inbox);
+}
+
+#[test]
+fn parses_profile_commands() {
+    assert_eq!(
+        commands::parse_command("/profile @alice").unwrap(),
+        Command::Profile {
+            handle: "alice".into()
+        }
+    );
+    assert_eq!(
+        commands::parse_command("/profile edit display_name:\"Alice L\"").unwrap(),
+        Command::ProfileEdit {
+            display_name: Some("Alice L".into())
+        }
+    );
+    assert_eq!(
+        commands::parse_command("/profile edit display_name:\"\"").unwrap(),
+        Command::ProfileEdit { display_name: None }
+    );
+    assert!(commands::parse_command("/profile edit").is_err());
+    assert_eq!(
+        commands::parse_command("/who bob").unwrap(),
+        Command::Who {
+            query: "bob".into()
+        }
+    );
+    assert!(commands::parse_command("/who").is_err());
+}
+
+#[test]
+fn parses_tag_and_mention_feeds() {
+    assert_eq!(
+        commands::parse_command("/feed #Rust").unwrap(),
+        Command::Feed {
+            filter: FeedFilter::Tag("rust".into())
+        }
+    );
+    assert_eq!(
+        commands::parse_command("/feed mentions").unwrap(),
+        Command::Feed {
+            filter: FeedFilter::Mentions
+        }
+    );
+    assert_eq!(
+        commands::parse_command("/feed more #rust").unwrap(),
+        Command::FeedMore {
+            filter: Some(FeedFilter::Tag("rust".into()))
+        }
+    );
+    assert!(commands::parse_command("/feed #").is_err());
+}
+
+#[test]
+fn parses_compose_and_draft_commands() {
+    assert_eq!(
+        commands::parse_command("/compose \"hello\" audience:private").unwrap(),
+        Command::Compose {
+            text: Some("hello".into()),
+            audience: AudienceScope::Private
+        }
+    );
+    assert_eq!(commands::parse_command("/drafts").unwrap(), Command::Drafts);
+    assert_eq!(
+        commands::parse_command("/drafts open #3").unwrap(),
+        Command::DraftOpen { id: 3 }
+    );
+    assert_eq!(
+        commands::parse_command("/drafts rm 3").unwrap(),
+        Command::DraftDelete { id: 3 }
+    );
+    assert!(commands::parse_command("/drafts send").is_err());
+}
+
+#[test]
+fn parses_scheduled_posts() {
+    assert_eq!(
+        commands::parse_command("/post \"lunch at:noon?\" at:2026-10-19T09:00:00Z").unwrap(),
+        Command::SchedulePost {
+            text: "lunch at:noon?".into(),
+            audience: AudienceScope::Public,
+            send_at: datetime!(2026-10-19 09:00 UTC),
+        }
+    );
+    let before = OffsetDateTime::now_utc();
+    let Command::SchedulePost { send_at, .. } =
+        commands::parse_command("/post \"later\" in:1h30m").unwrap()
+    else {
+        panic!("expected a scheduled post");
+    };
+    assert!(send_at - before >= Duration::minutes(90));
+    assert!(send_at - before < Duration::minutes(91));
+    assert!(commands::parse_command("/post \"x\" in:2x").is_err());
+    assert!(commands::parse_command("/post \"x\" in:2h at:2026-10-19T09:00").is_err());
+
+    assert_eq!(
+        commands::parse_command("/scheduled").unwrap(),
+        Command::Scheduled
+    );
+    assert_eq!(
+        commands::parse_command("/scheduled edit #4 \"new text\" audience:private").unwrap(),
+        Command::ScheduledEdit {
+            id: 4,
+            text: Some("new text".into()),
+            audience: Some(AudienceScope::Private),
+            send_at: None,
+        }
+    );
+    assert_eq!(
+        commands::parse_command("/scheduled cancel 4").unwrap(),
+        Command::ScheduledCancel { id: 4 }
+    );
+}
+
+#[test]
+fn parses_notification_commands() {
+    assert_eq!(
+        commands::parse_command("/notifications").unwrap(),
+        Command::Notifications
+    );
+    assert_eq!(
+        commands::parse_command("/notifications open #7").unwrap(),
+        Command::NotificationOpen { id: 7 }
+    );
+    assert_eq!(
+        commands::parse_command("/notifications read").unwrap(),
+        Command::NotificationsRead
+    );
+    assert!(commands::parse_command("/notifications open x").is_err());
+}
+
+#[test]
+fn tokenizer_handles_quotes_escapes_and_options() {
+    let tokens = tokenize(r#"/post "say \"hi\"" audience:private note\ two key:"a b""#).unwrap();
+    let texts = tokens
+        .iter()
+        .map(|token| (token.key.as_deref(), token.text.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        texts,
+        [
+            (None, "/post"),
+            (None, "say \"hi\""),
+            (Some("audience"), "private"),
+            (None, "note two"),
+            (Some("key"), "a b"),
+        ]
+    );
+    assert_eq!(tokens[1].span, 6..18);
+
+    let tokens = tokenize(r#"desc="a b" due=2d x\=y ==z"#).unwrap();
+    let texts = tokens
+        .iter()
+        .map(|token| (token.key.as_deref(), token.text.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        texts,
+        [
+            (Some("desc"), "a b"),
+            (Some("due"), "2d"),
+            (None, "x=y"),
+            (None, "==z"),
+        ]
+    );
+    let Command::TasksEdit { changes, .. } =
+        commands::parse_command(r#"/tasks edit 4 desc="a b" priority=high"#).unwrap()
+    else {
+        panic!("expected edit");
+    };
+    assert_eq!(changes.description.as_deref(), Some("a b"));
+    assert_eq!(changes.priority, Some(TaskPriority::High));
+    // Commands without such an option keep `name=value` as a word.
+    assert_eq!(
+        commands::parse_command("/settings set theme=dark").unwrap(),
+        Command::SettingsSet {
+            key: "theme".into(),
+            value: "dark".into()
+        }
+    );
+    assert_eq!(
+        commands::parse_command("/post x=y audience=private").unwrap(),
+        Command::Post {
+            text: "x=y".into(),
+            audience: AudienceScope::Private
+        }
+    );
+    assert_eq!(
+        commands::parse_command(r#"/post "she said \"ship it\"""#).unwrap(),
+        Command::Post {
+            text: "she said \"ship it\"".into(),
+            audience: AudienceScope::Public
+        }
+    );
+    assert_eq!(
+        commands::parse_command("/login email:me@example.com pw:\"p@ss word\"").unwrap(),
+        Command::Login {
+            email: "me@example.com".into(),
+            password: "p@ss word".into()
+        }
+    );
+    assert_eq!(
+        commands::parse_command("/tasks add \"title only\"").unwrap(),
+        Command::TasksAdd {
+            task: NewTask {
+                title: "title only".into(),
+                ..Default::default()
+            }
+        }
+    );
+}
+
+#[test]
+fn parse_errors_are_typed_and_positioned() {
+    let err = |input: &str| commands::parse_command(input).unwrap_err();
+
+    let input = "/tasks ls foo";
+    let e = err(input);
+    assert!(matches!(e, ParseError::Invalid { what: "status", .. }));
+    assert_eq!(&input[e.span()], "foo");
+
+    let input = "/post \"hi\" audiense:private";
+    let e = err(input);
+    assert!(matches!(
+        e,
+        ParseError::UnknownOption {
+            command: "post",
+            ..
+        }
+    ));
+    assert_eq!(&input[e.span()], "audiense:private");
+
+    let input = "/post \"hi\" at:2026-10-19T09:00 in:2h";
+    let e = err(input);
+    assert!(matches!(e, ParseError::ConflictingOptions { .. }));
+    assert_eq!(&input[e.span()], "in:2h");
+
+    let input = "/post \"unclosed";
+    assert_eq!(err(input), ParseError::UnclosedQuote { span: 6..15 });
+
+    let input = "/reply ";
+    let e = err(input);
+    assert!(matches!(
+        e,
+        ParseError::Missing {
+            what: "post id",
+            ..
+        }
+    ));
+    assert_eq!(e.span(), 6..6);
+
+    assert!(matches!(
+        err("/drafts wipe"),
+        ParseError::UnknownSubcommand { .. }
+    ));
+    assert!(matches!(
+        err("/follow @a extra"),
+        ParseError::Unexpected { .. }
+    ));
+    assert!(matches!(
+        err("/post \"a\" audience:public audience:private"),
+        ParseError::DuplicateOption { .. }
+    ));
+    assert_eq!(
+        err("/nope x"),
+        ParseError::UnknownCommand {
+            name: "nope".into(),
+            span: 0..5
+        }
+    );
+    assert!(matches!(err("hello"), ParseError::NotACommand { .. }));
+}
+
+#[test]
+fn help_comes_from_the_command_table() {
+    assert_eq!(
+        commands::parse_command("/help /post").unwrap(),
+        Command::Help {
+            command: Some("post".into())
+        }
+    );
+    assert!(commands::parse_command("/help nope").is_err());
+    let all = help_text(None);
+    for spec in COMMANDS {
+        assert!(all.contains(spec.synopsis), "{} missing", spec.name);
+        assert!(
+            spec.synopsis.chars().count() <= 23,
+            "{} too wide",
+            spec.name
+        );
+    }
+    let post = help_text(Some("post"));
+    assert!(post
+        .lines()
+        .nth(1)
+        .unwrap()
+        .trim()
+        .starts_with("/post \"text\""));
+}
+
+#[test]
+fn tasks_take_priorities_due_dates_tags_and_filters() {
+    let Command::TasksAdd { task } =
+        commands::parse_command("/tasks add \"rotate keys\" priority:urgent due:2d tag:infra,#Ops")
+            .unwrap()
+    else {
+        panic!("expected add");
+    };
+    assert_eq!(task.priority, TaskPriority::Urgent);
+    assert!(task.due_at.is_some());
+    assert_eq!(task.tags, vec!["infra".to_string(), "ops".to_string()]);
+
+    let Command::TasksList { query } =
+        commands::parse_command("/tasks ls due<7d tag:infra sort:priority in_progress").unwrap()
+    else {
+        panic!("expected ls");
+    };
+    assert_eq!(query.status, Some(TaskStatus::InProgress));
+    assert_eq!(query.tag.as_deref(), Some("infra"));
+    assert_eq!(query.sort, TaskSort::Priority);
+    assert!(query.due_before.is_some());
+
+    let Command::TasksEdit { id, changes } =
+        commands::parse_command("/tasks edit #4 title:\"new title\" due:none").unwrap()
+    else {
+        panic!("expected edit");
+    };
+    assert_eq!(id, 4);
+    assert_eq!(changes.title.as_deref(), Some("new title"));
+    assert_eq!(changes.due_at, Some(None));
+
+    assert_eq!(
+        commands::parse_command("/tasks reopen 4").unwrap(),
+        Command::TasksSetStatus {
+            id: 4,
+            status: TaskStatus::Open
+        }
+    );
+    assert!(matches!(
+        commands::parse_command("/tasks edit 4").unwrap_err(),
+        ParseError::Missing { .. }
+    ));
+    assert!(matches!(
+        commands::parse_command("/tasks ls due<someday").unwrap_err(),
+        ParseError::Invalid { what: "due", .. }
+    ));
+    assert!(matches!(
+        commands::parse_command("/tasks tag 4").unwrap_err(),
+        ParseError::Missing { .. }
+    ));
+}
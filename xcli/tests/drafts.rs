@@ -0,0 +1,126 @@
+mod support;
+
+use cli_twitter::commands::{self, Command, CommandContext};
+use cli_twitter::data::drafts::DraftDao;
+use cli_twitter::domain::{AudienceScope, Draft, POST_MAX_CHARS};
+use cli_twitter::ui::Composer;
+use serde_json::json;
+use support::{client_for, session, temp_db, Reply, StubServer};
+use uuid::Uuid;
+
+#[test]
+fn composer_edits_several_lines_and_cycles_the_audience() {
+    let mut composer = Composer::new(Draft::new("", AudienceScope::Public));
+    "say \"hi\"".chars().for_each(|c| composer.insert(c));
+    composer.newline();
+    composer.insert('x');
+    assert_eq!(composer.text(), "say \"hi\"\nx");
+    assert_eq!(composer.char_count(), 10);
+    assert_eq!(composer.cursor(), (1, 1));
+
+    composer.home();
+    composer.backspace();
+    assert_eq!(composer.text(), "say \"hi\"x");
+    composer.up();
+    composer.newline();
+    assert_eq!(composer.lines(), ["say \"hi\"", "x"]);
+    assert!(composer.take_dirty());
+    assert!(!composer.take_dirty());
+
+    composer.cycle_audience();
+    assert_eq!(composer.audience(), AudienceScope::Restrained);
+    composer.cycle_audience();
+    composer.cycle_audience();
+    assert_eq!(composer.audience(), AudienceScope::Public);
+}
+
+#[test]
+fn drafts_are_saved_updated_and_removed() {
+    let db = temp_db();
+    let dao = DraftDao::new(&db);
+    let mut draft = Draft::new("first\nsecond", AudienceScope::Private);
+    let id = dao.save("me", &draft).unwrap();
+    draft.id = Some(id);
+    draft.body = "first, edited".into();
+    assert_eq!(dao.save("me", &draft).unwrap(), id);
+
+    let drafts = dao.list("me").unwrap();
+    assert_eq!(drafts.len(), 1);
+    assert_eq!(drafts[0].body, "first, edited");
+    assert_eq!(drafts[0].audience, AudienceScope::Private);
+
+    // Another account neither sees nor overwrites it.
+    assert!(dao.list("someone-else").unwrap().is_empty());
+    assert!(dao.get("someone-else", id).unwrap().is_none());
+    assert!(!dao.delete("someone-else", id).unwrap());
+    let theirs = dao.save("someone-else", &draft).unwrap();
+    assert_ne!(theirs, id);
+    assert_eq!(dao.get("me", id).unwrap().unwrap().body, "first, edited");
+
+    assert!(dao.delete("me", id).unwrap());
+    assert!(dao.get("me", id).unwrap().is_none());
+    // Drafts saved while signed out are offered to any account.
+    let unowned = dao
+        .save("", &Draft::new("signed out", AudienceScope::Public))
+        .unwrap();
+    assert_eq!(dao.get("me", unowned).unwrap().unwrap().body, "signed out");
+    assert_eq!(
+        Draft::new("first\nsecond", AudienceScope::Public).preview(20),
+        "first…"
+    );
+}
+
+#[tokio::test]
+async fn sending_a_draft_posts_it_verbatim_and_drops_it() {
+    let me = session("access-1");
+    let author = me.user_id.clone();
+    let server = StubServer::start(move |req| match req.path() {
+        "/rest/v1/posts" => {
+            let body = req.json();
+            Reply::json(
+                201,
+                json!([{
+                    "id": Uuid::new_v4(),
+                    "author": author,
+                    "body": body["body"],
+                    "audience": body["audience"],
+                    "created_at": "2026-10-18T09:00:00Z"
+                }]),
+            )
+        }
+        _ => Reply::json(200, json!([])),
+    })
+    .await;
+    let client = client_for(&server, &me).await;
+    let db = temp_db();
+    let ctx = CommandContext::new(&db, &client);
+    let dao = DraftDao::new(&db);
+    let body = "she said \"ship it\"\nso we did";
+    let id = dao
+        .save(&me.user_id, &Draft::new(body, AudienceScope::Restrained))
+        .unwrap();
+    let too_long = dao
+        .save(
+            &me.user_id,
+            &Draft::new("a".repeat(POST_MAX_CHARS + 1), AudienceScope::Public),
+        )
+        .unwrap();
+
+    let err = commands::execute(ctx, Command::DraftSend { id: too_long })
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("the limit is 280"));
+
+    let cmd = commands::parse_command(&format!("/drafts send {}", id)).unwrap();
+    let output = commands::execute(ctx, cmd).await.unwrap();
+    assert!(output.message.starts_with("Posted "));
+    let sent = server
+        .requests()
+        .into_iter()
+        .find(|r| r.path() == "/rest/v1/posts")
+        .unwrap();
+    assert_eq!(sent.json()["body"], body);
+    assert_eq!(sent.json()["audience"], "restrained");
+    assert!(dao.get(&me.user_id, id).unwrap().is_none());
+    assert!(dao.get(&me.user_id, too_long).unwrap().is_some());
+}
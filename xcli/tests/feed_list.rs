@@ -0,0 +1,69 @@
+use cli_twitter::domain::{AudienceScope, Post};
+use cli_twitter::ui::{self, FeedList, FeedView};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use uuid::Uuid;
+
+fn post(body: &str) -> Post {
+    let mut post = Post::new(Uuid::new_v4(), body, AudienceScope::Public);
+    post.author_handle = Some("alice".into());
+    post
+}
+
+fn text(line: &Line) -> String {
+    line.spans
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect()
+}
+
+#[test]
+fn cursor_stays_within_the_feed_and_asks_for_more_at_the_end() {
+    let mut list = FeedList::default();
+    list.reset(3);
+    assert_eq!(list.selected(), Some(0));
+
+    assert!(!list.move_by(-1, 3));
+    assert_eq!(list.selected(), Some(0));
+    assert!(!list.move_by(10, 3));
+    assert_eq!(list.selected(), Some(2));
+    assert!(list.move_by(1, 3), "moving past the last post loads more");
+    assert_eq!(list.selected(), Some(2));
+
+    list.first(3);
+    assert_eq!(list.selected(), Some(0));
+    list.last(3);
+    list.clamp(1);
+    assert_eq!(list.selected(), Some(0));
+    list.reset(0);
+    assert_eq!(list.selected(), None);
+    assert!(!list.move_by(1, 0));
+}
+
+#[test]
+fn page_size_counts_wrapped_posts_that_fit() {
+    let posts = vec![post("short"), post(&"word ".repeat(12)), post("short")];
+    let items = ui::feed_items(&posts, &FeedView::Feed, 40);
+    assert_eq!(items[0].height(), 1);
+    assert!(items[1].height() > 1);
+
+    let mut list = FeedList::default();
+    list.reset(posts.len());
+    list.measure(&items, 1 + items[1].height());
+    assert_eq!(list.page(), 2);
+}
+
+#[test]
+fn wrap_keeps_styles_and_splits_long_words() {
+    let red = Style::default().fg(Color::Red);
+    let line = Line::from(vec![
+        Span::raw("hello "),
+        Span::styled("big world", red),
+        Span::raw(" abcdefghij"),
+    ]);
+    let lines = ui::wrap_line(line, 8);
+    let texts: Vec<_> = lines.iter().map(text).collect();
+    assert_eq!(texts, ["hello ", "big ", "world ", "abcdefgh", "ij"]);
+    assert_eq!(lines[1].spans[0].style, red);
+    assert_eq!(lines[2].spans[0].style, red);
+}
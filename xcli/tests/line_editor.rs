@@ -0,0 +1,130 @@
+mod support;
+
+use cli_twitter::commands::complete::complete;
+use cli_twitter::data::{cache::CacheDao, drafts::DraftDao, history::HistoryDao, tasks::TaskDao};
+use cli_twitter::domain::{AudienceScope, Draft, Profile};
+use cli_twitter::ui::LineEditor;
+use support::temp_db;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+fn typed(text: &str) -> LineEditor {
+    let mut editor = LineEditor::default();
+    text.chars().for_each(|c| editor.insert(c));
+    editor
+}
+
+#[test]
+fn edits_in_the_middle_of_the_line() {
+    let mut editor = typed("/post \"hello wrld\"");
+    editor.word_left();
+    editor.right();
+    editor.insert('o');
+    assert_eq!(editor.text(), "/post \"hello world\"");
+
+    editor.end();
+    editor.delete_word();
+    assert_eq!(editor.text(), "/post \"hello ");
+    editor.word_left();
+    editor.delete_to_end();
+    assert_eq!(editor.text(), "/post ");
+    editor.home();
+    editor.delete();
+    assert_eq!(editor.text(), "post ");
+    editor.end();
+    editor.insert('é');
+    editor.left();
+    editor.delete_to_start();
+    assert_eq!((editor.text(), editor.cursor()), ("é", 0));
+}
+
+#[test]
+fn recalls_history_and_searches_it_backwards() {
+    let mut editor = LineEditor::new(vec![
+        "/feed global".into(),
+        "/post \"one\"".into(),
+        "/feed me".into(),
+    ]);
+    editor.insert('/');
+    editor.history_prev();
+    editor.history_prev();
+    assert_eq!(editor.text(), "/post \"one\"");
+    editor.history_next();
+    editor.history_next();
+    assert_eq!(
+        editor.text(),
+        "/",
+        "Down past the newest entry restores the draft"
+    );
+
+    editor.search_older();
+    "feed".chars().for_each(|c| editor.search_push(c));
+    assert_eq!(editor.text(), "/feed me");
+    editor.search_older();
+    assert_eq!(editor.text(), "/feed global");
+    editor.search_push('x');
+    assert!(editor.search_failed());
+    editor.cancel_search();
+    assert_eq!(editor.text(), "/");
+    assert_eq!(editor.search_query(), None);
+}
+
+#[test]
+fn history_persists_without_passwords_or_repeats() {
+    let db = temp_db();
+    let history = HistoryDao::new(&db);
+    let now = OffsetDateTime::now_utc();
+    assert!(history.push("/feed me", now).unwrap());
+    assert!(!history.push("/feed me", now).unwrap());
+    assert!(!history.push("/login email:a@b.c pw:secret", now).unwrap());
+    assert!(history.push("/inbox", now).unwrap());
+    assert_eq!(history.recent(10).unwrap(), ["/feed me", "/inbox"]);
+    assert_eq!(history.recent(1).unwrap(), ["/inbox"]);
+}
+
+#[test]
+fn completes_commands_subcommands_handles_and_task_ids() {
+    let db = temp_db();
+    let cache = CacheDao::new(&db);
+    for handle in ["alice", "alfred", "bob"] {
+        cache
+            .upsert_profile(&Profile {
+                id: Uuid::new_v4(),
+                handle: handle.into(),
+                display_name: None,
+                created_at: OffsetDateTime::now_utc(),
+            })
+            .unwrap();
+    }
+    let task = TaskDao::new(&db).add("write docs", "").unwrap();
+    let drafts = DraftDao::new(&db);
+    let draft = drafts
+        .save("me", &Draft::new("mine", AudienceScope::Public))
+        .unwrap();
+    drafts
+        .save("someone-else", &Draft::new("theirs", AudienceScope::Public))
+        .unwrap();
+
+    let mut editor = typed("/noti");
+    let completion = complete(&db, "me", editor.text()).unwrap();
+    editor.complete(completion.start, &completion.candidates);
+    assert_eq!(editor.text(), "/notifications ");
+
+    let mut editor = typed("/dm @al");
+    let completion = complete(&db, "me", editor.text()).unwrap();
+    assert_eq!(completion.candidates, ["@alfred", "@alice"]);
+    editor.complete(completion.start, &completion.candidates);
+    assert_eq!(editor.text(), "/dm @al", "no longer shared prefix to add");
+
+    let candidates = |line: &str| complete(&db, "me", line).unwrap().candidates;
+    assert_eq!(candidates("/tasks d"), ["done"]);
+    assert_eq!(candidates("/tasks done "), [task.id.to_string()]);
+    assert_eq!(candidates("/settings s"), ["show", "set"]);
+    assert_eq!(candidates("/feed m"), ["me", "mentions", "more"]);
+    assert_eq!(
+        candidates("/post \"hi\" audience:p"),
+        ["audience:public", "audience:private"]
+    );
+    assert_eq!(candidates("/follow "), ["@alfred", "@alice", "@bob"]);
+    assert_eq!(candidates("/drafts rm "), [draft.to_string()]);
+}
@@ -0,0 +1,124 @@
+mod support;
+
+use cli_twitter::commands::{self, Command, CommandContext};
+use cli_twitter::data::messages::MessageDao;
+use cli_twitter::domain::Mailbox;
+use serde_json::json;
+use support::{client_for, session, temp_db, Reply, StubServer};
+use uuid::Uuid;
+
+fn message_row(id: Uuid, sender: &str, recipient: &str, body: &str, at: &str) -> serde_json::Value {
+    json!({
+        "id": id,
+        "sender": sender,
+        "recipient": recipient,
+        "body": body,
+        "created_at": at,
+        "read_at": null,
+        "sender_profile": null,
+        "recipient_profile": { "handle": "bob" }
+    })
+}
+
+#[tokio::test]
+async fn dm_delivers_through_postgrest_and_caches_the_message() {
+    let me = session("access-1");
+    let bob = Uuid::new_v4();
+    let sent = message_row(
+        Uuid::new_v4(),
+        &me.user_id,
+        &bob.to_string(),
+        "hi bob",
+        "2026-10-18T09:00:00Z",
+    );
+    let server = StubServer::start(move |req| match (req.method.as_str(), req.path()) {
+        ("GET", "/rest/v1/profiles") => Reply::json(
+            200,
+            json!([{ "id": bob, "handle": "bob", "display_name": null, "created_at": null }]),
+        ),
+        ("POST", "/rest/v1/messages") => Reply::json(201, json!([sent.clone()])),
+        _ => Reply::json(200, json!([])),
+    })
+    .await;
+    let client = client_for(&server, &me).await;
+    let db = temp_db();
+
+    let cmd = commands::parse_command("/dm @bob \"hi bob\"").unwrap();
+    let output = commands::execute(CommandContext::new(&db, &client), cmd)
+        .await
+        .unwrap();
+
+    let post = server
+        .requests()
+        .into_iter()
+        .find(|r| r.method == "POST")
+        .unwrap();
+    assert_eq!(post.json()["recipient"], bob.to_string());
+    assert_eq!(post.json()["sender"], me.user_id.as_str());
+    assert_eq!(post.json()["body"], "hi bob");
+
+    let Some(Mailbox::Conversation {
+        peer,
+        peer_handle,
+        messages,
+    }) = output.mailbox
+    else {
+        panic!("expected a conversation");
+    };
+    assert_eq!(peer, Some(bob));
+    assert_eq!(peer_handle, "bob");
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].body, "hi bob");
+
+    let conversations = MessageDao::new(&db).conversations(&me.user_id).unwrap();
+    assert_eq!(conversations.len(), 1);
+    assert_eq!(conversations[0].peer_handle.as_deref(), Some("bob"));
+    assert_eq!(conversations[0].unread, 0);
+}
+
+#[tokio::test]
+async fn opening_a_conversation_marks_it_read() {
+    let me = session("access-1");
+    let bob = Uuid::new_v4();
+    let mut incoming = message_row(
+        Uuid::new_v4(),
+        &bob.to_string(),
+        &me.user_id,
+        "are you there?",
+        "2026-10-18T09:00:00Z",
+    );
+    incoming["sender_profile"] = json!({ "handle": "bob" });
+    incoming["recipient_profile"] = json!({ "handle": "me" });
+    let server = StubServer::start(move |req| match (req.method.as_str(), req.path()) {
+        ("GET", "/rest/v1/messages") if !req.target.contains("created_at=gt") => {
+            Reply::json(200, json!([incoming.clone()]))
+        }
+        ("PATCH", "/rest/v1/messages") => Reply::json(204, json!(null)),
+        _ => Reply::json(200, json!([])),
+    })
+    .await;
+    let client = client_for(&server, &me).await;
+    let db = temp_db();
+    let ctx = CommandContext::new(&db, &client);
+
+    let output = commands::execute(ctx, Command::Inbox).await.unwrap();
+    assert_eq!(output.message, "1 conversation, 1 unread");
+    let Some(Mailbox::Inbox(conversations)) = output.mailbox else {
+        panic!("expected the inbox");
+    };
+    assert_eq!(conversations[0].peer, bob);
+    assert_eq!(conversations[0].last_body, "are you there?");
+
+    let cmd = commands::parse_command("/dm @bob").unwrap();
+    commands::execute(ctx, cmd).await.unwrap();
+
+    let patch = server
+        .requests()
+        .into_iter()
+        .find(|r| r.method == "PATCH")
+        .expect("read marks are sent to the server");
+    assert!(patch.target.contains(&format!("sender=eq.{}", bob)));
+    assert!(patch.target.contains("read_at=is.null"));
+    assert!(patch.json()["read_at"].is_string());
+    assert_eq!(MessageDao::new(&db).unread_count(&me.user_id).unwrap(), 0);
+}
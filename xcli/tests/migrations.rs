@@ -0,0 +1,141 @@
+use cli_twitter::data::migrations::{self, MigrationState};
+use cli_twitter::data::{outbox::OutboxDao, AppDatabase, DatabaseConfig};
+use cli_twitter::domain::OutboxPayload;
+
+fn temp_cfg() -> DatabaseConfig {
+    let path = std::env::temp_dir().join(format!("cli-twitter-test-{}.db", uuid::Uuid::new_v4()));
+    DatabaseConfig { path }
+}
+
+#[test]
+fn fresh_database_is_at_latest_version() {
+    let db = AppDatabase::open(&temp_cfg()).unwrap();
+    let conn = db.connection();
+    assert_eq!(
+        migrations::current_version(&conn).unwrap(),
+        migrations::latest_version()
+    );
+    assert!(migrations::status(&conn)
+        .unwrap()
+        .iter()
+        .all(|s| s.state == MigrationState::Applied));
+}
+
+#[test]
+fn migrate_down_and_back_up() {
+    let db = AppDatabase::open(&temp_cfg()).unwrap();
+    let mut conn = db.connection();
+    let log = migrations::migrate_to(&mut conn, 1).unwrap();
+    assert!(!log.is_empty());
+    assert_eq!(migrations::current_version(&conn).unwrap(), 1);
+    assert!(conn.prepare("SELECT attempts FROM outbox").is_err());
+
+    migrations::migrate_to(&mut conn, migrations::latest_version()).unwrap();
+    assert_eq!(
+        migrations::current_version(&conn).unwrap(),
+        migrations::latest_version()
+    );
+    assert!(migrations::migrate_to(&mut conn, migrations::latest_version() + 1).is_err());
+}
+
+#[test]
+fn legacy_user_version_is_adopted() {
+    let cfg = temp_cfg();
+    {
+        let conn = rusqlite::Connection::open(&cfg.path).unwrap();
+        conn.execute_batch(include_str!("../migrations/sqlite/001_init.sql"))
+            .unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+    }
+    let db = AppDatabase::open(&cfg).unwrap();
+    let conn = db.connection();
+    let status = migrations::status(&conn).unwrap();
+    assert_eq!(status[0].state, MigrationState::Applied);
+    assert_eq!(
+        migrations::current_version(&conn).unwrap(),
+        migrations::latest_version()
+    );
+}
+
+#[test]
+fn queued_posts_and_messages_get_a_stable_id() {
+    let db = AppDatabase::open(&temp_cfg()).unwrap();
+    {
+        let mut conn = db.connection();
+        migrations::migrate_to(&mut conn, 15).unwrap();
+        for (kind, payload) in [
+            ("post", r#"{"kind":"post","text":"hi","audience":"public"}"#),
+            (
+                "message",
+                r#"{"kind":"message","handle":"bob","text":"hi"}"#,
+            ),
+        ] {
+            conn.execute(
+                "INSERT INTO outbox(kind, payload, created_at, user_id) VALUES(?1, ?2, '2026-10-18T09:00:00Z', 'u')",
+                rusqlite::params![kind, payload],
+            )
+            .unwrap();
+        }
+        migrations::migrate_to(&mut conn, migrations::latest_version()).unwrap();
+    }
+    let ids = || {
+        OutboxDao::new(&db)
+            .list("u", 10)
+            .unwrap()
+            .into_iter()
+            .map(|item| match item.payload {
+                OutboxPayload::Post { id, .. } | OutboxPayload::Message { id, .. } => id,
+                other => panic!("unexpected payload {:?}", other),
+            })
+            .collect::<Vec<_>>()
+    };
+    let first = ids();
+    assert_eq!(first.len(), 2);
+    assert_ne!(first[0], first[1]);
+    assert_eq!(ids(), first);
+
+    // A payload that somehow still lacks an id is an error, not a new id.
+    db.connection()
+        .execute(
+            "UPDATE outbox SET payload = json_remove(payload, '$.id') WHERE kind = 'post'",
+            [],
+        )
+        .unwrap();
+    assert!(OutboxDao::new(&db).list("u", 10).is_err());
+}
+
+#[test]
+fn stored_timestamps_become_fixed_width() {
+    let db = AppDatabase::open(&temp_cfg()).unwrap();
+    let mut conn = db.connection();
+    migrations::migrate_to(&mut conn, 14).unwrap();
+    for (id, at) in [
+        ("a", "2026-10-18T09:00:00Z"),
+        ("b", "2026-10-18T09:00:00.5Z"),
+        ("c", "2026-10-18T11:00:01.123456+02:00"),
+    ] {
+        conn.execute(
+            "INSERT INTO cache_posts(viewer, id, author, body, audience, created_at)
+             VALUES('v', ?1, 'u', 'x', 'public', ?2)",
+            rusqlite::params![id, at],
+        )
+        .unwrap();
+    }
+    migrations::migrate_to(&mut conn, migrations::latest_version()).unwrap();
+    let mut stmt = conn
+        .prepare("SELECT created_at FROM cache_posts ORDER BY created_at")
+        .unwrap();
+    let stored = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .unwrap()
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(
+        stored,
+        vec![
+            "2026-10-18T09:00:00.000000Z",
+            "2026-10-18T09:00:00.500000Z",
+            "2026-10-18T09:00:01.123000Z",
+        ]
+    );
+}
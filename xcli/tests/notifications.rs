@@ -0,0 +1,154 @@
+mod support;
+
+use cli_twitter::commands::{self, Command, CommandContext};
+use cli_twitter::data::notifications::NotificationDao;
+use cli_twitter::domain::NotificationKind;
+use serde_json::json;
+use support::{client_for, session, temp_db, Reply, StubServer};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn notifications_are_stored_once_and_open_the_liked_post() {
+    let me = session("access-1");
+    let bob = Uuid::new_v4();
+    let post_id = Uuid::new_v4();
+    let author = me.user_id.clone();
+    let server = StubServer::start(move |req| match req.path() {
+        "/rest/v1/rpc/notifications" => Reply::json(
+            200,
+            json!([
+                {
+                    "key": format!("like:{}:{}", post_id, bob),
+                    "kind": "like",
+                    "actor": bob,
+                    "actor_handle": "bob",
+                    "post_id": post_id,
+                    "excerpt": "first post",
+                    "created_at": "2026-10-18T09:05:00Z"
+                },
+                {
+                    "key": format!("follow:{}", bob),
+                    "kind": "follow",
+                    "actor": bob,
+                    "actor_handle": "bob",
+                    "post_id": null,
+                    "excerpt": null,
+                    "created_at": "2026-10-18T09:00:00Z"
+                },
+                {
+                    "key": "poke:1",
+                    "kind": "poke",
+                    "actor": bob,
+                    "actor_handle": "bob",
+                    "post_id": null,
+                    "excerpt": null,
+                    "created_at": "2026-10-18T08:00:00Z"
+                }
+            ]),
+        ),
+        "/rest/v1/rpc/thread" => Reply::json(
+            200,
+            json!([{
+                "id": post_id,
+                "author": author,
+                "body": "first post",
+                "audience": "public",
+                "created_at": "2026-10-18T08:55:00Z"
+            }]),
+        ),
+        _ => Reply::json(404, json!({ "message": "not found" })),
+    })
+    .await;
+    let client = client_for(&server, &me).await;
+    let db = temp_db();
+    let ctx = CommandContext::new(&db, &client);
+
+    let output = commands::execute(ctx, Command::Notifications)
+        .await
+        .unwrap();
+    assert_eq!(output.message, "2 notifications, 2 unread");
+    // A second poll returns the same rows; they are not stored twice.
+    let output = commands::execute(ctx, Command::Notifications)
+        .await
+        .unwrap();
+    assert_eq!(output.message, "2 notifications, 2 unread");
+    let notifications = output.notifications.unwrap();
+    assert_eq!(notifications[0].kind, NotificationKind::Like);
+    assert_eq!(notifications[1].kind, NotificationKind::Follow);
+    let polls: Vec<_> = server
+        .requests()
+        .into_iter()
+        .filter(|r| r.path() == "/rest/v1/rpc/notifications")
+        .collect();
+    assert!(polls[0].json()["since"].is_null());
+    assert_eq!(polls[1].json()["since"], "2026-10-18T09:05:00Z");
+
+    let cmd =
+        commands::parse_command(&format!("/notifications open {}", notifications[0].id)).unwrap();
+    let output = commands::execute(ctx, cmd).await.unwrap();
+    assert_eq!(
+        output.message,
+        "@bob liked your post: \"first post\" · Thread with 0 replies"
+    );
+    assert_eq!(output.thread.unwrap().entries[0].post.id, post_id);
+    let dao = NotificationDao::new(&db);
+    assert_eq!(dao.unread_count(&me.user_id).unwrap(), 1);
+
+    let output = commands::execute(ctx, Command::NotificationsRead)
+        .await
+        .unwrap();
+    assert_eq!(output.message, "Marked 1 notification read");
+    assert!(output.notifications.unwrap().iter().all(|n| !n.is_unread()));
+    assert_eq!(dao.unread_count(&me.user_id).unwrap(), 0);
+}
+
+#[tokio::test]
+async fn opening_a_follow_shows_the_follower_profile() {
+    let me = session("access-1");
+    let bob = Uuid::new_v4();
+    let server = StubServer::start(move |req| match req.path() {
+        "/rest/v1/rpc/notifications" => Reply::json(
+            200,
+            json!([{
+                "key": format!("follow:{}", bob),
+                "kind": "follow",
+                "actor": bob,
+                "actor_handle": "bob",
+                "post_id": null,
+                "excerpt": null,
+                "created_at": "2026-10-18T09:00:00Z"
+            }]),
+        ),
+        "/rest/v1/rpc/profile_card" => Reply::json(
+            200,
+            json!([{
+                "id": bob,
+                "handle": "bob",
+                "display_name": null,
+                "created_at": "2026-02-01T10:00:00Z",
+                "followers": 3,
+                "following": 4
+            }]),
+        ),
+        _ => Reply::json(200, json!([])),
+    })
+    .await;
+    let client = client_for(&server, &me).await;
+    let db = temp_db();
+    let ctx = CommandContext::new(&db, &client);
+
+    let output = commands::execute(ctx, Command::Notifications)
+        .await
+        .unwrap();
+    let id = output.notifications.unwrap()[0].id;
+
+    let output = commands::execute(ctx, Command::NotificationOpen { id })
+        .await
+        .unwrap();
+    assert_eq!(output.profile.unwrap().profile.handle, "bob");
+    assert!(output.message.starts_with("@bob followed you · @bob"));
+    assert_eq!(
+        NotificationDao::new(&db).unread_count(&me.user_id).unwrap(),
+        0
+    );
+}
@@ -0,0 +1,123 @@
+mod support;
+
+use cli_twitter::data::{cache::CacheDao, outbox::OutboxDao};
+use cli_twitter::domain::{AudienceScope, OutboxPayload, OutboxStatus, Post};
+use cli_twitter::sync::outbox as sync_outbox;
+use support::temp_db;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+const USER: &str = "8d0c5d4e-55a4-4d8e-9a4b-0f6c1e2f3a4b";
+
+#[test]
+fn claimed_items_are_not_claimed_twice() {
+    let db = temp_db();
+    let dao = OutboxDao::new(&db);
+    let item = dao
+        .enqueue(
+            USER,
+            &OutboxPayload::Post {
+                id: Uuid::new_v4(),
+                text: "hello".into(),
+                audience: AudienceScope::Public,
+                reply_to: None,
+            },
+        )
+        .unwrap();
+    // Another signed-in account never picks up this item.
+    assert!(dao
+        .claim_due("someone-else", OffsetDateTime::now_utc(), 10)
+        .unwrap()
+        .is_empty());
+    assert!(dao.list("someone-else", 10).unwrap().is_empty());
+    let claimed = dao.claim_due(USER, OffsetDateTime::now_utc(), 10).unwrap();
+    assert_eq!(claimed.len(), 1);
+    assert_eq!(claimed[0].status, OutboxStatus::Sending);
+    assert!(dao.claim(USER, item.id).unwrap().is_none());
+    // A fresh claim may belong to another process that is still sending.
+    let now = OffsetDateTime::now_utc();
+    assert_eq!(dao.requeue_stale(now - Duration::minutes(15)).unwrap(), 0);
+    assert_eq!(dao.claim_due(USER, now, 10).unwrap().len(), 0);
+    assert_eq!(dao.requeue_stale(now + Duration::seconds(1)).unwrap(), 1);
+    assert!(dao.claim(USER, item.id).unwrap().is_some());
+}
+
+#[test]
+fn retry_backoff_and_manual_retry() {
+    let db = temp_db();
+    let dao = OutboxDao::new(&db);
+    let item = dao
+        .enqueue(
+            USER,
+            &OutboxPayload::Follow {
+                handle: "alice".into(),
+            },
+        )
+        .unwrap();
+    dao.claim(USER, item.id).unwrap().unwrap();
+    dao.mark_retry(
+        item.id,
+        "offline",
+        OffsetDateTime::now_utc() + Duration::minutes(5),
+    )
+    .unwrap();
+    assert!(dao
+        .claim_due(USER, OffsetDateTime::now_utc(), 10)
+        .unwrap()
+        .is_empty());
+
+    let stored = dao.get(item.id).unwrap().unwrap();
+    assert_eq!(stored.status, OutboxStatus::Pending);
+    assert_eq!(stored.attempts, 1);
+    assert_eq!(stored.last_error.as_deref(), Some("offline"));
+
+    dao.mark_failed(item.id, "gave up").unwrap();
+    assert!(dao.retry(USER, item.id).unwrap());
+    let claimed = dao.claim_due(USER, OffsetDateTime::now_utc(), 10).unwrap();
+    assert_eq!(claimed.len(), 1);
+    assert_eq!(claimed[0].attempts, 0);
+    dao.mark_sent(item.id).unwrap();
+    assert_eq!(dao.pending_count(USER).unwrap(), 0);
+    assert!(dao.remove(USER, item.id).unwrap().is_some());
+    assert!(dao.remove(USER, item.id).unwrap().is_none());
+}
+
+#[test]
+fn dropping_a_queued_like_rolls_back_the_cache() {
+    let db = temp_db();
+    let cache = CacheDao::new(&db);
+    let post = Post::new(Uuid::new_v4(), "hello", AudienceScope::Public);
+    cache.upsert_post(USER, &post).unwrap();
+    let payload = OutboxPayload::Like {
+        post_id: post.id.to_string(),
+    };
+    let item = OutboxDao::new(&db).enqueue(USER, &payload).unwrap();
+    sync_outbox::mirror(&db, USER, &payload, true).unwrap();
+    assert!(
+        cache
+            .get_post(USER, &post.id.to_string())
+            .unwrap()
+            .unwrap()
+            .liked
+    );
+
+    // Another signed-in account can neither retry nor drop it.
+    let dao = OutboxDao::new(&db);
+    assert_eq!(dao.pending_count("someone-else").unwrap(), 0);
+    assert_eq!(dao.pending_count(USER).unwrap(), 1);
+    assert!(!dao.retry("someone-else", item.id).unwrap());
+    assert!(!sync_outbox::drop_item(&db, "someone-else", item.id).unwrap());
+    assert!(
+        cache
+            .get_post(USER, &post.id.to_string())
+            .unwrap()
+            .unwrap()
+            .liked
+    );
+
+    assert!(sync_outbox::drop_item(&db, USER, item.id).unwrap());
+    let cached = cache.get_post(USER, &post.id.to_string()).unwrap().unwrap();
+    assert!(!cached.liked);
+    assert_eq!(cached.like_count, 0);
+    assert!(!sync_outbox::drop_item(&db, USER, item.id).unwrap());
+}
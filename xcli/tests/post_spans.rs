@@ -0,0 +1,47 @@
+use cli_twitter::domain::{parse_spans, FeedFilter, SpanKind};
+
+#[test]
+fn finds_mentions_hashtags_and_urls() {
+    let body = "Thanks @Alice! Notes at https://example.com/notes. #Rust #async";
+    let spans = parse_spans(body);
+    let found: Vec<_> = spans
+        .iter()
+        .map(|s| (s.kind, s.text(body), s.value.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (SpanKind::Mention, "@Alice", "alice"),
+            (
+                SpanKind::Url,
+                "https://example.com/notes",
+                "https://example.com/notes"
+            ),
+            (SpanKind::Hashtag, "#Rust", "rust"),
+            (SpanKind::Hashtag, "#async", "async"),
+        ]
+    );
+}
+
+#[test]
+fn sigils_inside_words_and_urls_are_plain_text() {
+    let body = "mail me@example.com, see issue #42 or a#b, https://x.dev/#anchor @@x";
+    let spans = parse_spans(body);
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0].kind, SpanKind::Url);
+    assert_eq!(spans[0].text(body), "https://x.dev/#anchor");
+}
+
+#[test]
+fn feed_filter_round_trips_through_strings() {
+    for filter in [
+        FeedFilter::Global,
+        FeedFilter::Mentions,
+        FeedFilter::tag("#Rust"),
+    ] {
+        assert_eq!(filter.to_string().parse::<FeedFilter>().unwrap(), filter);
+    }
+    assert_eq!(FeedFilter::tag("#Rust").to_string(), "#rust");
+    assert!("#not-a-tag".parse::<FeedFilter>().is_err());
+    assert!("#".parse::<FeedFilter>().is_err());
+}
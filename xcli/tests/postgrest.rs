@@ -0,0 +1,183 @@
+mod support;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use cli_twitter::domain::FeedFilter;
+use cli_twitter::supabase::error::user_message;
+use cli_twitter::supabase::SupabaseError;
+use reqwest::Method;
+use serde_json::json;
+use support::{client_for, session, Reply, StubServer};
+
+#[tokio::test]
+async fn expired_token_is_refreshed_and_request_replayed() {
+    let server = StubServer::start(|req| match req.path() {
+        "/auth/v1/token" => Reply::json(
+            200,
+            json!({
+                "access_token": "access-2",
+                "refresh_token": "refresh-2",
+                "expires_in": 3600,
+                "user": { "id": "ignored", "email": "me@example.com" }
+            }),
+        ),
+        "/rest/v1/rpc/feed_global" if req.bearer() == Some("access-2") => Reply::json(
+            200,
+            json!([{
+                "id": uuid::Uuid::new_v4(),
+                "author": uuid::Uuid::new_v4(),
+                "body": "after refresh",
+                "audience": "public",
+                "created_at": "2026-10-18T09:00:00Z"
+            }]),
+        ),
+        _ => Reply::json(401, json!({ "code": "PGRST301", "message": "JWT expired" })),
+    })
+    .await;
+    let session = session("access-1");
+    let client = client_for(&server, &session).await;
+
+    let posts = client
+        .fetch_feed(&session, &FeedFilter::Global, None, 20)
+        .await
+        .unwrap();
+    assert_eq!(posts[0].body, "after refresh");
+
+    let requests = server.requests();
+    let paths: Vec<_> = requests.iter().map(|r| r.path()).collect();
+    assert_eq!(
+        paths,
+        [
+            "/rest/v1/rpc/feed_global",
+            "/auth/v1/token",
+            "/rest/v1/rpc/feed_global"
+        ]
+    );
+    assert_eq!(requests[0].bearer(), Some("access-1"));
+    assert_eq!(requests[1].json()["refresh_token"], "refresh-1");
+    assert_eq!(requests[2].json()["uid"], session.user_id.as_str());
+    assert!(requests.iter().all(|r| r.headers["apikey"] == "anon"));
+
+    let stored = client.restore_session().await.unwrap().unwrap();
+    assert_eq!(stored.tokens.access_token, "access-2");
+    assert_eq!(stored.tokens.refresh_token, "refresh-2");
+
+    // Holding the old session doesn't resend the old token, so the rotated
+    // refresh token is never used again.
+    client
+        .fetch_feed(&session, &FeedFilter::Global, None, 20)
+        .await
+        .unwrap();
+    let requests = server.requests();
+    assert_eq!(requests.len(), 4);
+    assert_eq!(requests[3].bearer(), Some("access-2"));
+}
+
+#[tokio::test]
+async fn tokens_refreshed_by_another_process_are_picked_up() {
+    let server = StubServer::start(|req| match req.path() {
+        "/rest/v1/rpc/feed_global" if req.bearer() == Some("access-2") => {
+            Reply::json(200, json!([]))
+        }
+        "/auth/v1/token" => Reply::json(400, json!({ "error_code": "refresh_token_already_used" })),
+        _ => Reply::json(401, json!({ "code": "PGRST301", "message": "JWT expired" })),
+    })
+    .await;
+    let session = session("access-1");
+    let client = client_for(&server, &session).await;
+    let mut rotated = session.clone();
+    rotated.tokens.access_token = "access-2".into();
+    rotated.tokens.refresh_token = "refresh-2".into();
+    client.sessions.save_tokens(&rotated).await.unwrap();
+
+    client
+        .fetch_feed(&session, &FeedFilter::Global, None, 20)
+        .await
+        .unwrap();
+    let paths = server
+        .requests()
+        .iter()
+        .map(|r| r.path().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        ["/rest/v1/rpc/feed_global", "/rest/v1/rpc/feed_global"]
+    );
+}
+
+#[tokio::test]
+async fn postgrest_error_body_becomes_typed_error() {
+    let server = StubServer::start(|_| {
+        Reply::json(
+            403,
+            json!({
+                "code": "42501",
+                "message": "new row violates row-level security policy for table \"posts\"",
+                "details": null,
+                "hint": "check the author column"
+            }),
+        )
+    })
+    .await;
+    let session = session("access-1");
+    let client = client_for(&server, &session).await;
+
+    let err = client
+        .rest(Method::POST, "posts")
+        .json(&json!({ "body": "hi" }))
+        .execute(&session)
+        .await
+        .unwrap_err();
+    let Some(SupabaseError::Forbidden(api)) = SupabaseError::find(&err) else {
+        panic!("expected a forbidden error, got {:?}", err);
+    };
+    assert_eq!(api.status, 403);
+    assert_eq!(api.code.as_deref(), Some("42501"));
+    assert_eq!(api.hint.as_deref(), Some("check the author column"));
+    assert!(err.to_string().contains("row-level security"));
+    assert!(user_message(&err).contains("/account"));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let server = StubServer::start(|_| {
+        Reply::json(
+            400,
+            json!({ "code": "PGRST100", "message": "failed to parse filter" }),
+        )
+    })
+    .await;
+    let session = session("access-1");
+    let client = client_for(&server, &session).await;
+
+    let err = client.find_profile(&session, "alice").await.unwrap_err();
+    assert!(matches!(
+        SupabaseError::find(&err),
+        Some(SupabaseError::Rejected(api)) if api.code.as_deref() == Some("PGRST100")
+    ));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn rate_limit_waits_for_retry_after() {
+    let calls = AtomicUsize::new(0);
+    let server = StubServer::start(move |_| match calls.fetch_add(1, Ordering::SeqCst) {
+        0 => Reply::json(429, json!({ "message": "slow down" })).header("Retry-After", "1"),
+        1 => Reply::json(503, json!({ "message": "unavailable" })),
+        _ => Reply::json(200, json!([])),
+    })
+    .await;
+    let session = session("access-1");
+    let client = client_for(&server, &session).await;
+
+    let started = Instant::now();
+    let posts = client
+        .fetch_feed(&session, &FeedFilter::Me, None, 20)
+        .await
+        .unwrap();
+    assert!(posts.is_empty());
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.requests().len(), 3);
+}
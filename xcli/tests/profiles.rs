@@ -0,0 +1,118 @@
+mod support;
+
+use cli_twitter::commands::{self, CommandContext};
+use cli_twitter::data::cache::CacheDao;
+use serde_json::json;
+use support::{client_for, session, temp_db, Reply, StubServer};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn profile_shows_counts_and_recent_posts_and_caches_the_profile() {
+    let me = session("access-1");
+    let alice = Uuid::new_v4();
+    let server = StubServer::start(move |req| match req.path() {
+        "/rest/v1/rpc/profile_card" => Reply::json(
+            200,
+            json!([{
+                "id": alice,
+                "handle": "alice",
+                "display_name": "Alice Liddell",
+                "created_at": "2026-01-05T10:00:00Z",
+                "followers": 12,
+                "following": 1
+            }]),
+        ),
+        "/rest/v1/rpc/profile_posts" => Reply::json(
+            200,
+            json!([{
+                "id": Uuid::new_v4(),
+                "author": alice,
+                "body": "down the rabbit hole",
+                "audience": "public",
+                "created_at": "2026-10-18T09:00:00Z",
+                "author_handle": "alice"
+            }]),
+        ),
+        _ => Reply::json(404, json!({ "message": "not found" })),
+    })
+    .await;
+    let client = client_for(&server, &me).await;
+    let db = temp_db();
+
+    let cmd = commands::parse_command("/profile @alice").unwrap();
+    let output = commands::execute(CommandContext::new(&db, &client), cmd)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        output.message,
+        "Alice Liddell (@alice) · joined 2026-01-05 · 12 followers · 1 following"
+    );
+    let card = output.profile.unwrap();
+    assert_eq!(card.posts.len(), 1);
+    let requests = server.requests();
+    assert_eq!(requests[0].json()["p_handle"], "alice");
+    assert_eq!(requests[1].json()["author_id"], alice.to_string());
+
+    let cached = CacheDao::new(&db)
+        .profile_by_handle("alice")
+        .unwrap()
+        .unwrap();
+    assert_eq!(cached.display_name.as_deref(), Some("Alice Liddell"));
+}
+
+#[tokio::test]
+async fn who_searches_the_directory_and_edit_updates_own_profile() {
+    let me = session("access-1");
+    let my_id = me.user_id.clone();
+    let server = StubServer::start(move |req| match req.method.as_str() {
+        "GET" => Reply::json(
+            200,
+            json!([
+                { "id": Uuid::new_v4(), "handle": "bob", "display_name": "Bob", "created_at": null },
+                { "id": Uuid::new_v4(), "handle": "bobby", "display_name": null, "created_at": null }
+            ]),
+        ),
+        "PATCH" => Reply::json(
+            200,
+            json!([{
+                "id": my_id,
+                "handle": "me",
+                "display_name": req.json()["display_name"],
+                "created_at": "2026-01-01T00:00:00Z"
+            }]),
+        ),
+        _ => Reply::json(404, json!({ "message": "not found" })),
+    })
+    .await;
+    let client = client_for(&server, &me).await;
+    let db = temp_db();
+    let ctx = CommandContext::new(&db, &client);
+
+    let output = commands::execute(ctx, commands::parse_command("/who bob").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(
+        output.message,
+        "2 profiles matching \"bob\"\nBob (@bob)\n@bobby"
+    );
+    let search = &server.requests()[0];
+    assert!(search.target.contains("handle.ilike.*bob*"));
+    assert!(search.target.contains("display_name.ilike.*bob*"));
+
+    let cmd = commands::parse_command("/profile edit display_name:\"Me Myself\"").unwrap();
+    let output = commands::execute(ctx, cmd).await.unwrap();
+    assert_eq!(output.message, "Display name set to Me Myself");
+    let patch = &server.requests()[1];
+    assert_eq!(patch.method, "PATCH");
+    assert!(patch.target.contains(&format!("id=eq.{}", me.user_id)));
+    assert_eq!(
+        CacheDao::new(&db)
+            .profile_by_handle("me")
+            .unwrap()
+            .unwrap()
+            .display_name
+            .as_deref(),
+        Some("Me Myself")
+    );
+}
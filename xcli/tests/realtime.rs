@@ -0,0 +1,176 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use cli_twitter::domain::{Session, SessionTokens};
+use cli_twitter::realtime::protocol::{Frame, POSTS_TOPIC};
+use cli_twitter::realtime::{RealtimeConfig, RealtimeWorker};
+use cli_twitter::supabase::secrets::EncryptedFileBackend;
+use cli_twitter::supabase::{SessionStore, SupabaseClient, SupabaseConfig};
+use cli_twitter::sync::SyncEvent;
+use futures::{SinkExt, StreamExt};
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+use tokio_tungstenite::{accept_async, tungstenite::Message, WebSocketStream};
+
+type Socket = WebSocketStream<tokio::net::TcpStream>;
+
+async fn client_for(port: u16) -> SupabaseClient {
+    let path =
+        std::env::temp_dir().join(format!("cli-twitter-session-{}.json", uuid::Uuid::new_v4()));
+    let store = SessionStore::with_backend(Arc::new(EncryptedFileBackend::new(path, "test")));
+    store
+        .save(&Session {
+            user_id: uuid::Uuid::new_v4().to_string(),
+            email: "me@example.com".into(),
+            tokens: SessionTokens {
+                access_token: "access".into(),
+                refresh_token: "refresh".into(),
+                expires_at: time::OffsetDateTime::now_utc() + time::Duration::hours(1),
+            },
+        })
+        .await
+        .unwrap();
+    let cfg = SupabaseConfig {
+        url: format!("http://127.0.0.1:{}", port),
+        anon_key: "anon".into(),
+        login_url: None,
+    };
+    SupabaseClient::new(cfg, store).unwrap()
+}
+
+fn realtime_cfg(client: &SupabaseClient) -> RealtimeConfig {
+    RealtimeConfig {
+        heartbeat_interval: Duration::from_millis(100),
+        max_backoff: Duration::from_millis(50),
+        ..RealtimeConfig::from_supabase(&client.cfg)
+    }
+}
+
+async fn recv_frame(socket: &mut Socket) -> Frame {
+    loop {
+        let message = timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("frame in time")
+            .expect("socket open")
+            .unwrap();
+        if let Message::Text(text) = message {
+            return Frame::decode(&text).unwrap();
+        }
+    }
+}
+
+async fn send(socket: &mut Socket, value: serde_json::Value) {
+    socket.send(Message::Text(value.to_string())).await.unwrap();
+}
+
+async fn accept_and_join(listener: &TcpListener) -> Socket {
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut socket = accept_async(stream).await.unwrap();
+    let join = recv_frame(&mut socket).await;
+    assert_eq!(join.event, "phx_join");
+    assert_eq!(join.topic, POSTS_TOPIC);
+    assert_eq!(join.payload["access_token"], "access");
+    send(
+        &mut socket,
+        json!({
+            "topic": POSTS_TOPIC,
+            "event": "phx_reply",
+            "payload": { "status": "ok", "response": {} },
+            "ref": join.reference,
+        }),
+    )
+    .await;
+    socket
+}
+
+async fn next_event(rx: &mut mpsc::UnboundedReceiver<SyncEvent>) -> SyncEvent {
+    timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("event in time")
+        .expect("worker alive")
+}
+
+#[tokio::test]
+async fn pushes_inserts_and_answers_heartbeats() {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+    let client = client_for(listener.local_addr().unwrap().port()).await;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let _worker = RealtimeWorker::spawn(client.clone(), realtime_cfg(&client), tx);
+
+    let mut socket = accept_and_join(&listener).await;
+    assert_eq!(next_event(&mut rx).await, SyncEvent::RealtimeConnected);
+
+    let post_id = uuid::Uuid::new_v4();
+    send(
+        &mut socket,
+        json!({
+            "topic": POSTS_TOPIC,
+            "event": "postgres_changes",
+            "payload": { "data": {
+                "type": "INSERT",
+                "schema": "public",
+                "table": "posts",
+                "record": {
+                    "id": post_id,
+                    "author": uuid::Uuid::new_v4(),
+                    "body": "hello live",
+                    "audience": "public",
+                    "created_at": "2026-10-18T09:00:00Z"
+                }
+            }},
+            "ref": null,
+        }),
+    )
+    .await;
+    match next_event(&mut rx).await {
+        SyncEvent::NewPost(post) => {
+            assert_eq!(post.id, post_id);
+            assert_eq!(post.body, "hello live");
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+
+    let heartbeat = recv_frame(&mut socket).await;
+    assert_eq!(heartbeat.event, "heartbeat");
+    assert_eq!(heartbeat.topic, "phoenix");
+}
+
+#[tokio::test]
+async fn reconnects_when_server_drops_connection() {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+    let client = client_for(listener.local_addr().unwrap().port()).await;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let _worker = RealtimeWorker::spawn(client.clone(), realtime_cfg(&client), tx);
+
+    let socket = accept_and_join(&listener).await;
+    assert_eq!(next_event(&mut rx).await, SyncEvent::RealtimeConnected);
+    drop(socket);
+
+    let _socket = accept_and_join(&listener).await;
+    // An abrupt drop is reported once before the worker is back.
+    let mut event = next_event(&mut rx).await;
+    if matches!(event, SyncEvent::RealtimeDown { .. }) {
+        event = next_event(&mut rx).await;
+    }
+    assert_eq!(event, SyncEvent::RealtimeConnected);
+}
+
+#[tokio::test]
+async fn unanswered_heartbeat_forces_reconnect() {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+    let client = client_for(listener.local_addr().unwrap().port()).await;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let _worker = RealtimeWorker::spawn(client.clone(), realtime_cfg(&client), tx);
+
+    // Keep the first socket open but never reply to heartbeats.
+    let _silent = accept_and_join(&listener).await;
+    assert_eq!(next_event(&mut rx).await, SyncEvent::RealtimeConnected);
+    assert!(matches!(
+        next_event(&mut rx).await,
+        SyncEvent::RealtimeDown { .. }
+    ));
+    let _socket = accept_and_join(&listener).await;
+    assert_eq!(next_event(&mut rx).await, SyncEvent::RealtimeConnected);
+}
@@ -0,0 +1,198 @@
+mod support;
+
+use cli_twitter::data::scheduled::ScheduledDao;
+use cli_twitter::domain::{AudienceScope, ScheduledStatus};
+use cli_twitter::sync::{scheduled, SyncEvent};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use support::{client_for, session, temp_db, Reply, StubServer};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+#[test]
+fn only_due_posts_of_the_account_are_claimed() {
+    let db = temp_db();
+    let dao = ScheduledDao::new(&db);
+    let now = OffsetDateTime::now_utc();
+    let due = dao
+        .add("me", "good morning", AudienceScope::Public, now)
+        .unwrap();
+    let later = dao
+        .add(
+            "me",
+            "later",
+            AudienceScope::Private,
+            now + Duration::hours(2),
+        )
+        .unwrap();
+    dao.add("someone-else", "theirs", AudienceScope::Public, now)
+        .unwrap();
+
+    let claimed = dao.claim_due("me", now, 10).unwrap();
+    assert_eq!(claimed.len(), 1);
+    assert_eq!(claimed[0].id, due.id);
+    assert_eq!(claimed[0].status, ScheduledStatus::Sending);
+    assert!(dao.claim_due("me", now, 10).unwrap().is_empty());
+    assert!(
+        !dao.cancel("me", due.id).unwrap(),
+        "in flight posts stay put"
+    );
+    // `xcli flush-scheduled` may still be publishing a fresh claim.
+    assert_eq!(dao.requeue_stale(now - Duration::minutes(15)).unwrap(), 0);
+    assert_eq!(dao.requeue_stale(now + Duration::seconds(1)).unwrap(), 1);
+
+    let list = dao.list("me").unwrap();
+    assert_eq!(
+        list.iter().map(|post| post.id).collect::<Vec<_>>(),
+        [due.id, later.id]
+    );
+    let edited = dao
+        .update("me", later.id, Some("sooner"), None, Some(now))
+        .unwrap()
+        .unwrap();
+    assert_eq!(edited.body, "sooner");
+    assert_eq!(edited.audience, AudienceScope::Private);
+    assert_eq!(dao.claim_due("me", now, 10).unwrap().len(), 2);
+    assert!(dao
+        .update("other", later.id, None, None, None)
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn due_posts_are_published_and_failures_kept() {
+    let me = session("access-1");
+    let author = me.user_id.clone();
+    let server = StubServer::start(move |req| {
+        let body = req.json();
+        if body["body"] == "rejected" {
+            return Reply::json(400, json!({"message": "body is not allowed"}));
+        }
+        Reply::json(
+            201,
+            json!([{
+                "id": Uuid::new_v4(),
+                "author": author,
+                "body": body["body"],
+                "audience": body["audience"],
+                "created_at": "2026-10-19T09:00:00Z"
+            }]),
+        )
+    })
+    .await;
+    let client = client_for(&server, &me).await;
+    let db = temp_db();
+    let dao = ScheduledDao::new(&db);
+    let now = OffsetDateTime::now_utc();
+    let sent = dao
+        .add(&me.user_id, "ship it", AudienceScope::Restrained, now)
+        .unwrap();
+    let rejected = dao
+        .add(&me.user_id, "rejected", AudienceScope::Public, now)
+        .unwrap();
+    let waiting = dao
+        .add(
+            &me.user_id,
+            "tomorrow",
+            AudienceScope::Public,
+            now + Duration::days(1),
+        )
+        .unwrap();
+
+    let events = scheduled::publish_due(&db, &client).await.unwrap();
+    assert_eq!(events.len(), 2);
+    assert!(matches!(
+        &events[0],
+        SyncEvent::ScheduledPublished { id, .. } if *id == sent.id
+    ));
+    assert!(matches!(
+        &events[1],
+        SyncEvent::ScheduledFailed { id, retrying: false, .. } if *id == rejected.id
+    ));
+    let posted = server
+        .requests()
+        .into_iter()
+        .filter(|r| r.path() == "/rest/v1/posts")
+        .collect::<Vec<_>>();
+    assert_eq!(posted[0].json()["audience"], "restrained");
+    assert_eq!(posted[0].json()["id"], sent.post_id.to_string());
+    assert!(posted[0].headers["prefer"].contains("resolution=ignore-duplicates"));
+
+    let left = dao.list(&me.user_id).unwrap();
+    assert_eq!(
+        left.iter().map(|post| post.id).collect::<Vec<_>>(),
+        [rejected.id, waiting.id]
+    );
+    assert_eq!(left[0].status, ScheduledStatus::Failed);
+    assert!(left[0].describe(now).contains("failed:"));
+    assert!(scheduled::publish_due(&db, &client)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn a_lost_insert_is_retried_later_with_the_same_id() {
+    let me = session("access-1");
+    let author = me.user_id.clone();
+    let inserts = Arc::new(AtomicUsize::new(0));
+    let seen = inserts.clone();
+    let server = StubServer::start(move |req| {
+        let row = |id: &str| {
+            json!([{
+                "id": id,
+                "author": author,
+                "body": "ship it",
+                "audience": "public",
+                "created_at": "2026-10-19T09:00:00Z"
+            }])
+        };
+        if req.method == "GET" {
+            let id = req.target.split("id=eq.").nth(1).unwrap_or_default();
+            return Reply::json(200, row(id));
+        }
+        match seen.fetch_add(1, Ordering::SeqCst) {
+            // The insert went through but the response didn't make it back.
+            0 => Reply::json(503, json!({"message": "upstream timed out"})),
+            // The second attempt is an ignored duplicate.
+            _ => Reply::json(201, json!([])),
+        }
+    })
+    .await;
+    let client = client_for(&server, &me).await;
+    let db = temp_db();
+    let dao = ScheduledDao::new(&db);
+    let now = OffsetDateTime::now_utc();
+    let queued = dao
+        .add(&me.user_id, "ship it", AudienceScope::Public, now)
+        .unwrap();
+
+    let events = scheduled::publish_due(&db, &client).await.unwrap();
+    assert!(matches!(
+        &events[0],
+        SyncEvent::ScheduledFailed { retrying: true, .. }
+    ));
+    assert_eq!(
+        inserts.load(Ordering::SeqCst),
+        1,
+        "inserts aren't retried in place"
+    );
+
+    dao.update(&me.user_id, queued.id, None, None, Some(now))
+        .unwrap()
+        .unwrap();
+    let events = scheduled::publish_due(&db, &client).await.unwrap();
+    assert!(matches!(
+        &events[0],
+        SyncEvent::ScheduledPublished { post_id, .. } if *post_id == queued.post_id.to_string()
+    ));
+    let ids = server
+        .requests()
+        .into_iter()
+        .filter(|r| r.method == "POST")
+        .map(|r| r.json()["id"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, [json!(queued.post_id), json!(queued.post_id)]);
+    assert!(dao.list(&me.user_id).unwrap().is_empty());
+}
@@ -0,0 +1,132 @@
+mod support;
+
+use cli_twitter::cli::script::{self, Step};
+use cli_twitter::cli::{Failure, Invalid};
+use cli_twitter::commands::{self, Command, CommandContext};
+use serde_json::json;
+use support::{client_for, session, temp_db, Reply, StubServer};
+use uuid::Uuid;
+
+#[test]
+fn scripts_skip_comments_and_check_every_line() {
+    let steps = script::parse_script(
+        "# nightly\n\n/feed #rust\nset -e\n  /tasks add \"a\" \"b\"  \nset +e\n",
+    )
+    .unwrap();
+    assert_eq!(
+        steps,
+        vec![
+            Step::Command {
+                line: 3,
+                text: "/feed #rust".into()
+            },
+            Step::StopOnError(true),
+            Step::Command {
+                line: 5,
+                text: "/tasks add \"a\" \"b\"".into()
+            },
+            Step::StopOnError(false),
+        ]
+    );
+    assert_eq!(script::check(&steps).unwrap(), 2);
+    assert!(script::parse_script("/whoami\nwhoami\n").is_err());
+
+    let lookup = |name: &str| (name == "task_id").then(|| "7".to_string());
+    assert_eq!(
+        script::substitute("/tasks done $task_id # ${task_id}x $$5", lookup).unwrap(),
+        "/tasks done 7 # 7x $5"
+    );
+    assert!(script::substitute("/post \"$post_id\"", lookup).is_err());
+    assert_eq!(
+        script::substitute("/post \"costs $5, or $ 4 $-\" $", lookup).unwrap(),
+        "/post \"costs $5, or $ 4 $-\" $"
+    );
+
+    let message = "said \"hi\" \\o/\nbye";
+    let lookup = |name: &str| (name == "message").then(|| message.to_string());
+    assert_eq!(
+        script::substitute("/post $message", lookup).unwrap(),
+        "/post said\\ \\\"hi\\\"\\ \\\\o/\\\nbye"
+    );
+    for line in ["/post $message", "/post \"re: $message\""] {
+        let Command::Post { text, .. } =
+            commands::parse_command(&script::substitute(line, lookup).unwrap()).unwrap()
+        else {
+            panic!("expected a post");
+        };
+        assert!(text.ends_with(message), "{:?}", text);
+    }
+    assert!(script::substitute("/post \"$nope\"", lookup).is_err());
+
+    let steps = script::parse_script("/tasks done $task_id\n/like $post_id\n/bogus\n/like $oops\n")
+        .unwrap();
+    let err = script::check(&steps).unwrap_err();
+    assert_eq!(Failure::of(&err), Failure::Invalid);
+    let problems = err.downcast::<Invalid>().unwrap().0;
+    let lines = problems.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("line 3: "));
+    assert!(lines[1].starts_with("line 4: unknown variable $oops"));
+}
+
+#[tokio::test]
+async fn scripts_share_context_and_pass_ids_along() {
+    let me = session("access-1");
+    let author = me.user_id.clone();
+    let server = StubServer::start(move |req| match req.path() {
+        "/rest/v1/posts" => {
+            let body = req.json();
+            Reply::json(
+                201,
+                json!([{
+                    "id": Uuid::new_v4(),
+                    "author": author,
+                    "body": body["body"],
+                    "audience": body["audience"],
+                    "created_at": "2026-10-18T09:00:00Z"
+                }]),
+            )
+        }
+        _ => Reply::json(200, json!([])),
+    })
+    .await;
+    let client = client_for(&server, &me).await;
+    let db = temp_db();
+    let ctx = CommandContext::new(&db, &client);
+    let posted = || {
+        server
+            .requests()
+            .into_iter()
+            .filter(|r| r.path() == "/rest/v1/posts")
+            .map(|r| r.json()["body"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let steps = script::parse_script(
+        "/tasks add \"ship\" \"v1\"\n\
+         /post \"working on task $task_id\"\n\
+         /drafts open 999\n\
+         /post \"still here after ${post_id}\"\n",
+    )
+    .unwrap();
+    let mut outputs = 0;
+    let mut failures = 0;
+    let err = script::run(ctx, &steps, false, |result| match result {
+        Ok(_) => outputs += 1,
+        Err(_) => failures += 1,
+    })
+    .await
+    .unwrap_err();
+    assert_eq!((outputs, failures), (3, 1));
+    assert!(format!("{:#}", err).contains("line 3: /drafts open 999"));
+    let bodies = posted();
+    assert_eq!(bodies.len(), 2);
+    assert!(bodies[0].starts_with("working on task "));
+    assert!(bodies[0]["working on task ".len()..].parse::<i64>().is_ok());
+    assert!(bodies[1].starts_with("still here after "));
+
+    let steps = script::parse_script("set -e\n/drafts open 999\n/post \"never\"\n").unwrap();
+    let err = script::run(ctx, &steps, false, |_| {}).await.unwrap_err();
+    assert!(format!("{:#}", err).starts_with("line 2: /drafts open 999: no draft #999"));
+    assert_eq!(posted().len(), 2);
+}
@@ -0,0 +1,137 @@
+use std::os::unix::fs::PermissionsExt;
+use std::sync::Arc;
+
+use cli_twitter::domain::{Session, SessionTokens};
+use cli_twitter::supabase::secrets::{EncryptedFileBackend, SecretBackend};
+use cli_twitter::supabase::session_store::migrate_plaintext;
+use cli_twitter::supabase::SessionStore;
+use tempfile::tempdir;
+use time::OffsetDateTime;
+
+fn session() -> Session {
+    Session {
+        user_id: uuid::Uuid::new_v4().to_string(),
+        email: "me@example.com".into(),
+        tokens: SessionTokens {
+            access_token: "access-secret".into(),
+            refresh_token: "refresh-secret".into(),
+            expires_at: OffsetDateTime::from_unix_timestamp(2_000_000_000).unwrap(),
+        },
+    }
+}
+
+fn mode(path: &std::path::Path) -> u32 {
+    std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+#[tokio::test]
+async fn encrypted_file_round_trips_without_plaintext() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("sessions.enc");
+    let store = SessionStore::with_backend(Arc::new(EncryptedFileBackend::new(
+        path.clone(),
+        "correct horse",
+    )));
+    let session = session();
+    store.save(&session).await.unwrap();
+
+    let raw = std::fs::read_to_string(&path).unwrap();
+    assert!(!raw.contains("refresh-secret"));
+    assert!(!raw.contains("me@example.com"));
+    assert_eq!(mode(&path), 0o600);
+    assert_eq!(store.load().await.unwrap(), Some(session));
+
+    let wrong = EncryptedFileBackend::new(path.clone(), "wrong horse");
+    let err = wrong.load().unwrap_err();
+    assert!(err.to_string().contains("wrong passphrase"));
+
+    store.clear().await.unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+fn loose_permissions_are_tightened_on_read() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("sessions.enc");
+    let backend = EncryptedFileBackend::new(path.clone(), "pass");
+    backend.store("{}").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    assert_eq!(backend.load().unwrap().as_deref(), Some("{}"));
+    assert_eq!(mode(&path), 0o600);
+}
+
+#[tokio::test]
+async fn plaintext_sessions_are_moved_into_backend() {
+    let dir = tempdir().unwrap();
+    let legacy = dir.path().join("sessions.json");
+    let session = session();
+    std::fs::write(
+        &legacy,
+        serde_json::to_string_pretty(&serde_json::json!({
+            "session": session,
+            "saved_at": OffsetDateTime::now_utc(),
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+
+    let backend = Arc::new(EncryptedFileBackend::new(
+        dir.path().join("sessions.enc"),
+        "pass",
+    ));
+    assert!(migrate_plaintext(&legacy, backend.as_ref()).unwrap());
+    assert!(!legacy.exists());
+    assert!(!migrate_plaintext(&legacy, backend.as_ref()).unwrap());
+
+    let store = SessionStore::with_backend(backend);
+    assert_eq!(store.load().await.unwrap(), Some(session));
+}
+
+#[tokio::test]
+async fn keeps_several_accounts_with_one_active() {
+    let dir = tempdir().unwrap();
+    let store = SessionStore::with_backend(Arc::new(EncryptedFileBackend::new(
+        dir.path().join("sessions.enc"),
+        "pass",
+    )));
+    let team = session();
+    let mut personal = session();
+    personal.email = "me@personal.example".into();
+    store.save(&team).await.unwrap();
+    store.save(&personal).await.unwrap();
+    assert_eq!(store.load().await.unwrap(), Some(personal.clone()));
+
+    // A refresh for the inactive account keeps the active one.
+    let mut refreshed = team.clone();
+    refreshed.tokens.access_token = "access-refreshed".into();
+    store.save_tokens(&refreshed).await.unwrap();
+    assert_eq!(store.load().await.unwrap(), Some(personal.clone()));
+    let team = refreshed;
+
+    let accounts = store.accounts().await.unwrap();
+    assert_eq!(accounts.len(), 2);
+    assert!(accounts
+        .iter()
+        .any(|a| a.email == "me@example.com" && !a.active));
+    assert!(accounts
+        .iter()
+        .any(|a| a.email == "me@personal.example" && a.active));
+
+    // Both accounts share the local part, so that alone is ambiguous.
+    assert!(store.switch("me").await.is_err());
+    assert_eq!(store.switch("me@example.com").await.unwrap(), team);
+    assert_eq!(store.load().await.unwrap(), Some(team.clone()));
+
+    let removed = store.remove("me@example.com").await.unwrap();
+    assert!(removed.active);
+    assert_eq!(store.load().await.unwrap(), None);
+    assert_eq!(store.switch("me").await.unwrap(), personal);
+    // Nor does a late refresh bring a removed account back.
+    store.save_tokens(&team).await.unwrap();
+    assert_eq!(store.accounts().await.unwrap().len(), 1);
+
+    store.clear().await.unwrap();
+    assert!(store.accounts().await.unwrap().is_empty());
+    assert!(!dir.path().join("sessions.enc").exists());
+}
@@ -0,0 +1,199 @@
+//! Helpers shared by integration tests that talk to a stand-in Supabase.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use cli_twitter::data::{AppDatabase, DatabaseConfig};
+use cli_twitter::domain::{Session, SessionTokens};
+use cli_twitter::supabase::secrets::SecretBackend;
+use cli_twitter::supabase::{SessionStore, SupabaseClient, SupabaseConfig};
+use parking_lot::Mutex;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+/// Keeps the secret in memory, so tests skip keyrings and Argon2.
+#[derive(Default)]
+pub struct MemoryBackend(Mutex<Option<String>>);
+
+impl SecretBackend for MemoryBackend {
+    fn describe(&self) -> String {
+        "memory".to_string()
+    }
+
+    fn load(&self) -> Result<Option<String>> {
+        Ok(self.0.lock().clone())
+    }
+
+    fn store(&self, secret: &str) -> Result<()> {
+        *self.0.lock() = Some(secret.to_string());
+        Ok(())
+    }
+
+    fn delete(&self) -> Result<()> {
+        *self.0.lock() = None;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Recorded {
+    pub method: String,
+    /// Path and query, e.g. `/rest/v1/posts?select=*`.
+    pub target: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Recorded {
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    pub fn bearer(&self) -> Option<&str> {
+        self.headers
+            .get("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap_or_default()
+    }
+}
+
+pub struct Reply {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Reply {
+    pub fn json(status: u16, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".into(), "application/json".into())],
+            body: body.to_string(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// A one-request-per-connection HTTP server answering with `handler` and
+/// recording every request it saw.
+pub struct StubServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<Recorded>>>,
+}
+
+impl StubServer {
+    pub async fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&Recorded) -> Reply + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler = Arc::new(handler);
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let seen = seen.clone();
+                tokio::spawn(async move {
+                    let (read, mut write) = stream.into_split();
+                    let Some(request) = read_request(BufReader::new(read)).await else {
+                        return;
+                    };
+                    let reply = handler(&request);
+                    seen.lock().push(request);
+                    let mut head = format!(
+                        "HTTP/1.1 {} Stub\r\nContent-Length: {}\r\nConnection: close\r\n",
+                        reply.status,
+                        reply.body.len()
+                    );
+                    for (name, value) in &reply.headers {
+                        head.push_str(&format!("{}: {}\r\n", name, value));
+                    }
+                    head.push_str("\r\n");
+                    let _ = write.write_all(head.as_bytes()).await;
+                    let _ = write.write_all(reply.body.as_bytes()).await;
+                    let _ = write.shutdown().await;
+                });
+            }
+        });
+        Self { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<Recorded> {
+        self.requests.lock().clone()
+    }
+}
+
+async fn read_request<R: tokio::io::AsyncRead + Unpin>(
+    mut reader: BufReader<R>,
+) -> Option<Recorded> {
+    let mut line = String::new();
+    reader.read_line(&mut line).await.ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?.to_string();
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    let len = headers
+        .get("content-length")
+        .and_then(|len| len.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).await.ok()?;
+    Some(Recorded {
+        method,
+        target,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+pub fn session(access_token: &str) -> Session {
+    Session {
+        user_id: uuid::Uuid::new_v4().to_string(),
+        email: "me@example.com".into(),
+        tokens: SessionTokens {
+            access_token: access_token.into(),
+            refresh_token: "refresh-1".into(),
+            expires_at: time::OffsetDateTime::now_utc() + time::Duration::hours(1),
+        },
+    }
+}
+
+/// A client for `server` whose store holds `session` as the active account.
+pub async fn client_for(server: &StubServer, session: &Session) -> SupabaseClient {
+    let store = SessionStore::with_backend(Arc::new(MemoryBackend::default()));
+    store.save(session).await.unwrap();
+    let cfg = SupabaseConfig {
+        url: server.url.clone(),
+        anon_key: "anon".into(),
+        login_url: None,
+    };
+    SupabaseClient::new(cfg, store).unwrap()
+}
+
+/// A fresh, fully migrated database in the temp directory.
+pub fn temp_db() -> AppDatabase {
+    let path = std::env::temp_dir().join(format!("cli-twitter-test-{}.db", uuid::Uuid::new_v4()));
+    AppDatabase::open(&DatabaseConfig { path }).unwrap()
+}
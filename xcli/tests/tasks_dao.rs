@@ -1,11 +1,9 @@
-use cli_twitter::data::{tasks::TaskDao, AppDatabase, DatabaseConfig};
-use cli_twitter::domain::TaskStatus;
+mod support;
 
-fn temp_db() -> AppDatabase {
-    let path = std::env::temp_dir().join(format!("cli-twitter-test-{}.db", uuid::Uuid::new_v4()));
-    let cfg = DatabaseConfig { path };
-    AppDatabase::open(&cfg).unwrap()
-}
+use cli_twitter::data::tasks::TaskDao;
+use cli_twitter::domain::{NewTask, TaskChanges, TaskPriority, TaskQuery, TaskSort, TaskStatus};
+use support::temp_db;
+use time::{Duration, OffsetDateTime};
 
 #[test]
 fn create_and_complete_task() {
@@ -16,3 +14,123 @@ fn create_and_complete_task() {
     let done = dao.mark_done(task.id).unwrap().unwrap();
     assert_eq!(done.status, TaskStatus::Done);
 }
+
+#[test]
+fn edit_tag_reopen_and_delete() {
+    let db = temp_db();
+    let dao = TaskDao::new(&db);
+    let due = OffsetDateTime::now_utc().replace_nanosecond(0).unwrap() + Duration::days(2);
+    let task = dao
+        .insert(&NewTask {
+            title: "Rotate keys".into(),
+            priority: TaskPriority::High,
+            due_at: Some(due),
+            tags: vec!["#Infra".into()],
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(task.due_at, Some(due));
+    assert_eq!(task.tags, vec!["infra".to_string()]);
+
+    let edited = dao
+        .edit(
+            task.id,
+            &TaskChanges {
+                title: Some("Rotate all keys".into()),
+                due_at: Some(None),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(edited.title, "Rotate all keys");
+    assert_eq!(edited.priority, TaskPriority::High);
+    assert_eq!(edited.due_at, None);
+
+    let tagged = dao.add_tags(task.id, &["ops".into()]).unwrap().unwrap();
+    assert_eq!(tagged.tags, vec!["infra".to_string(), "ops".to_string()]);
+    let untagged = dao
+        .remove_tags(task.id, &["infra".into()])
+        .unwrap()
+        .unwrap();
+    assert_eq!(untagged.tags, vec!["ops".to_string()]);
+
+    dao.mark_done(task.id).unwrap();
+    let reopened = dao.reopen(task.id).unwrap().unwrap();
+    assert_eq!(reopened.status, TaskStatus::Open);
+    assert_eq!(reopened.done_at, None);
+
+    assert!(dao.delete(task.id).unwrap());
+    assert!(!dao.delete(task.id).unwrap());
+    assert!(dao
+        .edit(task.id, &TaskChanges::default())
+        .unwrap()
+        .is_none());
+    assert!(dao.tags().unwrap().is_empty());
+}
+
+#[test]
+fn query_filters_and_sorts() {
+    let db = temp_db();
+    let dao = TaskDao::new(&db);
+    let now = OffsetDateTime::now_utc();
+    let add = |title: &str, priority, days: Option<i64>, tag: &str| {
+        dao.insert(&NewTask {
+            title: title.into(),
+            priority,
+            due_at: days.map(|days| now + Duration::days(days)),
+            tags: vec![tag.into()],
+            ..Default::default()
+        })
+        .unwrap()
+    };
+    let overdue = add("overdue", TaskPriority::Low, Some(-1), "infra");
+    let soon = add("soon", TaskPriority::Urgent, Some(3), "infra");
+    let later = add("later", TaskPriority::Normal, Some(30), "docs");
+    let undated = add("undated", TaskPriority::High, None, "infra");
+    dao.set_status(soon.id, TaskStatus::InProgress).unwrap();
+
+    let titles = |query: TaskQuery| {
+        dao.query(&query)
+            .unwrap()
+            .into_iter()
+            .map(|task| task.title)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        titles(TaskQuery {
+            due_before: Some(now + Duration::days(7)),
+            sort: TaskSort::Due,
+            ..Default::default()
+        }),
+        vec!["overdue", "soon"]
+    );
+    assert_eq!(
+        titles(TaskQuery {
+            tag: Some("infra".into()),
+            sort: TaskSort::Priority,
+            ..Default::default()
+        }),
+        vec!["soon", "undated", "overdue"]
+    );
+    assert_eq!(
+        titles(TaskQuery {
+            priority: Some(TaskPriority::High),
+            status: Some(TaskStatus::Open),
+            ..Default::default()
+        }),
+        vec!["undated"]
+    );
+    assert!(overdue.is_overdue(now));
+    assert!(!later.is_overdue(now));
+    assert!(!undated.is_overdue(now));
+
+    dao.set_status(overdue.id, TaskStatus::Cancelled).unwrap();
+    assert_eq!(
+        titles(TaskQuery {
+            due_before: Some(now),
+            ..Default::default()
+        }),
+        Vec::<String>::new()
+    );
+}